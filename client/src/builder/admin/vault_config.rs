@@ -1,15 +1,18 @@
 use std::rc::Rc;
+use std::sync::Arc;
 
 use crate::settings::config::load_cfg;
 use crate::settings::config::ClientConfig;
+use crate::user::compress_tokens::compress_tokens_inx;
+use crate::user::user_vault::{
+    get_program_addresses, initialize_user_vault, initialize_user_vault_instructions,
+};
+use crate::utils::config::keypair_1;
+use crate::utils::events::{simulate_events, SodaEvent};
 use anchor_client::{Client, Cluster};
 use anchor_lang::prelude::AccountMeta;
 use anyhow::Result;
 
-use zk_onchain::state::*;
-use zk_onchain::vaults::config_authority;
-use zk_onchain::vaults::ServiceSignerOperation;
-use zk_onchain::{accounts as soda_accounts, instruction as soda_instructions};
 use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_client::rpc_config::RpcSendTransactionConfig;
 use solana_sdk::commitment_config::CommitmentConfig;
@@ -18,8 +21,18 @@ use solana_sdk::signature::read_keypair_file;
 use solana_sdk::signature::Keypair;
 use solana_sdk::signer::Signer;
 use solana_sdk::system_program;
-use solana_sdk::transaction::Transaction;
-use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
+use solana_sdk::transaction::{Transaction, TransactionError};
+use solana_sdk::{
+    instruction::{Instruction, InstructionError},
+    pubkey::Pubkey,
+};
+use zk_onchain::state::*;
+use zk_onchain::vaults::config_authority;
+use zk_onchain::vaults::{
+    ConfigAction, ServiceSignerEntry, ServiceSignerOperation, PAUSE_VAULTS, PERMISSION_ALL,
+    PERMISSION_COMPRESS, PERMISSION_CREATE_USER_VAULT,
+};
+use zk_onchain::{accounts as soda_accounts, instruction as soda_instructions};
 
 /// Initialize a new vault config with service signers
 pub fn initialize_vault_config(
@@ -37,7 +50,7 @@ pub fn initialize_vault_config(
     let (config_pda, _) = Pubkey::find_program_address(
         &[
             VAULT_CONFIG_SEED.as_bytes(),
-            program.payer().to_bytes().as_ref(),
+            config_authority::id().to_bytes().as_ref(),
         ],
         &zk_onchain::id(),
     );
@@ -72,11 +85,12 @@ pub fn initialize_vault_config(
     Ok(instructions)
 }
 
-/// Update the vault authority
-pub fn update_vault_authority(
+/// Propose a new vault authority, acceptable once the transfer delay has elapsed
+pub fn propose_vault_authority(
     config: &ClientConfig,
     current_authority: Pubkey,
     new_authority: Pubkey,
+    proposal: Option<Pubkey>,
 ) -> Result<Vec<Instruction>> {
     let payer = read_keypair_file(&config.payer_path).unwrap();
     let url = Cluster::Custom(config.http_url.clone(), config.ws_url.clone());
@@ -89,19 +103,124 @@ pub fn update_vault_authority(
     let (config_pda, _) = Pubkey::find_program_address(
         &[
             VAULT_CONFIG_SEED.as_bytes(),
-            program.payer().to_bytes().as_ref(),
+            config_authority::id().to_bytes().as_ref(),
         ],
         &zk_onchain::id(),
     );
 
     let instructions = program
         .request()
-        .accounts(soda_accounts::UpdateVaultAuthority {
+        .accounts(soda_accounts::ProposeVaultAuthority {
             config: config_pda,
             current_authority,
             new_authority,
+            proposal,
+        })
+        .args(soda_instructions::ProposeVaultAuthority {})
+        .instructions()?;
+
+    Ok(instructions)
+}
+
+/// Accept a pending vault authority transfer
+pub fn accept_vault_authority(
+    config: &ClientConfig,
+    new_authority: Pubkey,
+) -> Result<Vec<Instruction>> {
+    let payer = read_keypair_file(&config.payer_path).unwrap();
+    let url = Cluster::Custom(config.http_url.clone(), config.ws_url.clone());
+
+    // Client
+    let client = Client::new(url, Rc::new(payer));
+    let program = client.program(zk_onchain::id())?;
+
+    // Find config PDA
+    let (config_pda, _) = Pubkey::find_program_address(
+        &[
+            VAULT_CONFIG_SEED.as_bytes(),
+            config_authority::id().to_bytes().as_ref(),
+        ],
+        &zk_onchain::id(),
+    );
+
+    let instructions = program
+        .request()
+        .accounts(soda_accounts::AcceptVaultAuthority {
+            config: config_pda,
+            new_authority,
+        })
+        .args(soda_instructions::AcceptVaultAuthority {})
+        .instructions()?;
+
+    Ok(instructions)
+}
+
+/// Cancel a pending vault authority transfer
+pub fn cancel_vault_authority_transfer(
+    config: &ClientConfig,
+    current_authority: Pubkey,
+    proposal: Option<Pubkey>,
+) -> Result<Vec<Instruction>> {
+    let payer = read_keypair_file(&config.payer_path).unwrap();
+    let url = Cluster::Custom(config.http_url.clone(), config.ws_url.clone());
+
+    // Client
+    let client = Client::new(url, Rc::new(payer));
+    let program = client.program(zk_onchain::id())?;
+
+    // Find config PDA
+    let (config_pda, _) = Pubkey::find_program_address(
+        &[
+            VAULT_CONFIG_SEED.as_bytes(),
+            config_authority::id().to_bytes().as_ref(),
+        ],
+        &zk_onchain::id(),
+    );
+
+    let instructions = program
+        .request()
+        .accounts(soda_accounts::CancelVaultAuthorityTransfer {
+            config: config_pda,
+            current_authority,
+            proposal,
+        })
+        .args(soda_instructions::CancelVaultAuthorityTransfer {})
+        .instructions()?;
+
+    Ok(instructions)
+}
+
+/// Update the delay applied to future vault authority transfers
+pub fn update_authority_transfer_delay(
+    config: &ClientConfig,
+    current_authority: Pubkey,
+    delay: i64,
+    proposal: Option<Pubkey>,
+) -> Result<Vec<Instruction>> {
+    let payer = read_keypair_file(&config.payer_path).unwrap();
+    let url = Cluster::Custom(config.http_url.clone(), config.ws_url.clone());
+
+    // Client
+    let client = Client::new(url, Rc::new(payer));
+    let program = client.program(zk_onchain::id())?;
+
+    // Find config PDA
+    let (config_pda, _) = Pubkey::find_program_address(
+        &[
+            VAULT_CONFIG_SEED.as_bytes(),
+            config_authority::id().to_bytes().as_ref(),
+        ],
+        &zk_onchain::id(),
+    );
+
+    let instructions = program
+        .request()
+        .accounts(soda_accounts::UpdateAuthorityTransferDelay {
+            config: config_pda,
+            current_authority,
+            proposal,
         })
-        .args(soda_instructions::UpdateVaultAuthority {})
+        .args(soda_instructions::UpdateAuthorityTransferDelay { delay })
         .instructions()?;
 
     Ok(instructions)
@@ -113,6 +232,7 @@ pub fn manage_service_signer(
     authority: Pubkey,
     service_signer: Pubkey,
     operation: ServiceSignerOperation,
+    proposal: Option<Pubkey>,
 ) -> Result<Vec<Instruction>> {
     let payer = read_keypair_file(&config.payer_path).unwrap();
     let url = Cluster::Custom(config.http_url.clone(), config.ws_url.clone());
@@ -125,21 +245,347 @@ pub fn manage_service_signer(
     let (config_pda, _) = Pubkey::find_program_address(
         &[
             VAULT_CONFIG_SEED.as_bytes(),
-            program.payer().to_bytes().as_ref(),
+            config_authority::id().to_bytes().as_ref(),
         ],
         &zk_onchain::id(),
     );
 
-    let instructions = program
+    // The service signer only has to sign when it is being added
+    let signs = matches!(operation, ServiceSignerOperation::Add { .. });
+
+    // A rotation also passes the replacement key and its registry entry
+    let (new_service_signer, new_service_signer_entry) = match &operation {
+        ServiceSignerOperation::Rotate { new_signer, .. } => (
+            Some(*new_signer),
+            Some(ServiceSignerEntry::find_address(&config_pda, new_signer).0),
+        ),
+        _ => (None, None),
+    };
+
+    let mut instructions = program
         .request()
         .accounts(soda_accounts::ManageServiceSigner {
             config: config_pda,
             authority,
             service_signer,
+            service_signer_entry: ServiceSignerEntry::find_address(&config_pda, &service_signer).0,
+            payer: program.payer(),
+            system_program: system_program::id(),
+            proposal,
+            new_service_signer,
+            new_service_signer_entry,
         })
         .args(soda_instructions::ManageServiceSigner { operation })
         .instructions()?;
 
+    if signs {
+        for meta in instructions
+            .iter_mut()
+            .flat_map(|ix| ix.accounts.iter_mut())
+            .filter(|meta| meta.pubkey == service_signer)
+        {
+            meta.is_signer = true;
+        }
+    }
+
+    Ok(instructions)
+}
+
+/// Replace the permission bitmask of a registered service signer
+pub fn update_service_signer_permissions(
+    config: &ClientConfig,
+    authority: Pubkey,
+    service_signer: Pubkey,
+    permissions: u32,
+    proposal: Option<Pubkey>,
+) -> Result<Vec<Instruction>> {
+    manage_service_signer(
+        config,
+        authority,
+        service_signer,
+        ServiceSignerOperation::UpdatePermissions { permissions },
+        proposal,
+    )
+}
+
+/// Rotate a service signer to a new key that inherits its permissions.
+/// Without `activate_at` the old key is replaced right away, otherwise both keys
+/// are valid until `activate_at`.
+pub fn rotate_service_signer(
+    config: &ClientConfig,
+    authority: Pubkey,
+    old_signer: Pubkey,
+    new_signer: Pubkey,
+    activate_at: Option<i64>,
+    valid_until: i64,
+    proposal: Option<Pubkey>,
+) -> Result<Vec<Instruction>> {
+    manage_service_signer(
+        config,
+        authority,
+        old_signer,
+        ServiceSignerOperation::Rotate {
+            new_signer,
+            activate_at: activate_at.unwrap_or(0),
+            valid_until,
+        },
+        proposal,
+    )
+}
+
+/// Set the guardian allowed to pause the program
+pub fn set_guardian(
+    config: &ClientConfig,
+    authority: Pubkey,
+    guardian: Pubkey,
+    proposal: Option<Pubkey>,
+) -> Result<Vec<Instruction>> {
+    let payer = read_keypair_file(&config.payer_path).unwrap();
+    let url = Cluster::Custom(config.http_url.clone(), config.ws_url.clone());
+
+    // Client
+    let client = Client::new(url, Rc::new(payer));
+    let program = client.program(zk_onchain::id())?;
+
+    let (config_pda, _) = config_proposal_pda(0);
+
+    let instructions = program
+        .request()
+        .accounts(soda_accounts::SetGuardian {
+            config: config_pda,
+            authority,
+            proposal,
+        })
+        .args(soda_instructions::SetGuardian { guardian })
+        .instructions()?;
+
+    Ok(instructions)
+}
+
+/// Pause or resume the whole program and individual subsystems
+pub fn set_pause_state(
+    config: &ClientConfig,
+    guardian: Pubkey,
+    paused: bool,
+    pause_flags: u8,
+) -> Result<Vec<Instruction>> {
+    let payer = read_keypair_file(&config.payer_path).unwrap();
+    let url = Cluster::Custom(config.http_url.clone(), config.ws_url.clone());
+
+    // Client
+    let client = Client::new(url, Rc::new(payer));
+    let program = client.program(zk_onchain::id())?;
+
+    let (config_pda, _) = config_proposal_pda(0);
+
+    let instructions = program
+        .request()
+        .accounts(soda_accounts::SetPauseState {
+            config: config_pda,
+            guardian,
+        })
+        .args(soda_instructions::SetPauseState {
+            paused,
+            pause_flags,
+        })
+        .instructions()?;
+
+    Ok(instructions)
+}
+
+/// Grow a config created before the timelock fields to the current layout
+pub fn migrate_vault_config(config: &ClientConfig) -> Result<Vec<Instruction>> {
+    let payer = read_keypair_file(&config.payer_path).unwrap();
+    let url = Cluster::Custom(config.http_url.clone(), config.ws_url.clone());
+
+    // Client
+    let client = Client::new(url, Rc::new(payer));
+    let program = client.program(zk_onchain::id())?;
+
+    let (config_pda, _) = config_proposal_pda(0);
+
+    let instructions = program
+        .request()
+        .accounts(soda_accounts::MigrateVaultConfig {
+            config: config_pda,
+            payer: program.payer(),
+            system_program: system_program::id(),
+        })
+        .args(soda_instructions::MigrateVaultConfig {})
+        .instructions()?;
+
+    Ok(instructions)
+}
+
+/// Move the legacy fixed-array service signers into registry entries
+pub fn migrate_service_signers(
+    config: &ClientConfig,
+    legacy_signers: Vec<Pubkey>,
+) -> Result<Vec<Instruction>> {
+    let payer = read_keypair_file(&config.payer_path).unwrap();
+    let url = Cluster::Custom(config.http_url.clone(), config.ws_url.clone());
+
+    // Client
+    let client = Client::new(url, Rc::new(payer));
+    let program = client.program(zk_onchain::id())?;
+
+    let (config_pda, _) = config_proposal_pda(0);
+
+    // Entry PDAs in the same order as the legacy array
+    let remaining_accounts: Vec<AccountMeta> = legacy_signers
+        .iter()
+        .map(|signer| AccountMeta {
+            pubkey: ServiceSignerEntry::find_address(&config_pda, signer).0,
+            is_signer: false,
+            is_writable: true,
+        })
+        .collect();
+
+    let instructions = program
+        .request()
+        .accounts(soda_accounts::MigrateServiceSigners {
+            config: config_pda,
+            payer: program.payer(),
+            system_program: system_program::id(),
+        })
+        .args(soda_instructions::MigrateServiceSigners {})
+        .accounts(remaining_accounts)
+        .instructions()?;
+
+    Ok(instructions)
+}
+
+/// Derive the config PDA and the PDA of one of its proposals
+pub fn config_proposal_pda(proposal_id: u64) -> (Pubkey, Pubkey) {
+    let (config_pda, _) = Pubkey::find_program_address(
+        &[
+            VAULT_CONFIG_SEED.as_bytes(),
+            config_authority::id().to_bytes().as_ref(),
+        ],
+        &zk_onchain::id(),
+    );
+
+    let (proposal_pda, _) = Pubkey::find_program_address(
+        &[
+            CONFIG_PROPOSAL_SEED.as_bytes(),
+            config_pda.as_ref(),
+            &proposal_id.to_le_bytes(),
+        ],
+        &zk_onchain::id(),
+    );
+
+    (config_pda, proposal_pda)
+}
+
+/// Replace the config admin set and approval threshold
+pub fn set_config_admins(
+    config: &ClientConfig,
+    authority: Pubkey,
+    admins: Vec<Pubkey>,
+    threshold: u8,
+    proposal: Option<Pubkey>,
+) -> Result<Vec<Instruction>> {
+    let payer = read_keypair_file(&config.payer_path).unwrap();
+    let url = Cluster::Custom(config.http_url.clone(), config.ws_url.clone());
+
+    // Client
+    let client = Client::new(url, Rc::new(payer));
+    let program = client.program(zk_onchain::id())?;
+
+    let (config_pda, _) = config_proposal_pda(0);
+
+    let instructions = program
+        .request()
+        .accounts(soda_accounts::SetConfigAdmins {
+            config: config_pda,
+            authority,
+            proposal,
+        })
+        .args(soda_instructions::SetConfigAdmins { admins, threshold })
+        .instructions()?;
+
+    Ok(instructions)
+}
+
+/// Create a config proposal, `proposal_id` must be the config's current `proposal_count`
+pub fn create_config_proposal(
+    config: &ClientConfig,
+    proposer: Pubkey,
+    proposal_id: u64,
+    action: ConfigAction,
+) -> Result<Vec<Instruction>> {
+    let payer = read_keypair_file(&config.payer_path).unwrap();
+    let url = Cluster::Custom(config.http_url.clone(), config.ws_url.clone());
+
+    // Client
+    let client = Client::new(url, Rc::new(payer));
+    let program = client.program(zk_onchain::id())?;
+
+    let (config_pda, proposal) = config_proposal_pda(proposal_id);
+
+    let instructions = program
+        .request()
+        .accounts(soda_accounts::CreateConfigProposal {
+            config: config_pda,
+            proposal,
+            proposer,
+            system_program: system_program::id(),
+        })
+        .args(soda_instructions::CreateConfigProposal { action })
+        .instructions()?;
+
+    Ok(instructions)
+}
+
+/// Approve a config proposal as one of the config admins
+pub fn approve_config_proposal(
+    config: &ClientConfig,
+    admin: Pubkey,
+    proposal_id: u64,
+) -> Result<Vec<Instruction>> {
+    let payer = read_keypair_file(&config.payer_path).unwrap();
+    let url = Cluster::Custom(config.http_url.clone(), config.ws_url.clone());
+
+    // Client
+    let client = Client::new(url, Rc::new(payer));
+    let program = client.program(zk_onchain::id())?;
+
+    let (config_pda, proposal) = config_proposal_pda(proposal_id);
+
+    let instructions = program
+        .request()
+        .accounts(soda_accounts::ApproveConfigProposal {
+            config: config_pda,
+            proposal,
+            admin,
+        })
+        .args(soda_instructions::ApproveConfigProposal {})
+        .instructions()?;
+
+    Ok(instructions)
+}
+
+/// Close a config proposal and return its rent to the proposer
+pub fn close_config_proposal(
+    config: &ClientConfig,
+    proposer: Pubkey,
+    proposal_id: u64,
+) -> Result<Vec<Instruction>> {
+    let payer = read_keypair_file(&config.payer_path).unwrap();
+    let url = Cluster::Custom(config.http_url.clone(), config.ws_url.clone());
+
+    // Client
+    let client = Client::new(url, Rc::new(payer));
+    let program = client.program(zk_onchain::id())?;
+
+    let (_, proposal) = config_proposal_pda(proposal_id);
+
+    let instructions = program
+        .request()
+        .accounts(soda_accounts::CloseConfigProposal { proposal, proposer })
+        .args(soda_instructions::CloseConfigProposal {})
+        .instructions()?;
+
     Ok(instructions)
 }
 
@@ -176,20 +622,31 @@ pub async fn initialize_vault_config_test(
         init_service_signers.iter().map(|kp| kp.pubkey()).collect(),
     )?);
 
-    // 2. Update authority
-    instructions.extend(update_vault_authority(
+    // 2. Propose a new authority, then cancel it during the delay
+    instructions.extend(propose_vault_authority(
         &config,
         payer.pubkey(),
         new_authority.pubkey(),
+        None,
+    )?);
+    instructions.extend(cancel_vault_authority_transfer(
+        &config,
+        payer.pubkey(),
+        None,
     )?);
 
     // 3. Add more signers
     for service_signer in &additional_signers {
         instructions.extend(manage_service_signer(
             &config,
-            new_authority.pubkey(),
+            payer.pubkey(),
             service_signer.pubkey(),
-            ServiceSignerOperation::Add,
+            ServiceSignerOperation::Add {
+                permissions: PERMISSION_ALL,
+                valid_from: 0,
+                valid_until: 0,
+            },
+            None,
         )?);
     }
 
@@ -197,16 +654,28 @@ pub async fn initialize_vault_config_test(
     if !additional_signers.is_empty() {
         instructions.extend(manage_service_signer(
             &config,
-            new_authority.pubkey(),
+            payer.pubkey(),
             additional_signers[0].pubkey(),
             ServiceSignerOperation::Remove,
+            None,
         )?);
     }
 
+    // 5. Propose again, accepting is only possible after the delay
+    instructions.extend(propose_vault_authority(
+        &config,
+        payer.pubkey(),
+        new_authority.pubkey(),
+        None,
+    )?);
+
+    // Accepting right away must fail with AuthorityTransferTimelocked
+    let early_accept = accept_vault_authority(&config, new_authority.pubkey())?;
+
     let recent_blockhash = rpc_client.get_latest_blockhash().await?;
 
     // Collect all signers needed for the transaction
-    let mut all_signers = vec![&payer, &new_authority];
+    let mut all_signers = vec![&payer];
     all_signers.extend(init_service_signers.iter());
     all_signers.extend(additional_signers.iter());
 
@@ -230,6 +699,34 @@ pub async fn initialize_vault_config_test(
 
     println!("Transaction signature: {}", signature);
 
+    let recent_blockhash = rpc_client.get_latest_blockhash().await?;
+
+    let transaction = Transaction::new_signed_with_payer(
+        &early_accept,
+        Some(&payer.pubkey()),
+        &[&payer, &new_authority],
+        recent_blockhash,
+    );
+
+    let result = rpc_client
+        .send_and_confirm_transaction_with_spinner_and_config(
+            &transaction,
+            CommitmentConfig::processed(),
+            RpcSendTransactionConfig {
+                skip_preflight: true,
+                ..Default::default()
+            },
+        )
+        .await;
+
+    match result {
+        Ok(signature) => anyhow::bail!(
+            "Vault authority accepted before the transfer delay: {}",
+            signature
+        ),
+        Err(err) => println!("Early authority accept rejected: {}", err),
+    }
+
     Ok(())
 }
 
@@ -254,7 +751,6 @@ pub async fn initialize_and_manage_vault_test(
     // Create all keypairs upfront
     let init_service_signers: Vec<Keypair> = (0..initial_signers).map(|_| Keypair::new()).collect();
     let additional_signers: Vec<Keypair> = (0..2).map(|_| Keypair::new()).collect();
-    let new_authority = Keypair::new();
 
     // 1. Initialize with signers
     instructions.extend(initialize_vault_config(
@@ -262,37 +758,36 @@ pub async fn initialize_and_manage_vault_test(
         init_service_signers.iter().map(|kp| kp.pubkey()).collect(),
     )?);
 
-    // 2. Update authority
-    instructions.extend(update_vault_authority(
-        &config,
-        payer.pubkey(),
-        new_authority.pubkey(),
-    )?);
-
-    // 3. Add more signers
+    // 2. Add more signers
     for service_signer in &additional_signers {
         instructions.extend(manage_service_signer(
             &config,
-            new_authority.pubkey(),
+            payer.pubkey(),
             service_signer.pubkey(),
-            ServiceSignerOperation::Add,
+            ServiceSignerOperation::Add {
+                permissions: PERMISSION_ALL,
+                valid_from: 0,
+                valid_until: 0,
+            },
+            None,
         )?);
     }
 
-    // 4. Remove a signer
+    // 3. Remove a signer
     if !additional_signers.is_empty() {
         instructions.extend(manage_service_signer(
             &config,
-            new_authority.pubkey(),
+            payer.pubkey(),
             additional_signers[0].pubkey(),
             ServiceSignerOperation::Remove,
+            None,
         )?);
     }
 
     let recent_blockhash = rpc_client.get_latest_blockhash().await?;
 
     // Collect all signers in the correct order
-    let mut all_signers = vec![&payer, &new_authority];
+    let mut all_signers = vec![&payer];
     all_signers.extend(init_service_signers.iter());
     all_signers.extend(additional_signers.iter());
 
@@ -318,3 +813,482 @@ pub async fn initialize_and_manage_vault_test(
 
     Ok(())
 }
+
+pub async fn reject_unregistered_service_signer_test(rpc_client: RpcClient) -> anyhow::Result<()> {
+    let client_config = "client_config.ini";
+    let config = load_cfg(&client_config.to_string()).unwrap();
+    let payer = Arc::new(read_keypair_file(&config.payer_path).unwrap());
+
+    // Never registered through manage_service_signer. The instructions are pointed at the
+    // registry entry of a registered signer, so the program reaches the registry check
+    // instead of failing on a missing entry account.
+    let unregistered_signer = Keypair::new();
+    let registered_signer = keypair_1();
+    log::info!(
+        "Running unregistered service signer scenario with {}",
+        unregistered_signer.pubkey()
+    );
+
+    let (config_pda, _, _) = get_program_addresses()?;
+    let unregistered_entry =
+        ServiceSignerEntry::find_address(&config_pda, &unregistered_signer.pubkey()).0;
+    let registered_entry =
+        ServiceSignerEntry::find_address(&config_pda, &registered_signer.pubkey()).0;
+
+    // 1. User vault initialization must be refused
+    let current_authority = Keypair::new();
+    let mut instructions = initialize_user_vault_instructions(
+        &config,
+        &payer,
+        &current_authority,
+        None,
+        &unregistered_signer,
+    )
+    .await?;
+    replace_account(&mut instructions, unregistered_entry, registered_entry);
+
+    let result = send_config_instructions(
+        &rpc_client,
+        &instructions,
+        &payer,
+        &[&current_authority, &unregistered_signer],
+    )
+    .await;
+    expect_custom_error(result, CustomError::UnregisteredServiceSigner)?;
+    println!("User vault initialization rejected with UnregisteredServiceSigner");
+
+    // 2. Compressed mint creation must be refused, the compression after it never runs
+    let client = Client::new_with_options(
+        Cluster::Custom(config.http_url.clone(), config.ws_url.clone()),
+        payer.clone(),
+        CommitmentConfig::processed(),
+    );
+    let program = client.program(zk_onchain::id())?;
+
+    use rand::Rng;
+    let nonce = rand::thread_rng().gen_range(100..10000) as u16;
+    let mut instructions =
+        compress_tokens_inx(program, unregistered_signer.pubkey(), nonce, 1).await?;
+    replace_account(&mut instructions, unregistered_entry, registered_entry);
+
+    let result =
+        send_config_instructions(&rpc_client, &instructions, &payer, &[&unregistered_signer]).await;
+    expect_custom_error(result, CustomError::UnregisteredServiceSigner)?;
+    println!("Compressed mint creation rejected with UnregisteredServiceSigner");
+
+    Ok(())
+}
+
+// Point every account meta of `from` at `to`
+fn replace_account(instructions: &mut [Instruction], from: Pubkey, to: Pubkey) {
+    for meta in instructions
+        .iter_mut()
+        .flat_map(|instruction| instruction.accounts.iter_mut())
+        .filter(|meta| meta.pubkey == from)
+    {
+        meta.pubkey = to;
+    }
+}
+
+// Fail unless the transaction was rejected with `expected`
+fn expect_custom_error(
+    result: Result<solana_sdk::signature::Signature, solana_client::client_error::ClientError>,
+    expected: CustomError,
+) -> Result<()> {
+    let expected_code = u32::from(expected);
+
+    match result {
+        Ok(signature) => anyhow::bail!(
+            "Transaction {} succeeded, expected error {}",
+            signature,
+            expected_code
+        ),
+        Err(err) => match err.get_transaction_error() {
+            Some(TransactionError::InstructionError(_, InstructionError::Custom(code)))
+                if code == expected_code =>
+            {
+                Ok(())
+            }
+            _ => anyhow::bail!("Expected error {}, got: {}", expected_code, err),
+        },
+    }
+}
+
+async fn send_config_instructions(
+    rpc_client: &RpcClient,
+    instructions: &[Instruction],
+    payer: &Keypair,
+    signers: &[&Keypair],
+) -> Result<solana_sdk::signature::Signature, solana_client::client_error::ClientError> {
+    let recent_blockhash = rpc_client.get_latest_blockhash().await?;
+
+    let mut all_signers = vec![payer];
+    all_signers.extend(signers.iter().copied());
+
+    let transaction = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&payer.pubkey()),
+        &all_signers,
+        recent_blockhash,
+    );
+
+    rpc_client
+        .send_and_confirm_transaction_with_spinner_and_config(
+            &transaction,
+            CommitmentConfig::processed(),
+            RpcSendTransactionConfig {
+                skip_preflight: true,
+                ..Default::default()
+            },
+        )
+        .await
+}
+
+pub async fn multisig_config_admin_test(rpc_client: RpcClient) -> anyhow::Result<()> {
+    let client_config = "client_config.ini";
+    let config = load_cfg(&client_config.to_string()).unwrap();
+    let payer = read_keypair_file(&config.payer_path).unwrap();
+
+    let admins: Vec<Keypair> = (0..3).map(|_| Keypair::new()).collect();
+    let init_service_signer = Keypair::new();
+    let new_service_signer = Keypair::new();
+    log::info!("Running 2-of-3 config admin scenario");
+
+    // 1. Initialize and hand administration to a 2-of-3 admin set
+    let mut instructions = initialize_vault_config(&config, vec![init_service_signer.pubkey()])?;
+    instructions.extend(set_config_admins(
+        &config,
+        payer.pubkey(),
+        admins.iter().map(|kp| kp.pubkey()).collect(),
+        2,
+        None,
+    )?);
+
+    let signature =
+        send_config_instructions(&rpc_client, &instructions, &payer, &[&init_service_signer])
+            .await?;
+    println!("Admins configured: {}", signature);
+
+    // 2. The former single authority can no longer act alone
+    let instructions = manage_service_signer(
+        &config,
+        payer.pubkey(),
+        new_service_signer.pubkey(),
+        ServiceSignerOperation::Add {
+            permissions: PERMISSION_ALL,
+            valid_from: 0,
+            valid_until: 0,
+        },
+        None,
+    )?;
+    match send_config_instructions(&rpc_client, &instructions, &payer, &[&new_service_signer]).await
+    {
+        Ok(signature) => anyhow::bail!("Config changed without a proposal: {}", signature),
+        Err(err) => println!("Single authority change rejected: {}", err),
+    }
+
+    // 3. One approval is below the threshold
+    let action = ConfigAction::ManageServiceSigner {
+        service_signer: new_service_signer.pubkey(),
+        operation: ServiceSignerOperation::Add {
+            permissions: PERMISSION_ALL,
+            valid_from: 0,
+            valid_until: 0,
+        },
+    };
+    let instructions = create_config_proposal(&config, admins[0].pubkey(), 0, action)?;
+    let signature =
+        send_config_instructions(&rpc_client, &instructions, &payer, &[&admins[0]]).await?;
+    println!("Proposal created: {}", signature);
+
+    let (_, proposal) = config_proposal_pda(0);
+    let execute = manage_service_signer(
+        &config,
+        admins[0].pubkey(),
+        new_service_signer.pubkey(),
+        ServiceSignerOperation::Add {
+            permissions: PERMISSION_ALL,
+            valid_from: 0,
+            valid_until: 0,
+        },
+        Some(proposal),
+    )?;
+    match send_config_instructions(
+        &rpc_client,
+        &execute,
+        &payer,
+        &[&admins[0], &new_service_signer],
+    )
+    .await
+    {
+        Ok(signature) => anyhow::bail!("Proposal executed below threshold: {}", signature),
+        Err(err) => println!("Under-approved proposal rejected: {}", err),
+    }
+
+    // 4. A second approval reaches the threshold
+    let instructions = approve_config_proposal(&config, admins[1].pubkey(), 0)?;
+    let signature =
+        send_config_instructions(&rpc_client, &instructions, &payer, &[&admins[1]]).await?;
+    println!("Proposal approved: {}", signature);
+
+    // The execution must register the new signer
+    let transaction = Transaction::new_signed_with_payer(
+        &execute,
+        Some(&payer.pubkey()),
+        &[&payer, &admins[0], &new_service_signer],
+        rpc_client.get_latest_blockhash().await?,
+    );
+    let events = simulate_events(&rpc_client, &transaction).await?;
+    let signer_added = events.iter().any(|event| {
+        matches!(
+            event,
+            SodaEvent::ServiceSignerAdded(added) if added.signer == new_service_signer.pubkey()
+        )
+    });
+    if !signer_added {
+        anyhow::bail!(
+            "Proposal execution emits no ServiceSignerAdded: {:?}",
+            events
+        );
+    }
+
+    let signature = send_config_instructions(
+        &rpc_client,
+        &execute,
+        &payer,
+        &[&admins[0], &new_service_signer],
+    )
+    .await?;
+    println!("Proposal executed: {}", signature);
+
+    // 5. An executed proposal cannot be replayed
+    match send_config_instructions(
+        &rpc_client,
+        &execute,
+        &payer,
+        &[&admins[0], &new_service_signer],
+    )
+    .await
+    {
+        Ok(signature) => anyhow::bail!("Executed proposal replayed: {}", signature),
+        Err(err) => println!("Proposal replay rejected: {}", err),
+    }
+
+    let instructions = close_config_proposal(&config, admins[0].pubkey(), 0)?;
+    let signature =
+        send_config_instructions(&rpc_client, &instructions, &payer, &[&admins[0]]).await?;
+    println!("Proposal closed: {}", signature);
+
+    Ok(())
+}
+
+pub async fn scoped_service_signer_permissions_test(rpc_client: RpcClient) -> anyhow::Result<()> {
+    let client_config = "client_config.ini";
+    let config = load_cfg(&client_config.to_string()).unwrap();
+    let payer = read_keypair_file(&config.payer_path).unwrap();
+
+    let service_signer = Keypair::new();
+    log::info!(
+        "Running scoped permissions scenario with {}",
+        service_signer.pubkey()
+    );
+
+    // 1. Register a signer that may only compress
+    let instructions = manage_service_signer(
+        &config,
+        payer.pubkey(),
+        service_signer.pubkey(),
+        ServiceSignerOperation::Add {
+            permissions: PERMISSION_COMPRESS,
+            valid_from: 0,
+            valid_until: 0,
+        },
+        None,
+    )?;
+    let signature =
+        send_config_instructions(&rpc_client, &instructions, &payer, &[&service_signer]).await?;
+    println!("Compress-only signer registered: {}", signature);
+
+    // 2. Creating a user vault needs PERMISSION_CREATE_USER_VAULT
+    let result = initialize_user_vault_with_signer(
+        &rpc_client,
+        Some(Arc::new(Keypair::new())),
+        None,
+        &service_signer,
+    )
+    .await;
+
+    match result {
+        Ok(_) => anyhow::bail!("User vault initialized without the create user vault permission"),
+        Err(err) => println!("User vault initialization rejected: {}", err),
+    }
+
+    // 3. Granting the permission lets the same signer create the vault
+    let instructions = update_service_signer_permissions(
+        &config,
+        payer.pubkey(),
+        service_signer.pubkey(),
+        PERMISSION_COMPRESS | PERMISSION_CREATE_USER_VAULT,
+        None,
+    )?;
+    let signature = send_config_instructions(&rpc_client, &instructions, &payer, &[]).await?;
+    println!("Permissions updated: {}", signature);
+
+    initialize_user_vault_with_signer(
+        &rpc_client,
+        Some(Arc::new(Keypair::new())),
+        None,
+        &service_signer,
+    )
+    .await?;
+
+    // 4. Clean up the registry entry
+    let instructions = manage_service_signer(
+        &config,
+        payer.pubkey(),
+        service_signer.pubkey(),
+        ServiceSignerOperation::Remove,
+        None,
+    )?;
+    let signature = send_config_instructions(&rpc_client, &instructions, &payer, &[]).await?;
+    println!("Signer removed: {}", signature);
+
+    Ok(())
+}
+
+pub async fn service_signer_rotation_test(rpc_client: RpcClient) -> anyhow::Result<()> {
+    let client_config = "client_config.ini";
+    let config = load_cfg(&client_config.to_string()).unwrap();
+    let payer = read_keypair_file(&config.payer_path).unwrap();
+
+    let first_key = Keypair::new();
+    let second_key = Keypair::new();
+    let third_key = Keypair::new();
+    log::info!("Running service signer rotation scenario");
+
+    // 1. Register the first key
+    let instructions = manage_service_signer(
+        &config,
+        payer.pubkey(),
+        first_key.pubkey(),
+        ServiceSignerOperation::Add {
+            permissions: PERMISSION_CREATE_USER_VAULT,
+            valid_from: 0,
+            valid_until: 0,
+        },
+        None,
+    )?;
+    let signature =
+        send_config_instructions(&rpc_client, &instructions, &payer, &[&first_key]).await?;
+    println!("First key registered: {}", signature);
+
+    // 2. Rotate to the second key right away, the first key is gone
+    let instructions = rotate_service_signer(
+        &config,
+        payer.pubkey(),
+        first_key.pubkey(),
+        second_key.pubkey(),
+        None,
+        0,
+        None,
+    )?;
+    let signature =
+        send_config_instructions(&rpc_client, &instructions, &payer, &[&second_key]).await?;
+    println!("Rotated to second key: {}", signature);
+
+    let result = initialize_user_vault_with_signer(
+        &rpc_client,
+        Some(Arc::new(Keypair::new())),
+        None,
+        &first_key,
+    )
+    .await;
+    match result {
+        Ok(_) => anyhow::bail!("Rotated out key still accepted"),
+        Err(err) => println!("Rotated out key rejected: {}", err),
+    }
+
+    // 3. Schedule a rotation to the third key an hour from now
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs() as i64;
+    let activate_at = now + 60 * 60;
+    let instructions = rotate_service_signer(
+        &config,
+        payer.pubkey(),
+        second_key.pubkey(),
+        third_key.pubkey(),
+        Some(activate_at),
+        0,
+        None,
+    )?;
+    let signature =
+        send_config_instructions(&rpc_client, &instructions, &payer, &[&third_key]).await?;
+    println!("Rotation to third key scheduled: {}", signature);
+
+    // The second key signs until the hand-over, the third one not before
+    initialize_user_vault_with_signer(
+        &rpc_client,
+        Some(Arc::new(Keypair::new())),
+        None,
+        &second_key,
+    )
+    .await?;
+
+    let result = initialize_user_vault_with_signer(
+        &rpc_client,
+        Some(Arc::new(Keypair::new())),
+        None,
+        &third_key,
+    )
+    .await;
+    match result {
+        Ok(_) => anyhow::bail!("Scheduled key accepted before its activation"),
+        Err(err) => println!("Scheduled key rejected before activation: {}", err),
+    }
+
+    Ok(())
+}
+
+pub async fn emergency_pause_test(rpc_client: RpcClient) -> anyhow::Result<()> {
+    let client_config = "client_config.ini";
+    let config = load_cfg(&client_config.to_string()).unwrap();
+    let payer = read_keypair_file(&config.payer_path).unwrap();
+
+    let guardian = Keypair::new();
+    log::info!("Running emergency pause scenario");
+
+    // 1. Hand the pause switch to a dedicated guardian
+    let instructions = set_guardian(&config, payer.pubkey(), guardian.pubkey(), None)?;
+    let signature = send_config_instructions(&rpc_client, &instructions, &payer, &[]).await?;
+    println!("Guardian set: {}", signature);
+
+    // 2. The update authority itself cannot pause
+    let instructions = set_pause_state(&config, payer.pubkey(), true, 0)?;
+    match send_config_instructions(&rpc_client, &instructions, &payer, &[]).await {
+        Ok(signature) => anyhow::bail!("Program paused by a non guardian: {}", signature),
+        Err(err) => println!("Non guardian pause rejected: {}", err),
+    }
+
+    // 3. Pausing vaults refuses new user vaults
+    let instructions = set_pause_state(&config, guardian.pubkey(), false, PAUSE_VAULTS)?;
+    let signature =
+        send_config_instructions(&rpc_client, &instructions, &payer, &[&guardian]).await?;
+    println!("Vaults paused: {}", signature);
+
+    let result = initialize_user_vault(&rpc_client, Some(Arc::new(Keypair::new())), None).await;
+    match result {
+        Ok(_) => anyhow::bail!("User vault initialized while vaults are paused"),
+        Err(err) => println!("User vault initialization rejected: {}", err),
+    }
+
+    // 4. Resuming lets vaults be created again
+    let instructions = set_pause_state(&config, guardian.pubkey(), false, 0)?;
+    let signature =
+        send_config_instructions(&rpc_client, &instructions, &payer, &[&guardian]).await?;
+    println!("Vaults resumed: {}", signature);
+
+    initialize_user_vault(&rpc_client, Some(Arc::new(Keypair::new())), None).await?;
+
+    Ok(())
+}
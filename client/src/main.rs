@@ -3,18 +3,44 @@ use std::sync::Arc;
 use anyhow::Result;
 use clap::Parser;
 use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signer::Signer;
 use zk_client::{
     builder::admin::vault_config::{
-        initialize_and_manage_vault_test, initialize_vault_config_test,
+        emergency_pause_test, initialize_and_manage_vault_test, initialize_vault_config_test,
+        multisig_config_admin_test, reject_unregistered_service_signer_test,
+        scoped_service_signer_permissions_test, service_signer_rotation_test,
     },
+    settings::config::load_cfg,
     user::{
+        airdrop_tree::AirdropTree,
+        airdrop_vault::{claim_airdrop, clawback_airdrop, create_airdrop_vault},
         compress_tokens::create_compress_tokens_inx,
         compressed_mint,
         cpda_tokens_transfer::create_user_vaults_transfer,
+        escrow_vault::{
+            accept_escrow_offer, cancel_escrow_offer, create_arbiter_escrow, create_escrow_offer,
+            list_escrow_offers, open_arbiter_dispute, payer_keypair, release_arbiter_escrow,
+            resolve_arbiter_dispute,
+        },
+        mint_metadata::{transfer_mint_metadata_authority, update_mint_metadata},
         server_vault::{initialize_server_vault, update_server_vault},
-        user_vault::{initialize_user_vault, update_user_vault},
+        token_burn::{burn_tokens, user_vault_burn_scenario},
+        token_delegation::{
+            approve_delegate, delegated_transfer, revoke_delegate, user_vault_delegation_scenario,
+            user_vault_token_owner,
+        },
+        token_freeze::{freeze_tokens, thaw_tokens},
+        user_vault::{
+            find_user_vault, initialize_user_vault, migrate_user_vault, update_user_vault,
+        },
+        vault_token_transfer::transfer_user_vault_tokens,
+        vesting_vault::{create_vesting_vault, revoke_vesting, withdraw_vested_tokens},
     },
+    utils::config::{keypair_1, keypair_2, keypair_3},
 };
+use zk_onchain::user::{Token2022Extensions, TransferFeeParams};
+use zk_onchain::vaults::{ArbiterEscrowParams, EscrowVaultParams, VaultType, VestingVaultParams};
 
 #[derive(Debug, Parser)]
 pub struct Opts {
@@ -29,10 +55,195 @@ pub enum SodaCommands {
     InitializeUserVault,
     InitializeServerVault,
     UpdateUserVault,
+    /// Move the legacy user vault of `keypair_1` to the layout with a vault id
+    MigrateUserVault,
     UpdateServerVault,
-    CreateCompressedMint,
+    CreateCompressedMint {
+        /// Keep the freeze authority so token accounts of the mint can be frozen
+        #[clap(long)]
+        freezable: bool,
+        /// Create the metadata mutable so it can be updated later
+        #[clap(long)]
+        mutable: bool,
+        /// Create a Token-2022 mint keeping its metadata in the mint instead of Metaplex
+        #[clap(long)]
+        token_2022: bool,
+        /// Transfer fee of the Token-2022 mint in basis points
+        #[clap(long, requires = "token_2022")]
+        transfer_fee: Option<u16>,
+        /// Cap on the transfer fee of one transfer, no cap without it
+        #[clap(long, requires = "transfer_fee")]
+        maximum_transfer_fee: Option<u64>,
+        /// Make the mint authority PDA the permanent delegate of the Token-2022 mint
+        #[clap(long, requires = "token_2022")]
+        permanent_delegate: bool,
+    },
+    /// Give the payer's mint created with `nonce` before mint configs existed a config
+    MigrateCompressedMint {
+        nonce: u16,
+    },
+    /// Replace the metadata of the payer's mint created with `nonce`
+    UpdateMintMetadata {
+        nonce: u16,
+        name: String,
+        symbol: String,
+        uri: String,
+    },
+    /// Hand the metadata update authority of the payer's mint created with `nonce` to a wallet
+    TransferMintMetadataAuthority {
+        nonce: u16,
+        new_update_authority: Pubkey,
+    },
     CreateCompressTokens,
     TransferCompressedTokens,
+    /// Send tokens held by the token owner PDA of the payer's user vault to a recipient
+    TransferUserVaultTokens {
+        mint: Pubkey,
+        amount: u64,
+        recipient: Pubkey,
+    },
+    #[clap(subcommand)]
+    Escrow(EscrowCommands),
+    #[clap(subcommand)]
+    Airdrop(AirdropCommands),
+    #[clap(subcommand)]
+    Vesting(VestingCommands),
+    #[clap(subcommand)]
+    Delegation(DelegationCommands),
+    #[clap(subcommand)]
+    Freeze(FreezeCommands),
+    #[clap(subcommand)]
+    Burn(BurnCommands),
+}
+
+#[derive(Debug, Parser)]
+pub enum BurnCommands {
+    /// Burn from the payer's user vault
+    User { mint: Pubkey, amount: u64 },
+    /// Burn from the server vault of `keypair_1`
+    Server { mint: Pubkey, amount: u64 },
+    /// Burn from the payer's user vault and check the burn spent the vault's token owner
+    Scenario { mint: Pubkey, amount: u64 },
+}
+
+#[derive(Debug, Parser)]
+pub enum FreezeCommands {
+    /// Freeze the token accounts of `owner` for a freezable mint of the payer, `keypair_2` co-signs
+    Freeze { owner: Pubkey, mint: Pubkey },
+    /// Thaw the frozen token accounts of `owner` for a mint of the payer, `keypair_2` co-signs
+    Thaw { owner: Pubkey, mint: Pubkey },
+}
+
+#[derive(Debug, Parser)]
+pub enum DelegationCommands {
+    /// Let a delegate spend up to `amount` from the payer's user vault
+    Approve {
+        mint: Pubkey,
+        delegate: Pubkey,
+        amount: u64,
+    },
+    /// Revoke the delegations covering `amount` in the payer's user vault
+    Revoke { mint: Pubkey, amount: u64 },
+    /// Approve `keypair_3` from the payer's user vault and revoke it, checking the events
+    Scenario { mint: Pubkey, amount: u64 },
+    /// Spend from the user vault with `vault_id` as the delegate `keypair_3`
+    Transfer {
+        vault_id: Pubkey,
+        mint: Pubkey,
+        amount: u64,
+        recipient: Pubkey,
+    },
+}
+
+#[derive(Debug, Parser)]
+pub enum VestingCommands {
+    /// Grant compressed tokens from the payer, vesting from now into the beneficiary's user vault
+    Create {
+        vesting_id: u64,
+        beneficiary: Pubkey,
+        mint: Pubkey,
+        amount: u64,
+        /// Seconds until the cliff, 0 for none
+        cliff_in: i64,
+        /// Seconds until everything is vested
+        duration: i64,
+        #[clap(long)]
+        revocable: bool,
+    },
+    /// Withdraw what has vested as `keypair_3`
+    Withdraw { grantor: Pubkey, vesting_id: u64 },
+    /// Take back the unvested part of a grant as the payer
+    Revoke { vesting_id: u64 },
+}
+
+#[derive(Debug, Parser)]
+pub enum AirdropCommands {
+    /// Print the root and every proof of a `recipient,amount` CSV as JSON
+    Build { csv: String },
+    /// Fund an airdrop from `keypair_1` for the recipients of a CSV
+    Create {
+        csv: String,
+        mint: Pubkey,
+        /// Seconds recipients have to claim
+        claim_window: i64,
+    },
+    /// Claim for a recipient of the CSV from the airdrop `airdrop_id` of `creator`
+    Claim {
+        csv: String,
+        creator: Pubkey,
+        airdrop_id: Pubkey,
+        recipient: Pubkey,
+    },
+    /// Sweep the unclaimed tokens of the expired airdrop of `keypair_1` back to its server vault
+    Clawback { airdrop_id: Pubkey },
+}
+
+#[derive(Debug, Parser)]
+pub enum EscrowCommands {
+    /// Offer compressed tokens of one mint at a price in another, as the payer
+    Create {
+        offer_id: u64,
+        mint_offered: Pubkey,
+        amount_offered: u64,
+        mint_wanted: Pubkey,
+        /// Units of `mint_wanted` paid per `price_denominator` units offered
+        price_numerator: u64,
+        price_denominator: u64,
+        /// Smallest fill a taker may take, except for the last piece
+        min_fill_amount: u64,
+        /// Seconds until the offer expires
+        expires_in: i64,
+    },
+    /// Fill part or all of an offer as `keypair_3`
+    Accept {
+        maker: Pubkey,
+        offer_id: u64,
+        fill_amount: u64,
+    },
+    /// List open offers for a mint pair, cheapest first
+    List {
+        mint_offered: Pubkey,
+        mint_wanted: Pubkey,
+    },
+    /// Cancel an offer as the payer, or reclaim an expired offer for its maker
+    Cancel { maker: Pubkey, offer_id: u64 },
+    /// Lock a payment for an off-chain delivery as the payer, `keypair_2` arbitrates
+    Trade {
+        trade_id: u64,
+        seller: Pubkey,
+        mint: Pubkey,
+        amount: u64,
+    },
+    /// Release a trade to the seller as the payer
+    Release { trade_id: u64 },
+    /// Dispute a trade as the payer
+    Dispute { buyer: Pubkey, trade_id: u64 },
+    /// Settle a disputed trade as `keypair_2`
+    Resolve {
+        buyer: Pubkey,
+        trade_id: u64,
+        buyer_amount: u64,
+    },
 }
 
 #[derive(Debug, Parser)]
@@ -53,6 +264,16 @@ pub enum VaultConfigScenarios {
         #[clap(value_parser = clap::value_parser!(u8).range(1..=3))]
         num_signers: Option<u8>,
     },
+    /// Check that vault and mint instructions refuse an unregistered service signer
+    RejectUnregisteredSigner,
+    /// Run a 2-of-3 admin proposal, approval and execution sequence
+    MultisigAdmins,
+    /// Check that a service signer is limited to its permission bits
+    ScopedPermissions,
+    /// Run an immediate and a scheduled service signer rotation
+    RotateSigner,
+    /// Check that a paused subsystem refuses its instructions
+    EmergencyPause,
 }
 
 #[derive(Debug, Parser)]
@@ -79,10 +300,8 @@ pub enum ManageServiceSignerCommands {
 async fn main() -> Result<()> {
     env_logger::init();
 
-    let rpc_client = RpcClient::new(
-        "https://devnet.helius-rpc.com/?api-key=16ef3f61-7567-47d9-9c44-edec13422455".to_string(),
-        //"http://127.0.0.1:8899".to_string(),
-    );
+    let config = load_cfg(&"client_config.ini".to_string()).unwrap();
+    let rpc_client = RpcClient::new(config.http_url);
 
     let opts = Opts::parse();
     match opts.command {
@@ -94,6 +313,26 @@ async fn main() -> Result<()> {
             VaultConfigScenarios::InitAndManageSigners { num_signers } => {
                 initialize_and_manage_vault_test(num_signers, rpc_client).await?;
             }
+
+            VaultConfigScenarios::RejectUnregisteredSigner => {
+                reject_unregistered_service_signer_test(rpc_client).await?;
+            }
+
+            VaultConfigScenarios::MultisigAdmins => {
+                multisig_config_admin_test(rpc_client).await?;
+            }
+
+            VaultConfigScenarios::ScopedPermissions => {
+                scoped_service_signer_permissions_test(rpc_client).await?;
+            }
+
+            VaultConfigScenarios::RotateSigner => {
+                service_signer_rotation_test(rpc_client).await?;
+            }
+
+            VaultConfigScenarios::EmergencyPause => {
+                emergency_pause_test(rpc_client).await?;
+            }
         },
         SodaCommands::InitializeUserVault {} => {
             initialize_user_vault(&rpc_client, None, None).await?;
@@ -104,11 +343,52 @@ async fn main() -> Result<()> {
         SodaCommands::UpdateUserVault {} => {
             update_user_vault(rpc_client).await?;
         }
+        SodaCommands::MigrateUserVault {} => {
+            migrate_user_vault(rpc_client).await?;
+        }
         SodaCommands::UpdateServerVault {} => {
             update_server_vault(rpc_client).await?;
         }
-        SodaCommands::CreateCompressedMint {} => {
-            compressed_mint::create_compressed_mint(rpc_client).await?;
+        SodaCommands::CreateCompressedMint {
+            freezable,
+            mutable,
+            token_2022,
+            transfer_fee,
+            maximum_transfer_fee,
+            permanent_delegate,
+        } => {
+            let extensions = token_2022.then(|| Token2022Extensions {
+                transfer_fee: transfer_fee.map(|basis_points| TransferFeeParams {
+                    basis_points,
+                    maximum_fee: maximum_transfer_fee.unwrap_or(u64::MAX),
+                }),
+                permanent_delegate,
+            });
+            compressed_mint::create_compressed_mint(rpc_client, freezable, mutable, extensions)
+                .await?;
+        }
+        SodaCommands::MigrateCompressedMint { nonce } => {
+            compressed_mint::migrate_compressed_mint(rpc_client, nonce).await?;
+        }
+        SodaCommands::UpdateMintMetadata {
+            nonce,
+            name,
+            symbol,
+            uri,
+        } => {
+            update_mint_metadata(&rpc_client, payer_keypair(), nonce, name, symbol, uri).await?;
+        }
+        SodaCommands::TransferMintMetadataAuthority {
+            nonce,
+            new_update_authority,
+        } => {
+            transfer_mint_metadata_authority(
+                &rpc_client,
+                payer_keypair(),
+                nonce,
+                new_update_authority,
+            )
+            .await?;
         }
         SodaCommands::CreateCompressTokens {} => {
             create_compress_tokens_inx(rpc_client).await?;
@@ -116,6 +396,235 @@ async fn main() -> Result<()> {
         SodaCommands::TransferCompressedTokens {} => {
             create_user_vaults_transfer(Arc::new(rpc_client)).await?;
         }
+        SodaCommands::TransferUserVaultTokens {
+            mint,
+            amount,
+            recipient,
+        } => {
+            transfer_user_vault_tokens(&rpc_client, payer_keypair(), mint, amount, recipient)
+                .await?;
+        }
+        SodaCommands::Escrow(command) => match command {
+            EscrowCommands::Create {
+                offer_id,
+                mint_offered,
+                amount_offered,
+                mint_wanted,
+                price_numerator,
+                price_denominator,
+                min_fill_amount,
+                expires_in,
+            } => {
+                let now = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)?
+                    .as_secs() as i64;
+
+                let params = EscrowVaultParams {
+                    server_id: "6345".into(),
+                    server_name: "Soda".into(),
+                    offer_id,
+                    mint_offered,
+                    amount_offered,
+                    mint_wanted,
+                    price_numerator,
+                    price_denominator,
+                    min_fill_amount,
+                    expires_at: now + expires_in,
+                };
+                create_escrow_offer(&rpc_client, payer_keypair(), &keypair_2(), params).await?;
+            }
+            EscrowCommands::Accept {
+                maker,
+                offer_id,
+                fill_amount,
+            } => {
+                accept_escrow_offer(
+                    &rpc_client,
+                    Arc::new(keypair_3()),
+                    maker,
+                    offer_id,
+                    fill_amount,
+                )
+                .await?;
+            }
+            EscrowCommands::List {
+                mint_offered,
+                mint_wanted,
+            } => {
+                for (escrow, offer) in
+                    list_escrow_offers(&rpc_client, mint_offered, mint_wanted).await?
+                {
+                    println!(
+                        "{escrow} maker {} offer {}: {} left at {}/{}, min fill {}",
+                        offer.current_authority,
+                        offer.offer_id,
+                        offer.remaining_offered,
+                        offer.price_numerator,
+                        offer.price_denominator,
+                        offer.min_fill_amount
+                    );
+                }
+            }
+            EscrowCommands::Cancel { maker, offer_id } => {
+                cancel_escrow_offer(&rpc_client, payer_keypair(), maker, offer_id).await?;
+            }
+            EscrowCommands::Trade {
+                trade_id,
+                seller,
+                mint,
+                amount,
+            } => {
+                let arbiter = keypair_2();
+                let params = ArbiterEscrowParams {
+                    trade_id,
+                    seller,
+                    arbiter: arbiter.pubkey(),
+                    mint,
+                    amount,
+                };
+                create_arbiter_escrow(&rpc_client, payer_keypair(), &arbiter, params).await?;
+            }
+            EscrowCommands::Release { trade_id } => {
+                release_arbiter_escrow(&rpc_client, payer_keypair(), trade_id).await?;
+            }
+            EscrowCommands::Dispute { buyer, trade_id } => {
+                open_arbiter_dispute(&rpc_client, payer_keypair(), buyer, trade_id).await?;
+            }
+            EscrowCommands::Resolve {
+                buyer,
+                trade_id,
+                buyer_amount,
+            } => {
+                resolve_arbiter_dispute(
+                    &rpc_client,
+                    Arc::new(keypair_2()),
+                    buyer,
+                    trade_id,
+                    buyer_amount,
+                )
+                .await?;
+            }
+        },
+        SodaCommands::Airdrop(command) => match command {
+            AirdropCommands::Build { csv } => {
+                let tree = AirdropTree::from_csv(&csv)?;
+                println!("{}", serde_json::to_string_pretty(&tree.to_json())?);
+            }
+            AirdropCommands::Create {
+                csv,
+                mint,
+                claim_window,
+            } => {
+                create_airdrop_vault(&rpc_client, &csv, mint, claim_window).await?;
+            }
+            AirdropCommands::Claim {
+                csv,
+                creator,
+                airdrop_id,
+                recipient,
+            } => {
+                claim_airdrop(&rpc_client, &csv, creator, airdrop_id, recipient).await?;
+            }
+            AirdropCommands::Clawback { airdrop_id } => {
+                clawback_airdrop(&rpc_client, airdrop_id).await?;
+            }
+        },
+        SodaCommands::Vesting(command) => match command {
+            VestingCommands::Create {
+                vesting_id,
+                beneficiary,
+                mint,
+                amount,
+                cliff_in,
+                duration,
+                revocable,
+            } => {
+                let now = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)?
+                    .as_secs() as i64;
+                let (_, beneficiary_vault, _) = find_user_vault(beneficiary).await?;
+
+                let params = VestingVaultParams {
+                    vesting_id,
+                    beneficiary,
+                    beneficiary_vault_id: beneficiary_vault.vault_id,
+                    mint,
+                    total_amount: amount,
+                    start_at: now,
+                    cliff_at: now + cliff_in,
+                    end_at: now + duration,
+                    revocable,
+                };
+                create_vesting_vault(&rpc_client, payer_keypair(), &keypair_2(), params).await?;
+            }
+            VestingCommands::Withdraw {
+                grantor,
+                vesting_id,
+            } => {
+                withdraw_vested_tokens(&rpc_client, Arc::new(keypair_3()), grantor, vesting_id)
+                    .await?;
+            }
+            VestingCommands::Revoke { vesting_id } => {
+                revoke_vesting(&rpc_client, payer_keypair(), vesting_id).await?;
+            }
+        },
+        SodaCommands::Delegation(command) => match command {
+            DelegationCommands::Approve {
+                mint,
+                delegate,
+                amount,
+            } => {
+                approve_delegate(&rpc_client, payer_keypair(), mint, delegate, amount).await?;
+            }
+            DelegationCommands::Revoke { mint, amount } => {
+                revoke_delegate(&rpc_client, payer_keypair(), mint, amount).await?;
+            }
+            DelegationCommands::Scenario { mint, amount } => {
+                user_vault_delegation_scenario(&rpc_client, payer_keypair(), mint, amount).await?;
+            }
+            DelegationCommands::Transfer {
+                vault_id,
+                mint,
+                amount,
+                recipient,
+            } => {
+                delegated_transfer(
+                    &rpc_client,
+                    Arc::new(keypair_3()),
+                    user_vault_token_owner(vault_id),
+                    mint,
+                    amount,
+                    recipient,
+                )
+                .await?;
+            }
+        },
+        SodaCommands::Freeze(command) => match command {
+            FreezeCommands::Freeze { owner, mint } => {
+                freeze_tokens(&rpc_client, payer_keypair(), &keypair_2(), owner, mint).await?;
+            }
+            FreezeCommands::Thaw { owner, mint } => {
+                thaw_tokens(&rpc_client, payer_keypair(), &keypair_2(), owner, mint).await?;
+            }
+        },
+        SodaCommands::Burn(command) => match command {
+            BurnCommands::User { mint, amount } => {
+                burn_tokens(&rpc_client, payer_keypair(), VaultType::User, mint, amount).await?;
+            }
+            BurnCommands::Server { mint, amount } => {
+                burn_tokens(
+                    &rpc_client,
+                    Arc::new(keypair_1()),
+                    VaultType::Server,
+                    mint,
+                    amount,
+                )
+                .await?;
+            }
+            BurnCommands::Scenario { mint, amount } => {
+                user_vault_burn_scenario(&rpc_client, payer_keypair(), mint, amount).await?;
+            }
+        },
     }
 
     Ok(())
@@ -1,4 +1,5 @@
 use configparser::ini::Ini;
+use photon_api::apis::configuration::{ApiKey, Configuration};
 
 pub fn load_cfg(client_config: &String) -> eyre::Result<ClientConfig> {
     let mut config = Ini::new();
@@ -32,6 +33,13 @@ pub fn load_cfg(client_config: &String) -> eyre::Result<ClientConfig> {
         panic!("admin_path must not be empty");
     }
 
+    let photon_url = config.get("Global", "photon_url").unwrap();
+    if photon_url.is_empty() {
+        panic!("photon_url must not be empty");
+    }
+
+    let photon_api_key = config.get("Global", "photon_api_key").unwrap_or_default();
+
     Ok(ClientConfig {
         http_url,
         ws_url,
@@ -39,6 +47,8 @@ pub fn load_cfg(client_config: &String) -> eyre::Result<ClientConfig> {
         queue_keypair,
         merkle_keypair,
         admin_path,
+        photon_url,
+        photon_api_key,
     })
 }
 
@@ -50,4 +60,20 @@ pub struct ClientConfig {
     pub admin_path: String,
     pub queue_keypair: String,
     pub merkle_keypair: String,
+    pub photon_url: String,
+    /// Left empty for an indexer that needs no key
+    pub photon_api_key: String,
+}
+
+/// Photon indexer configuration from `client_config.ini`
+pub fn photon_config() -> Configuration {
+    let config = load_cfg(&"client_config.ini".to_string()).unwrap();
+    Configuration {
+        base_path: config.photon_url,
+        api_key: (!config.photon_api_key.is_empty()).then(|| ApiKey {
+            prefix: None,
+            key: config.photon_api_key,
+        }),
+        ..Configuration::default()
+    }
 }
@@ -0,0 +1,213 @@
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use anyhow::Result;
+use solana_sdk::bs58;
+use solana_sdk::pubkey::Pubkey;
+use zk_onchain::vaults::{airdrop_leaf, airdrop_node};
+
+/// Merkle tree over `(recipient, amount)` leaves, hashed the way `claim_airdrop` verifies them
+pub struct AirdropTree {
+    pub leaves: Vec<(Pubkey, u64)>,
+    layers: Vec<Vec<[u8; 32]>>,
+}
+
+impl AirdropTree {
+    pub fn new(leaves: Vec<(Pubkey, u64)>) -> Result<Self> {
+        if leaves.is_empty() {
+            return Err(anyhow::anyhow!("Airdrop has no recipients"));
+        }
+
+        // A recipient has a single claim receipt, so it can only appear once
+        let mut recipients = HashSet::new();
+        for (recipient, amount) in &leaves {
+            if *amount == 0 {
+                return Err(anyhow::anyhow!("{recipient} has a zero amount"));
+            }
+            if !recipients.insert(*recipient) {
+                return Err(anyhow::anyhow!("{recipient} is listed more than once"));
+            }
+        }
+
+        let mut layers = vec![leaves
+            .iter()
+            .map(|(recipient, amount)| airdrop_leaf(recipient, *amount))
+            .collect::<Vec<_>>()];
+
+        while layers.last().unwrap().len() > 1 {
+            // An odd node moves up unchanged
+            let next = layers
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| match pair {
+                    [a, b] => airdrop_node(a, b),
+                    [a] => *a,
+                    _ => unreachable!(),
+                })
+                .collect();
+            layers.push(next);
+        }
+
+        Ok(Self { leaves, layers })
+    }
+
+    /// Reads `recipient,amount` lines. Blank lines and a `recipient,amount` header are skipped.
+    pub fn from_csv(path: &str) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+
+        let mut leaves = Vec::new();
+        for (line_number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || (line_number == 0 && line.starts_with("recipient")) {
+                continue;
+            }
+
+            let (recipient, amount) = line.split_once(',').ok_or_else(|| {
+                anyhow::anyhow!("Line {}: expected recipient,amount", line_number + 1)
+            })?;
+            leaves.push((
+                Pubkey::from_str(recipient.trim())?,
+                amount.trim().parse::<u64>()?,
+            ));
+        }
+
+        Self::new(leaves)
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        self.layers.last().unwrap()[0]
+    }
+
+    pub fn total_amount(&self) -> u64 {
+        self.leaves.iter().map(|(_, amount)| amount).sum()
+    }
+
+    /// Amount and proof of `recipient`, if it is in the airdrop
+    pub fn proof(&self, recipient: &Pubkey) -> Option<(u64, Vec<[u8; 32]>)> {
+        let mut index = self
+            .leaves
+            .iter()
+            .position(|(leaf_recipient, _)| leaf_recipient == recipient)?;
+        let amount = self.leaves[index].1;
+
+        let mut proof = Vec::new();
+        for layer in &self.layers[..self.layers.len() - 1] {
+            if let Some(sibling) = layer.get(index ^ 1) {
+                proof.push(*sibling);
+            }
+            index /= 2;
+        }
+
+        Some((amount, proof))
+    }
+
+    /// Root and every recipient's proof as JSON, for publishing next to the airdrop
+    pub fn to_json(&self) -> serde_json::Value {
+        let claims: serde_json::Map<String, serde_json::Value> = self
+            .leaves
+            .iter()
+            .map(|(recipient, _)| {
+                let (amount, proof) = self.proof(recipient).unwrap();
+                (
+                    recipient.to_string(),
+                    serde_json::json!({
+                        "amount": amount,
+                        "proof": proof
+                            .iter()
+                            .map(|node| bs58::encode(node).into_string())
+                            .collect::<Vec<_>>(),
+                    }),
+                )
+            })
+            .collect();
+
+        serde_json::json!({
+            "root": bs58::encode(self.root()).into_string(),
+            "total_amount": self.total_amount(),
+            "claims": claims,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zk_onchain::vaults::verify_airdrop_proof;
+
+    fn recipients(count: usize) -> Vec<(Pubkey, u64)> {
+        (0..count)
+            .map(|amount| (Pubkey::new_unique(), amount as u64 + 1))
+            .collect()
+    }
+
+    #[test]
+    fn every_proof_verifies_against_the_root() {
+        for count in 1..=9 {
+            let tree = AirdropTree::new(recipients(count)).unwrap();
+
+            for (recipient, amount) in &tree.leaves {
+                let (proof_amount, proof) = tree.proof(recipient).unwrap();
+                assert_eq!(proof_amount, *amount);
+                assert!(verify_airdrop_proof(
+                    &tree.root(),
+                    airdrop_leaf(recipient, proof_amount),
+                    &proof
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn proof_does_not_verify_another_amount() {
+        let tree = AirdropTree::new(recipients(5)).unwrap();
+        let (recipient, amount) = tree.leaves[2];
+        let (_, proof) = tree.proof(&recipient).unwrap();
+
+        assert!(!verify_airdrop_proof(
+            &tree.root(),
+            airdrop_leaf(&recipient, amount + 1),
+            &proof
+        ));
+    }
+
+    #[test]
+    fn unknown_recipient_has_no_proof() {
+        let tree = AirdropTree::new(recipients(3)).unwrap();
+
+        assert!(tree.proof(&Pubkey::new_unique()).is_none());
+    }
+
+    #[test]
+    fn total_amount_sums_the_leaves() {
+        let tree = AirdropTree::new(recipients(4)).unwrap();
+
+        assert_eq!(tree.total_amount(), 1 + 2 + 3 + 4);
+    }
+
+    #[test]
+    fn invalid_leaves_are_rejected() {
+        let recipient = Pubkey::new_unique();
+
+        assert!(AirdropTree::new(Vec::new()).is_err());
+        assert!(AirdropTree::new(vec![(recipient, 0)]).is_err());
+        assert!(AirdropTree::new(vec![(recipient, 1), (recipient, 2)]).is_err());
+    }
+
+    #[test]
+    fn csv_header_and_blank_lines_are_skipped() {
+        let first = Pubkey::new_unique();
+        let second = Pubkey::new_unique();
+        let path = std::env::temp_dir().join(format!("airdrop-{first}.csv"));
+        std::fs::write(
+            &path,
+            format!("recipient,amount\n{first}, 10\n\n{second},20\n"),
+        )
+        .unwrap();
+
+        let tree = AirdropTree::from_csv(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(tree.leaves, vec![(first, 10), (second, 20)]);
+    }
+}
@@ -0,0 +1,607 @@
+use anchor_lang::AnchorDeserialize;
+use light_compressed_token::process_transfer::get_cpi_authority_pda as get_token_cpi_authority_pda;
+use light_sdk::address::derive_address;
+use light_sdk::address::derive_address_seed;
+use light_sdk::merkle_context::pack_address_merkle_context;
+use light_sdk::merkle_context::pack_merkle_context;
+use light_sdk::merkle_context::AddressMerkleContext;
+use light_sdk::merkle_context::MerkleContext;
+use light_sdk::merkle_context::RemainingAccounts;
+use light_sdk::verify::find_cpi_signer;
+use light_sdk::PROGRAM_ID_ACCOUNT_COMPRESSION;
+use light_sdk::PROGRAM_ID_LIGHT_SYSTEM;
+
+use photon_api::apis::default_api::get_compressed_accounts_by_owner_post;
+use photon_api::apis::default_api::get_validity_proof_post;
+use photon_api::models::GetCompressedAccountsByOwnerPostRequest;
+use photon_api::models::GetCompressedAccountsByOwnerPostRequestParams;
+use photon_api::models::{GetValidityProofPostRequest, GetValidityProofPostRequestParams};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcSendTransactionConfig;
+use solana_sdk::bs58;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::signature::{Keypair, Signature};
+use solana_sdk::transaction::Transaction;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::settings::config::load_cfg;
+use crate::settings::config::photon_config;
+use crate::utils::config::keypair_1;
+use crate::utils::config::keypair_2;
+use anchor_client::{Client, Cluster};
+use anyhow::Result;
+
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::read_keypair_file;
+use solana_sdk::signer::Signer;
+use solana_sdk::system_program;
+use zk_onchain::state::*;
+use zk_onchain::vaults::{AirdropVaultParams, AirdropVaultState, ServiceSignerEntry};
+use zk_onchain::{accounts as soda_accounts, instruction as soda_instructions};
+
+use super::airdrop_tree::AirdropTree;
+use super::server_vault::{get_program_addresses, get_proof};
+use super::token_inputs::fetch_token_inputs;
+
+pub fn derive_airdrop_vault(
+    creator: Pubkey,
+    airdrop_id: Pubkey,
+    address_merkle_context: AddressMerkleContext,
+) -> [u8; 32] {
+    let address_seed = derive_address_seed(
+        &[
+            AIRDROP_VAULT.as_bytes(),
+            creator.as_ref(),
+            airdrop_id.as_ref(),
+        ],
+        &zk_onchain::ID,
+    );
+
+    derive_address(&address_seed, &address_merkle_context)
+}
+
+pub fn derive_airdrop_claim(
+    token_authority: Pubkey,
+    recipient: Pubkey,
+    address_merkle_context: AddressMerkleContext,
+) -> [u8; 32] {
+    let address_seed = derive_address_seed(
+        &[
+            AIRDROP_CLAIM.as_bytes(),
+            token_authority.as_ref(),
+            recipient.as_ref(),
+        ],
+        &zk_onchain::ID,
+    );
+
+    derive_address(&address_seed, &address_merkle_context)
+}
+
+fn address_merkle_context() -> Result<AddressMerkleContext> {
+    Ok(AddressMerkleContext {
+        address_merkle_tree_pubkey: Pubkey::from_str(
+            "amt1Ayt45jfbdw5YSo7iz6WZxUmnZsQTYXy82hVwyC2",
+        )?,
+        address_queue_pubkey: Pubkey::from_str("aq1S9z4reTSQAdgWHGD2zDaS39sjGrAxbR31vxJ2F4F")?,
+    })
+}
+
+/// Fetch the airdrop `airdrop_id` of `creator` with its account hash and raw data
+pub async fn get_airdrop_vault(
+    creator: Pubkey,
+    airdrop_id: Pubkey,
+) -> Result<(String, AirdropVaultState, Vec<u8>)> {
+    let airdrop_vault = bs58::encode(derive_airdrop_vault(
+        creator,
+        airdrop_id,
+        address_merkle_context()?,
+    ))
+    .into_string();
+
+    let compressed_accounts = get_compressed_accounts_by_owner_post(
+        &photon_config(),
+        GetCompressedAccountsByOwnerPostRequest {
+            params: Box::new(GetCompressedAccountsByOwnerPostRequestParams {
+                owner: zk_onchain::ID.to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    let item = compressed_accounts
+        .result
+        .map(|result| result.value.items)
+        .unwrap_or_default()
+        .into_iter()
+        .find(|item| item.address.as_deref() == Some(airdrop_vault.as_str()))
+        .ok_or_else(|| anyhow::anyhow!("Airdrop vault {airdrop_vault} not found"))?;
+
+    let data = base64::decode(item.data.clone().unwrap().data)?;
+    let state = AirdropVaultState::try_from_slice(&data)?;
+
+    Ok((item.hash, state, data))
+}
+
+/// Create an airdrop vault for the leaves of `tree`, funded by `authority`.
+/// Unclaimed tokens can be clawed back to `authority` after `claim_deadline`.
+pub async fn create_airdrop_vault_instructions(
+    payer: Arc<Keypair>,
+    authority: Pubkey,
+    service_signer: Pubkey,
+    tree: &AirdropTree,
+    mint: Pubkey,
+    airdrop_id: Pubkey,
+    claim_deadline: i64,
+) -> Result<Vec<Instruction>> {
+    let merkle_tree_pubkey = Pubkey::from_str("smt1NamzXdq4AMqS2fS2F1i5KTYPZRhoHgWx38d8WsT")?;
+    let nullifier_queue_pubkey = Pubkey::from_str("nfq1NvQDJ2GEgnS8zt9prAe8rjjpAW1zFkrvZoBR148")?;
+
+    let mut remaining_accounts = RemainingAccounts::default();
+
+    let address_merkle_context = address_merkle_context()?;
+    let airdrop_vault = bs58::encode(derive_airdrop_vault(
+        authority,
+        airdrop_id,
+        address_merkle_context,
+    ))
+    .into_string();
+    let merkle_context = pack_merkle_context(
+        MerkleContext {
+            merkle_tree_pubkey,
+            nullifier_queue_pubkey,
+            leaf_index: 0,
+            queue_index: None,
+        },
+        &mut remaining_accounts,
+    );
+    let address_merkle_context =
+        pack_address_merkle_context(address_merkle_context, &mut remaining_accounts);
+
+    let proof_result = get_validity_proof_post(
+        &photon_config(),
+        GetValidityProofPostRequest {
+            params: Box::new(GetValidityProofPostRequestParams {
+                new_addresses: Some(vec![airdrop_vault]),
+                new_addresses_with_trees: None,
+                hashes: None,
+            }),
+            ..Default::default()
+        },
+    )
+    .await?;
+    let (compressed_proof, address_root_index) = get_proof(proof_result).await?;
+
+    let funder_inputs = fetch_token_inputs(
+        authority,
+        mint,
+        tree.total_amount(),
+        &mut remaining_accounts,
+    )
+    .await?;
+
+    let client_config = "client_config.ini";
+    let config = load_cfg(&client_config.to_string()).unwrap();
+    let client = Client::new_with_options(
+        Cluster::Custom(config.http_url.clone(), config.ws_url.clone()),
+        payer.clone(),
+        CommitmentConfig::processed(),
+    );
+    let program = client.program(zk_onchain::id())?;
+
+    let (config_pda, registered_program_pda, account_compression_authority) =
+        get_program_addresses()?;
+
+    let mut instructions = program
+        .request()
+        .accounts(soda_accounts::InitializeAirdropVault {
+            payer: payer.pubkey(),
+            self_program: zk_onchain::id(),
+            service_signer,
+            current_authority: authority,
+            airdrop_id,
+            cpi_signer: find_cpi_signer(&zk_onchain::ID),
+            airdrop_token_authority: AirdropVaultState::find_token_authority(
+                &authority,
+                &airdrop_id,
+            )
+            .0,
+            config: config_pda,
+            service_signer_entry: ServiceSignerEntry::find_address(&config_pda, &service_signer).0,
+            compressed_token_cpi_authority_pda: get_token_cpi_authority_pda().0,
+            compressed_token_program: light_compressed_token::ID,
+            /* Light Accounts */
+            system_program: system_program::id(),
+            light_system_program: PROGRAM_ID_LIGHT_SYSTEM,
+            account_compression_program: PROGRAM_ID_ACCOUNT_COMPRESSION,
+            registered_program_pda,
+            noop_program: light_sdk::PROGRAM_ID_NOOP,
+            account_compression_authority,
+        })
+        .args(soda_instructions::InitializeAirdropVault {
+            proof: compressed_proof,
+            inputs: Vec::new(),
+            merkle_context,
+            address_merkle_context,
+            address_merkle_tree_root_index: address_root_index,
+            merkle_tree_root_index: 0,
+            params: AirdropVaultParams {
+                server_id: "6345".into(),
+                server_name: "Soda".into(),
+                mint,
+                merkle_root: tree.root(),
+                total_amount: tree.total_amount(),
+                claim_deadline,
+                clawback_destination: authority,
+            },
+            funder_inputs,
+        })
+        .instructions()?;
+
+    instructions[0]
+        .accounts
+        .extend(remaining_accounts.to_account_metas());
+
+    Ok(instructions)
+}
+
+/// Claim the leaf of `recipient` from the airdrop `airdrop_id` of `creator`. Anyone may pay for it.
+pub async fn claim_airdrop_instructions(
+    payer: Arc<Keypair>,
+    creator: Pubkey,
+    airdrop_id: Pubkey,
+    recipient: Pubkey,
+    tree: &AirdropTree,
+) -> Result<Vec<Instruction>> {
+    let merkle_tree_pubkey = Pubkey::from_str("smt1NamzXdq4AMqS2fS2F1i5KTYPZRhoHgWx38d8WsT")?;
+    let nullifier_queue_pubkey = Pubkey::from_str("nfq1NvQDJ2GEgnS8zt9prAe8rjjpAW1zFkrvZoBR148")?;
+
+    let (amount, merkle_proof) = tree
+        .proof(&recipient)
+        .ok_or_else(|| anyhow::anyhow!("{recipient} is not in the airdrop"))?;
+
+    let (vault_hash, airdrop_vault, vault_data) = get_airdrop_vault(creator, airdrop_id).await?;
+    if airdrop_vault.merkle_root != tree.root() {
+        return Err(anyhow::anyhow!("CSV does not match the airdrop root"));
+    }
+    let (token_authority, _) = AirdropVaultState::find_token_authority(&creator, &airdrop_id);
+
+    let mut remaining_accounts = RemainingAccounts::default();
+
+    let address_merkle_context = address_merkle_context()?;
+    let claim_receipt = bs58::encode(derive_airdrop_claim(
+        token_authority,
+        recipient,
+        address_merkle_context,
+    ))
+    .into_string();
+    let address_merkle_context =
+        pack_address_merkle_context(address_merkle_context, &mut remaining_accounts);
+
+    // One proof for the vault being updated and the receipt address being created
+    let proof_result = get_validity_proof_post(
+        &photon_config(),
+        GetValidityProofPostRequest {
+            params: Box::new(GetValidityProofPostRequestParams {
+                new_addresses: Some(vec![claim_receipt]),
+                new_addresses_with_trees: None,
+                hashes: Some(vec![vault_hash]),
+            }),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    let proof_value = proof_result
+        .result
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("No validity proof returned"))?
+        .value;
+    // Input hash roots come first, then the new address roots
+    let root_indices = (
+        proof_value.root_indices[0] as u16,
+        proof_value.root_indices[1] as u16,
+    );
+    let merkle_context = pack_merkle_context(
+        MerkleContext {
+            merkle_tree_pubkey,
+            nullifier_queue_pubkey,
+            leaf_index: proof_value.leaf_indices[0] as u32,
+            queue_index: None,
+        },
+        &mut remaining_accounts,
+    );
+    let (compressed_proof, _) = get_proof(proof_result).await?;
+
+    let vault_inputs = fetch_token_inputs(
+        token_authority,
+        airdrop_vault.mint,
+        amount,
+        &mut remaining_accounts,
+    )
+    .await?;
+
+    let client_config = "client_config.ini";
+    let config = load_cfg(&client_config.to_string()).unwrap();
+    let client = Client::new_with_options(
+        Cluster::Custom(config.http_url.clone(), config.ws_url.clone()),
+        payer.clone(),
+        CommitmentConfig::processed(),
+    );
+    let program = client.program(zk_onchain::id())?;
+
+    let (config_pda, registered_program_pda, account_compression_authority) =
+        get_program_addresses()?;
+
+    let mut instructions = program
+        .request()
+        .accounts(soda_accounts::ClaimAirdrop {
+            payer: payer.pubkey(),
+            self_program: zk_onchain::id(),
+            cpi_signer: find_cpi_signer(&zk_onchain::ID),
+            recipient,
+            airdrop_creator: creator,
+            airdrop_id,
+            airdrop_token_authority: token_authority,
+            config: config_pda,
+            compressed_token_cpi_authority_pda: get_token_cpi_authority_pda().0,
+            compressed_token_program: light_compressed_token::ID,
+            /* Light Accounts */
+            system_program: system_program::id(),
+            light_system_program: PROGRAM_ID_LIGHT_SYSTEM,
+            account_compression_program: PROGRAM_ID_ACCOUNT_COMPRESSION,
+            registered_program_pda,
+            noop_program: light_sdk::PROGRAM_ID_NOOP,
+            account_compression_authority,
+        })
+        .args(soda_instructions::ClaimAirdrop {
+            proof: compressed_proof,
+            inputs: vec![vault_data],
+            merkle_context,
+            address_merkle_context,
+            address_merkle_tree_root_index: root_indices.1,
+            merkle_tree_root_index: root_indices.0,
+            amount,
+            merkle_proof,
+            vault_inputs,
+        })
+        .instructions()?;
+
+    instructions[0]
+        .accounts
+        .extend(remaining_accounts.to_account_metas());
+
+    Ok(instructions)
+}
+
+/// Sweep the unclaimed tokens of an expired airdrop of `creator` to its clawback destination.
+/// `authority` is the airdrop's current authority and signs.
+pub async fn clawback_airdrop_instructions(
+    payer: Arc<Keypair>,
+    creator: Pubkey,
+    airdrop_id: Pubkey,
+    authority: Pubkey,
+) -> Result<Vec<Instruction>> {
+    let merkle_tree_pubkey = Pubkey::from_str("smt1NamzXdq4AMqS2fS2F1i5KTYPZRhoHgWx38d8WsT")?;
+    let nullifier_queue_pubkey = Pubkey::from_str("nfq1NvQDJ2GEgnS8zt9prAe8rjjpAW1zFkrvZoBR148")?;
+
+    let (vault_hash, airdrop_vault, vault_data) = get_airdrop_vault(creator, airdrop_id).await?;
+    let (token_authority, _) = AirdropVaultState::find_token_authority(&creator, &airdrop_id);
+
+    let mut remaining_accounts = RemainingAccounts::default();
+    let address_merkle_context =
+        pack_address_merkle_context(address_merkle_context()?, &mut remaining_accounts);
+
+    let proof_result = get_validity_proof_post(
+        &photon_config(),
+        GetValidityProofPostRequest {
+            params: Box::new(GetValidityProofPostRequestParams {
+                new_addresses: None,
+                new_addresses_with_trees: None,
+                hashes: Some(vec![vault_hash]),
+            }),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    let proof_value = proof_result
+        .result
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("No validity proof returned"))?
+        .value;
+    let merkle_context = pack_merkle_context(
+        MerkleContext {
+            merkle_tree_pubkey,
+            nullifier_queue_pubkey,
+            leaf_index: proof_value.leaf_indices[0] as u32,
+            queue_index: None,
+        },
+        &mut remaining_accounts,
+    );
+    let (compressed_proof, root_index) = get_proof(proof_result).await?;
+
+    let vault_inputs = fetch_token_inputs(
+        token_authority,
+        airdrop_vault.mint,
+        airdrop_vault.unclaimed_amount(),
+        &mut remaining_accounts,
+    )
+    .await?;
+
+    let client_config = "client_config.ini";
+    let config = load_cfg(&client_config.to_string()).unwrap();
+    let client = Client::new_with_options(
+        Cluster::Custom(config.http_url.clone(), config.ws_url.clone()),
+        payer.clone(),
+        CommitmentConfig::processed(),
+    );
+    let program = client.program(zk_onchain::id())?;
+
+    let (config_pda, registered_program_pda, account_compression_authority) =
+        get_program_addresses()?;
+
+    let mut instructions = program
+        .request()
+        .accounts(soda_accounts::ClawbackAirdrop {
+            payer: payer.pubkey(),
+            self_program: zk_onchain::id(),
+            current_authority: authority,
+            airdrop_creator: creator,
+            airdrop_id,
+            cpi_signer: find_cpi_signer(&zk_onchain::ID),
+            airdrop_token_authority: token_authority,
+            config: config_pda,
+            compressed_token_cpi_authority_pda: get_token_cpi_authority_pda().0,
+            compressed_token_program: light_compressed_token::ID,
+            /* Light Accounts */
+            system_program: system_program::id(),
+            light_system_program: PROGRAM_ID_LIGHT_SYSTEM,
+            account_compression_program: PROGRAM_ID_ACCOUNT_COMPRESSION,
+            registered_program_pda,
+            noop_program: light_sdk::PROGRAM_ID_NOOP,
+            account_compression_authority,
+        })
+        .args(soda_instructions::ClawbackAirdrop {
+            proof: compressed_proof,
+            inputs: vec![vault_data],
+            merkle_context,
+            address_merkle_context,
+            address_merkle_tree_root_index: 0,
+            merkle_tree_root_index: root_index,
+            vault_inputs,
+        })
+        .instructions()?;
+
+    instructions[0]
+        .accounts
+        .extend(remaining_accounts.to_account_metas());
+
+    Ok(instructions)
+}
+
+async fn send_airdrop_instructions(
+    rpc_client: &RpcClient,
+    instructions: Vec<Instruction>,
+    signers: &[&Keypair],
+) -> Result<Signature> {
+    let mut all_instructions = vec![
+        ComputeBudgetInstruction::set_compute_unit_limit(1_000_000),
+        ComputeBudgetInstruction::set_compute_unit_price(100000),
+    ];
+    all_instructions.extend(instructions);
+
+    let recent_blockhash = rpc_client.get_latest_blockhash().await?;
+    let transaction = Transaction::new_signed_with_payer(
+        &all_instructions,
+        Some(&signers[0].pubkey()),
+        signers,
+        recent_blockhash,
+    );
+
+    Ok(rpc_client
+        .send_and_confirm_transaction_with_spinner_and_config(
+            &transaction,
+            CommitmentConfig::processed(),
+            RpcSendTransactionConfig {
+                skip_preflight: true,
+                ..Default::default()
+            },
+        )
+        .await?)
+}
+
+/// Create and fund an airdrop from a CSV. `keypair_1` is the server authority and
+/// `keypair_2` the service signer.
+pub async fn create_airdrop_vault(
+    rpc_client: &RpcClient,
+    csv_path: &str,
+    mint: Pubkey,
+    claim_window: i64,
+) -> Result<()> {
+    let client_config = "client_config.ini";
+    let config = load_cfg(&client_config.to_string()).unwrap();
+    let payer = Arc::new(read_keypair_file(&config.payer_path).unwrap());
+    let authority = keypair_1();
+    let service_signer = keypair_2();
+
+    let tree = AirdropTree::from_csv(csv_path)?;
+    let airdrop_id = Keypair::new().pubkey();
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs() as i64;
+
+    let instructions = create_airdrop_vault_instructions(
+        payer.clone(),
+        authority.pubkey(),
+        service_signer.pubkey(),
+        &tree,
+        mint,
+        airdrop_id,
+        now + claim_window,
+    )
+    .await?;
+
+    let signature = send_airdrop_instructions(
+        rpc_client,
+        instructions,
+        &[&payer, &authority, &service_signer],
+    )
+    .await?;
+
+    println!(
+        "Airdrop {airdrop_id} created for {} recipients, root {}: {signature}",
+        tree.leaves.len(),
+        bs58::encode(tree.root()).into_string()
+    );
+    Ok(())
+}
+
+/// Claim for `recipient` from the airdrop `airdrop_id` of `creator`, with the payer keypair
+/// as fee payer
+pub async fn claim_airdrop(
+    rpc_client: &RpcClient,
+    csv_path: &str,
+    creator: Pubkey,
+    airdrop_id: Pubkey,
+    recipient: Pubkey,
+) -> Result<()> {
+    let client_config = "client_config.ini";
+    let config = load_cfg(&client_config.to_string()).unwrap();
+    let payer = Arc::new(read_keypair_file(&config.payer_path).unwrap());
+
+    let tree = AirdropTree::from_csv(csv_path)?;
+    let instructions =
+        claim_airdrop_instructions(payer.clone(), creator, airdrop_id, recipient, &tree).await?;
+
+    let signature = send_airdrop_instructions(rpc_client, instructions, &[&payer]).await?;
+
+    println!("Airdrop claimed for {recipient}: {signature}");
+    Ok(())
+}
+
+/// Claw back the unclaimed tokens of the expired airdrop `airdrop_id` of `keypair_1`
+pub async fn clawback_airdrop(rpc_client: &RpcClient, airdrop_id: Pubkey) -> Result<()> {
+    let client_config = "client_config.ini";
+    let config = load_cfg(&client_config.to_string()).unwrap();
+    let payer = Arc::new(read_keypair_file(&config.payer_path).unwrap());
+    let authority = keypair_1();
+
+    let instructions = clawback_airdrop_instructions(
+        payer.clone(),
+        authority.pubkey(),
+        airdrop_id,
+        authority.pubkey(),
+    )
+    .await?;
+
+    let signature =
+        send_airdrop_instructions(rpc_client, instructions, &[&payer, &authority]).await?;
+
+    println!("Airdrop clawed back: {signature}");
+    Ok(())
+}
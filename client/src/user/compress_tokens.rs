@@ -21,11 +21,15 @@ use solana_sdk::{
 };
 use std::sync::Arc;
 use zk_onchain::{
-    accounts as soda_accounts, instruction as soda_instructions, state::MINT_AUTHORITY,
-    user::COMPRESSED_MINT_SEED,
+    accounts as soda_accounts, instruction as soda_instructions,
+    user::{find_freeze_authority, find_mint_authority, MintConfig, COMPRESSED_MINT_SEED},
+    vaults::ServiceSignerEntry,
 };
 
-use crate::{settings::config::load_cfg, utils::config::keypair_1};
+use crate::{
+    settings::config::load_cfg, user::compressed_mint::default_mint_limits,
+    utils::config::keypair_1,
+};
 
 use super::user_vault::get_program_addresses;
 
@@ -46,8 +50,7 @@ pub async fn compress_tokens_inx(
     );
 
     // authority
-    let (pda_authority, _) =
-        Pubkey::find_program_address(&[&MINT_AUTHORITY.as_bytes()], &zk_onchain::ID);
+    let (pda_authority, _) = find_mint_authority(&program.payer(), nonce);
 
     let associated_account = get_associated_token_address(&pda_authority, &derived_mint);
 
@@ -60,7 +63,8 @@ pub async fn compress_tokens_inx(
         &mpl_token_metadata::programs::MPL_TOKEN_METADATA_ID,
     );
 
-    let (_, registered_program_pda, account_compression_authority) = get_program_addresses()?;
+    let (config_pda, registered_program_pda, account_compression_authority) =
+        get_program_addresses()?;
 
     let struct_data = CompressedTokenInstructionDataTransfer {
         proof: None, // Correctly initialized as None
@@ -91,10 +95,15 @@ pub async fn compress_tokens_inx(
         .accounts(soda_accounts::CreateCompressedMint {
             payer: program.payer(),
             service_signer,
+            config: config_pda,
+            service_signer_entry: ServiceSignerEntry::find_address(&config_pda, &service_signer).0,
             authority: pda_authority,
+            freeze_authority: find_freeze_authority(&program.payer(), nonce).0,
             cpi_authority_pda: get_cpi_authority_pda().0,
             token_pool_pda: get_token_pool_pda(&derived_mint),
             compressed_mint: derived_mint,
+            mint: derived_mint,
+            mint_config: MintConfig::find_address(&derived_mint).0,
             metadata_account,
             compressed_token_program: light_compressed_token::id(),
             token_program: spl_token::id(),
@@ -108,6 +117,9 @@ pub async fn compress_tokens_inx(
             decimals: 6,
             uri: "URI".into(),
             nonce,
+            freezable: false,
+            limits: default_mint_limits(),
+            is_mutable: false,
         })
         .instructions()?;
 
@@ -129,6 +141,11 @@ pub async fn compress_tokens_inx(
         .accounts(soda_accounts::CompressTokens {
             payer: program.payer(),
             service_signer,
+            config: config_pda,
+            service_signer_entry: ServiceSignerEntry::find_address(&config_pda, &service_signer).0,
+            mint: derived_mint,
+            mint_config: MintConfig::find_address(&derived_mint).0,
+            server_authority: program.payer(),
             authority: pda_authority,
             compress_token_account: associated_account,
             cpi_authority_pda: get_cpi_authority_pda().0,
@@ -1,6 +1,7 @@
 use anchor_client::{Client, Cluster, Program};
 use anchor_lang::system_program;
 use anchor_spl::token::spl_token;
+use anchor_spl::token_2022::spl_token_2022;
 use light_compressed_token::{get_token_pool_pda, process_transfer::get_cpi_authority_pda};
 use solana_client::{nonblocking::rpc_client::RpcClient, rpc_config::RpcSendTransactionConfig};
 use solana_sdk::{
@@ -14,22 +15,53 @@ use solana_sdk::{
 };
 use std::sync::Arc;
 use zk_onchain::{
-    accounts as soda_accounts, instruction as soda_instructions, state::MINT_AUTHORITY,
-    user::COMPRESSED_MINT_SEED,
+    accounts as soda_accounts, instruction as soda_instructions,
+    state::MINT_AUTHORITY,
+    user::{
+        find_freeze_authority, find_mint_authority, MintConfig, MintLimits, Token2022Extensions,
+        COMPRESSED_MINT_SEED,
+    },
+    vaults::ServiceSignerEntry,
 };
 
 use crate::{
     settings::config::load_cfg,
+    user::user_vault::get_program_addresses,
     utils::{
         config::keypair_1,
         pinata_service::{json_metadata_ipfs, pinata_image_ipfs, JsonMetaData},
     },
 };
 
+/// Limits the client scenarios create mints with: one million tokens at 6 decimals, of which
+/// at most 10,000 a day and 1,000 a day per service signer
+pub fn default_mint_limits() -> MintLimits {
+    MintLimits {
+        max_supply: 1_000_000_000_000,
+        period_duration: 24 * 60 * 60,
+        period_limit: 10_000_000_000,
+        service_signer_quota: 1_000_000_000,
+    }
+}
+
+async fn cmint_metadata_uri() -> String {
+    let image_metadata = pinata_image_ipfs().await.unwrap();
+    json_metadata_ipfs(JsonMetaData {
+        name: "cMINT".into(),
+        symbol: "$cMINT".into(),
+        image: image_metadata,
+        description: None,
+    })
+    .await
+    .unwrap()
+}
+
 pub async fn create_compressed_mint_inx(
     program: Program<Arc<Keypair>>,
     service_signer: Pubkey,
     nonce: u16,
+    freezable: bool,
+    is_mutable: bool,
 ) -> anyhow::Result<Vec<Instruction>> {
     // mint
     let (derived_mint, _) = Pubkey::find_program_address(
@@ -41,19 +73,12 @@ pub async fn create_compressed_mint_inx(
         &zk_onchain::ID,
     );
 
-    let image_metadata = pinata_image_ipfs().await.unwrap();
-    let uri = json_metadata_ipfs(JsonMetaData {
-        name: "cMINT".into(),
-        symbol: "$cMINT".into(),
-        image: image_metadata,
-        description: None,
-    })
-    .await
-    .unwrap();
+    let uri = cmint_metadata_uri().await;
+
+    let (config_pda, _, _) = get_program_addresses()?;
 
     // authority
-    let (pda_authority, _) =
-        Pubkey::find_program_address(&[&MINT_AUTHORITY.as_bytes()], &zk_onchain::ID);
+    let (pda_authority, _) = find_mint_authority(&program.payer(), nonce);
 
     let (metadata_account, _) = Pubkey::find_program_address(
         &[
@@ -69,10 +94,14 @@ pub async fn create_compressed_mint_inx(
         .accounts(soda_accounts::CreateCompressedMint {
             payer: program.payer(),
             service_signer,
+            config: config_pda,
+            service_signer_entry: ServiceSignerEntry::find_address(&config_pda, &service_signer).0,
             authority: pda_authority,
+            freeze_authority: find_freeze_authority(&program.payer(), nonce).0,
             cpi_authority_pda: get_cpi_authority_pda().0,
             token_pool_pda: get_token_pool_pda(&derived_mint),
             compressed_mint: derived_mint,
+            mint_config: MintConfig::find_address(&derived_mint).0,
             metadata_account: metadata_account,
             compressed_token_program: light_compressed_token::id(),
             token_program: spl_token::id(),
@@ -86,11 +115,167 @@ pub async fn create_compressed_mint_inx(
             decimals: 6,
             uri,
             nonce,
+            freezable,
+            limits: default_mint_limits(),
+            is_mutable,
+        })
+        .instructions()?)
+}
+
+/// Token-2022 variant of `create_compressed_mint_inx`, the metadata lives in the mint itself
+pub async fn create_compressed_mint_2022_inx(
+    program: Program<Arc<Keypair>>,
+    service_signer: Pubkey,
+    nonce: u16,
+    freezable: bool,
+    extensions: Token2022Extensions,
+) -> anyhow::Result<Vec<Instruction>> {
+    let (derived_mint, _) = Pubkey::find_program_address(
+        &[
+            &COMPRESSED_MINT_SEED.as_bytes(),
+            &program.payer().to_bytes(),
+            &nonce.to_be_bytes(),
+        ],
+        &zk_onchain::ID,
+    );
+
+    let uri = cmint_metadata_uri().await;
+
+    let (config_pda, _, _) = get_program_addresses()?;
+
+    Ok(program
+        .request()
+        .accounts(soda_accounts::CreateCompressedMint2022 {
+            payer: program.payer(),
+            service_signer,
+            config: config_pda,
+            service_signer_entry: ServiceSignerEntry::find_address(&config_pda, &service_signer).0,
+            authority: find_mint_authority(&program.payer(), nonce).0,
+            freeze_authority: find_freeze_authority(&program.payer(), nonce).0,
+            compressed_mint: derived_mint,
+            mint_config: MintConfig::find_address(&derived_mint).0,
+            cpi_authority_pda: get_cpi_authority_pda().0,
+            token_pool_pda: get_token_pool_pda(&derived_mint),
+            compressed_token_program: light_compressed_token::id(),
+            token_program: spl_token_2022::id(),
+            system_program: system_program::ID,
+        })
+        .args(soda_instructions::CreateCompressedMint2022 {
+            name: "cMINT".into(),
+            symbol: "$cMINT".into(),
+            decimals: 6,
+            uri,
+            nonce,
+            freezable,
+            limits: default_mint_limits(),
+            extensions,
         })
         .instructions()?)
 }
 
-pub async fn create_compressed_mint(rpc_client: RpcClient) -> anyhow::Result<()> {
+/// Give a mint the payer created before mint configs existed a config with
+/// `default_mint_limits` and move it, and its metadata if mutable, to the per-server mint
+/// authority
+pub fn migrate_compressed_mint_inx(
+    program: Program<Arc<Keypair>>,
+    service_signer: Pubkey,
+    nonce: u16,
+) -> anyhow::Result<Vec<Instruction>> {
+    let (derived_mint, _) = Pubkey::find_program_address(
+        &[
+            &COMPRESSED_MINT_SEED.as_bytes(),
+            &program.payer().to_bytes(),
+            &nonce.to_be_bytes(),
+        ],
+        &zk_onchain::ID,
+    );
+
+    let (config_pda, _, _) = get_program_addresses()?;
+    let (metadata_account, _) = Pubkey::find_program_address(
+        &[
+            b"metadata",
+            mpl_token_metadata::programs::MPL_TOKEN_METADATA_ID.as_ref(),
+            derived_mint.as_ref(),
+        ],
+        &mpl_token_metadata::programs::MPL_TOKEN_METADATA_ID,
+    );
+
+    Ok(program
+        .request()
+        .accounts(soda_accounts::MigrateCompressedMint {
+            payer: program.payer(),
+            service_signer,
+            config: config_pda,
+            service_signer_entry: ServiceSignerEntry::find_address(&config_pda, &service_signer).0,
+            legacy_authority: Pubkey::find_program_address(
+                &[MINT_AUTHORITY.as_bytes()],
+                &zk_onchain::ID,
+            )
+            .0,
+            authority: find_mint_authority(&program.payer(), nonce).0,
+            compressed_mint: derived_mint,
+            mint_config: MintConfig::find_address(&derived_mint).0,
+            metadata_account,
+            mpl_token_metadata: mpl_token_metadata::ID,
+            token_program: spl_token::id(),
+            system_program: system_program::ID,
+        })
+        .args(soda_instructions::MigrateCompressedMint {
+            nonce,
+            limits: default_mint_limits(),
+        })
+        .instructions()?)
+}
+
+/// Migrates the payer's legacy mint created with `nonce`, `keypair_1` co-signs
+pub async fn migrate_compressed_mint(rpc_client: RpcClient, nonce: u16) -> anyhow::Result<()> {
+    let client_config = "client_config.ini";
+    let config = load_cfg(&client_config.to_string()).unwrap();
+
+    let payer = Arc::new(read_keypair_file(&config.payer_path).unwrap());
+    let service_signer = keypair_1();
+
+    let client = Client::new_with_options(
+        Cluster::Custom(config.http_url.clone(), config.ws_url.clone()),
+        payer.clone(),
+        CommitmentConfig::processed(),
+    );
+    let program = client.program(zk_onchain::id())?;
+
+    let instructions = migrate_compressed_mint_inx(program, service_signer.pubkey(), nonce)?;
+
+    let recent_blockhash = rpc_client.get_latest_blockhash().await?;
+    let transaction = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&payer.pubkey()),
+        &[&payer, &service_signer],
+        recent_blockhash,
+    );
+
+    let signature = rpc_client
+        .send_and_confirm_transaction_with_spinner_and_config(
+            &transaction,
+            CommitmentConfig::processed(),
+            RpcSendTransactionConfig {
+                skip_preflight: true,
+                ..Default::default()
+            },
+        )
+        .await?;
+
+    println!("Mint migrated: {}", signature);
+    Ok(())
+}
+
+/// Creates a compressed mint, `freezable` keeps the freeze authority PDA on the mint and
+/// `is_mutable` lets its metadata be updated. With `token_2022` set the mint is a Token-2022
+/// mint with those extensions, its metadata is then always kept in the mint.
+pub async fn create_compressed_mint(
+    rpc_client: RpcClient,
+    freezable: bool,
+    is_mutable: bool,
+    token_2022: Option<Token2022Extensions>,
+) -> anyhow::Result<()> {
     let client_config = "client_config.ini";
     let config = load_cfg(&client_config.to_string()).unwrap();
 
@@ -104,8 +289,28 @@ pub async fn create_compressed_mint(rpc_client: RpcClient) -> anyhow::Result<()>
     );
     let program = client.program(zk_onchain::id())?;
 
-    let compressed_mint_ix =
-        create_compressed_mint_inx(program, service_signer.pubkey(), 3u16).await?;
+    let compressed_mint_ix = match token_2022 {
+        Some(extensions) => {
+            create_compressed_mint_2022_inx(
+                program,
+                service_signer.pubkey(),
+                3u16,
+                freezable,
+                extensions,
+            )
+            .await?
+        }
+        None => {
+            create_compressed_mint_inx(
+                program,
+                service_signer.pubkey(),
+                3u16,
+                freezable,
+                is_mutable,
+            )
+            .await?
+        }
+    };
 
     let recent_blockhash = rpc_client.get_latest_blockhash().await?;
 
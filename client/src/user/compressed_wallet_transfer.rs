@@ -23,10 +23,7 @@ use light_system_program::{
     InstructionDataInvoke, NewAddressParams,
 };
 use photon_api::{
-    apis::{
-        configuration::{ApiKey, Configuration},
-        default_api::get_compressed_accounts_by_owner_post,
-    },
+    apis::default_api::get_compressed_accounts_by_owner_post,
     models::{
         GetCompressedAccountsByOwnerPostRequest, GetCompressedAccountsByOwnerPostRequestParams,
     },
@@ -47,15 +44,18 @@ use zk_onchain::{
     accounts as soda_accounts,
     // compressed_transfers::sdk::{get_token_owner_pda, CreateCompressedPdaEscrowInstructionInputs},
     instruction as soda_instructions,
-    state::MINT_AUTHORITY,
-    user::COMPRESSED_MINT_SEED,
+    user::{
+        find_freeze_authority, find_mint_authority, MintConfig, MintQuota, COMPRESSED_MINT_SEED,
+    },
+    vaults::{ServiceSignerEntry, UserVaultAuthority, UserVaultStateV2},
 };
 
 use crate::{
-    settings::config::load_cfg,
+    settings::config::{load_cfg, photon_config},
+    user::compressed_mint::default_mint_limits,
     user::{
         compressed_vault_bump::derive_user_vault_with_bump,
-        user_vault::{derive_user_vault, initialize_user_vault},
+        user_vault::{derive_user_vault, get_program_addresses, initialize_user_vault},
     },
     utils::config::keypair_1,
 };
@@ -97,9 +97,10 @@ pub async fn user_vaults_transfer_ix(
         address_queue_pubkey: address_merkle_tree_queue_pubkey,
     };
 
+    let (config_pda, _, _) = get_program_addresses()?;
+
     // authority
-    let (pda_authority, _) =
-        Pubkey::find_program_address(&[&MINT_AUTHORITY.as_bytes()], &zk_onchain::ID);
+    let (pda_authority, _) = find_mint_authority(&program.payer(), nonce);
 
     let associated_account = get_associated_token_address(&pda_authority, &derived_mint);
 
@@ -141,10 +142,14 @@ pub async fn user_vaults_transfer_ix(
         .accounts(soda_accounts::CreateCompressedMint {
             payer: program.payer(),
             service_signer,
+            config: config_pda,
+            service_signer_entry: ServiceSignerEntry::find_address(&config_pda, &service_signer).0,
             authority: pda_authority,
+            freeze_authority: find_freeze_authority(&program.payer(), nonce).0,
             cpi_authority_pda: get_cpi_authority_pda().0,
             token_pool_pda: get_token_pool_pda(&derived_mint),
             compressed_mint: derived_mint,
+            mint_config: MintConfig::find_address(&derived_mint).0,
             metadata_account,
             compressed_token_program: light_compressed_token::id(),
             token_program: spl_token::id(),
@@ -158,6 +163,9 @@ pub async fn user_vaults_transfer_ix(
             decimals: 6,
             uri: "URI".into(),
             nonce,
+            freezable: false,
+            limits: default_mint_limits(),
+            is_mutable: false,
         })
         .instructions()?;
 
@@ -166,9 +174,14 @@ pub async fn user_vaults_transfer_ix(
         .accounts(soda_accounts::MintTokens {
             payer: program.payer(),
             service_signer,
+            config: config_pda,
+            service_signer_entry: ServiceSignerEntry::find_address(&config_pda, &service_signer).0,
             cpi_authority_pda: get_cpi_authority_pda().0,
+            server_authority: program.payer(),
             authority: pda_authority,
             mint: derived_mint,
+            mint_config: MintConfig::find_address(&derived_mint).0,
+            mint_quota: MintQuota::find_address(&derived_mint, &service_signer).0,
             token_pool_pda: get_token_pool_pda(&derived_mint),
             associated_token_program: anchor_spl::associated_token::ID,
             token_program: spl_token::id(),
@@ -287,8 +300,14 @@ pub async fn create_wallets_transfer(rpc_client: Arc<RpcClient>) -> anyhow::Resu
 
     println!("Transaction signature: {}", signature);
     let program = client.program(zk_onchain::id())?;
-    let transfer_inx =
-        compressed_transfer(program, &user_1_pubkey, user_vault_1, recipient.pubkey()).await;
+    let (user_1_token_owner, _) = UserVaultStateV2::find_token_owner(&user_1_pubkey);
+    let transfer_inx = compressed_transfer(
+        program,
+        &user_1_pubkey,
+        user_1_token_owner,
+        recipient.pubkey(),
+    )
+    .await;
 
     let recent_blockhash = rpc_client.get_latest_blockhash().await?;
 
@@ -318,19 +337,12 @@ pub async fn create_wallets_transfer(rpc_client: Arc<RpcClient>) -> anyhow::Resu
 pub async fn compressed_transfer(
     program: Program<Arc<Keypair>>,
     current_authority: &Pubkey,
-    user_1_vault: Pubkey,
+    token_owner: Pubkey,
     recipient: Pubkey,
 ) -> Vec<Instruction> {
     sleep(Duration::from_secs(10)).await;
     // Get proof
-    let rpc_config = Configuration {
-        base_path: "https://devnet.helius-rpc.com/".into(),
-        api_key: Some(ApiKey {
-            prefix: None,
-            key: "16ef3f61-7567-47d9-9c44-edec13422455".into(),
-        }),
-        ..Configuration::default()
-    };
+    let rpc_config = photon_config();
 
     let compressed_accounts = get_compressed_accounts_by_owner_post(
         &rpc_config,
@@ -352,13 +364,13 @@ pub async fn compressed_transfer(
     let input_compressed_accounts = vec![
         CompressedAccount {
             lamports: 100,
-            owner: user_1_vault,
+            owner: token_owner,
             address: None,
             data: None,
         },
         CompressedAccount {
             lamports: 100,
-            owner: user_1_vault,
+            owner: token_owner,
             address: None,
             data: None,
         },
@@ -367,7 +379,7 @@ pub async fn compressed_transfer(
     let output_compressed_accounts = vec![
         CompressedAccount {
             lamports: 50,
-            owner: user_1_vault,
+            owner: token_owner,
             address: None,
             data: None,
         },
@@ -414,7 +426,7 @@ pub async fn compressed_transfer(
         &payer,
         &payer,
         current_authority,
-        user_1_vault,
+        token_owner,
         &input_compressed_accounts[..],
         &output_compressed_accounts[..],
         &input_merkle_context[..],
@@ -434,7 +446,7 @@ pub fn create_invoke_instruction(
     fee_payer: &Pubkey,
     service_signer: &Pubkey,
     current_authority: &Pubkey,
-    user_1_vault: Pubkey,
+    token_owner: Pubkey,
     input_compressed_accounts: &[CompressedAccount],
     output_compressed_accounts: &[CompressedAccount],
     merkle_context: &[MerkleContext],
@@ -468,6 +480,8 @@ pub fn create_invoke_instruction(
 
     InstructionDataInvoke::serialize(&inputs_struct, &mut inputs).unwrap();
 
+    let (config_pda, _, _) = get_program_addresses().unwrap();
+
     // program.request().accounts(soda_accounts::EscrowCompressedTokensWithCompressedPda{
     //     signer: *fee_payer,
     //     token_owner_pda:
@@ -486,12 +500,16 @@ pub fn create_invoke_instruction(
             account_compression_authority: get_cpi_authority_pda().0,
             service_signer: *service_signer,
             current_authority: *current_authority,
-            user_vault: user_1_vault,
+            // The vault id of a vault stays its first authority
+            vault_id: *current_authority,
+            vault_authority: UserVaultAuthority::find_address(current_authority).0,
+            token_owner,
             self_program: zk_onchain::id(),
             light_compressed_token: LightCompressedToken::id(),
             system_program: solana_sdk::system_program::ID,
             associated_token_program: associated_token::ID,
             rent_program: rent::id(),
+            config: config_pda,
         })
         .args(soda_instructions::TransferCompressedTokens {
             transfer_inputs: inputs,
@@ -18,16 +18,22 @@ use solana_sdk::{
 };
 use std::{str::FromStr, sync::Arc};
 use zk_onchain::{
-    accounts as soda_accounts, instruction as soda_instructions, state::MINT_AUTHORITY,
-    user::COMPRESSED_MINT_SEED,
+    accounts as soda_accounts,
+    compressed_transfers::TokenTransferRecipient,
+    instruction as soda_instructions,
+    user::{
+        find_freeze_authority, find_mint_authority, MintConfig, MintQuota, COMPRESSED_MINT_SEED,
+    },
+    vaults::ServiceSignerEntry,
 };
 
 use crate::{
     settings::config::load_cfg,
+    user::compressed_mint::default_mint_limits,
     user::{
-        compressed_transfer_ix::create_escrow_ix,
+        compressed_transfer_ix::create_vault_transfer_ix,
         compressed_vault_bump::derive_user_vault_with_bump,
-        user_vault::{derive_user_vault, initialize_user_vault},
+        user_vault::{derive_user_vault, get_program_addresses, initialize_user_vault},
     },
     utils::config::keypair_1,
 };
@@ -58,9 +64,10 @@ pub async fn user_vaults_transfer_ix(
 
     let merkle_tree_pubkey = Pubkey::from_str("smt1NamzXdq4AMqS2fS2F1i5KTYPZRhoHgWx38d8WsT")?;
 
+    let (config_pda, _, _) = get_program_addresses()?;
+
     // authority
-    let (pda_authority, _) =
-        Pubkey::find_program_address(&[&MINT_AUTHORITY.as_bytes()], &zk_onchain::ID);
+    let (pda_authority, _) = find_mint_authority(&program.payer(), nonce);
 
     let (metadata_account, _) = Pubkey::find_program_address(
         &[
@@ -76,10 +83,14 @@ pub async fn user_vaults_transfer_ix(
         .accounts(soda_accounts::CreateCompressedMint {
             payer: program.payer(),
             service_signer,
+            config: config_pda,
+            service_signer_entry: ServiceSignerEntry::find_address(&config_pda, &service_signer).0,
             authority: pda_authority,
+            freeze_authority: find_freeze_authority(&program.payer(), nonce).0,
             cpi_authority_pda: get_cpi_authority_pda().0,
             token_pool_pda: get_token_pool_pda(&derived_mint),
             compressed_mint: derived_mint,
+            mint_config: MintConfig::find_address(&derived_mint).0,
             metadata_account,
             compressed_token_program: light_compressed_token::id(),
             token_program: spl_token::id(),
@@ -93,6 +104,9 @@ pub async fn user_vaults_transfer_ix(
             decimals: 6,
             uri: "URI".into(),
             nonce,
+            freezable: false,
+            limits: default_mint_limits(),
+            is_mutable: false,
         })
         .instructions()?;
 
@@ -101,9 +115,14 @@ pub async fn user_vaults_transfer_ix(
         .accounts(soda_accounts::MintTokens {
             payer: program.payer(),
             service_signer,
+            config: config_pda,
+            service_signer_entry: ServiceSignerEntry::find_address(&config_pda, &service_signer).0,
             cpi_authority_pda: get_cpi_authority_pda().0,
+            server_authority: program.payer(),
             authority: pda_authority,
             mint: derived_mint,
+            mint_config: MintConfig::find_address(&derived_mint).0,
+            mint_quota: MintQuota::find_address(&derived_mint, &service_signer).0,
             token_pool_pda: get_token_pool_pda(&derived_mint),
             associated_token_program: anchor_spl::associated_token::ID,
             token_program: spl_token::id(),
@@ -125,7 +144,7 @@ pub async fn user_vaults_transfer_ix(
             sol_pool_pda: None,
         })
         .args(soda_instructions::MintTokens {
-            public_keys: vec![user_1_vault],
+            public_keys: vec![user_1.pubkey()],
             amounts: vec![100000u64],
             lamports: None,
         })
@@ -214,13 +233,23 @@ pub async fn create_user_vaults_transfer(rpc_client: Arc<RpcClient>) -> anyhow::
 
     let program = client.program(zk_onchain::id())?;
 
-    let (_, mut transfer_inx) = create_escrow_ix(
+    // Pay both vaults in one transfer, the rest stays with user_1 as change
+    let mut transfer_inx = create_vault_transfer_ix(
         program,
-        &payer,
+        payer.pubkey(),
         user_1_pubkey,
         user_vault_1,
-        user_vault_2,
-        &mint,
+        mint,
+        vec![
+            TokenTransferRecipient {
+                owner: user_vault_2,
+                amount: 10,
+            },
+            TokenTransferRecipient {
+                owner: user_vault_1,
+                amount: 5,
+            },
+        ],
     )
     .await?;
 
@@ -0,0 +1,633 @@
+use std::sync::Arc;
+
+use anchor_client::{Client, Cluster};
+use anchor_lang::{AccountDeserialize, Discriminator};
+use anyhow::Result;
+use light_compressed_token::process_transfer::get_cpi_authority_pda;
+use light_sdk::merkle_context::RemainingAccounts;
+use light_sdk::{PROGRAM_ID_ACCOUNT_COMPRESSION, PROGRAM_ID_LIGHT_SYSTEM};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::{RpcProgramAccountsConfig, RpcSendTransactionConfig};
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{read_keypair_file, Keypair, Signature};
+use solana_sdk::signer::Signer;
+use solana_sdk::system_program;
+use solana_sdk::transaction::Transaction;
+use zk_onchain::vaults::{
+    ArbiterEscrowParams, ArbiterEscrowState, EscrowVaultParams, EscrowVaultState,
+    ServiceSignerEntry,
+};
+use zk_onchain::{accounts as soda_accounts, instruction as soda_instructions};
+
+use super::token_inputs::fetch_token_inputs;
+use super::user_vault::get_program_addresses;
+use crate::settings::config::load_cfg;
+
+fn escrow_program_client(payer: Arc<Keypair>) -> Result<anchor_client::Program<Arc<Keypair>>> {
+    let client_config = "client_config.ini";
+    let config = load_cfg(&client_config.to_string()).unwrap();
+
+    let client = Client::new_with_options(
+        Cluster::Custom(config.http_url.clone(), config.ws_url.clone()),
+        payer,
+        CommitmentConfig::processed(),
+    );
+
+    Ok(client.program(zk_onchain::id())?)
+}
+
+/// Fetch and decode an open escrow offer
+pub async fn get_escrow_offer(
+    rpc_client: &RpcClient,
+    maker: Pubkey,
+    offer_id: u64,
+) -> Result<(Pubkey, EscrowVaultState)> {
+    let (escrow_vault, _) = EscrowVaultState::find_address(&maker, offer_id);
+    let data = rpc_client.get_account_data(&escrow_vault).await?;
+
+    Ok((
+        escrow_vault,
+        EscrowVaultState::try_deserialize(&mut data.as_slice())?,
+    ))
+}
+
+/// Open offers selling `mint_offered` for `mint_wanted`, cheapest first
+pub async fn list_escrow_offers(
+    rpc_client: &RpcClient,
+    mint_offered: Pubkey,
+    mint_wanted: Pubkey,
+) -> Result<Vec<(Pubkey, EscrowVaultState)>> {
+    let accounts = rpc_client
+        .get_program_accounts_with_config(
+            &zk_onchain::id(),
+            RpcProgramAccountsConfig {
+                filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+                    0,
+                    &EscrowVaultState::DISCRIMINATOR,
+                ))]),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+    let mut offers: Vec<(Pubkey, EscrowVaultState)> = accounts
+        .into_iter()
+        .filter_map(|(address, account)| {
+            EscrowVaultState::try_deserialize(&mut account.data.as_slice())
+                .ok()
+                .map(|offer| (address, offer))
+        })
+        .filter(|(_, offer)| {
+            offer.mint_offered == mint_offered
+                && offer.mint_wanted == mint_wanted
+                && offer.remaining_offered > 0
+        })
+        .collect();
+
+    // Compare num_a / den_a against num_b / den_b without dividing
+    offers.sort_by(|(_, a), (_, b)| {
+        (a.price_numerator as u128 * b.price_denominator as u128)
+            .cmp(&(b.price_numerator as u128 * a.price_denominator as u128))
+    });
+
+    Ok(offers)
+}
+
+/// Open an offer, locking `params.amount_offered` of the maker's compressed tokens
+pub async fn create_escrow_offer_instructions(
+    payer: Arc<Keypair>,
+    maker: Pubkey,
+    service_signer: Pubkey,
+    params: EscrowVaultParams,
+) -> Result<Vec<Instruction>> {
+    let program = escrow_program_client(payer.clone())?;
+    let (config_pda, registered_program_pda, account_compression_authority) =
+        get_program_addresses()?;
+
+    let mut remaining_accounts = RemainingAccounts::default();
+    let maker_inputs = fetch_token_inputs(
+        maker,
+        params.mint_offered,
+        params.amount_offered,
+        &mut remaining_accounts,
+    )
+    .await?;
+
+    let (escrow_vault, _) = EscrowVaultState::find_address(&maker, params.offer_id);
+
+    let mut instructions = program
+        .request()
+        .accounts(soda_accounts::InitializeEscrowVault {
+            payer: payer.pubkey(),
+            service_signer,
+            current_authority: maker,
+            escrow_vault,
+            config: config_pda,
+            service_signer_entry: ServiceSignerEntry::find_address(&config_pda, &service_signer).0,
+            compressed_token_cpi_authority_pda: get_cpi_authority_pda().0,
+            compressed_token_program: light_compressed_token::ID,
+            light_system_program: PROGRAM_ID_LIGHT_SYSTEM,
+            registered_program_pda,
+            noop_program: light_sdk::PROGRAM_ID_NOOP,
+            account_compression_authority,
+            account_compression_program: PROGRAM_ID_ACCOUNT_COMPRESSION,
+            system_program: system_program::id(),
+        })
+        .args(soda_instructions::InitializeEscrowVault {
+            params,
+            maker_inputs,
+        })
+        .instructions()?;
+
+    instructions[0]
+        .accounts
+        .extend(remaining_accounts.to_account_metas());
+
+    Ok(instructions)
+}
+
+/// Fill `fill_amount` of an open offer, paying the maker at the offer price
+pub async fn accept_escrow_offer_instructions(
+    rpc_client: &RpcClient,
+    payer: Arc<Keypair>,
+    taker: Pubkey,
+    maker: Pubkey,
+    offer_id: u64,
+    fill_amount: u64,
+) -> Result<Vec<Instruction>> {
+    let program = escrow_program_client(payer.clone())?;
+    let (config_pda, registered_program_pda, account_compression_authority) =
+        get_program_addresses()?;
+    let (escrow_vault, offer) = get_escrow_offer(rpc_client, maker, offer_id).await?;
+
+    offer
+        .validate_fill(fill_amount)
+        .map_err(|err| anyhow::anyhow!("{err}"))?;
+    let amount_paid = offer
+        .fill_price(fill_amount)
+        .map_err(|err| anyhow::anyhow!("{err}"))?;
+
+    let mut remaining_accounts = RemainingAccounts::default();
+    let taker_inputs = fetch_token_inputs(
+        taker,
+        offer.mint_wanted,
+        amount_paid,
+        &mut remaining_accounts,
+    )
+    .await?;
+    let escrow_inputs = fetch_token_inputs(
+        escrow_vault,
+        offer.mint_offered,
+        fill_amount,
+        &mut remaining_accounts,
+    )
+    .await?;
+
+    let mut instructions = program
+        .request()
+        .accounts(soda_accounts::AcceptEscrowOffer {
+            payer: payer.pubkey(),
+            taker,
+            escrow_vault,
+            maker,
+            config: config_pda,
+            compressed_token_cpi_authority_pda: get_cpi_authority_pda().0,
+            compressed_token_program: light_compressed_token::ID,
+            light_system_program: PROGRAM_ID_LIGHT_SYSTEM,
+            registered_program_pda,
+            noop_program: light_sdk::PROGRAM_ID_NOOP,
+            account_compression_authority,
+            account_compression_program: PROGRAM_ID_ACCOUNT_COMPRESSION,
+            system_program: system_program::id(),
+        })
+        .args(soda_instructions::AcceptEscrowOffer {
+            fill_amount,
+            taker_inputs,
+            escrow_inputs,
+        })
+        .instructions()?;
+
+    instructions[0]
+        .accounts
+        .extend(remaining_accounts.to_account_metas());
+
+    Ok(instructions)
+}
+
+/// Return the unfilled tokens to the maker. Anyone may do this once the offer expired.
+pub async fn cancel_escrow_offer_instructions(
+    rpc_client: &RpcClient,
+    signer: Arc<Keypair>,
+    maker: Pubkey,
+    offer_id: u64,
+) -> Result<Vec<Instruction>> {
+    let program = escrow_program_client(signer.clone())?;
+    let (config_pda, registered_program_pda, account_compression_authority) =
+        get_program_addresses()?;
+    let (escrow_vault, offer) = get_escrow_offer(rpc_client, maker, offer_id).await?;
+
+    let mut remaining_accounts = RemainingAccounts::default();
+    let escrow_inputs = fetch_token_inputs(
+        escrow_vault,
+        offer.mint_offered,
+        offer.remaining_offered,
+        &mut remaining_accounts,
+    )
+    .await?;
+
+    let mut instructions = program
+        .request()
+        .accounts(soda_accounts::CancelEscrowOffer {
+            signer: signer.pubkey(),
+            escrow_vault,
+            maker,
+            config: config_pda,
+            compressed_token_cpi_authority_pda: get_cpi_authority_pda().0,
+            compressed_token_program: light_compressed_token::ID,
+            light_system_program: PROGRAM_ID_LIGHT_SYSTEM,
+            registered_program_pda,
+            noop_program: light_sdk::PROGRAM_ID_NOOP,
+            account_compression_authority,
+            account_compression_program: PROGRAM_ID_ACCOUNT_COMPRESSION,
+            system_program: system_program::id(),
+        })
+        .args(soda_instructions::CancelEscrowOffer { escrow_inputs })
+        .instructions()?;
+
+    instructions[0]
+        .accounts
+        .extend(remaining_accounts.to_account_metas());
+
+    Ok(instructions)
+}
+
+/// Fetch and decode an open arbiter escrow
+pub async fn get_arbiter_escrow(
+    rpc_client: &RpcClient,
+    buyer: Pubkey,
+    trade_id: u64,
+) -> Result<(Pubkey, ArbiterEscrowState)> {
+    let (arbiter_escrow, _) = ArbiterEscrowState::find_address(&buyer, trade_id);
+    let data = rpc_client.get_account_data(&arbiter_escrow).await?;
+
+    Ok((
+        arbiter_escrow,
+        ArbiterEscrowState::try_deserialize(&mut data.as_slice())?,
+    ))
+}
+
+/// Open a trade, locking `params.amount` of the buyer's compressed tokens.
+/// Pass `arbiter_is_service_signer` when the arbiter is in the service signer registry,
+/// otherwise the arbiter has to sign the transaction.
+pub async fn create_arbiter_escrow_instructions(
+    payer: Arc<Keypair>,
+    buyer: Pubkey,
+    service_signer: Pubkey,
+    arbiter_is_service_signer: bool,
+    params: ArbiterEscrowParams,
+) -> Result<Vec<Instruction>> {
+    let program = escrow_program_client(payer.clone())?;
+    let (config_pda, registered_program_pda, account_compression_authority) =
+        get_program_addresses()?;
+
+    let mut remaining_accounts = RemainingAccounts::default();
+    let buyer_inputs =
+        fetch_token_inputs(buyer, params.mint, params.amount, &mut remaining_accounts).await?;
+
+    let (arbiter_escrow, _) = ArbiterEscrowState::find_address(&buyer, params.trade_id);
+    let arbiter_entry = arbiter_is_service_signer
+        .then(|| ServiceSignerEntry::find_address(&config_pda, &params.arbiter).0);
+
+    let mut instructions = program
+        .request()
+        .accounts(soda_accounts::InitializeArbiterEscrow {
+            payer: payer.pubkey(),
+            service_signer,
+            buyer,
+            arbiter: params.arbiter,
+            arbiter_entry,
+            arbiter_escrow,
+            config: config_pda,
+            service_signer_entry: ServiceSignerEntry::find_address(&config_pda, &service_signer).0,
+            compressed_token_cpi_authority_pda: get_cpi_authority_pda().0,
+            compressed_token_program: light_compressed_token::ID,
+            light_system_program: PROGRAM_ID_LIGHT_SYSTEM,
+            registered_program_pda,
+            noop_program: light_sdk::PROGRAM_ID_NOOP,
+            account_compression_authority,
+            account_compression_program: PROGRAM_ID_ACCOUNT_COMPRESSION,
+            system_program: system_program::id(),
+        })
+        .args(soda_instructions::InitializeArbiterEscrow {
+            params,
+            buyer_inputs,
+        })
+        .instructions()?;
+
+    instructions[0]
+        .accounts
+        .extend(remaining_accounts.to_account_metas());
+
+    Ok(instructions)
+}
+
+/// Confirm delivery, paying the escrowed tokens to the seller
+pub async fn release_arbiter_escrow_instructions(
+    rpc_client: &RpcClient,
+    buyer: Arc<Keypair>,
+    trade_id: u64,
+) -> Result<Vec<Instruction>> {
+    let program = escrow_program_client(buyer.clone())?;
+    let (config_pda, registered_program_pda, account_compression_authority) =
+        get_program_addresses()?;
+    let (arbiter_escrow, trade) = get_arbiter_escrow(rpc_client, buyer.pubkey(), trade_id).await?;
+
+    let mut remaining_accounts = RemainingAccounts::default();
+    let escrow_inputs = fetch_token_inputs(
+        arbiter_escrow,
+        trade.mint,
+        trade.amount,
+        &mut remaining_accounts,
+    )
+    .await?;
+
+    let mut instructions = program
+        .request()
+        .accounts(soda_accounts::ReleaseArbiterEscrow {
+            buyer: buyer.pubkey(),
+            arbiter_escrow,
+            config: config_pda,
+            compressed_token_cpi_authority_pda: get_cpi_authority_pda().0,
+            compressed_token_program: light_compressed_token::ID,
+            light_system_program: PROGRAM_ID_LIGHT_SYSTEM,
+            registered_program_pda,
+            noop_program: light_sdk::PROGRAM_ID_NOOP,
+            account_compression_authority,
+            account_compression_program: PROGRAM_ID_ACCOUNT_COMPRESSION,
+            system_program: system_program::id(),
+        })
+        .args(soda_instructions::ReleaseArbiterEscrow { escrow_inputs })
+        .instructions()?;
+
+    instructions[0]
+        .accounts
+        .extend(remaining_accounts.to_account_metas());
+
+    Ok(instructions)
+}
+
+/// Hand a trade to its arbiter, as the buyer or the seller
+pub fn open_arbiter_dispute_instructions(
+    signer: Arc<Keypair>,
+    buyer: Pubkey,
+    trade_id: u64,
+) -> Result<Vec<Instruction>> {
+    let program = escrow_program_client(signer.clone())?;
+    let (config_pda, _, _) = get_program_addresses()?;
+
+    Ok(program
+        .request()
+        .accounts(soda_accounts::OpenArbiterDispute {
+            signer: signer.pubkey(),
+            arbiter_escrow: ArbiterEscrowState::find_address(&buyer, trade_id).0,
+            config: config_pda,
+        })
+        .args(soda_instructions::OpenArbiterDispute {})
+        .instructions()?)
+}
+
+/// Settle a disputed trade, `buyer_amount` back to the buyer and the rest to the seller
+pub async fn resolve_arbiter_dispute_instructions(
+    rpc_client: &RpcClient,
+    arbiter: Arc<Keypair>,
+    buyer: Pubkey,
+    trade_id: u64,
+    buyer_amount: u64,
+) -> Result<Vec<Instruction>> {
+    let program = escrow_program_client(arbiter.clone())?;
+    let (config_pda, registered_program_pda, account_compression_authority) =
+        get_program_addresses()?;
+    let (arbiter_escrow, trade) = get_arbiter_escrow(rpc_client, buyer, trade_id).await?;
+
+    let mut remaining_accounts = RemainingAccounts::default();
+    let escrow_inputs = fetch_token_inputs(
+        arbiter_escrow,
+        trade.mint,
+        trade.amount,
+        &mut remaining_accounts,
+    )
+    .await?;
+
+    let mut instructions = program
+        .request()
+        .accounts(soda_accounts::ResolveArbiterDispute {
+            arbiter: arbiter.pubkey(),
+            arbiter_escrow,
+            buyer,
+            config: config_pda,
+            compressed_token_cpi_authority_pda: get_cpi_authority_pda().0,
+            compressed_token_program: light_compressed_token::ID,
+            light_system_program: PROGRAM_ID_LIGHT_SYSTEM,
+            registered_program_pda,
+            noop_program: light_sdk::PROGRAM_ID_NOOP,
+            account_compression_authority,
+            account_compression_program: PROGRAM_ID_ACCOUNT_COMPRESSION,
+            system_program: system_program::id(),
+        })
+        .args(soda_instructions::ResolveArbiterDispute {
+            buyer_amount,
+            escrow_inputs,
+        })
+        .instructions()?;
+
+    instructions[0]
+        .accounts
+        .extend(remaining_accounts.to_account_metas());
+
+    Ok(instructions)
+}
+
+async fn send_escrow_instructions(
+    rpc_client: &RpcClient,
+    instructions: Vec<Instruction>,
+    payer: &Keypair,
+    signers: &[&Keypair],
+) -> Result<Signature> {
+    let mut all_instructions = vec![
+        ComputeBudgetInstruction::set_compute_unit_limit(1_000_000),
+        ComputeBudgetInstruction::set_compute_unit_price(100000),
+    ];
+    all_instructions.extend(instructions);
+
+    let mut all_signers = vec![payer];
+    all_signers.extend(signers.iter().copied());
+
+    let recent_blockhash = rpc_client.get_latest_blockhash().await?;
+    let transaction = Transaction::new_signed_with_payer(
+        &all_instructions,
+        Some(&payer.pubkey()),
+        &all_signers,
+        recent_blockhash,
+    );
+
+    Ok(rpc_client
+        .send_and_confirm_transaction_with_spinner_and_config(
+            &transaction,
+            CommitmentConfig::processed(),
+            RpcSendTransactionConfig {
+                skip_preflight: true,
+                ..Default::default()
+            },
+        )
+        .await?)
+}
+
+/// Open an offer with the maker keypair as fee payer
+pub async fn create_escrow_offer(
+    rpc_client: &RpcClient,
+    maker: Arc<Keypair>,
+    service_signer: &Keypair,
+    params: EscrowVaultParams,
+) -> Result<()> {
+    let instructions = create_escrow_offer_instructions(
+        maker.clone(),
+        maker.pubkey(),
+        service_signer.pubkey(),
+        params,
+    )
+    .await?;
+
+    let signature =
+        send_escrow_instructions(rpc_client, instructions, &maker, &[service_signer]).await?;
+
+    println!("Escrow offer created: {}", signature);
+    Ok(())
+}
+
+/// Fill an offer with the taker keypair as fee payer
+pub async fn accept_escrow_offer(
+    rpc_client: &RpcClient,
+    taker: Arc<Keypair>,
+    maker: Pubkey,
+    offer_id: u64,
+    fill_amount: u64,
+) -> Result<()> {
+    let instructions = accept_escrow_offer_instructions(
+        rpc_client,
+        taker.clone(),
+        taker.pubkey(),
+        maker,
+        offer_id,
+        fill_amount,
+    )
+    .await?;
+
+    let signature = send_escrow_instructions(rpc_client, instructions, &taker, &[]).await?;
+
+    println!("Escrow offer filled: {}", signature);
+    Ok(())
+}
+
+/// Cancel an offer, or reclaim an expired one for its maker
+pub async fn cancel_escrow_offer(
+    rpc_client: &RpcClient,
+    signer: Arc<Keypair>,
+    maker: Pubkey,
+    offer_id: u64,
+) -> Result<()> {
+    let instructions =
+        cancel_escrow_offer_instructions(rpc_client, signer.clone(), maker, offer_id).await?;
+
+    let signature = send_escrow_instructions(rpc_client, instructions, &signer, &[]).await?;
+
+    println!("Escrow offer cancelled: {}", signature);
+    Ok(())
+}
+
+/// Open a trade with the buyer keypair as fee payer. The service signer is also the arbiter.
+pub async fn create_arbiter_escrow(
+    rpc_client: &RpcClient,
+    buyer: Arc<Keypair>,
+    service_signer: &Keypair,
+    params: ArbiterEscrowParams,
+) -> Result<()> {
+    let instructions = create_arbiter_escrow_instructions(
+        buyer.clone(),
+        buyer.pubkey(),
+        service_signer.pubkey(),
+        params.arbiter == service_signer.pubkey(),
+        params,
+    )
+    .await?;
+
+    let signature =
+        send_escrow_instructions(rpc_client, instructions, &buyer, &[service_signer]).await?;
+
+    println!("Arbiter escrow created: {}", signature);
+    Ok(())
+}
+
+/// Release a trade to its seller
+pub async fn release_arbiter_escrow(
+    rpc_client: &RpcClient,
+    buyer: Arc<Keypair>,
+    trade_id: u64,
+) -> Result<()> {
+    let instructions =
+        release_arbiter_escrow_instructions(rpc_client, buyer.clone(), trade_id).await?;
+
+    let signature = send_escrow_instructions(rpc_client, instructions, &buyer, &[]).await?;
+
+    println!("Arbiter escrow released: {}", signature);
+    Ok(())
+}
+
+/// Dispute a trade as the buyer or the seller
+pub async fn open_arbiter_dispute(
+    rpc_client: &RpcClient,
+    signer: Arc<Keypair>,
+    buyer: Pubkey,
+    trade_id: u64,
+) -> Result<()> {
+    let instructions = open_arbiter_dispute_instructions(signer.clone(), buyer, trade_id)?;
+
+    let signature = send_escrow_instructions(rpc_client, instructions, &signer, &[]).await?;
+
+    println!("Arbiter dispute opened: {}", signature);
+    Ok(())
+}
+
+/// Settle a disputed trade as its arbiter
+pub async fn resolve_arbiter_dispute(
+    rpc_client: &RpcClient,
+    arbiter: Arc<Keypair>,
+    buyer: Pubkey,
+    trade_id: u64,
+    buyer_amount: u64,
+) -> Result<()> {
+    let instructions = resolve_arbiter_dispute_instructions(
+        rpc_client,
+        arbiter.clone(),
+        buyer,
+        trade_id,
+        buyer_amount,
+    )
+    .await?;
+
+    let signature = send_escrow_instructions(rpc_client, instructions, &arbiter, &[]).await?;
+
+    println!("Arbiter dispute resolved: {}", signature);
+    Ok(())
+}
+
+/// Keypair of the configured payer, used as maker in the escrow commands
+pub fn payer_keypair() -> Arc<Keypair> {
+    let client_config = "client_config.ini";
+    let config = load_cfg(&client_config.to_string()).unwrap();
+
+    Arc::new(read_keypair_file(&config.payer_path).unwrap())
+}
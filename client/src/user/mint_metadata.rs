@@ -0,0 +1,190 @@
+use std::sync::Arc;
+
+use anchor_client::{Client, Cluster};
+use anchor_spl::token_2022::spl_token_2022;
+use anyhow::Result;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcSendTransactionConfig;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signature};
+use solana_sdk::signer::Signer;
+use solana_sdk::system_program;
+use solana_sdk::transaction::Transaction;
+use zk_onchain::user::{find_mint_authority, MintConfig, COMPRESSED_MINT_SEED};
+use zk_onchain::{accounts as soda_accounts, instruction as soda_instructions};
+
+use super::user_vault::get_program_addresses;
+use crate::settings::config::load_cfg;
+
+fn metadata_program_client(payer: Arc<Keypair>) -> Result<anchor_client::Program<Arc<Keypair>>> {
+    let client_config = "client_config.ini";
+    let config = load_cfg(&client_config.to_string()).unwrap();
+
+    let client = Client::new_with_options(
+        Cluster::Custom(config.http_url.clone(), config.ws_url.clone()),
+        payer,
+        CommitmentConfig::processed(),
+    );
+
+    Ok(client.program(zk_onchain::id())?)
+}
+
+/// Compressed mint `server_authority` created with `nonce`
+pub fn find_compressed_mint(server_authority: &Pubkey, nonce: u16) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            COMPRESSED_MINT_SEED.as_bytes(),
+            server_authority.as_ref(),
+            &nonce.to_be_bytes(),
+        ],
+        &zk_onchain::ID,
+    )
+    .0
+}
+
+/// Token-2022 mints keep their metadata in the mint, the others in a Metaplex account
+async fn update_metadata_accounts(
+    rpc_client: &RpcClient,
+    server_authority: Pubkey,
+    nonce: u16,
+) -> Result<soda_accounts::UpdateCompressedMintMetadata> {
+    let (config_pda, _, _) = get_program_addresses()?;
+    let mint = find_compressed_mint(&server_authority, nonce);
+    let token_program = rpc_client.get_account(&mint).await?.owner;
+
+    let (metadata_account, mpl_token_metadata) = if token_program == spl_token_2022::id() {
+        (None, None)
+    } else {
+        let (metadata_account, _) = Pubkey::find_program_address(
+            &[
+                "metadata".as_bytes(),
+                mpl_token_metadata::programs::MPL_TOKEN_METADATA_ID.as_ref(),
+                &mint.to_bytes(),
+            ],
+            &mpl_token_metadata::programs::MPL_TOKEN_METADATA_ID,
+        );
+        (Some(metadata_account), Some(mpl_token_metadata::ID))
+    };
+
+    Ok(soda_accounts::UpdateCompressedMintMetadata {
+        server_authority,
+        mint_config: MintConfig::find_address(&mint).0,
+        authority: find_mint_authority(&server_authority, nonce).0,
+        mint,
+        metadata_account,
+        mpl_token_metadata,
+        token_program,
+        system_program: system_program::id(),
+        config: config_pda,
+    })
+}
+
+/// Replace the metadata of the mint `server_authority` created with `nonce`
+pub async fn update_mint_metadata_instructions(
+    rpc_client: &RpcClient,
+    server_authority: Arc<Keypair>,
+    nonce: u16,
+    name: String,
+    symbol: String,
+    uri: String,
+) -> Result<Vec<Instruction>> {
+    let program = metadata_program_client(server_authority.clone())?;
+
+    Ok(program
+        .request()
+        .accounts(update_metadata_accounts(rpc_client, server_authority.pubkey(), nonce).await?)
+        .args(soda_instructions::UpdateCompressedMintMetadata { name, symbol, uri })
+        .instructions()?)
+}
+
+/// Hand the metadata update authority of the mint `server_authority` created with `nonce`
+/// to `new_update_authority`
+pub async fn transfer_mint_metadata_authority_instructions(
+    rpc_client: &RpcClient,
+    server_authority: Arc<Keypair>,
+    nonce: u16,
+    new_update_authority: Pubkey,
+) -> Result<Vec<Instruction>> {
+    let program = metadata_program_client(server_authority.clone())?;
+
+    Ok(program
+        .request()
+        .accounts(update_metadata_accounts(rpc_client, server_authority.pubkey(), nonce).await?)
+        .args(soda_instructions::TransferCompressedMintMetadataAuthority {
+            new_update_authority,
+        })
+        .instructions()?)
+}
+
+async fn send_metadata_instructions(
+    rpc_client: &RpcClient,
+    instructions: Vec<Instruction>,
+    payer: &Keypair,
+) -> Result<Signature> {
+    let recent_blockhash = rpc_client.get_latest_blockhash().await?;
+    let transaction = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&payer.pubkey()),
+        &[payer],
+        recent_blockhash,
+    );
+
+    Ok(rpc_client
+        .send_and_confirm_transaction_with_spinner_and_config(
+            &transaction,
+            CommitmentConfig::processed(),
+            RpcSendTransactionConfig {
+                skip_preflight: true,
+                ..Default::default()
+            },
+        )
+        .await?)
+}
+
+/// Update mint metadata with the server authority as fee payer
+pub async fn update_mint_metadata(
+    rpc_client: &RpcClient,
+    server_authority: Arc<Keypair>,
+    nonce: u16,
+    name: String,
+    symbol: String,
+    uri: String,
+) -> Result<()> {
+    let instructions = update_mint_metadata_instructions(
+        rpc_client,
+        server_authority.clone(),
+        nonce,
+        name,
+        symbol,
+        uri,
+    )
+    .await?;
+
+    let signature = send_metadata_instructions(rpc_client, instructions, &server_authority).await?;
+
+    println!("Mint metadata updated: {}", signature);
+    Ok(())
+}
+
+/// Hand off the metadata update authority with the server authority as fee payer
+pub async fn transfer_mint_metadata_authority(
+    rpc_client: &RpcClient,
+    server_authority: Arc<Keypair>,
+    nonce: u16,
+    new_update_authority: Pubkey,
+) -> Result<()> {
+    let instructions = transfer_mint_metadata_authority_instructions(
+        rpc_client,
+        server_authority.clone(),
+        nonce,
+        new_update_authority,
+    )
+    .await?;
+
+    let signature = send_metadata_instructions(rpc_client, instructions, &server_authority).await?;
+
+    println!("Mint metadata authority transferred: {}", signature);
+    Ok(())
+}
@@ -1,8 +1,18 @@
+pub mod airdrop_tree;
+pub mod airdrop_vault;
 pub mod compress_tokens;
 pub mod compressed_mint;
 pub mod compressed_transfer_ix;
 pub mod compressed_vault_bump;
 pub mod compressed_wallet_transfer;
 pub mod cpda_tokens_transfer;
+pub mod escrow_vault;
+pub mod mint_metadata;
 pub mod server_vault;
+pub mod token_burn;
+pub mod token_delegation;
+pub mod token_freeze;
+pub mod token_inputs;
 pub mod user_vault;
+pub mod vault_token_transfer;
+pub mod vesting_vault;
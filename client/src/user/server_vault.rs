@@ -1,4 +1,5 @@
 use anchor_lang::AnchorDeserialize;
+use light_compressed_token::process_transfer::get_cpi_authority_pda as get_token_cpi_authority_pda;
 use light_sdk::address::derive_address;
 use light_sdk::address::derive_address_seed;
 use light_sdk::merkle_context::pack_address_merkle_context;
@@ -13,9 +14,8 @@ use light_sdk::utils::get_cpi_authority_pda;
 use light_sdk::verify::find_cpi_signer;
 use light_sdk::PROGRAM_ID_ACCOUNT_COMPRESSION;
 use light_sdk::PROGRAM_ID_LIGHT_SYSTEM;
-use zk_onchain::vaults::server_vault::ServerVaultParams;
+use zk_onchain::vaults::server_vault::{ServerVaultParams, ServerVaultState};
 
-use photon_api::apis::configuration::{ApiKey, Configuration};
 use photon_api::apis::default_api::get_compressed_accounts_by_owner_post;
 use photon_api::apis::default_api::get_validity_proof_post;
 use photon_api::models::GetCompressedAccountsByOwnerPostRequest;
@@ -34,7 +34,9 @@ use solana_sdk::transaction::Transaction;
 use std::str::FromStr;
 use std::sync::Arc;
 
+use super::token_inputs::fetch_token_inputs_by_mint;
 use crate::settings::config::load_cfg;
+use crate::settings::config::photon_config;
 use crate::settings::config::ClientConfig;
 use crate::utils::config::keypair_1;
 use crate::utils::config::keypair_2;
@@ -48,7 +50,7 @@ use solana_sdk::signature::read_keypair_file;
 use solana_sdk::signer::Signer;
 use solana_sdk::system_program;
 use zk_onchain::state::*;
-use zk_onchain::vaults::config_authority;
+use zk_onchain::vaults::{config_authority, ServiceSignerEntry};
 use zk_onchain::{accounts as soda_accounts, instruction as soda_instructions};
 
 pub fn derive_server_vault(
@@ -63,6 +65,40 @@ pub fn derive_server_vault(
     derive_address(&address_seed, &address_merkle_context)
 }
 
+/// Fetch the server vault of `authority` with its account hash and raw data
+pub async fn get_server_vault(
+    authority: Pubkey,
+    address_merkle_context: AddressMerkleContext,
+) -> Result<(String, ServerVaultState, Vec<u8>)> {
+    let server_vault =
+        bs58::encode(derive_server_vault(authority, address_merkle_context)).into_string();
+
+    let compressed_accounts = get_compressed_accounts_by_owner_post(
+        &photon_config(),
+        GetCompressedAccountsByOwnerPostRequest {
+            params: Box::new(GetCompressedAccountsByOwnerPostRequestParams {
+                owner: zk_onchain::ID.to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    let item = compressed_accounts
+        .result
+        .map(|result| result.value.items)
+        .unwrap_or_default()
+        .into_iter()
+        .find(|item| item.address.as_deref() == Some(server_vault.as_str()))
+        .ok_or_else(|| anyhow::anyhow!("Server vault {server_vault} not found"))?;
+
+    let data = base64::decode(item.data.clone().unwrap().data)?;
+    let state = ServerVaultState::try_from_slice(&data)?;
+
+    Ok((item.hash, state, data))
+}
+
 /// Implementation for Solana RpcConnection Error
 // Get Merkle account parameters and proof
 async fn get_account_params(
@@ -111,14 +147,7 @@ async fn get_account_params(
         pack_address_merkle_context(address_merkle_context, &mut remaining_accounts);
 
     // Get proof
-    let rpc_config = Configuration {
-        base_path: "https://devnet.helius-rpc.com/".into(),
-        api_key: Some(ApiKey {
-            prefix: None,
-            key: "16ef3f61-7567-47d9-9c44-edec13422455".into(),
-        }),
-        ..Configuration::default()
-    };
+    let rpc_config = photon_config();
 
     let proof_result = get_validity_proof_post(
         &rpc_config,
@@ -255,7 +284,11 @@ async fn build_instructions(
             current_authority: current_authority.pubkey(),
             cpi_signer,
             config: config_pda,
-            config_authority: config_authority::id(),
+            service_signer_entry: ServiceSignerEntry::find_address(
+                &config_pda,
+                &service_signer.pubkey(),
+            )
+            .0,
             /* Light Accounts */
             system_program: system_program::id(),
             light_system_program: PROGRAM_ID_LIGHT_SYSTEM,
@@ -337,10 +370,12 @@ pub async fn initialize_server_vault(
     Ok(())
 }
 
+// Get proof for closing the current vault and creating it at the new authority's address
 async fn get_update_server_cpda_params(
     current_authority: Pubkey,
+    new_authority: Pubkey,
 ) -> Result<(
-    u16,
+    (u16, u16),
     PackedMerkleContext,
     PackedAddressMerkleContext,
     CompressedProof,
@@ -362,18 +397,19 @@ async fn get_update_server_cpda_params(
         address_queue_pubkey: address_merkle_tree_queue_pubkey,
     };
 
+    let server_vault = bs58::encode(derive_server_vault(
+        current_authority,
+        address_merkle_context,
+    ))
+    .into_string();
+    let new_server_vault =
+        bs58::encode(derive_server_vault(new_authority, address_merkle_context)).into_string();
+
     let address_merkle_context =
         pack_address_merkle_context(address_merkle_context, &mut remaining_accounts);
 
     // Get proof
-    let rpc_config = Configuration {
-        base_path: "https://devnet.helius-rpc.com/".into(),
-        api_key: Some(ApiKey {
-            prefix: None,
-            key: "16ef3f61-7567-47d9-9c44-edec13422455".into(),
-        }),
-        ..Configuration::default()
-    };
+    let rpc_config = photon_config();
 
     let compressed_accounts = get_compressed_accounts_by_owner_post(
         &rpc_config,
@@ -391,7 +427,12 @@ async fn get_update_server_cpda_params(
 
     let mut item_account = photon_api::models::Account::default();
     if let Some(compressed_account) = compressed_accounts.result {
-        item_account = compressed_account.value.items[0].clone();
+        item_account = compressed_account
+            .value
+            .items
+            .into_iter()
+            .find(|item| item.address.as_deref() == Some(server_vault.as_str()))
+            .ok_or_else(|| anyhow::anyhow!("Server vault {server_vault} not found"))?;
         let compressed_data = item_account.data.clone().unwrap().data;
 
         // First decode the base58 string into a Vec<u8>
@@ -407,7 +448,7 @@ async fn get_update_server_cpda_params(
         &rpc_config,
         GetValidityProofPostRequest {
             params: Box::new(GetValidityProofPostRequestParams {
-                new_addresses: None,
+                new_addresses: Some(vec![new_server_vault]),
                 new_addresses_with_trees: None,
                 hashes: Some(vec![item_account.hash.clone()]),
             }),
@@ -422,7 +463,7 @@ async fn get_update_server_cpda_params(
         c: [0; 32],
     };
 
-    let mut root_indices: u16 = 0;
+    let mut root_indices: (u16, u16) = (0, 0);
     let mut merkle_context = PackedMerkleContext::default();
     if let Some(result_box) = proof_result.result {
         let result = *result_box.value.compressed_proof;
@@ -432,7 +473,11 @@ async fn get_update_server_cpda_params(
         let b_array: [u8; 64] = vec_to_array(result.b, "b").unwrap();
         let c_array: [u8; 32] = vec_to_array(result.c, "c").unwrap();
 
-        root_indices = result_box.value.root_indices[0] as u16;
+        // Input hash roots come first, then the new address roots
+        root_indices = (
+            result_box.value.root_indices[0] as u16,
+            result_box.value.root_indices[1] as u16,
+        );
 
         // Setup contexts
         merkle_context = pack_merkle_context(
@@ -479,7 +524,7 @@ async fn update_server_vault_instructions(
     new_authority: &Keypair,
     service_signer: &Keypair,
     params: (
-        u16,
+        (u16, u16),
         PackedMerkleContext,
         PackedAddressMerkleContext,
         CompressedProof,
@@ -509,6 +554,11 @@ async fn update_server_vault_instructions(
         compressed_inputs,
     ) = params;
 
+    // Every balance of the old authority moves with the vault
+    let mut remaining_accounts = remaining_accounts;
+    let balances =
+        fetch_token_inputs_by_mint(current_authority.pubkey(), &mut remaining_accounts).await?;
+
     // upate-server-vault-instruction
     let mut update_server_vault_ix = program
         .request()
@@ -519,6 +569,14 @@ async fn update_server_vault_instructions(
             current_authority: current_authority.pubkey(),
             new_authority: new_authority.pubkey(),
             cpi_signer,
+            compressed_token_cpi_authority_pda: get_token_cpi_authority_pda().0,
+            compressed_token_program: light_compressed_token::ID,
+            config: config_pda,
+            service_signer_entry: ServiceSignerEntry::find_address(
+                &config_pda,
+                &service_signer.pubkey(),
+            )
+            .0,
             /* Light Accounts */
             system_program: system_program::id(),
             light_system_program: PROGRAM_ID_LIGHT_SYSTEM,
@@ -532,8 +590,9 @@ async fn update_server_vault_instructions(
             inputs: vec![compressed_inputs],
             merkle_context,
             address_merkle_context,
-            address_merkle_tree_root_index: 0,
-            merkle_tree_root_index: root_indices,
+            address_merkle_tree_root_index: root_indices.1,
+            merkle_tree_root_index: root_indices.0,
+            balances,
         })
         .instructions()?;
 
@@ -566,7 +625,8 @@ pub async fn update_server_vault(rpc_client: RpcClient) -> Result<()> {
     let payer = Arc::new(read_keypair_file(&config.payer_path).unwrap());
 
     // Get account parameters and proof
-    let params = get_update_server_cpda_params(current_authority.pubkey()).await?;
+    let params =
+        get_update_server_cpda_params(current_authority.pubkey(), new_authority.pubkey()).await?;
 
     // Build instructions
     let instructions = update_server_vault_instructions(
@@ -0,0 +1,300 @@
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anchor_client::{Client, Cluster};
+use anchor_spl::token::spl_token;
+use anyhow::Result;
+use light_compressed_token::get_token_pool_pda;
+use light_compressed_token::process_transfer::get_cpi_authority_pda;
+use light_sdk::merkle_context::{
+    pack_address_merkle_context, pack_merkle_context, AddressMerkleContext, MerkleContext,
+    RemainingAccounts,
+};
+use light_sdk::verify::find_cpi_signer;
+use light_sdk::{PROGRAM_ID_ACCOUNT_COMPRESSION, PROGRAM_ID_LIGHT_SYSTEM};
+use photon_api::apis::default_api::get_validity_proof_post;
+use photon_api::models::{GetValidityProofPostRequest, GetValidityProofPostRequestParams};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcSendTransactionConfig;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signature};
+use solana_sdk::signer::Signer;
+use solana_sdk::system_program;
+use solana_sdk::transaction::Transaction;
+use zk_onchain::vaults::{UserVaultAuthority, VaultType};
+use zk_onchain::{accounts as soda_accounts, instruction as soda_instructions};
+
+use super::server_vault::{get_proof, get_server_vault};
+use super::token_delegation::user_vault_token_owner;
+use super::token_inputs::fetch_token_inputs;
+use super::user_vault::{find_user_vault, get_program_addresses};
+use crate::settings::config::{load_cfg, photon_config};
+use crate::utils::events::{simulate_events, SodaEvent};
+
+fn burn_program_client(payer: Arc<Keypair>) -> Result<anchor_client::Program<Arc<Keypair>>> {
+    let client_config = "client_config.ini";
+    let config = load_cfg(&client_config.to_string()).unwrap();
+
+    let client = Client::new_with_options(
+        Cluster::Custom(config.http_url.clone(), config.ws_url.clone()),
+        payer,
+        CommitmentConfig::processed(),
+    );
+
+    Ok(client.program(zk_onchain::id())?)
+}
+
+/// Burn `amount` of `mint` from the server vault of `authority`, the owner of its tokens.
+/// The server vault is proven and written back unchanged.
+pub async fn burn_server_tokens_instructions(
+    authority: Arc<Keypair>,
+    mint: Pubkey,
+    amount: u64,
+) -> Result<Vec<Instruction>> {
+    let merkle_tree_pubkey = Pubkey::from_str("smt1NamzXdq4AMqS2fS2F1i5KTYPZRhoHgWx38d8WsT")?;
+    let nullifier_queue_pubkey = Pubkey::from_str("nfq1NvQDJ2GEgnS8zt9prAe8rjjpAW1zFkrvZoBR148")?;
+    let address_merkle_context = AddressMerkleContext {
+        address_merkle_tree_pubkey: Pubkey::from_str(
+            "amt1Ayt45jfbdw5YSo7iz6WZxUmnZsQTYXy82hVwyC2",
+        )?,
+        address_queue_pubkey: Pubkey::from_str("aq1S9z4reTSQAdgWHGD2zDaS39sjGrAxbR31vxJ2F4F")?,
+    };
+
+    let program = burn_program_client(authority.clone())?;
+    let (config_pda, registered_program_pda, account_compression_authority) =
+        get_program_addresses()?;
+
+    let (server_vault_hash, _, server_vault_data) =
+        get_server_vault(authority.pubkey(), address_merkle_context).await?;
+
+    let mut remaining_accounts = RemainingAccounts::default();
+    let address_merkle_context =
+        pack_address_merkle_context(address_merkle_context, &mut remaining_accounts);
+
+    let proof_result = get_validity_proof_post(
+        &photon_config(),
+        GetValidityProofPostRequest {
+            params: Box::new(GetValidityProofPostRequestParams {
+                new_addresses: None,
+                new_addresses_with_trees: None,
+                hashes: Some(vec![server_vault_hash]),
+            }),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    let proof_value = proof_result
+        .result
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("No validity proof returned"))?
+        .value;
+    let merkle_context = pack_merkle_context(
+        MerkleContext {
+            merkle_tree_pubkey,
+            nullifier_queue_pubkey,
+            leaf_index: proof_value.leaf_indices[0] as u32,
+            queue_index: None,
+        },
+        &mut remaining_accounts,
+    );
+    let (compressed_proof, root_index) = get_proof(proof_result).await?;
+
+    let token_inputs =
+        fetch_token_inputs(authority.pubkey(), mint, amount, &mut remaining_accounts).await?;
+
+    let mut instructions = program
+        .request()
+        .accounts(soda_accounts::BurnCompressedTokens {
+            payer: authority.pubkey(),
+            self_program: zk_onchain::id(),
+            current_authority: authority.pubkey(),
+            cpi_signer: find_cpi_signer(&zk_onchain::ID),
+            mint,
+            token_pool_pda: get_token_pool_pda(&mint),
+            token_program: spl_token::id(),
+            compressed_token_cpi_authority_pda: get_cpi_authority_pda().0,
+            compressed_token_program: light_compressed_token::ID,
+            config: config_pda,
+            /* Light Accounts */
+            system_program: system_program::id(),
+            light_system_program: PROGRAM_ID_LIGHT_SYSTEM,
+            account_compression_program: PROGRAM_ID_ACCOUNT_COMPRESSION,
+            registered_program_pda,
+            noop_program: light_sdk::PROGRAM_ID_NOOP,
+            account_compression_authority,
+        })
+        .args(soda_instructions::BurnCompressedTokens {
+            proof: compressed_proof,
+            inputs: vec![server_vault_data],
+            merkle_context,
+            address_merkle_context,
+            address_merkle_tree_root_index: 0,
+            merkle_tree_root_index: root_index,
+            amount,
+            token_inputs,
+        })
+        .instructions()?;
+
+    instructions[0]
+        .accounts
+        .extend(remaining_accounts.to_account_metas());
+
+    Ok(instructions)
+}
+
+/// Burn `amount` of `mint` from the user vault of `authority`. The program checks the
+/// authority against the vault's authority record.
+pub async fn burn_user_vault_tokens_instructions(
+    authority: Arc<Keypair>,
+    mint: Pubkey,
+    amount: u64,
+) -> Result<Vec<Instruction>> {
+    let program = burn_program_client(authority.clone())?;
+    let (config_pda, registered_program_pda, account_compression_authority) =
+        get_program_addresses()?;
+
+    let (_, vault, _) = find_user_vault(authority.pubkey()).await?;
+    let vault_id = vault.vault_id;
+
+    let mut remaining_accounts = RemainingAccounts::default();
+    let token_owner = user_vault_token_owner(vault_id);
+    let token_inputs =
+        fetch_token_inputs(token_owner, mint, amount, &mut remaining_accounts).await?;
+
+    let mut instructions = program
+        .request()
+        .accounts(soda_accounts::BurnUserVaultTokens {
+            payer: authority.pubkey(),
+            current_authority: authority.pubkey(),
+            vault_id,
+            vault_authority: UserVaultAuthority::find_address(&vault_id).0,
+            token_owner,
+            mint,
+            token_pool_pda: get_token_pool_pda(&mint),
+            token_program: spl_token::id(),
+            compressed_token_cpi_authority_pda: get_cpi_authority_pda().0,
+            compressed_token_program: light_compressed_token::ID,
+            light_system_program: PROGRAM_ID_LIGHT_SYSTEM,
+            registered_program_pda,
+            noop_program: light_sdk::PROGRAM_ID_NOOP,
+            account_compression_authority,
+            account_compression_program: PROGRAM_ID_ACCOUNT_COMPRESSION,
+            system_program: system_program::id(),
+            config: config_pda,
+        })
+        .args(soda_instructions::BurnUserVaultTokens {
+            amount,
+            token_inputs,
+        })
+        .instructions()?;
+
+    instructions[0]
+        .accounts
+        .extend(remaining_accounts.to_account_metas());
+
+    Ok(instructions)
+}
+
+async fn send_burn_instructions(
+    rpc_client: &RpcClient,
+    instructions: Vec<Instruction>,
+    payer: &Keypair,
+) -> Result<Signature> {
+    let mut all_instructions = vec![
+        ComputeBudgetInstruction::set_compute_unit_limit(1_000_000),
+        ComputeBudgetInstruction::set_compute_unit_price(100000),
+    ];
+    all_instructions.extend(instructions);
+
+    let recent_blockhash = rpc_client.get_latest_blockhash().await?;
+    let transaction = Transaction::new_signed_with_payer(
+        &all_instructions,
+        Some(&payer.pubkey()),
+        &[payer],
+        recent_blockhash,
+    );
+
+    Ok(rpc_client
+        .send_and_confirm_transaction_with_spinner_and_config(
+            &transaction,
+            CommitmentConfig::processed(),
+            RpcSendTransactionConfig {
+                skip_preflight: true,
+                ..Default::default()
+            },
+        )
+        .await?)
+}
+
+/// Burn tokens with the vault authority as fee payer
+pub async fn burn_tokens(
+    rpc_client: &RpcClient,
+    authority: Arc<Keypair>,
+    vault_type: VaultType,
+    mint: Pubkey,
+    amount: u64,
+) -> Result<()> {
+    let instructions = match vault_type {
+        VaultType::User => {
+            burn_user_vault_tokens_instructions(authority.clone(), mint, amount).await?
+        }
+        VaultType::Server => {
+            burn_server_tokens_instructions(authority.clone(), mint, amount).await?
+        }
+        _ => anyhow::bail!("Tokens can only be burned from user and server vaults"),
+    };
+
+    let signature = send_burn_instructions(rpc_client, instructions, &authority).await?;
+
+    println!("Burned {} of {}: {}", amount, mint, signature);
+    Ok(())
+}
+
+/// Burn `amount` of `mint` from the user vault of `authority`. The burn is simulated first
+/// and must emit `TokensBurned` for the token owner PDA of the vault.
+pub async fn user_vault_burn_scenario(
+    rpc_client: &RpcClient,
+    authority: Arc<Keypair>,
+    mint: Pubkey,
+    amount: u64,
+) -> Result<()> {
+    let instructions = burn_user_vault_tokens_instructions(authority.clone(), mint, amount).await?;
+
+    let mut simulated = vec![ComputeBudgetInstruction::set_compute_unit_limit(1_000_000)];
+    simulated.extend_from_slice(&instructions);
+    let transaction = Transaction::new_signed_with_payer(
+        &simulated,
+        Some(&authority.pubkey()),
+        &[&authority],
+        rpc_client.get_latest_blockhash().await?,
+    );
+    let events = simulate_events(rpc_client, &transaction).await?;
+
+    let burned = events.iter().find_map(|event| match event {
+        SodaEvent::TokensBurned(burned) => Some(burned),
+        _ => None,
+    });
+    let Some(burned) = burned else {
+        anyhow::bail!("User vault burn emits no TokensBurned: {:?}", events);
+    };
+    let (_, vault, _) = find_user_vault(authority.pubkey()).await?;
+    let vault_id = vault.vault_id;
+    if burned.owner != user_vault_token_owner(vault_id)
+        || burned.authority != authority.pubkey()
+        || burned.amount != amount
+    {
+        anyhow::bail!("User vault burn spent the wrong tokens: {:?}", burned);
+    }
+
+    let signature = send_burn_instructions(rpc_client, instructions, &authority).await?;
+    println!(
+        "Burned {} of {} from vault {}: {}",
+        amount, mint, vault_id, signature
+    );
+
+    Ok(())
+}
@@ -0,0 +1,357 @@
+use std::sync::Arc;
+
+use anchor_client::{Client, Cluster};
+use anyhow::Result;
+use light_compressed_token::process_transfer::get_cpi_authority_pda;
+use light_sdk::merkle_context::RemainingAccounts;
+use light_sdk::{PROGRAM_ID_ACCOUNT_COMPRESSION, PROGRAM_ID_LIGHT_SYSTEM};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcSendTransactionConfig;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signature};
+use solana_sdk::signer::Signer;
+use solana_sdk::system_program;
+use solana_sdk::transaction::Transaction;
+use zk_onchain::vaults::{UserVaultAuthority, UserVaultStateV2};
+use zk_onchain::{accounts as soda_accounts, instruction as soda_instructions};
+
+use super::token_inputs::{fetch_delegated_token_inputs, fetch_token_inputs};
+use super::user_vault::{find_user_vault, get_program_addresses};
+use crate::settings::config::load_cfg;
+use crate::utils::config::keypair_3;
+use crate::utils::events::{simulate_events, SodaEvent};
+
+fn delegation_program_client(payer: Arc<Keypair>) -> Result<anchor_client::Program<Arc<Keypair>>> {
+    let client_config = "client_config.ini";
+    let config = load_cfg(&client_config.to_string()).unwrap();
+
+    let client = Client::new_with_options(
+        Cluster::Custom(config.http_url.clone(), config.ws_url.clone()),
+        payer,
+        CommitmentConfig::processed(),
+    );
+
+    Ok(client.program(zk_onchain::id())?)
+}
+
+/// Token owner of the user vault with `vault_id`
+pub fn user_vault_token_owner(vault_id: Pubkey) -> Pubkey {
+    UserVaultStateV2::find_token_owner(&vault_id).0
+}
+
+fn manage_delegation_accounts(
+    payer: Pubkey,
+    authority: Pubkey,
+    vault_id: Pubkey,
+) -> Result<soda_accounts::ManageTokenDelegation> {
+    let (config_pda, registered_program_pda, account_compression_authority) =
+        get_program_addresses()?;
+
+    Ok(soda_accounts::ManageTokenDelegation {
+        payer,
+        current_authority: authority,
+        vault_id,
+        vault_authority: UserVaultAuthority::find_address(&vault_id).0,
+        token_owner: user_vault_token_owner(vault_id),
+        compressed_token_cpi_authority_pda: get_cpi_authority_pda().0,
+        compressed_token_program: light_compressed_token::ID,
+        light_system_program: PROGRAM_ID_LIGHT_SYSTEM,
+        registered_program_pda,
+        noop_program: light_sdk::PROGRAM_ID_NOOP,
+        account_compression_authority,
+        account_compression_program: PROGRAM_ID_ACCOUNT_COMPRESSION,
+        system_program: system_program::id(),
+        config: config_pda,
+    })
+}
+
+/// Let `delegate` spend up to `amount` of `mint` from the user vault of `authority`.
+/// The program checks the authority against the vault's authority record.
+pub async fn approve_delegate_instructions(
+    authority: Arc<Keypair>,
+    mint: Pubkey,
+    delegate: Pubkey,
+    amount: u64,
+) -> Result<Vec<Instruction>> {
+    let program = delegation_program_client(authority.clone())?;
+
+    let (_, vault, _) = find_user_vault(authority.pubkey()).await?;
+    let vault_id = vault.vault_id;
+
+    let mut remaining_accounts = RemainingAccounts::default();
+
+    let vault_inputs = fetch_token_inputs(
+        user_vault_token_owner(vault_id),
+        mint,
+        amount,
+        &mut remaining_accounts,
+    )
+    .await?;
+
+    let mut instructions = program
+        .request()
+        .accounts(manage_delegation_accounts(
+            authority.pubkey(),
+            authority.pubkey(),
+            vault_id,
+        )?)
+        .args(soda_instructions::ApproveDelegate {
+            mint,
+            delegate,
+            delegated_amount: amount,
+            vault_inputs,
+        })
+        .instructions()?;
+
+    instructions[0]
+        .accounts
+        .extend(remaining_accounts.to_account_metas());
+
+    Ok(instructions)
+}
+
+/// Revoke the delegations of the user vault of `authority` covering `amount` of `mint`
+pub async fn revoke_delegate_instructions(
+    authority: Arc<Keypair>,
+    mint: Pubkey,
+    amount: u64,
+) -> Result<Vec<Instruction>> {
+    let program = delegation_program_client(authority.clone())?;
+
+    let (_, vault, _) = find_user_vault(authority.pubkey()).await?;
+    let vault_id = vault.vault_id;
+
+    let mut remaining_accounts = RemainingAccounts::default();
+
+    let vault_inputs = fetch_token_inputs(
+        user_vault_token_owner(vault_id),
+        mint,
+        amount,
+        &mut remaining_accounts,
+    )
+    .await?;
+
+    let mut instructions = program
+        .request()
+        .accounts(manage_delegation_accounts(
+            authority.pubkey(),
+            authority.pubkey(),
+            vault_id,
+        )?)
+        .args(soda_instructions::RevokeDelegate { mint, vault_inputs })
+        .instructions()?;
+
+    instructions[0]
+        .accounts
+        .extend(remaining_accounts.to_account_metas());
+
+    Ok(instructions)
+}
+
+/// Spend `amount` of `owner`'s tokens delegated to `delegate`
+pub async fn delegated_transfer_instructions(
+    delegate: Arc<Keypair>,
+    owner: Pubkey,
+    mint: Pubkey,
+    amount: u64,
+    recipient: Pubkey,
+) -> Result<Vec<Instruction>> {
+    let program = delegation_program_client(delegate.clone())?;
+    let (config_pda, registered_program_pda, account_compression_authority) =
+        get_program_addresses()?;
+
+    let mut remaining_accounts = RemainingAccounts::default();
+    let delegated_inputs = fetch_delegated_token_inputs(
+        owner,
+        delegate.pubkey(),
+        mint,
+        amount,
+        &mut remaining_accounts,
+    )
+    .await?;
+
+    let mut instructions = program
+        .request()
+        .accounts(soda_accounts::DelegatedTransferTokens {
+            payer: delegate.pubkey(),
+            delegate: delegate.pubkey(),
+            compressed_token_cpi_authority_pda: get_cpi_authority_pda().0,
+            compressed_token_program: light_compressed_token::ID,
+            light_system_program: PROGRAM_ID_LIGHT_SYSTEM,
+            registered_program_pda,
+            noop_program: light_sdk::PROGRAM_ID_NOOP,
+            account_compression_authority,
+            account_compression_program: PROGRAM_ID_ACCOUNT_COMPRESSION,
+            system_program: system_program::id(),
+            config: config_pda,
+        })
+        .args(soda_instructions::DelegatedTransfer {
+            owner,
+            mint,
+            amount,
+            recipient,
+            delegated_inputs,
+        })
+        .instructions()?;
+
+    instructions[0]
+        .accounts
+        .extend(remaining_accounts.to_account_metas());
+
+    Ok(instructions)
+}
+
+async fn send_delegation_instructions(
+    rpc_client: &RpcClient,
+    instructions: Vec<Instruction>,
+    payer: &Keypair,
+) -> Result<Signature> {
+    let mut all_instructions = vec![
+        ComputeBudgetInstruction::set_compute_unit_limit(1_000_000),
+        ComputeBudgetInstruction::set_compute_unit_price(100000),
+    ];
+    all_instructions.extend(instructions);
+
+    let recent_blockhash = rpc_client.get_latest_blockhash().await?;
+    let transaction = Transaction::new_signed_with_payer(
+        &all_instructions,
+        Some(&payer.pubkey()),
+        &[payer],
+        recent_blockhash,
+    );
+
+    Ok(rpc_client
+        .send_and_confirm_transaction_with_spinner_and_config(
+            &transaction,
+            CommitmentConfig::processed(),
+            RpcSendTransactionConfig {
+                skip_preflight: true,
+                ..Default::default()
+            },
+        )
+        .await?)
+}
+
+/// Approve a delegate with the vault authority as fee payer
+pub async fn approve_delegate(
+    rpc_client: &RpcClient,
+    authority: Arc<Keypair>,
+    mint: Pubkey,
+    delegate: Pubkey,
+    amount: u64,
+) -> Result<()> {
+    let instructions =
+        approve_delegate_instructions(authority.clone(), mint, delegate, amount).await?;
+
+    let signature = send_delegation_instructions(rpc_client, instructions, &authority).await?;
+
+    println!("Delegate approved: {}", signature);
+    Ok(())
+}
+
+/// Revoke delegations with the vault authority as fee payer
+pub async fn revoke_delegate(
+    rpc_client: &RpcClient,
+    authority: Arc<Keypair>,
+    mint: Pubkey,
+    amount: u64,
+) -> Result<()> {
+    let instructions = revoke_delegate_instructions(authority.clone(), mint, amount).await?;
+
+    let signature = send_delegation_instructions(rpc_client, instructions, &authority).await?;
+
+    println!("Delegation revoked: {}", signature);
+    Ok(())
+}
+
+/// Spend delegated tokens with the delegate as fee payer
+pub async fn delegated_transfer(
+    rpc_client: &RpcClient,
+    delegate: Arc<Keypair>,
+    owner: Pubkey,
+    mint: Pubkey,
+    amount: u64,
+    recipient: Pubkey,
+) -> Result<()> {
+    let instructions =
+        delegated_transfer_instructions(delegate.clone(), owner, mint, amount, recipient).await?;
+
+    let signature = send_delegation_instructions(rpc_client, instructions, &delegate).await?;
+
+    println!("Delegated tokens transferred: {}", signature);
+    Ok(())
+}
+
+/// Approve `keypair_3` for `amount` of `mint` from the user vault of `authority`, then revoke
+/// it again. Each step is simulated first and must emit its event for the vault's token owner.
+/// The vault needs at least `amount` of `mint`, for example from a vesting withdrawal.
+pub async fn user_vault_delegation_scenario(
+    rpc_client: &RpcClient,
+    authority: Arc<Keypair>,
+    mint: Pubkey,
+    amount: u64,
+) -> Result<()> {
+    let delegate = keypair_3().pubkey();
+
+    let instructions =
+        approve_delegate_instructions(authority.clone(), mint, delegate, amount).await?;
+    let events = simulate_delegation_instructions(rpc_client, &instructions, &authority).await?;
+    let delegated = events.iter().find_map(|event| match event {
+        SodaEvent::TokensDelegated(delegated) => Some(delegated),
+        _ => None,
+    });
+    let Some(delegated) = delegated else {
+        anyhow::bail!("Approve emits no TokensDelegated: {:?}", events);
+    };
+    if delegated.owner != user_vault_token_owner(delegated.vault_id)
+        || delegated.delegate != delegate
+        || delegated.amount != amount
+    {
+        anyhow::bail!("Approve delegated from the wrong owner: {:?}", delegated);
+    }
+
+    let signature = send_delegation_instructions(rpc_client, instructions, &authority).await?;
+    println!(
+        "Delegate approved from vault {}: {}",
+        delegated.vault_id, signature
+    );
+
+    let instructions = revoke_delegate_instructions(authority.clone(), mint, amount).await?;
+    let events = simulate_delegation_instructions(rpc_client, &instructions, &authority).await?;
+    let revoked = events.iter().any(|event| {
+        matches!(
+            event,
+            SodaEvent::DelegationRevoked(revoked)
+                if revoked.owner == delegated.owner && revoked.mint == mint
+        )
+    });
+    if !revoked {
+        anyhow::bail!("Revoke emits no DelegationRevoked: {:?}", events);
+    }
+
+    let signature = send_delegation_instructions(rpc_client, instructions, &authority).await?;
+    println!("Delegation revoked: {}", signature);
+
+    Ok(())
+}
+
+async fn simulate_delegation_instructions(
+    rpc_client: &RpcClient,
+    instructions: &[Instruction],
+    payer: &Keypair,
+) -> Result<Vec<SodaEvent>> {
+    let mut all_instructions = vec![ComputeBudgetInstruction::set_compute_unit_limit(1_000_000)];
+    all_instructions.extend_from_slice(instructions);
+
+    let transaction = Transaction::new_signed_with_payer(
+        &all_instructions,
+        Some(&payer.pubkey()),
+        &[payer],
+        rpc_client.get_latest_blockhash().await?,
+    );
+
+    simulate_events(rpc_client, &transaction).await
+}
@@ -0,0 +1,236 @@
+use std::sync::Arc;
+
+use anchor_client::{Client, Cluster};
+use anchor_lang::AccountDeserialize;
+use anyhow::{ensure, Result};
+use light_compressed_token::process_transfer::get_cpi_authority_pda;
+use light_sdk::merkle_context::RemainingAccounts;
+use light_sdk::{PROGRAM_ID_ACCOUNT_COMPRESSION, PROGRAM_ID_LIGHT_SYSTEM};
+use photon_api::models::AccountState;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcSendTransactionConfig;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signature};
+use solana_sdk::signer::Signer;
+use solana_sdk::system_program;
+use solana_sdk::transaction::Transaction;
+use zk_onchain::user::{find_freeze_authority, MintConfig};
+use zk_onchain::vaults::ServiceSignerEntry;
+use zk_onchain::{accounts as soda_accounts, instruction as soda_instructions};
+
+use super::token_inputs::fetch_token_inputs_by_state;
+use super::user_vault::get_program_addresses;
+use crate::settings::config::load_cfg;
+
+fn freeze_program_client(payer: Arc<Keypair>) -> Result<anchor_client::Program<Arc<Keypair>>> {
+    let client_config = "client_config.ini";
+    let config = load_cfg(&client_config.to_string()).unwrap();
+
+    let client = Client::new_with_options(
+        Cluster::Custom(config.http_url.clone(), config.ws_url.clone()),
+        payer,
+        CommitmentConfig::processed(),
+    );
+
+    Ok(client.program(zk_onchain::id())?)
+}
+
+/// Fetch and decode the config of a mint created through the program
+pub async fn get_mint_config(rpc_client: &RpcClient, mint: Pubkey) -> Result<(Pubkey, MintConfig)> {
+    let (mint_config, _) = MintConfig::find_address(&mint);
+    let data = rpc_client.get_account_data(&mint_config).await?;
+
+    Ok((
+        mint_config,
+        MintConfig::try_deserialize(&mut data.as_slice())?,
+    ))
+}
+
+/// The payer signs as the mint's server authority
+async fn freeze_accounts(
+    rpc_client: &RpcClient,
+    payer: Pubkey,
+    service_signer: Pubkey,
+    mint: Pubkey,
+) -> Result<soda_accounts::FreezeCompressedTokens> {
+    let (mint_config_pda, mint_config) = get_mint_config(rpc_client, mint).await?;
+    ensure!(
+        mint_config.server_authority == payer,
+        "{} is not the server authority of {}",
+        payer,
+        mint
+    );
+
+    let (config_pda, registered_program_pda, account_compression_authority) =
+        get_program_addresses()?;
+
+    Ok(soda_accounts::FreezeCompressedTokens {
+        payer,
+        server_authority: payer,
+        service_signer,
+        config: config_pda,
+        service_signer_entry: ServiceSignerEntry::find_address(&config_pda, &service_signer).0,
+        mint_config: mint_config_pda,
+        freeze_authority: find_freeze_authority(&mint_config.server_authority, mint_config.nonce).0,
+        mint,
+        compressed_token_cpi_authority_pda: get_cpi_authority_pda().0,
+        compressed_token_program: light_compressed_token::ID,
+        light_system_program: PROGRAM_ID_LIGHT_SYSTEM,
+        registered_program_pda,
+        noop_program: light_sdk::PROGRAM_ID_NOOP,
+        account_compression_authority,
+        account_compression_program: PROGRAM_ID_ACCOUNT_COMPRESSION,
+        system_program: system_program::id(),
+    })
+}
+
+/// Freeze the initialized token accounts of `owner` for `mint`, up to `MAX_TOKEN_INPUTS`.
+/// Returns the instructions and the frozen amount.
+pub async fn freeze_tokens_instructions(
+    rpc_client: &RpcClient,
+    payer: Arc<Keypair>,
+    service_signer: Pubkey,
+    owner: Pubkey,
+    mint: Pubkey,
+) -> Result<(Vec<Instruction>, u64)> {
+    let program = freeze_program_client(payer.clone())?;
+
+    let mut remaining_accounts = RemainingAccounts::default();
+    let (token_inputs, amount) = fetch_token_inputs_by_state(
+        owner,
+        mint,
+        AccountState::Initialized,
+        &mut remaining_accounts,
+    )
+    .await?;
+
+    let mut instructions = program
+        .request()
+        .accounts(freeze_accounts(rpc_client, payer.pubkey(), service_signer, mint).await?)
+        .args(soda_instructions::FreezeCompressedTokens {
+            owner,
+            token_inputs,
+        })
+        .instructions()?;
+
+    instructions[0]
+        .accounts
+        .extend(remaining_accounts.to_account_metas());
+
+    Ok((instructions, amount))
+}
+
+/// Thaw the frozen token accounts of `owner` for `mint`, up to `MAX_TOKEN_INPUTS`.
+/// Returns the instructions and the thawed amount.
+pub async fn thaw_tokens_instructions(
+    rpc_client: &RpcClient,
+    payer: Arc<Keypair>,
+    service_signer: Pubkey,
+    owner: Pubkey,
+    mint: Pubkey,
+) -> Result<(Vec<Instruction>, u64)> {
+    let program = freeze_program_client(payer.clone())?;
+
+    let mut remaining_accounts = RemainingAccounts::default();
+    let (token_inputs, amount) =
+        fetch_token_inputs_by_state(owner, mint, AccountState::Frozen, &mut remaining_accounts)
+            .await?;
+
+    let mut instructions = program
+        .request()
+        .accounts(freeze_accounts(rpc_client, payer.pubkey(), service_signer, mint).await?)
+        .args(soda_instructions::ThawCompressedTokens {
+            owner,
+            token_inputs,
+        })
+        .instructions()?;
+
+    instructions[0]
+        .accounts
+        .extend(remaining_accounts.to_account_metas());
+
+    Ok((instructions, amount))
+}
+
+async fn send_freeze_instructions(
+    rpc_client: &RpcClient,
+    instructions: Vec<Instruction>,
+    payer: &Keypair,
+    service_signer: &Keypair,
+) -> Result<Signature> {
+    let mut all_instructions = vec![
+        ComputeBudgetInstruction::set_compute_unit_limit(1_000_000),
+        ComputeBudgetInstruction::set_compute_unit_price(100000),
+    ];
+    all_instructions.extend(instructions);
+
+    let recent_blockhash = rpc_client.get_latest_blockhash().await?;
+    let transaction = Transaction::new_signed_with_payer(
+        &all_instructions,
+        Some(&payer.pubkey()),
+        &[payer, service_signer],
+        recent_blockhash,
+    );
+
+    Ok(rpc_client
+        .send_and_confirm_transaction_with_spinner_and_config(
+            &transaction,
+            CommitmentConfig::processed(),
+            RpcSendTransactionConfig {
+                skip_preflight: true,
+                ..Default::default()
+            },
+        )
+        .await?)
+}
+
+/// Freeze tokens of `owner`, the payer signs as the mint's server and the service signer co-signs
+pub async fn freeze_tokens(
+    rpc_client: &RpcClient,
+    payer: Arc<Keypair>,
+    service_signer: &Keypair,
+    owner: Pubkey,
+    mint: Pubkey,
+) -> Result<()> {
+    let (instructions, amount) = freeze_tokens_instructions(
+        rpc_client,
+        payer.clone(),
+        service_signer.pubkey(),
+        owner,
+        mint,
+    )
+    .await?;
+
+    let signature =
+        send_freeze_instructions(rpc_client, instructions, &payer, service_signer).await?;
+
+    println!("Frozen {} of {}: {}", amount, mint, signature);
+    Ok(())
+}
+
+/// Thaw tokens of `owner`, the payer signs as the mint's server and the service signer co-signs
+pub async fn thaw_tokens(
+    rpc_client: &RpcClient,
+    payer: Arc<Keypair>,
+    service_signer: &Keypair,
+    owner: Pubkey,
+    mint: Pubkey,
+) -> Result<()> {
+    let (instructions, amount) = thaw_tokens_instructions(
+        rpc_client,
+        payer.clone(),
+        service_signer.pubkey(),
+        owner,
+        mint,
+    )
+    .await?;
+
+    let signature =
+        send_freeze_instructions(rpc_client, instructions, &payer, service_signer).await?;
+
+    println!("Thawed {} of {}: {}", amount, mint, signature);
+    Ok(())
+}
@@ -0,0 +1,319 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use anyhow::Result;
+use light_compressed_token::process_transfer::InputTokenDataWithContext;
+use light_sdk::merkle_context::RemainingAccounts;
+use light_system_program::sdk::compressed_account::PackedMerkleContext;
+use photon_api::apis::default_api::{
+    get_compressed_token_accounts_by_delegate_post, get_compressed_token_accounts_by_owner_post,
+    get_validity_proof_post,
+};
+use photon_api::models::{
+    AccountState, GetCompressedTokenAccountsByDelegatePostRequest,
+    GetCompressedTokenAccountsByDelegatePostRequestParams,
+    GetCompressedTokenAccountsByOwnerPostRequest,
+    GetCompressedTokenAccountsByOwnerPostRequestParams, GetValidityProofPostRequest,
+    GetValidityProofPostRequestParams, TokenAcccount,
+};
+use solana_sdk::pubkey::Pubkey;
+use zk_onchain::compressed_transfers::{CompressedTokenInputs, MintTokenInputs};
+
+use crate::settings::config::photon_config;
+
+use super::compressed_transfer_ix::get_legacy_proof;
+
+/// Most token accounts one validity proof covers
+pub const MAX_TOKEN_INPUTS: usize = 4;
+
+/// Picks the token accounts to spend for `amount`, as indices into `amounts`.
+///
+/// A single account is preferred, the smallest one that covers the amount. Otherwise the
+/// largest accounts are taken until the amount is covered. Returns `None` when the balance
+/// is short or more than `MAX_TOKEN_INPUTS` accounts would be needed.
+pub fn select_token_accounts(amounts: &[u64], amount: u64) -> Option<Vec<usize>> {
+    if let Some((index, _)) = amounts
+        .iter()
+        .enumerate()
+        .filter(|(_, account_amount)| **account_amount >= amount)
+        .min_by_key(|(_, account_amount)| **account_amount)
+    {
+        return Some(vec![index]);
+    }
+
+    let mut indices = (0..amounts.len()).collect::<Vec<_>>();
+    indices.sort_by_key(|index| std::cmp::Reverse(amounts[*index]));
+
+    let mut selected_amount = 0u64;
+    let mut selected = Vec::new();
+    for index in indices.into_iter().take(MAX_TOKEN_INPUTS) {
+        selected_amount = selected_amount.saturating_add(amounts[index]);
+        selected.push(index);
+        if selected_amount >= amount {
+            return Some(selected);
+        }
+    }
+
+    None
+}
+
+/// Collects compressed token accounts of `owner` for `mint` that cover `amount`, see
+/// `select_token_accounts`, and packs them with their proof for a program-side transfer.
+pub async fn fetch_token_inputs(
+    owner: Pubkey,
+    mint: Pubkey,
+    amount: u64,
+    remaining_accounts: &mut RemainingAccounts,
+) -> Result<CompressedTokenInputs> {
+    let items = fetch_owner_token_accounts(owner, Some(mint)).await?;
+
+    pack_token_inputs(owner, mint, amount, items, remaining_accounts).await
+}
+
+/// Packs up to `MAX_TOKEN_INPUTS` token accounts of `owner` for `mint` in `state`, for freezing
+/// initialized accounts or thawing frozen ones. Returns the inputs and the amount they hold.
+pub async fn fetch_token_inputs_by_state(
+    owner: Pubkey,
+    mint: Pubkey,
+    state: AccountState,
+    remaining_accounts: &mut RemainingAccounts,
+) -> Result<(CompressedTokenInputs, u64)> {
+    let items = fetch_owner_token_accounts(owner, Some(mint))
+        .await?
+        .into_iter()
+        .filter(|item| item.token_data.state == state)
+        .take(MAX_TOKEN_INPUTS)
+        .collect::<Vec<_>>();
+    if items.is_empty() {
+        anyhow::bail!("{owner} holds no {state:?} accounts of {mint}");
+    }
+
+    let amount = items
+        .iter()
+        .map(|item| item.token_data.amount as u64)
+        .sum::<u64>();
+    let inputs = pack_token_inputs(owner, mint, amount, items, remaining_accounts).await?;
+
+    Ok((inputs, amount))
+}
+
+/// Packs the initialized token accounts of `owner`, one set of inputs spending all of them
+/// per mint, for handing the whole balance over. Frozen accounts can't move and are left
+/// out.
+pub async fn fetch_token_inputs_by_mint(
+    owner: Pubkey,
+    remaining_accounts: &mut RemainingAccounts,
+) -> Result<Vec<MintTokenInputs>> {
+    let mut items_by_mint = BTreeMap::<String, Vec<TokenAcccount>>::new();
+    for item in fetch_owner_token_accounts(owner, None).await? {
+        if item.token_data.state == AccountState::Initialized {
+            items_by_mint
+                .entry(item.token_data.mint.clone())
+                .or_default()
+                .push(item);
+        }
+    }
+
+    let mut inputs = Vec::with_capacity(items_by_mint.len());
+    for (mint, items) in items_by_mint {
+        let mint = Pubkey::from_str(&mint)?;
+        let amount = items
+            .iter()
+            .map(|item| item.token_data.amount as u64)
+            .sum::<u64>();
+        inputs.push(MintTokenInputs {
+            mint,
+            inputs: pack_token_inputs(owner, mint, amount, items, remaining_accounts).await?,
+        });
+    }
+
+    Ok(inputs)
+}
+
+async fn fetch_owner_token_accounts(
+    owner: Pubkey,
+    mint: Option<Pubkey>,
+) -> Result<Vec<TokenAcccount>> {
+    let token_accounts = get_compressed_token_accounts_by_owner_post(
+        &photon_config(),
+        GetCompressedTokenAccountsByOwnerPostRequest {
+            params: Box::new(GetCompressedTokenAccountsByOwnerPostRequestParams {
+                owner: owner.to_string(),
+                mint: Some(mint.map(|mint| mint.to_string())),
+                ..Default::default()
+            }),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    Ok(token_accounts
+        .result
+        .map(|result| result.value.items)
+        .unwrap_or_default())
+}
+
+/// Like `fetch_token_inputs`, but only spends accounts of `owner` delegated to `delegate`
+pub async fn fetch_delegated_token_inputs(
+    owner: Pubkey,
+    delegate: Pubkey,
+    mint: Pubkey,
+    amount: u64,
+    remaining_accounts: &mut RemainingAccounts,
+) -> Result<CompressedTokenInputs> {
+    let token_accounts = get_compressed_token_accounts_by_delegate_post(
+        &photon_config(),
+        GetCompressedTokenAccountsByDelegatePostRequest {
+            params: Box::new(GetCompressedTokenAccountsByDelegatePostRequestParams {
+                delegate: delegate.to_string(),
+                mint: Some(Some(mint.to_string())),
+                ..Default::default()
+            }),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    let items = token_accounts
+        .result
+        .map(|result| result.value.items)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|item| item.token_data.owner == owner.to_string())
+        .collect();
+
+    pack_token_inputs(owner, mint, amount, items, remaining_accounts).await
+}
+
+async fn pack_token_inputs(
+    owner: Pubkey,
+    mint: Pubkey,
+    amount: u64,
+    items: Vec<TokenAcccount>,
+    remaining_accounts: &mut RemainingAccounts,
+) -> Result<CompressedTokenInputs> {
+    let merkle_tree_pubkey = Pubkey::from_str("smt1NamzXdq4AMqS2fS2F1i5KTYPZRhoHgWx38d8WsT")?;
+    let nullifier_queue_pubkey = Pubkey::from_str("nfq1NvQDJ2GEgnS8zt9prAe8rjjpAW1zFkrvZoBR148")?;
+
+    let amounts = items
+        .iter()
+        .map(|item| item.token_data.amount as u64)
+        .collect::<Vec<_>>();
+    let selected = select_token_accounts(&amounts, amount)
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "{owner} holds {} of {mint} in {} accounts, {amount} needed from at most {MAX_TOKEN_INPUTS}",
+                amounts.iter().sum::<u64>(),
+                amounts.len()
+            )
+        })?
+        .into_iter()
+        .map(|index| items[index].clone())
+        .collect::<Vec<_>>();
+
+    let proof_result = get_validity_proof_post(
+        &photon_config(),
+        GetValidityProofPostRequest {
+            params: Box::new(GetValidityProofPostRequestParams {
+                hashes: Some(
+                    selected
+                        .iter()
+                        .map(|item| item.account.hash.clone())
+                        .collect(),
+                ),
+                new_addresses: None,
+                new_addresses_with_trees: None,
+            }),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    let proof_value = proof_result
+        .result
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("No validity proof returned"))?
+        .value;
+    let (proof, _) = get_legacy_proof(proof_result)?;
+
+    let merkle_tree_pubkey_index = remaining_accounts.insert_or_get(merkle_tree_pubkey);
+    let nullifier_queue_pubkey_index = remaining_accounts.insert_or_get(nullifier_queue_pubkey);
+
+    // Delegated accounts hash their delegate, so it has to be passed along
+    let mut input_token_data_with_context = Vec::new();
+    for (i, item) in selected.iter().enumerate() {
+        let delegate_index = match item.token_data.delegate.clone().flatten() {
+            Some(delegate) => Some(remaining_accounts.insert_or_get(Pubkey::from_str(&delegate)?)),
+            None => None,
+        };
+        input_token_data_with_context.push(InputTokenDataWithContext {
+            amount: item.token_data.amount as u64,
+            delegate_index,
+            merkle_context: PackedMerkleContext {
+                merkle_tree_pubkey_index,
+                nullifier_queue_pubkey_index,
+                leaf_index: proof_value.leaf_indices[i] as u32,
+                queue_index: None,
+            },
+            root_index: proof_value.root_indices[i] as u16,
+            lamports: None,
+            tlv: None,
+        });
+    }
+
+    Ok(CompressedTokenInputs {
+        proof,
+        input_token_data_with_context,
+        recipient_merkle_tree_index: merkle_tree_pubkey_index,
+        change_merkle_tree_index: merkle_tree_pubkey_index,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exact_amount_takes_that_account() {
+        assert_eq!(select_token_accounts(&[10, 25, 40], 25), Some(vec![1]));
+    }
+
+    #[test]
+    fn smallest_covering_account_is_preferred() {
+        assert_eq!(select_token_accounts(&[100, 30, 60, 5], 50), Some(vec![2]));
+    }
+
+    #[test]
+    fn largest_accounts_fill_the_amount() {
+        assert_eq!(
+            select_token_accounts(&[10, 40, 5, 30, 20], 75),
+            Some(vec![1, 3, 4])
+        );
+    }
+
+    #[test]
+    fn more_accounts_than_one_proof_covers_are_refused() {
+        let amounts = [1; MAX_TOKEN_INPUTS + 1];
+
+        assert_eq!(
+            select_token_accounts(&amounts, MAX_TOKEN_INPUTS as u64),
+            Some((0..MAX_TOKEN_INPUTS).collect())
+        );
+        assert_eq!(
+            select_token_accounts(&amounts, MAX_TOKEN_INPUTS as u64 + 1),
+            None
+        );
+    }
+
+    #[test]
+    fn short_balance_is_refused() {
+        assert_eq!(select_token_accounts(&[10, 20], 31), None);
+        assert_eq!(select_token_accounts(&[], 1), None);
+    }
+
+    #[test]
+    fn zero_amount_takes_the_smallest_account() {
+        assert_eq!(select_token_accounts(&[10, 3, 7], 0), Some(vec![1]));
+        assert_eq!(select_token_accounts(&[], 0), None);
+    }
+}
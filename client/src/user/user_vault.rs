@@ -1,4 +1,5 @@
 use anchor_lang::AnchorDeserialize;
+use light_compressed_token::process_transfer::get_cpi_authority_pda as get_token_cpi_authority_pda;
 
 use light_sdk::address::derive_address;
 use light_sdk::address::derive_address_seed;
@@ -15,7 +16,6 @@ use light_sdk::verify::find_cpi_signer;
 use light_sdk::PROGRAM_ID_ACCOUNT_COMPRESSION;
 use light_sdk::PROGRAM_ID_LIGHT_SYSTEM;
 
-use photon_api::apis::configuration::{ApiKey, Configuration};
 use photon_api::apis::default_api::get_compressed_accounts_by_owner_post;
 use photon_api::apis::default_api::get_validity_proof_post;
 use photon_api::models::GetCompressedAccountsByOwnerPostRequest;
@@ -33,9 +33,11 @@ use solana_sdk::signature::Keypair;
 use solana_sdk::transaction::Transaction;
 use std::str::FromStr;
 use std::sync::Arc;
-use zk_onchain::vaults::UserVaultState;
+use zk_onchain::vaults::{UserVaultState, UserVaultStateV2, VaultType};
 
+use super::token_inputs::fetch_token_inputs_by_mint;
 use crate::settings::config::load_cfg;
+use crate::settings::config::photon_config;
 use crate::settings::config::ClientConfig;
 use crate::utils::config::keypair_1;
 use crate::utils::config::keypair_2;
@@ -49,10 +51,23 @@ use solana_sdk::signature::read_keypair_file;
 use solana_sdk::signer::Signer;
 use solana_sdk::system_program;
 use zk_onchain::state::*;
-use zk_onchain::vaults::config_authority;
+use zk_onchain::vaults::{config_authority, ServiceSignerEntry, UserVaultAuthority};
 use zk_onchain::{accounts as soda_accounts, instruction as soda_instructions};
 
 pub fn derive_user_vault(
+    vault_id: Pubkey,
+    address_merkle_context: AddressMerkleContext,
+) -> [u8; 32] {
+    let address_seed = derive_address_seed(
+        &[USER_VAULT_V2.as_bytes(), vault_id.as_ref()],
+        &zk_onchain::ID,
+    );
+
+    derive_address(&address_seed, &address_merkle_context)
+}
+
+// Address of a vault created before vault ids, derived from its current authority
+pub fn derive_legacy_user_vault(
     authority: Pubkey,
     address_merkle_context: AddressMerkleContext,
 ) -> [u8; 32] {
@@ -64,6 +79,59 @@ pub fn derive_user_vault(
     derive_address(&address_seed, &address_merkle_context)
 }
 
+/// Fetch the vault held by `current_authority` with its account hash and raw data.
+///
+/// A vault that still has the legacy layout is reported instead of skipped,
+/// it has to go through `migrate-user-vault` first.
+pub async fn find_user_vault(
+    current_authority: Pubkey,
+) -> Result<(String, UserVaultStateV2, Vec<u8>)> {
+    let compressed_accounts = get_compressed_accounts_by_owner_post(
+        &photon_config(),
+        GetCompressedAccountsByOwnerPostRequest {
+            params: Box::new(GetCompressedAccountsByOwnerPostRequestParams {
+                owner: zk_onchain::ID.to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    let mut legacy_vault = false;
+    for item in compressed_accounts
+        .result
+        .map(|result| result.value.items)
+        .unwrap_or_default()
+    {
+        let Some(data) = item
+            .data
+            .as_ref()
+            .and_then(|data| base64::decode(data.data.clone()).ok())
+        else {
+            continue;
+        };
+
+        // Borsh rejects trailing bytes, so the two layouts never parse as each other
+        if let Ok(state) = UserVaultStateV2::try_from_slice(&data) {
+            if state.vault_type == VaultType::User && state.current_authority == current_authority {
+                return Ok((item.hash, state, data));
+            }
+        } else if let Ok(state) = UserVaultState::try_from_slice(&data) {
+            legacy_vault |=
+                state.vault_type == VaultType::User && state.current_authority == current_authority;
+        }
+    }
+
+    if legacy_vault {
+        return Err(anyhow::anyhow!(
+            "The vault of {current_authority} has the legacy layout, run migrate-user-vault first"
+        ));
+    }
+
+    Err(anyhow::anyhow!("No user vault held by {current_authority}"))
+}
+
 /// Implementation for Solana RpcConnection Error
 // Get Merkle account parameters and proof
 async fn get_account_params(
@@ -112,17 +180,8 @@ async fn get_account_params(
         pack_address_merkle_context(address_merkle_context, &mut remaining_accounts);
 
     // Get proof
-    let rpc_config = Configuration {
-        base_path: "https://devnet.helius-rpc.com/".into(),
-        api_key: Some(ApiKey {
-            prefix: None,
-            key: "16ef3f61-7567-47d9-9c44-edec13422455".into(),
-        }),
-        ..Configuration::default()
-    };
-
     let proof_result = get_validity_proof_post(
-        &rpc_config,
+        &photon_config(),
         GetValidityProofPostRequest {
             params: Box::new(GetValidityProofPostRequestParams {
                 new_addresses: Some(vec![address_string]),
@@ -255,8 +314,13 @@ async fn build_instructions(
             service_signer: service_signer.pubkey(),
             current_authority: current_authority.pubkey(),
             cpi_signer,
+            vault_authority: UserVaultAuthority::find_address(&current_authority.pubkey()).0,
             config: config_pda,
-            config_authority: config_authority::id(),
+            service_signer_entry: ServiceSignerEntry::find_address(
+                &config_pda,
+                &service_signer.pubkey(),
+            )
+            .0,
             /* Light Accounts */
             system_program: system_program::id(),
             light_system_program: PROGRAM_ID_LIGHT_SYSTEM,
@@ -290,32 +354,57 @@ async fn build_instructions(
     Ok(instructions)
 }
 
+// Vault initialization instructions, without sending them
+pub async fn initialize_user_vault_instructions(
+    config: &ClientConfig,
+    payer: &Arc<Keypair>,
+    current_authority: &Keypair,
+    user_vault: Option<Pubkey>,
+    service_signer: &Keypair,
+) -> Result<Vec<Instruction>> {
+    // Get account parameters and proof
+    let params = get_account_params(current_authority.pubkey(), user_vault).await?;
+
+    build_instructions(config, payer, current_authority, service_signer, params).await
+}
+
 // Main vault initialization function
 pub async fn initialize_user_vault(
     rpc_client: &RpcClient,
     authority: Option<Arc<Keypair>>,
     user_vault: Option<Pubkey>,
+) -> Result<()> {
+    initialize_user_vault_with_signer(rpc_client, authority, user_vault, &keypair_2()).await
+}
+
+// Vault initialization signed by an explicit service signer
+pub async fn initialize_user_vault_with_signer(
+    rpc_client: &RpcClient,
+    authority: Option<Arc<Keypair>>,
+    user_vault: Option<Pubkey>,
+    service_signer: &Keypair,
 ) -> Result<()> {
     let client_config = "client_config.ini";
     let config = &load_cfg(&client_config.to_string()).unwrap();
     let current_authority = authority.unwrap_or(Arc::new(keypair_1()));
     // Create new keypairs for each role
     let payer = Arc::new(read_keypair_file(&config.payer_path).unwrap());
-    let service_signer = keypair_2();
-
-    // Get account parameters and proof
-    let params = get_account_params(current_authority.pubkey(), user_vault).await?;
 
-    // Build instructions
-    let instructions =
-        build_instructions(config, &payer, &current_authority, &service_signer, params).await?;
+    let instructions = initialize_user_vault_instructions(
+        config,
+        &payer,
+        &current_authority,
+        user_vault,
+        service_signer,
+    )
+    .await?;
 
     let recent_blockhash = rpc_client.get_latest_blockhash().await?;
 
     let transaction = Transaction::new_signed_with_payer(
         &instructions,
         Some(&payer.pubkey()),
-        &[&payer, &current_authority, &service_signer],
+        &[&payer, &current_authority, service_signer],
         recent_blockhash,
     );
 
@@ -334,7 +423,11 @@ pub async fn initialize_user_vault(
     Ok(())
 }
 
-async fn get_update_light_account_params(
+// Find the vault currently held by `current_authority` and get its proof.
+// The vault id is returned since the vault address stays derived from it.
+/// Proof, contexts and data for updating the user vault of `current_authority`, with its
+/// vault id last. The trees are packed first, so callers can add their own accounts after.
+pub async fn get_update_light_account_params(
     current_authority: Pubkey,
 ) -> Result<(
     u16,
@@ -343,6 +436,7 @@ async fn get_update_light_account_params(
     CompressedProof,
     RemainingAccounts,
     Vec<u8>,
+    Pubkey,
 )> {
     // Initialize account pubkeys
     let merkle_tree_pubkey = Pubkey::from_str("smt1NamzXdq4AMqS2fS2F1i5KTYPZRhoHgWx38d8WsT")?;
@@ -362,50 +456,17 @@ async fn get_update_light_account_params(
     let address_merkle_context =
         pack_address_merkle_context(address_merkle_context, &mut remaining_accounts);
 
-    // Get proof
-    let rpc_config = Configuration {
-        base_path: "https://devnet.helius-rpc.com/".into(),
-        api_key: Some(ApiKey {
-            prefix: None,
-            key: "16ef3f61-7567-47d9-9c44-edec13422455".into(),
-        }),
-        ..Configuration::default()
-    };
-
-    let compressed_accounts = get_compressed_accounts_by_owner_post(
-        &rpc_config,
-        GetCompressedAccountsByOwnerPostRequest {
-            params: Box::new(GetCompressedAccountsByOwnerPostRequestParams {
-                owner: zk_onchain::ID.to_string(),
-                ..Default::default()
-            }),
-            ..Default::default()
-        },
-    )
-    .await?;
-
-    let mut decoded_bytes = Vec::new();
+    let (vault_hash, user_vault_state, decoded_bytes) = find_user_vault(current_authority).await?;
 
-    let mut item_account = photon_api::models::Account::default();
-    if let Some(compressed_account) = compressed_accounts.result {
-        item_account = compressed_account.value.items[0].clone();
-        let compressed_data = item_account.data.clone().unwrap().data;
-
-        // First decode the base58 string into a Vec<u8>
-        decoded_bytes = base64::decode(compressed_data.clone())?;
-        // Create a mutable slice reference
-        let user_vault_state = UserVaultState::try_from_slice(&decoded_bytes)?;
-
-        println!("User Vault: {user_vault_state:#?}");
-    }
+    println!("User Vault: {user_vault_state:#?}");
 
     let proof_result = get_validity_proof_post(
-        &rpc_config,
+        &photon_config(),
         GetValidityProofPostRequest {
             params: Box::new(GetValidityProofPostRequestParams {
                 new_addresses: None,
                 new_addresses_with_trees: None,
-                hashes: Some(vec![item_account.hash.clone()]),
+                hashes: Some(vec![vault_hash]),
             }),
             ..Default::default()
         },
@@ -465,6 +526,7 @@ async fn get_update_light_account_params(
         compressed_proof,
         remaining_accounts,
         decoded_bytes,
+        user_vault_state.vault_id,
     ))
 }
 
@@ -481,6 +543,7 @@ async fn update_user_vault_instructions(
         CompressedProof,
         RemainingAccounts,
         Vec<u8>,
+        Pubkey,
     ),
 ) -> Result<Vec<Instruction>> {
     // Setup client
@@ -491,7 +554,7 @@ async fn update_user_vault_instructions(
     );
     let program = client.program(zk_onchain::id())?;
 
-    let (_config_pda, registered_program_pda, account_compression_authority) =
+    let (config_pda, registered_program_pda, account_compression_authority) =
         get_program_addresses()?;
 
     let cpi_signer = find_cpi_signer(&zk_onchain::ID);
@@ -503,6 +566,7 @@ async fn update_user_vault_instructions(
         compressed_proof,
         remaining_accounts,
         compressed_inputs,
+        vault_id,
     ) = params;
 
     // upate-user-vault-instruction
@@ -514,7 +578,15 @@ async fn update_user_vault_instructions(
             service_signer: service_signer.pubkey(),
             current_authority: current_authority.pubkey(),
             new_authority: new_authority.pubkey(),
+            vault_id,
             cpi_signer,
+            vault_authority: UserVaultAuthority::find_address(&vault_id).0,
+            config: config_pda,
+            service_signer_entry: ServiceSignerEntry::find_address(
+                &config_pda,
+                &service_signer.pubkey(),
+            )
+            .0,
             /* Light Accounts */
             system_program: system_program::id(),
             light_system_program: PROGRAM_ID_LIGHT_SYSTEM,
@@ -598,3 +670,194 @@ pub async fn update_user_vault(rpc_client: RpcClient) -> Result<()> {
     println!("Transaction signature: {}", signature);
     Ok(())
 }
+
+/// Fetch the legacy vault of `authority` with its account hash and raw data
+async fn find_legacy_user_vault(
+    authority: Pubkey,
+    address_merkle_context: AddressMerkleContext,
+) -> Result<(String, UserVaultState, Vec<u8>)> {
+    let legacy_vault =
+        bs58::encode(derive_legacy_user_vault(authority, address_merkle_context)).into_string();
+
+    let compressed_accounts = get_compressed_accounts_by_owner_post(
+        &photon_config(),
+        GetCompressedAccountsByOwnerPostRequest {
+            params: Box::new(GetCompressedAccountsByOwnerPostRequestParams {
+                owner: zk_onchain::ID.to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    let item = compressed_accounts
+        .result
+        .map(|result| result.value.items)
+        .unwrap_or_default()
+        .into_iter()
+        .find(|item| item.address.as_deref() == Some(legacy_vault.as_str()))
+        .ok_or_else(|| anyhow::anyhow!("No legacy user vault held by {authority}"))?;
+
+    let data = base64::decode(item.data.clone().unwrap().data)?;
+    let state = UserVaultState::try_from_slice(&data)?;
+
+    Ok((item.hash, state, data))
+}
+
+// Move the legacy vault of `current_authority` to the layout with a vault id
+pub async fn migrate_user_vault_instructions(
+    config: &ClientConfig,
+    payer: &Arc<Keypair>,
+    current_authority: Pubkey,
+) -> Result<Vec<Instruction>> {
+    let merkle_tree_pubkey = Pubkey::from_str("smt1NamzXdq4AMqS2fS2F1i5KTYPZRhoHgWx38d8WsT")?;
+    let address_merkle_tree_pubkey =
+        Pubkey::from_str("amt1Ayt45jfbdw5YSo7iz6WZxUmnZsQTYXy82hVwyC2")?;
+    let nullifier_queue_pubkey = Pubkey::from_str("nfq1NvQDJ2GEgnS8zt9prAe8rjjpAW1zFkrvZoBR148")?;
+    let address_merkle_tree_queue_pubkey =
+        Pubkey::from_str("aq1S9z4reTSQAdgWHGD2zDaS39sjGrAxbR31vxJ2F4F")?;
+
+    let mut remaining_accounts = RemainingAccounts::default();
+
+    let address_merkle_context = AddressMerkleContext {
+        address_merkle_tree_pubkey,
+        address_queue_pubkey: address_merkle_tree_queue_pubkey,
+    };
+
+    let (legacy_hash, _, legacy_data) =
+        find_legacy_user_vault(current_authority, address_merkle_context).await?;
+    let user_vault =
+        bs58::encode(derive_user_vault(current_authority, address_merkle_context)).into_string();
+
+    let address_merkle_context =
+        pack_address_merkle_context(address_merkle_context, &mut remaining_accounts);
+
+    // One proof for the legacy vault being closed and the new address being created
+    let proof_result = get_validity_proof_post(
+        &photon_config(),
+        GetValidityProofPostRequest {
+            params: Box::new(GetValidityProofPostRequestParams {
+                new_addresses: Some(vec![user_vault]),
+                new_addresses_with_trees: None,
+                hashes: Some(vec![legacy_hash]),
+            }),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    let proof_value = proof_result
+        .result
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("No validity proof returned"))?
+        .value;
+    // Input hash roots come first, then the new address roots
+    let root_indices = (
+        proof_value.root_indices[0] as u16,
+        proof_value.root_indices[1] as u16,
+    );
+    let merkle_context = pack_merkle_context(
+        MerkleContext {
+            merkle_tree_pubkey,
+            nullifier_queue_pubkey,
+            leaf_index: proof_value.leaf_indices[0] as u32,
+            queue_index: None,
+        },
+        &mut remaining_accounts,
+    );
+    let (compressed_proof, _) = get_proof(proof_result).await?;
+
+    // Every balance of the legacy token owner moves to the token owner of the vault id
+    let (legacy_token_owner, _) = UserVaultState::find_legacy_token_owner(&current_authority);
+    let legacy_balances =
+        fetch_token_inputs_by_mint(legacy_token_owner, &mut remaining_accounts).await?;
+
+    let client = Client::new_with_options(
+        Cluster::Custom(config.http_url.clone(), config.ws_url.clone()),
+        payer.clone(),
+        CommitmentConfig::processed(),
+    );
+    let program = client.program(zk_onchain::id())?;
+
+    let (config_pda, registered_program_pda, account_compression_authority) =
+        get_program_addresses()?;
+
+    let mut migrate_vault_ix = program
+        .request()
+        .accounts(soda_accounts::MigrateUserVault {
+            payer: payer.pubkey(),
+            self_program: zk_onchain::id(),
+            current_authority,
+            cpi_signer: find_cpi_signer(&zk_onchain::ID),
+            vault_authority: UserVaultAuthority::find_address(&current_authority).0,
+            legacy_token_owner,
+            compressed_token_cpi_authority_pda: get_token_cpi_authority_pda().0,
+            compressed_token_program: light_compressed_token::ID,
+            config: config_pda,
+            /* Light Accounts */
+            system_program: system_program::id(),
+            light_system_program: PROGRAM_ID_LIGHT_SYSTEM,
+            account_compression_program: PROGRAM_ID_ACCOUNT_COMPRESSION,
+            registered_program_pda,
+            noop_program: light_sdk::PROGRAM_ID_NOOP,
+            account_compression_authority,
+        })
+        .args(soda_instructions::MigrateUserVault {
+            proof: compressed_proof,
+            inputs: vec![legacy_data],
+            merkle_context,
+            address_merkle_context,
+            address_merkle_tree_root_index: root_indices.1,
+            merkle_tree_root_index: root_indices.0,
+            legacy_balances,
+        })
+        .instructions()?;
+
+    migrate_vault_ix[0]
+        .accounts
+        .extend(remaining_accounts.to_account_metas());
+
+    let mut instructions = vec![
+        ComputeBudgetInstruction::set_compute_unit_limit(1000000000),
+        ComputeBudgetInstruction::set_compute_unit_price(100000),
+    ];
+    instructions.extend(migrate_vault_ix);
+
+    Ok(instructions)
+}
+
+// Migrate the legacy vault held by `keypair_1`
+pub async fn migrate_user_vault(rpc_client: RpcClient) -> Result<()> {
+    let client_config = "client_config.ini";
+    let config = load_cfg(&client_config.to_string()).unwrap();
+
+    let current_authority = keypair_1();
+    let payer = Arc::new(read_keypair_file(&config.payer_path).unwrap());
+
+    let instructions =
+        migrate_user_vault_instructions(&config, &payer, current_authority.pubkey()).await?;
+
+    let recent_blockhash = rpc_client.get_latest_blockhash().await?;
+
+    let transaction = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&payer.pubkey()),
+        &[&payer, &current_authority],
+        recent_blockhash,
+    );
+
+    let signature = rpc_client
+        .send_and_confirm_transaction_with_spinner_and_config(
+            &transaction,
+            CommitmentConfig::processed(),
+            RpcSendTransactionConfig {
+                skip_preflight: true,
+                ..Default::default()
+            },
+        )
+        .await?;
+
+    println!("Transaction signature: {}", signature);
+    Ok(())
+}
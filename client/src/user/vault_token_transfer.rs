@@ -0,0 +1,122 @@
+use std::sync::Arc;
+
+use anchor_client::{Client, Cluster};
+use anyhow::Result;
+use light_compressed_token::process_transfer::get_cpi_authority_pda;
+use light_sdk::merkle_context::RemainingAccounts;
+use light_sdk::{PROGRAM_ID_ACCOUNT_COMPRESSION, PROGRAM_ID_LIGHT_SYSTEM};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcSendTransactionConfig;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Keypair;
+use solana_sdk::signer::Signer;
+use solana_sdk::system_program;
+use solana_sdk::transaction::Transaction;
+use zk_onchain::vaults::UserVaultAuthority;
+use zk_onchain::{accounts as soda_accounts, instruction as soda_instructions};
+
+use super::token_delegation::user_vault_token_owner;
+use super::token_inputs::fetch_token_inputs;
+use super::user_vault::{find_user_vault, get_program_addresses};
+use crate::settings::config::load_cfg;
+
+/// Send `amount` of `mint` held by the token owner PDA of the user vault of `authority`
+/// to `recipient`
+pub async fn transfer_user_vault_tokens_instructions(
+    authority: Arc<Keypair>,
+    mint: Pubkey,
+    amount: u64,
+    recipient: Pubkey,
+) -> Result<Vec<Instruction>> {
+    let config = load_cfg(&"client_config.ini".to_string()).unwrap();
+    let client = Client::new_with_options(
+        Cluster::Custom(config.http_url.clone(), config.ws_url.clone()),
+        authority.clone(),
+        CommitmentConfig::processed(),
+    );
+    let program = client.program(zk_onchain::id())?;
+    let (config_pda, registered_program_pda, account_compression_authority) =
+        get_program_addresses()?;
+
+    let (_, vault, _) = find_user_vault(authority.pubkey()).await?;
+    let token_owner = user_vault_token_owner(vault.vault_id);
+
+    let mut remaining_accounts = RemainingAccounts::default();
+    let vault_inputs =
+        fetch_token_inputs(token_owner, mint, amount, &mut remaining_accounts).await?;
+
+    let mut instructions = program
+        .request()
+        .accounts(soda_accounts::TransferUserVaultTokens {
+            payer: authority.pubkey(),
+            current_authority: authority.pubkey(),
+            vault_id: vault.vault_id,
+            vault_authority: UserVaultAuthority::find_address(&vault.vault_id).0,
+            token_owner,
+            compressed_token_cpi_authority_pda: get_cpi_authority_pda().0,
+            compressed_token_program: light_compressed_token::ID,
+            light_system_program: PROGRAM_ID_LIGHT_SYSTEM,
+            registered_program_pda,
+            noop_program: light_sdk::PROGRAM_ID_NOOP,
+            account_compression_authority,
+            account_compression_program: PROGRAM_ID_ACCOUNT_COMPRESSION,
+            system_program: system_program::id(),
+            config: config_pda,
+        })
+        .args(soda_instructions::TransferUserVaultTokens {
+            mint,
+            amount,
+            recipient,
+            vault_inputs,
+        })
+        .instructions()?;
+
+    instructions[0]
+        .accounts
+        .extend(remaining_accounts.to_account_metas());
+
+    Ok(instructions)
+}
+
+/// Send tokens out of the user vault with its authority as fee payer
+pub async fn transfer_user_vault_tokens(
+    rpc_client: &RpcClient,
+    authority: Arc<Keypair>,
+    mint: Pubkey,
+    amount: u64,
+    recipient: Pubkey,
+) -> Result<()> {
+    let mut instructions = vec![
+        ComputeBudgetInstruction::set_compute_unit_limit(1_000_000),
+        ComputeBudgetInstruction::set_compute_unit_price(100000),
+    ];
+    instructions.extend(
+        transfer_user_vault_tokens_instructions(authority.clone(), mint, amount, recipient).await?,
+    );
+
+    let transaction = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&authority.pubkey()),
+        &[&authority],
+        rpc_client.get_latest_blockhash().await?,
+    );
+    let signature = rpc_client
+        .send_and_confirm_transaction_with_spinner_and_config(
+            &transaction,
+            CommitmentConfig::processed(),
+            RpcSendTransactionConfig {
+                skip_preflight: true,
+                ..Default::default()
+            },
+        )
+        .await?;
+
+    println!(
+        "Sent {} of {} from the user vault to {}: {}",
+        amount, mint, recipient, signature
+    );
+    Ok(())
+}
@@ -0,0 +1,290 @@
+use std::sync::Arc;
+
+use anchor_client::{Client, Cluster};
+use anchor_lang::AccountDeserialize;
+use anyhow::Result;
+use light_compressed_token::process_transfer::get_cpi_authority_pda;
+use light_sdk::merkle_context::RemainingAccounts;
+use light_sdk::{PROGRAM_ID_ACCOUNT_COMPRESSION, PROGRAM_ID_LIGHT_SYSTEM};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcSendTransactionConfig;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signature};
+use solana_sdk::signer::Signer;
+use solana_sdk::system_program;
+use solana_sdk::transaction::Transaction;
+use zk_onchain::vaults::{
+    ServiceSignerEntry, UserVaultAuthority, VestingVaultParams, VestingVaultState,
+};
+use zk_onchain::{accounts as soda_accounts, instruction as soda_instructions};
+
+use super::token_inputs::fetch_token_inputs;
+use super::user_vault::get_program_addresses;
+use crate::settings::config::load_cfg;
+
+fn vesting_program_client(payer: Arc<Keypair>) -> Result<anchor_client::Program<Arc<Keypair>>> {
+    let client_config = "client_config.ini";
+    let config = load_cfg(&client_config.to_string()).unwrap();
+
+    let client = Client::new_with_options(
+        Cluster::Custom(config.http_url.clone(), config.ws_url.clone()),
+        payer,
+        CommitmentConfig::processed(),
+    );
+
+    Ok(client.program(zk_onchain::id())?)
+}
+
+/// Fetch and decode an open grant
+pub async fn get_vesting_vault(
+    rpc_client: &RpcClient,
+    grantor: Pubkey,
+    vesting_id: u64,
+) -> Result<(Pubkey, VestingVaultState)> {
+    let (vesting_vault, _) = VestingVaultState::find_address(&grantor, vesting_id);
+    let data = rpc_client.get_account_data(&vesting_vault).await?;
+
+    Ok((
+        vesting_vault,
+        VestingVaultState::try_deserialize(&mut data.as_slice())?,
+    ))
+}
+
+/// Open a grant, locking `params.total_amount` of the grantor's compressed tokens
+pub async fn create_vesting_vault_instructions(
+    payer: Arc<Keypair>,
+    grantor: Pubkey,
+    service_signer: Pubkey,
+    params: VestingVaultParams,
+) -> Result<Vec<Instruction>> {
+    let program = vesting_program_client(payer.clone())?;
+    let (config_pda, registered_program_pda, account_compression_authority) =
+        get_program_addresses()?;
+
+    let mut remaining_accounts = RemainingAccounts::default();
+    let grantor_inputs = fetch_token_inputs(
+        grantor,
+        params.mint,
+        params.total_amount,
+        &mut remaining_accounts,
+    )
+    .await?;
+
+    let (vesting_vault, _) = VestingVaultState::find_address(&grantor, params.vesting_id);
+
+    let mut instructions = program
+        .request()
+        .accounts(soda_accounts::InitializeVestingVault {
+            payer: payer.pubkey(),
+            service_signer,
+            grantor,
+            vesting_vault,
+            beneficiary_vault: UserVaultAuthority::find_address(&params.beneficiary_vault_id).0,
+            config: config_pda,
+            service_signer_entry: ServiceSignerEntry::find_address(&config_pda, &service_signer).0,
+            compressed_token_cpi_authority_pda: get_cpi_authority_pda().0,
+            compressed_token_program: light_compressed_token::ID,
+            light_system_program: PROGRAM_ID_LIGHT_SYSTEM,
+            registered_program_pda,
+            noop_program: light_sdk::PROGRAM_ID_NOOP,
+            account_compression_authority,
+            account_compression_program: PROGRAM_ID_ACCOUNT_COMPRESSION,
+            system_program: system_program::id(),
+        })
+        .args(soda_instructions::InitializeVestingVault {
+            params,
+            grantor_inputs,
+        })
+        .instructions()?;
+
+    instructions[0]
+        .accounts
+        .extend(remaining_accounts.to_account_metas());
+
+    Ok(instructions)
+}
+
+/// Withdraw everything vested so far into the beneficiary's user vault
+pub async fn withdraw_vested_tokens_instructions(
+    rpc_client: &RpcClient,
+    beneficiary: Arc<Keypair>,
+    grantor: Pubkey,
+    vesting_id: u64,
+) -> Result<Vec<Instruction>> {
+    let program = vesting_program_client(beneficiary.clone())?;
+    let (config_pda, registered_program_pda, account_compression_authority) =
+        get_program_addresses()?;
+    let (vesting_vault, grant) = get_vesting_vault(rpc_client, grantor, vesting_id).await?;
+
+    // The releasable amount is worked out on-chain, so spend the whole locked balance
+    let mut remaining_accounts = RemainingAccounts::default();
+    let vesting_inputs = fetch_token_inputs(
+        vesting_vault,
+        grant.mint,
+        grant.total_amount - grant.withdrawn_amount,
+        &mut remaining_accounts,
+    )
+    .await?;
+
+    let mut instructions = program
+        .request()
+        .accounts(soda_accounts::WithdrawVestedTokens {
+            beneficiary: beneficiary.pubkey(),
+            vesting_vault,
+            grantor,
+            config: config_pda,
+            compressed_token_cpi_authority_pda: get_cpi_authority_pda().0,
+            compressed_token_program: light_compressed_token::ID,
+            light_system_program: PROGRAM_ID_LIGHT_SYSTEM,
+            registered_program_pda,
+            noop_program: light_sdk::PROGRAM_ID_NOOP,
+            account_compression_authority,
+            account_compression_program: PROGRAM_ID_ACCOUNT_COMPRESSION,
+            system_program: system_program::id(),
+        })
+        .args(soda_instructions::WithdrawVestedTokens { vesting_inputs })
+        .instructions()?;
+
+    instructions[0]
+        .accounts
+        .extend(remaining_accounts.to_account_metas());
+
+    Ok(instructions)
+}
+
+/// Take back the unvested part of a revocable grant
+pub async fn revoke_vesting_instructions(
+    rpc_client: &RpcClient,
+    grantor: Arc<Keypair>,
+    vesting_id: u64,
+) -> Result<Vec<Instruction>> {
+    let program = vesting_program_client(grantor.clone())?;
+    let (config_pda, registered_program_pda, account_compression_authority) =
+        get_program_addresses()?;
+    let (vesting_vault, grant) =
+        get_vesting_vault(rpc_client, grantor.pubkey(), vesting_id).await?;
+
+    let mut remaining_accounts = RemainingAccounts::default();
+    let vesting_inputs = fetch_token_inputs(
+        vesting_vault,
+        grant.mint,
+        grant.total_amount - grant.withdrawn_amount,
+        &mut remaining_accounts,
+    )
+    .await?;
+
+    let mut instructions = program
+        .request()
+        .accounts(soda_accounts::RevokeVesting {
+            grantor: grantor.pubkey(),
+            vesting_vault,
+            config: config_pda,
+            compressed_token_cpi_authority_pda: get_cpi_authority_pda().0,
+            compressed_token_program: light_compressed_token::ID,
+            light_system_program: PROGRAM_ID_LIGHT_SYSTEM,
+            registered_program_pda,
+            noop_program: light_sdk::PROGRAM_ID_NOOP,
+            account_compression_authority,
+            account_compression_program: PROGRAM_ID_ACCOUNT_COMPRESSION,
+            system_program: system_program::id(),
+        })
+        .args(soda_instructions::RevokeVesting { vesting_inputs })
+        .instructions()?;
+
+    instructions[0]
+        .accounts
+        .extend(remaining_accounts.to_account_metas());
+
+    Ok(instructions)
+}
+
+async fn send_vesting_instructions(
+    rpc_client: &RpcClient,
+    instructions: Vec<Instruction>,
+    payer: &Keypair,
+    signers: &[&Keypair],
+) -> Result<Signature> {
+    let mut all_instructions = vec![
+        ComputeBudgetInstruction::set_compute_unit_limit(1_000_000),
+        ComputeBudgetInstruction::set_compute_unit_price(100000),
+    ];
+    all_instructions.extend(instructions);
+
+    let mut all_signers = vec![payer];
+    all_signers.extend(signers.iter().copied());
+
+    let recent_blockhash = rpc_client.get_latest_blockhash().await?;
+    let transaction = Transaction::new_signed_with_payer(
+        &all_instructions,
+        Some(&payer.pubkey()),
+        &all_signers,
+        recent_blockhash,
+    );
+
+    Ok(rpc_client
+        .send_and_confirm_transaction_with_spinner_and_config(
+            &transaction,
+            CommitmentConfig::processed(),
+            RpcSendTransactionConfig {
+                skip_preflight: true,
+                ..Default::default()
+            },
+        )
+        .await?)
+}
+
+/// Open a grant with the grantor keypair as fee payer
+pub async fn create_vesting_vault(
+    rpc_client: &RpcClient,
+    grantor: Arc<Keypair>,
+    service_signer: &Keypair,
+    params: VestingVaultParams,
+) -> Result<()> {
+    let instructions = create_vesting_vault_instructions(
+        grantor.clone(),
+        grantor.pubkey(),
+        service_signer.pubkey(),
+        params,
+    )
+    .await?;
+
+    let signature =
+        send_vesting_instructions(rpc_client, instructions, &grantor, &[service_signer]).await?;
+
+    println!("Vesting vault created: {}", signature);
+    Ok(())
+}
+
+/// Withdraw vested tokens with the beneficiary keypair as fee payer
+pub async fn withdraw_vested_tokens(
+    rpc_client: &RpcClient,
+    beneficiary: Arc<Keypair>,
+    grantor: Pubkey,
+    vesting_id: u64,
+) -> Result<()> {
+    let instructions =
+        withdraw_vested_tokens_instructions(rpc_client, beneficiary.clone(), grantor, vesting_id)
+            .await?;
+
+    let signature = send_vesting_instructions(rpc_client, instructions, &beneficiary, &[]).await?;
+
+    println!("Vested tokens withdrawn: {}", signature);
+    Ok(())
+}
+
+/// Revoke a grant with the grantor keypair as fee payer
+pub async fn revoke_vesting(
+    rpc_client: &RpcClient,
+    grantor: Arc<Keypair>,
+    vesting_id: u64,
+) -> Result<()> {
+    let instructions = revoke_vesting_instructions(rpc_client, grantor.clone(), vesting_id).await?;
+
+    let signature = send_vesting_instructions(rpc_client, instructions, &grantor, &[]).await?;
+
+    println!("Vesting revoked: {}", signature);
+    Ok(())
+}
@@ -0,0 +1,217 @@
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use anyhow::Result;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{pubkey::Pubkey, transaction::Transaction};
+use std::str::FromStr;
+use zk_onchain::state::events::*;
+
+/// Prefix of the log line `emit!` writes the serialized event to
+const PROGRAM_DATA_LOG: &str = "Program data: ";
+
+/// Prefix of the runtime's invoke, success and failure log lines
+const PROGRAM_LOG: &str = "Program ";
+
+macro_rules! soda_events {
+    ($($event:ident),* $(,)?) => {
+        /// Every event emitted by the zk_onchain program
+        #[derive(Debug)]
+        pub enum SodaEvent {
+            $($event($event),)*
+        }
+
+        fn decode_event(data: &[u8]) -> Option<SodaEvent> {
+            if data.len() < 8 {
+                return None;
+            }
+            let (discriminator, mut payload) = data.split_at(8);
+
+            $(
+                if discriminator == <$event as Discriminator>::DISCRIMINATOR {
+                    return $event::deserialize(&mut payload).ok().map(SodaEvent::$event);
+                }
+            )*
+
+            None
+        }
+    };
+}
+
+soda_events!(
+    VaultConfigInitialized,
+    VaultConfigUpdated,
+    ServiceSignerAdded,
+    ServiceSignerRemoved,
+    ServiceSignerPermissionsUpdated,
+    ServiceSignerRotated,
+    UserVaultInitialized,
+    UserVaultAuthorityChanged,
+    UserVaultMigrated,
+    ServerVaultInitialized,
+    ServerVaultAuthorityChanged,
+    EscrowOfferCreated,
+    EscrowOfferUpdated,
+    EscrowOfferFilled,
+    EscrowOfferCancelled,
+    ArbiterEscrowCreated,
+    ArbiterEscrowReleased,
+    ArbiterDisputeOpened,
+    ArbiterDisputeResolved,
+    AirdropVaultInitialized,
+    AirdropVaultAuthorityChanged,
+    AirdropClaimed,
+    AirdropClawedBack,
+    VestingVaultCreated,
+    VestedTokensWithdrawn,
+    VestingRevoked,
+    CompressedMintCreated,
+    CompressedMint2022Created,
+    CompressedMintMetadataUpdated,
+    CompressedMintMetadataAuthorityTransferred,
+    TokensMinted,
+    TokensCompressed,
+    TokensDecompressed,
+    WalletTokensTransferred,
+    PdaTokensTransferred,
+    UserVaultTokensTransferred,
+    TokensDelegated,
+    DelegationRevoked,
+    DelegatedTokensTransferred,
+    TokensFrozen,
+    TokensThawed,
+    TokensBurned,
+    UserCreated,
+    UserHandleCreated,
+    UserHandleTransferred,
+    ServerCreated,
+    ServerOwnershipTransferred,
+);
+
+/// Decodes the events emitted by the zk_onchain program from transaction logs.
+///
+/// The runtime logs "Program <id> invoke [depth]" when a program starts and
+/// "Program <id> success" or "Program <id> failed: ..." when it returns, so the
+/// program that wrote a "Program data:" line is the innermost one still running.
+/// Data lines of other programs, such as CPI targets, are skipped.
+pub fn parse_events(logs: &[String]) -> Vec<SodaEvent> {
+    let program_id = zk_onchain::ID.to_string();
+    let mut invoked: Vec<&str> = Vec::new();
+    let mut events = Vec::new();
+
+    for log in logs {
+        if let Some(data) = log.strip_prefix(PROGRAM_DATA_LOG) {
+            if invoked.last() != Some(&program_id.as_str()) {
+                continue;
+            }
+            if let Some(event) = STANDARD
+                .decode(data)
+                .ok()
+                .and_then(|data| decode_event(&data))
+            {
+                events.push(event);
+            }
+        } else if let Some(rest) = log.strip_prefix(PROGRAM_LOG) {
+            let mut parts = rest.split_whitespace();
+            let (Some(program), Some(status)) = (parts.next(), parts.next()) else {
+                continue;
+            };
+            // Skips "Program log:", "Program return:" and the like
+            if Pubkey::from_str(program).is_err() {
+                continue;
+            }
+            match status {
+                "invoke" => invoked.push(program),
+                "success" | "failed:" => {
+                    invoked.pop();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    events
+}
+
+/// Simulates `transaction` and returns the events it would emit
+pub async fn simulate_events(
+    rpc_client: &RpcClient,
+    transaction: &Transaction,
+) -> Result<Vec<SodaEvent>> {
+    let simulation = rpc_client.simulate_transaction(transaction).await?;
+
+    if let Some(err) = simulation.value.err {
+        return Err(anyhow::anyhow!("Simulation failed: {:?}", err));
+    }
+
+    Ok(parse_events(&simulation.value.logs.unwrap_or_default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::Event;
+
+    fn burned(amount: u64) -> TokensBurned {
+        TokensBurned {
+            mint: Pubkey::new_unique(),
+            owner: Pubkey::new_unique(),
+            authority: Pubkey::new_unique(),
+            amount,
+        }
+    }
+
+    fn data_line(event: &impl Event) -> String {
+        format!("{PROGRAM_DATA_LOG}{}", STANDARD.encode(event.data()))
+    }
+
+    fn burned_amounts(events: &[SodaEvent]) -> Vec<u64> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                SodaEvent::TokensBurned(burned) => Some(burned.amount),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn only_events_of_the_program_decode() {
+        let program = zk_onchain::ID;
+        let token_program = Pubkey::new_unique();
+        let failing_program = Pubkey::new_unique();
+
+        let logs = vec![
+            format!("Program {program} invoke [1]"),
+            "Program log: Instruction: BurnUserVaultTokens".to_string(),
+            format!("Program {token_program} invoke [2]"),
+            data_line(&burned(1)),
+            format!("Program {token_program} success"),
+            format!("Program {failing_program} invoke [2]"),
+            data_line(&burned(2)),
+            format!("Program {failing_program} failed: custom program error: 0x1"),
+            format!("Program log: {}", STANDARD.encode(burned(3).data())),
+            data_line(&burned(4)),
+            format!("Program {program} consumed 52000 of 200000 compute units"),
+            format!("Program {program} success"),
+            data_line(&burned(5)),
+        ];
+
+        assert_eq!(burned_amounts(&parse_events(&logs)), vec![4]);
+    }
+
+    #[test]
+    fn undecodable_data_is_skipped() {
+        let program = zk_onchain::ID;
+
+        let logs = vec![
+            format!("Program {program} invoke [1]"),
+            format!("{PROGRAM_DATA_LOG}not base64!"),
+            format!("{PROGRAM_DATA_LOG}{}", STANDARD.encode([0u8; 4])),
+            format!("{PROGRAM_DATA_LOG}{}", STANDARD.encode([7u8; 40])),
+            data_line(&burned(6)),
+            format!("Program {program} success"),
+        ];
+
+        assert_eq!(burned_amounts(&parse_events(&logs)), vec![6]);
+    }
+}
@@ -1,4 +1,5 @@
 pub mod config;
+pub mod events;
 pub mod pinata_service;
 pub mod spl;
 pub mod vectorizer;
@@ -1,3 +1,7 @@
 pub mod user_vault_transfer;
 
 pub use user_vault_transfer::*;
+
+pub mod token_transfer;
+
+pub use token_transfer::*;
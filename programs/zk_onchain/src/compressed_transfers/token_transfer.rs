@@ -0,0 +1,273 @@
+use crate::state::CustomError;
+use anchor_lang::prelude::*;
+use light_compressed_token::process_transfer::{
+    CompressedTokenInstructionDataTransfer, DelegatedTransfer, InputTokenDataWithContext,
+    PackedTokenTransferOutputData,
+};
+use light_system_program::invoke::processor::CompressedProof;
+
+/// Compressed token accounts spent by one transfer, with the proof the client fetched for them
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct CompressedTokenInputs {
+    pub proof: CompressedProof,
+    pub input_token_data_with_context: Vec<InputTokenDataWithContext>,
+    /// Remaining account index of the output tree of the recipient
+    pub recipient_merkle_tree_index: u8,
+    /// Remaining account index of the output tree of the change
+    pub change_merkle_tree_index: u8,
+}
+
+/// Compressed token accounts of one mint that move as a whole
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct MintTokenInputs {
+    pub mint: Pubkey,
+    pub inputs: CompressedTokenInputs,
+}
+
+/// One output of a multi-recipient transfer
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct TokenTransferRecipient {
+    pub owner: Pubkey,
+    pub amount: u64,
+}
+
+/// Total amount of the input token accounts
+pub fn sum_token_inputs(inputs: &[InputTokenDataWithContext]) -> Result<u64> {
+    Ok(inputs
+        .iter()
+        .try_fold(0u64, |sum, input| sum.checked_add(input.amount))
+        .ok_or(CustomError::InsufficientTokenInput)?)
+}
+
+/// Accounts of a light compressed token transfer CPI
+pub struct CompressedTokenCpiAccounts<'a, 'info> {
+    pub fee_payer: AccountInfo<'info>,
+    /// Owner of the input token accounts
+    pub authority: AccountInfo<'info>,
+    pub cpi_authority_pda: AccountInfo<'info>,
+    pub compressed_token_program: AccountInfo<'info>,
+    pub light_system_program: AccountInfo<'info>,
+    pub registered_program_pda: AccountInfo<'info>,
+    pub noop_program: AccountInfo<'info>,
+    pub account_compression_authority: AccountInfo<'info>,
+    pub account_compression_program: AccountInfo<'info>,
+    pub system_program: AccountInfo<'info>,
+    /// Merkle trees and queues referenced by the inputs
+    pub remaining_accounts: &'a [AccountInfo<'info>],
+}
+
+/// Implements `token_cpi_accounts` for an instruction context with the compressed token
+/// and light system account fields every token moving context declares. `$fee_payer` names
+/// the field paying for the CPI, the token owner is passed on each call.
+macro_rules! impl_token_cpi_accounts {
+    ($context:ident, $fee_payer:ident) => {
+        impl<'info> $context<'info> {
+            pub(crate) fn token_cpi_accounts<'a>(
+                &self,
+                authority: AccountInfo<'info>,
+                remaining_accounts: &'a [AccountInfo<'info>],
+            ) -> $crate::compressed_transfers::CompressedTokenCpiAccounts<'a, 'info> {
+                $crate::compressed_transfers::CompressedTokenCpiAccounts {
+                    fee_payer: self.$fee_payer.to_account_info(),
+                    authority,
+                    cpi_authority_pda: self.compressed_token_cpi_authority_pda.to_account_info(),
+                    compressed_token_program: self.compressed_token_program.to_account_info(),
+                    light_system_program: self.light_system_program.to_account_info(),
+                    registered_program_pda: self.registered_program_pda.to_account_info(),
+                    noop_program: self.noop_program.to_account_info(),
+                    account_compression_authority: self
+                        .account_compression_authority
+                        .to_account_info(),
+                    account_compression_program: self.account_compression_program.to_account_info(),
+                    system_program: self.system_program.to_account_info(),
+                    remaining_accounts,
+                }
+            }
+        }
+    };
+}
+pub(crate) use impl_token_cpi_accounts;
+
+/// Moves `amount` of `mint` from the inputs to `recipient`, the rest goes to `change_owner`.
+///
+/// `signer_seeds` is empty when `authority` signed the transaction itself.
+pub fn transfer_compressed_tokens_cpi<'info>(
+    accounts: &CompressedTokenCpiAccounts<'_, 'info>,
+    signer_seeds: &[&[&[u8]]],
+    mint: Pubkey,
+    amount: u64,
+    recipient: Pubkey,
+    change_owner: Pubkey,
+    inputs: CompressedTokenInputs,
+) -> Result<()> {
+    let input_amount = sum_token_inputs(&inputs.input_token_data_with_context)?;
+    let change_amount = input_amount
+        .checked_sub(amount)
+        .ok_or(CustomError::InsufficientTokenInput)?;
+
+    let mut output_compressed_accounts = vec![PackedTokenTransferOutputData {
+        amount,
+        owner: recipient,
+        lamports: None,
+        merkle_tree_index: inputs.recipient_merkle_tree_index,
+        tlv: None,
+    }];
+    if change_amount > 0 {
+        output_compressed_accounts.push(PackedTokenTransferOutputData {
+            amount: change_amount,
+            owner: change_owner,
+            lamports: None,
+            merkle_tree_index: inputs.change_merkle_tree_index,
+            tlv: None,
+        });
+    }
+
+    invoke_transfer(
+        accounts,
+        signer_seeds,
+        mint,
+        inputs,
+        output_compressed_accounts,
+        None,
+    )
+}
+
+/// Moves everything in `balances` to `recipient`, one transfer per mint. Returns the mints.
+pub fn transfer_balances_cpi<'info>(
+    accounts: &CompressedTokenCpiAccounts<'_, 'info>,
+    signer_seeds: &[&[&[u8]]],
+    balances: Vec<MintTokenInputs>,
+    recipient: Pubkey,
+) -> Result<Vec<Pubkey>> {
+    let mut mints = Vec::with_capacity(balances.len());
+    for balance in balances {
+        let output = balance_output(&balance.inputs, recipient)?;
+        invoke_transfer(
+            accounts,
+            signer_seeds,
+            balance.mint,
+            balance.inputs,
+            vec![output],
+            None,
+        )?;
+        mints.push(balance.mint);
+    }
+
+    Ok(mints)
+}
+
+/// The one output taking everything in `inputs` to `recipient`, so no change is left
+pub fn balance_output(
+    inputs: &CompressedTokenInputs,
+    recipient: Pubkey,
+) -> Result<PackedTokenTransferOutputData> {
+    let amount = sum_token_inputs(&inputs.input_token_data_with_context)?;
+    require!(amount > 0, CustomError::ZeroAmount);
+
+    Ok(PackedTokenTransferOutputData {
+        amount,
+        owner: recipient,
+        lamports: None,
+        merkle_tree_index: inputs.recipient_merkle_tree_index,
+        tlv: None,
+    })
+}
+
+/// Moves `amount` of `owner`'s tokens to `recipient`, signed by the delegate as
+/// `accounts.authority`. The inputs must be delegated to it, and the rest of the allowance
+/// stays delegated in the change output.
+pub fn delegated_transfer_compressed_tokens_cpi<'info>(
+    accounts: &CompressedTokenCpiAccounts<'_, 'info>,
+    mint: Pubkey,
+    amount: u64,
+    recipient: Pubkey,
+    owner: Pubkey,
+    inputs: CompressedTokenInputs,
+) -> Result<()> {
+    require!(amount > 0, CustomError::ZeroAmount);
+
+    let input_amount = sum_token_inputs(&inputs.input_token_data_with_context)?;
+    let change_amount = input_amount
+        .checked_sub(amount)
+        .ok_or(CustomError::InsufficientTokenInput)?;
+
+    let mut output_compressed_accounts = vec![PackedTokenTransferOutputData {
+        amount,
+        owner: recipient,
+        lamports: None,
+        merkle_tree_index: inputs.recipient_merkle_tree_index,
+        tlv: None,
+    }];
+    let mut delegate_change_account_index = None;
+    if change_amount > 0 {
+        delegate_change_account_index = Some(output_compressed_accounts.len() as u8);
+        output_compressed_accounts.push(PackedTokenTransferOutputData {
+            amount: change_amount,
+            owner,
+            lamports: None,
+            merkle_tree_index: inputs.change_merkle_tree_index,
+            tlv: None,
+        });
+    }
+
+    invoke_transfer(
+        accounts,
+        &[],
+        mint,
+        inputs,
+        output_compressed_accounts,
+        Some(DelegatedTransfer {
+            owner,
+            delegate_change_account_index,
+        }),
+    )
+}
+
+fn invoke_transfer<'info>(
+    accounts: &CompressedTokenCpiAccounts<'_, 'info>,
+    signer_seeds: &[&[&[u8]]],
+    mint: Pubkey,
+    inputs: CompressedTokenInputs,
+    output_compressed_accounts: Vec<PackedTokenTransferOutputData>,
+    delegated_transfer: Option<DelegatedTransfer>,
+) -> Result<()> {
+    let inputs_struct = CompressedTokenInstructionDataTransfer {
+        proof: Some(inputs.proof),
+        mint,
+        delegated_transfer,
+        input_token_data_with_context: inputs.input_token_data_with_context,
+        output_compressed_accounts,
+        is_compress: false,
+        compress_or_decompress_amount: None,
+        cpi_context: None,
+        lamports_change_account_merkle_tree_index: None,
+    };
+
+    let mut data = Vec::new();
+    CompressedTokenInstructionDataTransfer::serialize(&inputs_struct, &mut data)?;
+
+    let cpi_accounts = light_compressed_token::cpi::accounts::TransferInstruction {
+        fee_payer: accounts.fee_payer.clone(),
+        authority: accounts.authority.clone(),
+        registered_program_pda: accounts.registered_program_pda.clone(),
+        noop_program: accounts.noop_program.clone(),
+        account_compression_authority: accounts.account_compression_authority.clone(),
+        account_compression_program: accounts.account_compression_program.clone(),
+        self_program: accounts.compressed_token_program.clone(),
+        cpi_authority_pda: accounts.cpi_authority_pda.clone(),
+        light_system_program: accounts.light_system_program.clone(),
+        token_pool_pda: None,
+        compress_or_decompress_token_account: None,
+        token_program: None,
+        system_program: accounts.system_program.clone(),
+    };
+
+    let cpi_ctx = CpiContext::new_with_signer(
+        accounts.compressed_token_program.clone(),
+        cpi_accounts,
+        signer_seeds,
+    )
+    .with_remaining_accounts(accounts.remaining_accounts.to_vec());
+
+    light_compressed_token::cpi::transfer(cpi_ctx, data)
+}
@@ -1,4 +1,7 @@
+use crate::compressed_transfers::{sum_token_inputs, TokenTransferRecipient};
 use crate::program::ZkOnchain;
+use crate::state::{CustomError, PdaTokensTransferred, VAULT_CONFIG_SEED};
+use crate::vaults::{config_authority, VaultConfigState};
 use anchor_lang::prelude::*;
 use light_compressed_token::{
     process_transfer::{
@@ -23,9 +26,6 @@ pub struct TransferCompressedTokensWithPda<'info> {
     #[account(mut)]
     pub source_pda: AccountInfo<'info>,
 
-    #[account(mut)]
-    pub destination_pda: AccountInfo<'info>,
-
     pub compressed_token_program: Program<'info, LightCompressedToken>,
 
     pub compressed_token_cpi_authority_pda: AccountInfo<'info>,
@@ -40,37 +40,74 @@ pub struct TransferCompressedTokensWithPda<'info> {
     #[authority]
     #[account(mut)]
     pub cpi_authority_pda: AccountInfo<'info>,
+
+    /// PDA transfers pause with the other transfers
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority::ID.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
 }
 
+/// Pays every recipient from the inputs in one transfer. The inputs are summed and whatever
+/// is left goes back to `source_pda_owner` as a single change output.
+///
+/// `output_state_merkle_tree_account_indices` holds the output tree of each recipient, in
+/// order, followed by the output tree of the change. The change tree can be left out when
+/// the inputs add up to exactly what the recipients get.
 pub fn transfer_compressed_tokens<'info>(
     ctx: Context<'_, '_, '_, 'info, TransferCompressedTokensWithPda<'info>>,
-    transfer_amount: u64,
+    recipients: Vec<TokenTransferRecipient>,
     proof: CompressedProof,
     mint: Pubkey,
     input_token_data_with_context: Vec<InputTokenDataWithContext>,
     output_state_merkle_tree_account_indices: Vec<u8>,
     mut cpi_context: CompressedCpiContext,
 ) -> Result<()> {
-    // Create the destination token data
-    let destination_token_data = PackedTokenTransferOutputData {
-        amount: transfer_amount,
-        owner: ctx.accounts.destination_pda.key(),
-        lamports: None,
-        merkle_tree_index: output_state_merkle_tree_account_indices[0],
-        tlv: None,
-    };
-
-    // Calculate remaining amount for source
-    let source_remaining_amount = input_token_data_with_context[0].amount - transfer_amount;
-    let change_token_data = PackedTokenTransferOutputData {
-        amount: source_remaining_amount,
-        owner: ctx.accounts.source_pda_owner.key(),
-        lamports: None,
-        merkle_tree_index: output_state_merkle_tree_account_indices[1],
-        tlv: None,
-    };
-
-    let output_compressed_accounts = vec![change_token_data, destination_token_data];
+    require!(
+        !recipients.is_empty()
+            && recipients.iter().all(|recipient| recipient.amount > 0)
+            && (output_state_merkle_tree_account_indices.len() == recipients.len()
+                || output_state_merkle_tree_account_indices.len() == recipients.len() + 1),
+        CustomError::InvalidTransferRecipients
+    );
+
+    let input_amount = sum_token_inputs(&input_token_data_with_context)?;
+    let transfer_amount = recipients
+        .iter()
+        .try_fold(0u64, |sum, recipient| sum.checked_add(recipient.amount))
+        .ok_or(CustomError::InsufficientTokenInput)?;
+    let change_amount = input_amount
+        .checked_sub(transfer_amount)
+        .ok_or(CustomError::InsufficientTokenInput)?;
+    require!(
+        change_amount == 0 || output_state_merkle_tree_account_indices.len() > recipients.len(),
+        CustomError::MissingChangeOutputTree
+    );
+
+    let mut output_compressed_accounts = recipients
+        .iter()
+        .zip(&output_state_merkle_tree_account_indices)
+        .map(
+            |(recipient, merkle_tree_index)| PackedTokenTransferOutputData {
+                amount: recipient.amount,
+                owner: recipient.owner,
+                lamports: None,
+                merkle_tree_index: *merkle_tree_index,
+                tlv: None,
+            },
+        )
+        .collect::<Vec<_>>();
+
+    if change_amount > 0 {
+        output_compressed_accounts.push(PackedTokenTransferOutputData {
+            amount: change_amount,
+            owner: ctx.accounts.source_pda_owner.key(),
+            lamports: None,
+            merkle_tree_index: output_state_merkle_tree_account_indices[recipients.len()],
+            tlv: None,
+        });
+    }
 
     // Set CPI context flags
     cpi_context.set_context = true;
@@ -121,5 +158,14 @@ pub fn transfer_compressed_tokens<'info>(
     // Execute the transfer
     light_compressed_token::cpi::transfer(cpi_ctx, inputs)?;
 
+    for recipient in recipients {
+        emit!(PdaTokensTransferred {
+            mint,
+            source: ctx.accounts.source_pda.key(),
+            destination: recipient.owner,
+            amount: recipient.amount,
+        });
+    }
+
     Ok(())
 }
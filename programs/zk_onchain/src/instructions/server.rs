@@ -4,7 +4,8 @@ use light_sdk::compressed_account::LightAccount;
 use light_sdk::merkle_context::PackedAddressMerkleContext;
 use light_sdk::{light_account, light_accounts};
 
-use crate::state::CustomError;
+use crate::state::{CustomError, VAULT_CONFIG_SEED};
+use crate::vaults::{config_authority, VaultConfigState};
 use crate::{ParamsCreateServer, ParamsTransferServerOwnership};
 
 // Server Account Structure
@@ -33,6 +34,12 @@ pub struct CreateServer<'info> {
     pub cpi_signer: AccountInfo<'info>,
     #[light_account(init, seeds = [b"server", name.as_bytes(), ticker.as_bytes()])]
     pub server_account: LightAccount<ServerAccount>,
+    /// Only the global pause stops server creation
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority::ID.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
 }
 
 #[light_accounts]
@@ -51,4 +58,10 @@ pub struct TransferServerOwnership<'info> {
         constraint = server_account.authority == signer.key() @ CustomError::Unauthorized
     )]
     pub server_account: LightAccount<ServerAccount>,
+    /// Only the global pause stops ownership transfers
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority::ID.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
 }
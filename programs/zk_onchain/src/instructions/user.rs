@@ -1,4 +1,5 @@
-use crate::state::CustomError;
+use crate::state::{CustomError, VAULT_CONFIG_SEED};
+use crate::vaults::{config_authority, VaultConfigState};
 use crate::{
  ParamsCreateUser, ParamsCreateUserHandle, ParamsTransferUserHandle,
     ParamsUpdateUserProfile,
@@ -51,6 +52,13 @@ pub struct CreateUser<'info> {
     //     bump
     // )]
     // pub user_vault: Account<'info, UserVault>,
+
+    /// Only the global pause stops user creation
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority::ID.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
 }
 
 
@@ -117,6 +125,12 @@ pub struct UpdateUserProfile<'info> {
         constraint = user_account.authority == signer.key() @ CustomError::Unauthorized
     )]
     pub user_account: LightAccount<UserAccount>,
+    /// Only the global pause stops profile updates
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority::ID.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
 }
 
 #[light_accounts]
@@ -138,6 +152,12 @@ pub struct CreateUserHandle<'info> {
     pub user_account: LightAccount<UserAccount>,
     #[light_account(init, seeds = [b"handle", handle.as_bytes()])]
     pub handle_asset: LightAccount<AssetAccount>,
+    /// Only the global pause stops handle creation
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority::ID.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
 }
 
 #[light_accounts]
@@ -168,4 +188,11 @@ pub struct TransferUserHandle<'info> {
       //  constraint = handle_asset.owner == from_user_account. @ CustomError::Unauthorized
     )]
     pub handle_asset: LightAccount<AssetAccount>,
+
+    /// Only the global pause stops handle transfers
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority::ID.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
 }
@@ -8,6 +8,7 @@ pub mod vaults;
 
 use crate::compressed_transfers::*;
 use crate::instructions::*;
+use crate::state::events::*;
 use crate::user::*;
 use crate::vaults::*;
 use anchor_lang::prelude::*;
@@ -33,8 +34,64 @@ pub mod zk_onchain {
         vaults::initialize_vault_config(ctx)
     }
 
-    pub fn update_vault_authority(ctx: Context<UpdateVaultAuthority>) -> Result<()> {
-        vaults::update_vault_authority(ctx)
+    pub fn migrate_vault_config(ctx: Context<MigrateVaultConfig>) -> Result<()> {
+        vaults::migrate_vault_config(ctx)
+    }
+
+    pub fn propose_vault_authority(ctx: Context<ProposeVaultAuthority>) -> Result<()> {
+        vaults::propose_vault_authority(ctx)
+    }
+
+    pub fn accept_vault_authority(ctx: Context<AcceptVaultAuthority>) -> Result<()> {
+        vaults::accept_vault_authority(ctx)
+    }
+
+    pub fn cancel_vault_authority_transfer(
+        ctx: Context<CancelVaultAuthorityTransfer>,
+    ) -> Result<()> {
+        vaults::cancel_vault_authority_transfer(ctx)
+    }
+
+    pub fn update_authority_transfer_delay(
+        ctx: Context<UpdateAuthorityTransferDelay>,
+        delay: i64,
+    ) -> Result<()> {
+        vaults::update_authority_transfer_delay(ctx, delay)
+    }
+
+    pub fn set_config_admins(
+        ctx: Context<SetConfigAdmins>,
+        admins: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        vaults::set_config_admins(ctx, admins, threshold)
+    }
+
+    pub fn create_config_proposal(
+        ctx: Context<CreateConfigProposal>,
+        action: ConfigAction,
+    ) -> Result<()> {
+        vaults::create_config_proposal(ctx, action)
+    }
+
+    pub fn approve_config_proposal(ctx: Context<ApproveConfigProposal>) -> Result<()> {
+        vaults::approve_config_proposal(ctx)
+    }
+
+    pub fn close_config_proposal(ctx: Context<CloseConfigProposal>) -> Result<()> {
+        vaults::close_config_proposal(ctx)
+    }
+
+    pub fn set_guardian(ctx: Context<SetGuardian>, guardian: Pubkey) -> Result<()> {
+        vaults::set_guardian(ctx, guardian)
+    }
+
+    pub fn set_pause_state(
+        ctx: Context<SetPauseState>,
+        paused: bool,
+        pause_flags: u8,
+    ) -> Result<()> {
+        vaults::set_pause_state(ctx, paused, pause_flags)
     }
 
     pub fn manage_service_signer(
@@ -44,14 +101,41 @@ pub mod zk_onchain {
         vaults::manage_service_signer(ctx, operation)
     }
 
+    pub fn migrate_service_signers<'info>(
+        ctx: Context<'_, '_, '_, 'info, MigrateServiceSigners<'info>>,
+    ) -> Result<()> {
+        vaults::migrate_service_signers(ctx)
+    }
+
     pub fn initialize_user_vault<'info>(
         ctx: LightContext<'_, '_, '_, 'info, InitializeUserVault<'info>>,
     ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_VAULTS)?;
+
+        require_service_signer(
+            &ctx.accounts.config,
+            &ctx.accounts.service_signer_entry,
+            &ctx.accounts.service_signer,
+            PERMISSION_CREATE_USER_VAULT,
+        )?;
+
         let clock = Clock::get()?;
 
         ctx.light_accounts.user_vault.current_authority = ctx.accounts.current_authority.key();
         ctx.light_accounts.user_vault.vault_type = VaultType::User;
         ctx.light_accounts.user_vault.modified_at = clock.unix_timestamp;
+        ctx.light_accounts.user_vault.vault_id = ctx.accounts.current_authority.key();
+
+        let vault_authority = &mut ctx.accounts.vault_authority;
+        vault_authority.vault_id = ctx.accounts.current_authority.key();
+        vault_authority.current_authority = ctx.accounts.current_authority.key();
+        vault_authority.bump = UserVaultAuthority::find_address(&vault_authority.vault_id).1;
+
+        emit!(UserVaultInitialized {
+            authority: ctx.accounts.current_authority.key(),
+            service_signer: ctx.accounts.service_signer.key(),
+            timestamp: clock.unix_timestamp,
+        });
 
         Ok(())
     }
@@ -59,14 +143,88 @@ pub mod zk_onchain {
     pub fn update_user_vault_authority<'info>(
         ctx: LightContext<'_, '_, '_, 'info, UpdateUserVaultAuthority<'info>>,
     ) -> Result<()> {
-        if ctx.accounts.current_authority.key() != ctx.light_accounts.user_vault.current_authority {
-            return Ok(());
-        }
+        require_not_paused(&ctx.accounts.config, PAUSE_VAULTS)?;
+
+        require_service_signer(
+            &ctx.accounts.config,
+            &ctx.accounts.service_signer_entry,
+            &ctx.accounts.service_signer,
+            PERMISSION_UPDATE_USER_VAULT,
+        )?;
+
+        require_keys_eq!(
+            ctx.accounts.current_authority.key(),
+            ctx.light_accounts.user_vault.current_authority,
+            CustomError::InvalidAuthority
+        );
 
         let clock = Clock::get()?;
+        let old_authority = ctx.light_accounts.user_vault.current_authority;
         ctx.light_accounts.user_vault.current_authority = ctx.accounts.new_authority.key();
         ctx.light_accounts.user_vault.modified_at = clock.unix_timestamp;
 
+        ctx.accounts.vault_authority.current_authority = ctx.accounts.new_authority.key();
+
+        emit!(UserVaultAuthorityChanged {
+            vault_id: ctx.light_accounts.user_vault.vault_id,
+            old_authority,
+            new_authority: ctx.accounts.new_authority.key(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Moves a legacy vault to the layout with a vault id. The legacy token owner PDA can't
+    /// sign anything after the migration, so every balance in `legacy_balances`, one entry
+    /// per mint, moves to the token owner of the vault id.
+    pub fn migrate_user_vault<'info>(
+        ctx: LightContext<'_, '_, '_, 'info, MigrateUserVault<'info>>,
+        legacy_balances: Vec<MintTokenInputs>,
+    ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_VAULTS)?;
+
+        let current_authority = ctx.accounts.current_authority.key();
+        require_keys_eq!(
+            current_authority,
+            ctx.light_accounts.legacy_vault.current_authority,
+            CustomError::InvalidAuthority
+        );
+
+        let (token_owner, _) = UserVaultStateV2::find_token_owner(&current_authority);
+        let bump = [UserVaultState::find_legacy_token_owner(&current_authority).1];
+        let seeds: &[&[u8]] = &[
+            state::USER_VAULT.as_bytes(),
+            current_authority.as_ref(),
+            &bump,
+        ];
+        let accounts = ctx.accounts.token_cpi_accounts(
+            ctx.accounts.legacy_token_owner.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        let mints = transfer_balances_cpi(&accounts, &[seeds], legacy_balances, token_owner)?;
+
+        // The legacy vault is closed, its authority becomes the vault id
+        let clock = Clock::get()?;
+        let vault_type = ctx.light_accounts.legacy_vault.vault_type.clone();
+        let user_vault = &mut ctx.light_accounts.user_vault;
+
+        user_vault.current_authority = current_authority;
+        user_vault.vault_type = vault_type;
+        user_vault.modified_at = clock.unix_timestamp;
+        user_vault.vault_id = current_authority;
+
+        let vault_authority = &mut ctx.accounts.vault_authority;
+        vault_authority.vault_id = current_authority;
+        vault_authority.current_authority = current_authority;
+        vault_authority.bump = UserVaultAuthority::find_address(&current_authority).1;
+
+        emit!(UserVaultMigrated {
+            vault_id: current_authority,
+            mints,
+            timestamp: clock.unix_timestamp,
+        });
+
         Ok(())
     }
 
@@ -76,6 +234,15 @@ pub mod zk_onchain {
         ctx: LightContext<'_, '_, '_, 'info, InitializeServerVault<'info>>,
         params: ServerVaultParams,
     ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_VAULTS)?;
+
+        require_service_signer(
+            &ctx.accounts.config,
+            &ctx.accounts.service_signer_entry,
+            &ctx.accounts.service_signer,
+            PERMISSION_CREATE_SERVER_VAULT,
+        )?;
+
         let clock = Clock::get()?;
         let current_timestamp = clock.unix_timestamp;
 
@@ -99,48 +266,473 @@ pub mod zk_onchain {
             CustomError::InvalidServerName
         );
 
+        emit!(ServerVaultInitialized {
+            authority,
+            server_id: server_vault.server_id.clone(),
+            server_name: server_vault.server_name.clone(),
+            service_signer: ctx.accounts.service_signer.key(),
+            timestamp: current_timestamp,
+        });
+
         Ok(())
     }
 
+    /// Moves the server vault record to the new authority. The server's compressed tokens
+    /// are owned by the authority key itself, so every balance in `balances` moves to the
+    /// new authority with it, one entry per mint.
     pub fn update_server_vault_authority<'info>(
         ctx: LightContext<'_, '_, '_, 'info, UpdateServerVaultAuthority<'info>>,
+        balances: Vec<MintTokenInputs>,
     ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_VAULTS)?;
+
+        require_service_signer(
+            &ctx.accounts.config,
+            &ctx.accounts.service_signer_entry,
+            &ctx.accounts.service_signer,
+            PERMISSION_UPDATE_SERVER_VAULT,
+        )?;
+
         require!(
             ctx.accounts.current_authority.key()
                 == ctx.light_accounts.server_vault.current_authority,
             CustomError::InvalidAuthority
         );
 
+        let old_authority = ctx.accounts.current_authority.key();
+        let new_authority = ctx.accounts.new_authority.key();
+        require_keys_neq!(old_authority, new_authority, CustomError::InvalidAuthority);
+
+        let accounts = ctx.accounts.token_cpi_accounts(
+            ctx.accounts.current_authority.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        let mints = transfer_balances_cpi(&accounts, &[], balances, new_authority)?;
+
+        // The address is derived from the authority, so the vault moves to a new one
         let clock = Clock::get()?;
-        let server_vault = &mut ctx.light_accounts.server_vault;
-        server_vault.modified_at = clock.unix_timestamp;
+        let server_vault = (*ctx.light_accounts.server_vault).clone();
+        let new_server_vault = &mut ctx.light_accounts.new_server_vault;
+
+        new_server_vault.server_id = server_vault.server_id;
+        new_server_vault.server_name = server_vault.server_name;
+        new_server_vault.current_authority = new_authority;
+        new_server_vault.vault_type = server_vault.vault_type;
+        new_server_vault.created_at = server_vault.created_at;
+        new_server_vault.modified_at = clock.unix_timestamp;
+
+        emit!(ServerVaultAuthorityChanged {
+            server_id: new_server_vault.server_id.clone(),
+            old_authority,
+            new_authority,
+            mints,
+            timestamp: clock.unix_timestamp,
+        });
 
         Ok(())
     }
 
     pub fn initialize_escrow_vault<'info>(
-        ctx: LightContext<'_, '_, '_, 'info, InitializeEscrowVault<'info>>,
+        ctx: Context<'_, '_, '_, 'info, InitializeEscrowVault<'info>>,
+        params: EscrowVaultParams,
+        maker_inputs: CompressedTokenInputs,
     ) -> Result<()> {
-        Ok(())
+        require_not_paused(&ctx.accounts.config, PAUSE_ESCROW)?;
+
+        require_service_signer(
+            &ctx.accounts.config,
+            &ctx.accounts.service_signer_entry,
+            &ctx.accounts.service_signer,
+            PERMISSION_MANAGE_ESCROW,
+        )?;
+
+        vaults::initialize_escrow_vault(ctx, params, maker_inputs)
+    }
+
+    pub fn update_escrow_vault(
+        ctx: Context<UpdateEscrowVault>,
+        price_numerator: u64,
+        price_denominator: u64,
+        expires_at: i64,
+    ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_ESCROW)?;
+
+        vaults::update_escrow_vault(ctx, price_numerator, price_denominator, expires_at)
     }
-    pub fn update_escrow_vault<'info>(
-        ctx: LightContext<'_, '_, '_, 'info, UpdateEscrowVaultAuthority<'info>>,
+
+    pub fn accept_escrow_offer<'info>(
+        ctx: Context<'_, '_, '_, 'info, AcceptEscrowOffer<'info>>,
+        fill_amount: u64,
+        taker_inputs: CompressedTokenInputs,
+        escrow_inputs: CompressedTokenInputs,
     ) -> Result<()> {
-        Ok(())
+        require_not_paused(&ctx.accounts.config, PAUSE_ESCROW)?;
+
+        vaults::accept_escrow_offer(ctx, fill_amount, taker_inputs, escrow_inputs)
+    }
+
+    pub fn cancel_escrow_offer<'info>(
+        ctx: Context<'_, '_, '_, 'info, CancelEscrowOffer<'info>>,
+        escrow_inputs: CompressedTokenInputs,
+    ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_ESCROW)?;
+
+        vaults::cancel_escrow_offer(ctx, escrow_inputs)
+    }
+
+    pub fn initialize_arbiter_escrow<'info>(
+        ctx: Context<'_, '_, '_, 'info, InitializeArbiterEscrow<'info>>,
+        params: ArbiterEscrowParams,
+        buyer_inputs: CompressedTokenInputs,
+    ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_ESCROW)?;
+
+        require_service_signer(
+            &ctx.accounts.config,
+            &ctx.accounts.service_signer_entry,
+            &ctx.accounts.service_signer,
+            PERMISSION_MANAGE_ESCROW,
+        )?;
+
+        vaults::initialize_arbiter_escrow(ctx, params, buyer_inputs)
+    }
+
+    pub fn release_arbiter_escrow<'info>(
+        ctx: Context<'_, '_, '_, 'info, ReleaseArbiterEscrow<'info>>,
+        escrow_inputs: CompressedTokenInputs,
+    ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_ESCROW)?;
+
+        vaults::release_arbiter_escrow(ctx, escrow_inputs)
+    }
+
+    pub fn open_arbiter_dispute(ctx: Context<OpenArbiterDispute>) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_ESCROW)?;
+
+        vaults::open_arbiter_dispute(ctx)
+    }
+
+    pub fn resolve_arbiter_dispute<'info>(
+        ctx: Context<'_, '_, '_, 'info, ResolveArbiterDispute<'info>>,
+        buyer_amount: u64,
+        escrow_inputs: CompressedTokenInputs,
+    ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_ESCROW)?;
+
+        vaults::resolve_arbiter_dispute(ctx, buyer_amount, escrow_inputs)
     }
 
     pub fn initialize_airdrop_vault<'info>(
         ctx: LightContext<'_, '_, '_, 'info, InitializeAirdropVault<'info>>,
+        params: AirdropVaultParams,
+        funder_inputs: CompressedTokenInputs,
     ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_AIRDROP)?;
+
+        require_service_signer(
+            &ctx.accounts.config,
+            &ctx.accounts.service_signer_entry,
+            &ctx.accounts.service_signer,
+            PERMISSION_MANAGE_AIRDROP,
+        )?;
+
+        require!(!params.server_id.is_empty(), CustomError::InvalidServerId);
+        require!(
+            !params.server_name.is_empty(),
+            CustomError::InvalidServerName
+        );
+        let clock = Clock::get()?;
+        require!(
+            params.total_amount > 0 && params.claim_deadline > clock.unix_timestamp,
+            CustomError::InvalidAirdrop
+        );
+        // Unclaimed tokens go back to the server authority funding the airdrop
+        require_keys_eq!(
+            params.clawback_destination,
+            ctx.accounts.current_authority.key(),
+            CustomError::InvalidAirdrop
+        );
+
+        let authority = ctx.accounts.current_authority.key();
+        let airdrop_id = ctx.accounts.airdrop_id.key();
+        let (token_authority, _) = AirdropVaultState::find_token_authority(&authority, &airdrop_id);
+        require_keys_eq!(
+            ctx.accounts.airdrop_token_authority.key(),
+            token_authority,
+            CustomError::InvalidAirdrop
+        );
+
+        // Fund the airdrop, the authority keeps its change
+        let accounts = ctx.accounts.token_cpi_accounts(
+            ctx.accounts.current_authority.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        transfer_compressed_tokens_cpi(
+            &accounts,
+            &[],
+            params.mint,
+            params.total_amount,
+            token_authority,
+            authority,
+            funder_inputs,
+        )?;
+
+        let airdrop_vault = &mut ctx.light_accounts.airdrop_vault;
+
+        airdrop_vault.server_id = params.server_id;
+        airdrop_vault.server_name = params.server_name;
+        airdrop_vault.creator = authority;
+        airdrop_vault.current_authority = authority;
+        airdrop_vault.vault_type = VaultType::Airdrop;
+        airdrop_vault.airdrop_id = airdrop_id;
+        airdrop_vault.mint = params.mint;
+        airdrop_vault.merkle_root = params.merkle_root;
+        airdrop_vault.total_amount = params.total_amount;
+        airdrop_vault.claimed_amount = 0;
+        airdrop_vault.claim_deadline = params.claim_deadline;
+        airdrop_vault.clawback_destination = params.clawback_destination;
+        airdrop_vault.clawed_back_amount = 0;
+        airdrop_vault.created_at = clock.unix_timestamp;
+        airdrop_vault.modified_at = clock.unix_timestamp;
+
+        emit!(AirdropVaultInitialized {
+            authority,
+            airdrop_id,
+            mint: params.mint,
+            merkle_root: params.merkle_root,
+            total_amount: params.total_amount,
+            service_signer: ctx.accounts.service_signer.key(),
+        });
+
         Ok(())
     }
 
     pub fn update_airdrop_vault<'info>(
         ctx: LightContext<'_, '_, '_, 'info, UpdateAirdropVaultAuthority<'info>>,
     ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_AIRDROP)?;
+
+        require_service_signer(
+            &ctx.accounts.config,
+            &ctx.accounts.service_signer_entry,
+            &ctx.accounts.service_signer,
+            PERMISSION_MANAGE_AIRDROP,
+        )?;
+
+        let old_authority = ctx.accounts.current_authority.key();
+        let new_authority = ctx.accounts.new_authority.key();
+        require_keys_eq!(
+            old_authority,
+            ctx.light_accounts.airdrop_vault.current_authority,
+            CustomError::InvalidAuthority
+        );
+        require_keys_neq!(old_authority, new_authority, CustomError::InvalidAuthority);
+
+        let clock = Clock::get()?;
+        let airdrop_vault = &mut ctx.light_accounts.airdrop_vault;
+        airdrop_vault.current_authority = new_authority;
+        airdrop_vault.modified_at = clock.unix_timestamp;
+        let airdrop_id = airdrop_vault.airdrop_id;
+
+        emit!(AirdropVaultAuthorityChanged {
+            airdrop_id,
+            old_authority,
+            new_authority,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn claim_airdrop<'info>(
+        ctx: LightContext<'_, '_, '_, 'info, ClaimAirdrop<'info>>,
+        amount: u64,
+        merkle_proof: Vec<[u8; 32]>,
+        vault_inputs: CompressedTokenInputs,
+    ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_AIRDROP)?;
+
+        let clock = Clock::get()?;
+        let airdrop_vault = &ctx.light_accounts.airdrop_vault;
+        require!(
+            !airdrop_vault.is_expired(clock.unix_timestamp),
+            CustomError::AirdropClaimExpired
+        );
+
+        let creator = airdrop_vault.creator;
+        let airdrop_id = airdrop_vault.airdrop_id;
+        let mint = airdrop_vault.mint;
+        let recipient = ctx.accounts.recipient.key();
+
+        let (token_authority, bump) =
+            AirdropVaultState::find_token_authority(&creator, &airdrop_id);
+        require_keys_eq!(
+            ctx.accounts.airdrop_token_authority.key(),
+            token_authority,
+            CustomError::InvalidAirdrop
+        );
+
+        // Everything is checked before the tokens move
+        require!(
+            amount <= airdrop_vault.unclaimed_amount(),
+            CustomError::InvalidAirdrop
+        );
+        require!(
+            verify_airdrop_proof(
+                &airdrop_vault.merkle_root,
+                airdrop_leaf(&recipient, amount),
+                &merkle_proof
+            ),
+            CustomError::InvalidAirdropProof
+        );
+
+        let seeds: &[&[u8]] = &[
+            state::AIRDROP_TOKEN_AUTHORITY.as_bytes(),
+            creator.as_ref(),
+            airdrop_id.as_ref(),
+            &[bump],
+        ];
+        let accounts = ctx.accounts.token_cpi_accounts(
+            ctx.accounts.airdrop_token_authority.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        transfer_compressed_tokens_cpi(
+            &accounts,
+            &[seeds],
+            mint,
+            amount,
+            recipient,
+            token_authority,
+            vault_inputs,
+        )?;
+
+        let airdrop_vault = &mut ctx.light_accounts.airdrop_vault;
+        airdrop_vault.claimed_amount = airdrop_vault
+            .claimed_amount
+            .checked_add(amount)
+            .ok_or(CustomError::Overflow)?;
+        airdrop_vault.modified_at = clock.unix_timestamp;
+        let claimed_amount = airdrop_vault.claimed_amount;
+
+        let claim_receipt = &mut ctx.light_accounts.claim_receipt;
+        claim_receipt.airdrop_id = airdrop_id;
+        claim_receipt.recipient = recipient;
+        claim_receipt.amount = amount;
+        claim_receipt.claimed_at = clock.unix_timestamp;
+
+        emit!(AirdropClaimed {
+            airdrop_id,
+            recipient,
+            amount,
+            claimed_amount,
+        });
+
+        Ok(())
+    }
+
+    pub fn clawback_airdrop<'info>(
+        ctx: LightContext<'_, '_, '_, 'info, ClawbackAirdrop<'info>>,
+        vault_inputs: CompressedTokenInputs,
+    ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_AIRDROP)?;
+
+        let airdrop_vault = &ctx.light_accounts.airdrop_vault;
+        require_keys_eq!(
+            ctx.accounts.current_authority.key(),
+            airdrop_vault.current_authority,
+            CustomError::InvalidAuthority
+        );
+
+        let clock = Clock::get()?;
+        require!(
+            airdrop_vault.is_expired(clock.unix_timestamp),
+            CustomError::AirdropNotExpired
+        );
+
+        let amount = airdrop_vault.unclaimed_amount();
+        require!(amount > 0, CustomError::InvalidAirdrop);
+
+        // Unclaimed tokens go back to the server authority that funded the airdrop
+        let creator = airdrop_vault.creator;
+        let airdrop_id = airdrop_vault.airdrop_id;
+        let destination = airdrop_vault.clawback_destination;
+        let (token_authority, bump) =
+            AirdropVaultState::find_token_authority(&creator, &airdrop_id);
+        require_keys_eq!(
+            ctx.accounts.airdrop_token_authority.key(),
+            token_authority,
+            CustomError::InvalidAirdrop
+        );
+
+        // Anything sent to the token authority on top of the airdrop goes along as change
+        let seeds: &[&[u8]] = &[
+            state::AIRDROP_TOKEN_AUTHORITY.as_bytes(),
+            creator.as_ref(),
+            airdrop_id.as_ref(),
+            &[bump],
+        ];
+        let accounts = ctx.accounts.token_cpi_accounts(
+            ctx.accounts.airdrop_token_authority.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        transfer_compressed_tokens_cpi(
+            &accounts,
+            &[seeds],
+            airdrop_vault.mint,
+            amount,
+            destination,
+            destination,
+            vault_inputs,
+        )?;
+
+        let airdrop_vault = &mut ctx.light_accounts.airdrop_vault;
+        airdrop_vault.clawed_back_amount = amount;
+        airdrop_vault.modified_at = clock.unix_timestamp;
+
+        emit!(AirdropClawedBack {
+            airdrop_id,
+            destination,
+            amount,
+        });
+
         Ok(())
     }
 
+    pub fn initialize_vesting_vault<'info>(
+        ctx: Context<'_, '_, '_, 'info, InitializeVestingVault<'info>>,
+        params: VestingVaultParams,
+        grantor_inputs: CompressedTokenInputs,
+    ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_VESTING)?;
+
+        require_service_signer(
+            &ctx.accounts.config,
+            &ctx.accounts.service_signer_entry,
+            &ctx.accounts.service_signer,
+            PERMISSION_MANAGE_VESTING,
+        )?;
+
+        vaults::initialize_vesting_vault(ctx, params, grantor_inputs)
+    }
+
+    pub fn withdraw_vested_tokens<'info>(
+        ctx: Context<'_, '_, '_, 'info, WithdrawVestedTokens<'info>>,
+        vesting_inputs: CompressedTokenInputs,
+    ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_VESTING)?;
+
+        vaults::withdraw_vested_tokens(ctx, vesting_inputs)
+    }
+
+    pub fn revoke_vesting<'info>(
+        ctx: Context<'_, '_, '_, 'info, RevokeVesting<'info>>,
+        vesting_inputs: CompressedTokenInputs,
+    ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_VESTING)?;
+
+        vaults::revoke_vesting(ctx, vesting_inputs)
+    }
+
     pub fn create_compressed_mint<'info>(
         ctx: Context<CreateCompressedMint>,
         name: String,
@@ -148,16 +740,71 @@ pub mod zk_onchain {
         decimals: u8,
         uri: String,
         nonce: u16,
+        freezable: bool,
+        limits: MintLimits,
+        is_mutable: bool,
     ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_MINTS)?;
+
         user::create_compressed_mint::create_compressed_mint(
-            ctx, name, symbol, decimals, uri, nonce,
+            ctx, name, symbol, decimals, uri, nonce, freezable, limits, is_mutable,
+        )
+    }
+
+    pub fn create_compressed_mint_2022<'info>(
+        ctx: Context<'_, '_, '_, 'info, CreateCompressedMint2022<'info>>,
+        name: String,
+        symbol: String,
+        decimals: u8,
+        uri: String,
+        nonce: u16,
+        freezable: bool,
+        limits: MintLimits,
+        extensions: Token2022Extensions,
+    ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_MINTS)?;
+
+        user::create_compressed_mint_2022(
+            ctx, name, symbol, decimals, uri, nonce, freezable, limits, extensions,
         )
     }
 
+    pub fn migrate_compressed_mint(
+        ctx: Context<MigrateCompressedMint>,
+        nonce: u16,
+        limits: MintLimits,
+    ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_MINTS)?;
+
+        user::migrate_compressed_mint(ctx, nonce, limits)
+    }
+
+    pub fn update_compressed_mint_metadata(
+        ctx: Context<UpdateCompressedMintMetadata>,
+        name: String,
+        symbol: String,
+        uri: String,
+    ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_MINTS)?;
+
+        user::update_compressed_mint_metadata(ctx, name, symbol, uri)
+    }
+
+    pub fn transfer_compressed_mint_metadata_authority(
+        ctx: Context<UpdateCompressedMintMetadata>,
+        new_update_authority: Pubkey,
+    ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_MINTS)?;
+
+        user::transfer_compressed_mint_metadata_authority(ctx, new_update_authority)
+    }
+
     pub fn compress_tokens<'info>(
         ctx: Context<'_, '_, '_, 'info, CompressTokens<'info>>,
         inputs: Vec<u8>,
     ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_TRANSFERS)?;
+
         user::compress_tokens(ctx, inputs)
     }
 
@@ -167,6 +814,8 @@ pub mod zk_onchain {
         amounts: Vec<u64>,
         lamports: Option<u64>,
     ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_MINTS)?;
+
         user::mint_tokens(ctx, public_keys, amounts, lamports)
     }
 
@@ -174,21 +823,25 @@ pub mod zk_onchain {
         ctx: Context<'_, '_, '_, '_, TransferCompressedTokensWallet<'_>>,
         transfer_inputs: Vec<u8>,
     ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_TRANSFERS)?;
+
         transfer_compressed_tokens::transfer_compressed_tokens_wallet(ctx, transfer_inputs)
     }
 
     pub fn transfer_compressed_tokens_with_pda<'info>(
         ctx: Context<'_, '_, '_, 'info, TransferCompressedTokensWithPda<'info>>,
-        amount: u64,
+        recipients: Vec<TokenTransferRecipient>,
         proof: CompressedProof,
         mint: Pubkey,
         input_token_data_with_context: Vec<InputTokenDataWithContext>,
         output_state_merkle_tree_account_indices: Vec<u8>,
         cpi_context: CompressedCpiContext,
     ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_TRANSFERS)?;
+
         user_vault_transfer::transfer_compressed_tokens(
             ctx,
-            amount,
+            recipients,
             proof,
             mint,
             input_token_data_with_context,
@@ -197,10 +850,131 @@ pub mod zk_onchain {
         )
     }
 
+    pub fn freeze_compressed_tokens<'info>(
+        ctx: Context<'_, '_, '_, 'info, FreezeCompressedTokens<'info>>,
+        owner: Pubkey,
+        token_inputs: CompressedTokenInputs,
+    ) -> Result<()> {
+        // Only the global pause applies, a server must be able to freeze during an incident
+        require_not_paused(&ctx.accounts.config, 0)?;
+
+        user::freeze_compressed_tokens(ctx, owner, token_inputs)
+    }
+
+    pub fn thaw_compressed_tokens<'info>(
+        ctx: Context<'_, '_, '_, 'info, FreezeCompressedTokens<'info>>,
+        owner: Pubkey,
+        token_inputs: CompressedTokenInputs,
+    ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, 0)?;
+
+        user::thaw_compressed_tokens(ctx, owner, token_inputs)
+    }
+
+    pub fn transfer_user_vault_tokens<'info>(
+        ctx: Context<'_, '_, '_, 'info, TransferUserVaultTokens<'info>>,
+        mint: Pubkey,
+        amount: u64,
+        recipient: Pubkey,
+        vault_inputs: CompressedTokenInputs,
+    ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_TRANSFERS)?;
+
+        user::transfer_user_vault_tokens(ctx, mint, amount, recipient, vault_inputs)
+    }
+
+    /// Burns `amount` of `mint` from the server authority's tokens, the rest of the inputs
+    /// goes back to it as change. The compressed token program burns the same amount from
+    /// the token pool, so the SPL supply goes down with it.
+    pub fn burn_compressed_tokens<'info>(
+        ctx: LightContext<'_, '_, '_, 'info, BurnCompressedTokens<'info>>,
+        amount: u64,
+        token_inputs: CompressedTokenInputs,
+    ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_TRANSFERS)?;
+
+        let authority = ctx.accounts.current_authority.key();
+        require_keys_eq!(
+            authority,
+            ctx.light_accounts.server_vault.current_authority,
+            CustomError::InvalidAuthority
+        );
+
+        let accounts = ctx.accounts.token_cpi_accounts(
+            ctx.accounts.current_authority.to_account_info(),
+            ctx.remaining_accounts,
+        );
+        user::burn_tokens_cpi(
+            &accounts,
+            &[],
+            ctx.accounts.mint.to_account_info(),
+            ctx.accounts.token_pool_pda.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            amount,
+            token_inputs,
+        )?;
+
+        emit!(TokensBurned {
+            mint: ctx.accounts.mint.key(),
+            owner: authority,
+            authority,
+            amount,
+        });
+
+        Ok(())
+    }
+
+    pub fn burn_user_vault_tokens<'info>(
+        ctx: Context<'_, '_, '_, 'info, BurnUserVaultTokens<'info>>,
+        amount: u64,
+        token_inputs: CompressedTokenInputs,
+    ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_TRANSFERS)?;
+
+        user::burn_user_vault_tokens(ctx, amount, token_inputs)
+    }
+
+    pub fn approve_delegate<'info>(
+        ctx: Context<'_, '_, '_, 'info, ManageTokenDelegation<'info>>,
+        mint: Pubkey,
+        delegate: Pubkey,
+        delegated_amount: u64,
+        vault_inputs: CompressedTokenInputs,
+    ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_TRANSFERS)?;
+
+        user::approve_delegate(ctx, mint, delegate, delegated_amount, vault_inputs)
+    }
+
+    pub fn revoke_delegate<'info>(
+        ctx: Context<'_, '_, '_, 'info, ManageTokenDelegation<'info>>,
+        mint: Pubkey,
+        vault_inputs: CompressedTokenInputs,
+    ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_TRANSFERS)?;
+
+        user::revoke_delegate(ctx, mint, vault_inputs)
+    }
+
+    pub fn delegated_transfer<'info>(
+        ctx: Context<'_, '_, '_, 'info, DelegatedTransferTokens<'info>>,
+        owner: Pubkey,
+        mint: Pubkey,
+        amount: u64,
+        recipient: Pubkey,
+        delegated_inputs: CompressedTokenInputs,
+    ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_TRANSFERS)?;
+
+        user::delegated_transfer(ctx, owner, mint, amount, recipient, delegated_inputs)
+    }
+
     pub fn decompress_tokens<'info>(
         ctx: Context<'_, '_, '_, 'info, DecompressTokens<'info>>,
         inputs: Vec<u8>,
     ) -> Result<()> {
+        require_not_paused(&ctx.accounts.config, PAUSE_TRANSFERS)?;
+
         user::decompress_tokens(ctx, inputs)
     }
 
@@ -211,9 +985,18 @@ pub mod zk_onchain {
         input: UserAccount,
         username: String,
     ) -> Result<()> {
+        // Only the global pause applies
+        require_not_paused(&ctx.accounts.config, 0)?;
+
         ctx.light_accounts.user_vault.authority = ctx.accounts.signer.key();
         *ctx.light_accounts.user_vault = input;
         ctx.light_accounts.user_vault.username = username;
+
+        emit!(UserCreated {
+            authority: ctx.light_accounts.user_vault.authority,
+            creator: ctx.accounts.signer.key(),
+            username: ctx.light_accounts.user_vault.username.clone(),
+        });
         Ok(())
     }
 
@@ -222,6 +1005,9 @@ pub mod zk_onchain {
         new_profile_effect: Option<Pubkey>,
         new_theme: Option<Pubkey>,
     ) -> Result<()> {
+        // Only the global pause applies
+        require_not_paused(&ctx.accounts.config, 0)?;
+
         if let Some(effect) = new_profile_effect {
             ctx.light_accounts.user_account.profile_effect = Some(effect);
         }
@@ -235,20 +1021,38 @@ pub mod zk_onchain {
         ctx: LightContext<'_, '_, '_, 'info, CreateUserHandle<'info>>,
         handle: String,
     ) -> Result<()> {
+        // Only the global pause applies
+        require_not_paused(&ctx.accounts.config, 0)?;
+
         ctx.light_accounts.handle_asset.owner = ctx.accounts.signer.key();
         ctx.light_accounts.handle_asset.asset_type = AssetType::UserHandle;
         ctx.light_accounts.handle_asset.data = handle;
         ctx.light_accounts.user_account.handle = Some(ctx.light_accounts.handle_asset.owner.key());
+
+        emit!(UserHandleCreated {
+            owner: ctx.accounts.signer.key(),
+            handle: ctx.light_accounts.handle_asset.data.clone(),
+        });
         Ok(())
     }
 
     pub fn transfer_user_handle<'info>(
         ctx: LightContext<'_, '_, '_, 'info, TransferUserHandle<'info>>,
     ) -> Result<()> {
+        // Only the global pause applies
+        require_not_paused(&ctx.accounts.config, 0)?;
+
+        let from = ctx.light_accounts.handle_asset.owner;
         ctx.light_accounts.handle_asset.owner = ctx.light_accounts.to_user_account.authority.key();
         ctx.light_accounts.from_user_account.handle = None;
         ctx.light_accounts.to_user_account.handle =
             Some(ctx.light_accounts.handle_asset.owner.key());
+
+        emit!(UserHandleTransferred {
+            handle: ctx.light_accounts.handle_asset.data.clone(),
+            from,
+            to: ctx.light_accounts.handle_asset.owner,
+        });
         Ok(())
     }
 
@@ -258,6 +1062,9 @@ pub mod zk_onchain {
         name: String,
         ticker: String,
     ) -> Result<()> {
+        // Only the global pause applies
+        require_not_paused(&ctx.accounts.config, 0)?;
+
         // Generate and associate a unique on-chain MPC-based signer with the server
         // This is handled by the Light Protocol, we just need to set the authority
         ctx.light_accounts.server_account.authority = ctx.accounts.signer.key();
@@ -267,6 +1074,12 @@ pub mod zk_onchain {
         // Mint an NFT representing server ownership
         // TODO
 
+        emit!(ServerCreated {
+            authority: ctx.accounts.signer.key(),
+            name: ctx.light_accounts.server_account.name.clone(),
+            ticker: ctx.light_accounts.server_account.ticker.clone(),
+        });
+
         Ok(())
     }
 
@@ -275,8 +1088,19 @@ pub mod zk_onchain {
         ctx: LightContext<'_, '_, '_, 'info, TransferServerOwnership<'info>>,
         new_owner: Pubkey,
     ) -> Result<()> {
+        // Only the global pause applies
+        require_not_paused(&ctx.accounts.config, 0)?;
+
+        let old_owner = ctx.light_accounts.server_account.authority;
         ctx.light_accounts.server_account.authority = new_owner;
 
+        emit!(ServerOwnershipTransferred {
+            name: ctx.light_accounts.server_account.name.clone(),
+            ticker: ctx.light_accounts.server_account.ticker.clone(),
+            old_owner,
+            new_owner,
+        });
+
         Ok(())
     }
 }
@@ -22,4 +22,110 @@ pub enum CustomError {
     InvalidServerId,
     #[msg("Invalid Server name")]
     InvalidServerName,
+    #[msg("Vault config has no registered service signers")]
+    ServiceSignersNotConfigured,
+    #[msg("Service signer is not registered in the vault config")]
+    UnregisteredServiceSigner,
+    #[msg("An authority transfer is already pending")]
+    AuthorityTransferPending,
+    #[msg("No pending authority transfer for this signer")]
+    NoPendingAuthorityTransfer,
+    #[msg("Authority transfer timelock has not elapsed")]
+    AuthorityTransferTimelocked,
+    #[msg("Invalid authority transfer delay")]
+    InvalidTimelockDelay,
+    #[msg("Signer is not a config admin")]
+    NotConfigAdmin,
+    #[msg("Too many config admins")]
+    TooManyConfigAdmins,
+    #[msg("Config admin already exists")]
+    DuplicateConfigAdmin,
+    #[msg("Invalid approval threshold")]
+    InvalidApprovalThreshold,
+    #[msg("An approved proposal is required for this action")]
+    ProposalRequired,
+    #[msg("Proposal does not match this config or action")]
+    InvalidProposal,
+    #[msg("Proposal has already been executed")]
+    ProposalAlreadyExecuted,
+    #[msg("Proposal has already been approved by this admin")]
+    ProposalAlreadyApproved,
+    #[msg("Proposal has not reached the approval threshold")]
+    ProposalNotApproved,
+    #[msg("Service signer entry does not match the expected address")]
+    InvalidServiceSignerEntry,
+    #[msg("Unknown service signer permission bits")]
+    InvalidServiceSignerPermissions,
+    #[msg("Service signer lacks the permission for this instruction")]
+    MissingServiceSignerPermission,
+    #[msg("Service signer validity window is invalid")]
+    InvalidServiceSignerWindow,
+    #[msg("Service signer is expired or not valid yet")]
+    ServiceSignerExpired,
+    #[msg("Signer is not the config guardian")]
+    NotGuardian,
+    #[msg("Program is paused")]
+    ProgramPaused,
+    #[msg("Vault config is not the canonical config")]
+    InvalidConfig,
+    #[msg("Escrow offer parameters are invalid")]
+    InvalidEscrowOffer,
+    #[msg("Escrow offer has expired")]
+    EscrowOfferExpired,
+    #[msg("Escrow offer has not expired yet")]
+    EscrowOfferNotExpired,
+    #[msg("Compressed token inputs do not cover the amount")]
+    InsufficientTokenInput,
+    #[msg("Fill amount is outside the limits of the escrow offer")]
+    InvalidEscrowFill,
+    #[msg("Arbiter must be a registered service signer or co-sign the escrow")]
+    InvalidArbiter,
+    #[msg("Arbiter escrow is not in the required state")]
+    InvalidArbiterEscrowStatus,
+    #[msg("Dispute split exceeds the escrowed amount")]
+    InvalidDisputeSplit,
+    #[msg("Airdrop parameters are invalid")]
+    InvalidAirdrop,
+    #[msg("Merkle proof does not match the airdrop root")]
+    InvalidAirdropProof,
+    #[msg("Airdrop claim deadline has passed")]
+    AirdropClaimExpired,
+    #[msg("Airdrop claim deadline has not passed yet")]
+    AirdropNotExpired,
+    #[msg("Vesting schedule is invalid")]
+    InvalidVestingSchedule,
+    #[msg("No vested tokens to withdraw")]
+    NothingVested,
+    #[msg("Vesting is not revocable")]
+    VestingNotRevocable,
+    #[msg("Transfer needs at least one recipient with a non-zero amount and an output tree for each output")]
+    InvalidTransferRecipients,
+    #[msg("Mint has no program freeze authority")]
+    MintNotFreezable,
+    #[msg("Tokens can only be burned from user and server vaults")]
+    InvalidBurnVault,
+    #[msg("Mint limits are invalid")]
+    InvalidMintLimits,
+    #[msg("Mint would exceed the max supply")]
+    MintSupplyCapExceeded,
+    #[msg("Mint would exceed the limit of the current period")]
+    MintPeriodLimitExceeded,
+    #[msg("Mint would exceed the quota of the service signer")]
+    MintQuotaExceeded,
+    #[msg("Transfer fee exceeds 100%")]
+    InvalidTransferFee,
+    #[msg("Registered service signer count is out of sync with the registry")]
+    ServiceSignerCountMismatch,
+    #[msg("Transfer leaves change but has no output tree for it")]
+    MissingChangeOutputTree,
+    #[msg("Metaplex metadata accounts are missing for a mint outside Token-2022")]
+    MissingMetadataAccount,
+    #[msg("Mint does not match the mint config")]
+    InvalidMint,
+    #[msg("Arithmetic overflow")]
+    Overflow,
+    #[msg("Amount must be greater than zero")]
+    ZeroAmount,
+    #[msg("Mint metadata is immutable or no longer updated by the mint authority")]
+    MetadataNotUpdatable,
 }
@@ -0,0 +1,435 @@
+use anchor_lang::prelude::*;
+
+// Vault config
+
+#[event]
+pub struct VaultConfigInitialized {
+    pub config: Pubkey,
+    pub created_by: Pubkey,
+    pub update_authority: Pubkey,
+    pub timestamp: i64,
+}
+
+/// What a `VaultConfigUpdated` event changed
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub enum VaultConfigChange {
+    AuthorityProposed { new_authority: Pubkey, eta: i64 },
+    AuthorityAccepted { new_authority: Pubkey },
+    AuthorityTransferCancelled,
+    TransferDelayUpdated { delay: i64 },
+    AdminsUpdated { admins: Vec<Pubkey>, threshold: u8 },
+    GuardianUpdated { guardian: Pubkey },
+    PauseStateUpdated { paused: bool, pause_flags: u8 },
+    ServiceSignersMigrated { count: u32 },
+    Migrated { len: u32 },
+}
+
+#[event]
+pub struct VaultConfigUpdated {
+    pub config: Pubkey,
+    pub updated_by: Pubkey,
+    pub change: VaultConfigChange,
+    pub timestamp: i64,
+}
+
+// Service signers
+
+#[event]
+pub struct ServiceSignerAdded {
+    pub config: Pubkey,
+    pub signer: Pubkey,
+    pub permissions: u32,
+    pub valid_from: i64,
+    pub valid_until: i64,
+    pub added_by: Pubkey,
+}
+
+#[event]
+pub struct ServiceSignerRemoved {
+    pub config: Pubkey,
+    pub signer: Pubkey,
+    pub removed_by: Pubkey,
+}
+
+#[event]
+pub struct ServiceSignerPermissionsUpdated {
+    pub config: Pubkey,
+    pub signer: Pubkey,
+    pub permissions: u32,
+    pub updated_by: Pubkey,
+}
+
+#[event]
+pub struct ServiceSignerRotated {
+    pub config: Pubkey,
+    pub old_signer: Pubkey,
+    pub new_signer: Pubkey,
+    pub activate_at: i64,
+    pub rotated_by: Pubkey,
+}
+
+// Vaults
+
+#[event]
+pub struct UserVaultInitialized {
+    pub authority: Pubkey,
+    pub service_signer: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct UserVaultAuthorityChanged {
+    pub vault_id: Pubkey,
+    pub old_authority: Pubkey,
+    pub new_authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct UserVaultMigrated {
+    pub vault_id: Pubkey,
+    /// Mints whose legacy balances moved to the token owner of the vault id
+    pub mints: Vec<Pubkey>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ServerVaultInitialized {
+    pub authority: Pubkey,
+    pub server_id: String,
+    pub server_name: String,
+    pub service_signer: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ServerVaultAuthorityChanged {
+    pub server_id: String,
+    pub old_authority: Pubkey,
+    pub new_authority: Pubkey,
+    /// Mints whose balances moved to the new authority
+    pub mints: Vec<Pubkey>,
+    pub timestamp: i64,
+}
+
+// Escrow
+
+#[event]
+pub struct EscrowOfferCreated {
+    pub escrow: Pubkey,
+    pub maker: Pubkey,
+    pub mint_offered: Pubkey,
+    pub amount_offered: u64,
+    pub mint_wanted: Pubkey,
+    pub price_numerator: u64,
+    pub price_denominator: u64,
+    pub min_fill_amount: u64,
+    pub expires_at: i64,
+}
+
+#[event]
+pub struct EscrowOfferUpdated {
+    pub escrow: Pubkey,
+    pub price_numerator: u64,
+    pub price_denominator: u64,
+    pub expires_at: i64,
+}
+
+#[event]
+pub struct EscrowOfferFilled {
+    pub escrow: Pubkey,
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    pub amount_filled: u64,
+    pub amount_paid: u64,
+    pub remaining_offered: u64,
+}
+
+#[event]
+pub struct EscrowOfferCancelled {
+    pub escrow: Pubkey,
+    pub maker: Pubkey,
+    pub expired: bool,
+}
+
+#[event]
+pub struct ArbiterEscrowCreated {
+    pub escrow: Pubkey,
+    pub buyer: Pubkey,
+    pub seller: Pubkey,
+    pub arbiter: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct ArbiterEscrowReleased {
+    pub escrow: Pubkey,
+    pub buyer: Pubkey,
+    pub seller: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct ArbiterDisputeOpened {
+    pub escrow: Pubkey,
+    pub opened_by: Pubkey,
+}
+
+#[event]
+pub struct ArbiterDisputeResolved {
+    pub escrow: Pubkey,
+    pub arbiter: Pubkey,
+    pub buyer_amount: u64,
+    pub seller_amount: u64,
+}
+
+// Airdrops
+
+#[event]
+pub struct AirdropVaultInitialized {
+    pub authority: Pubkey,
+    pub airdrop_id: Pubkey,
+    pub mint: Pubkey,
+    pub merkle_root: [u8; 32],
+    pub total_amount: u64,
+    pub service_signer: Pubkey,
+}
+
+#[event]
+pub struct AirdropVaultAuthorityChanged {
+    pub airdrop_id: Pubkey,
+    pub old_authority: Pubkey,
+    pub new_authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AirdropClaimed {
+    pub airdrop_id: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub claimed_amount: u64,
+}
+
+#[event]
+pub struct AirdropClawedBack {
+    pub airdrop_id: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+}
+
+// Vesting
+
+#[event]
+pub struct VestingVaultCreated {
+    pub vesting: Pubkey,
+    pub grantor: Pubkey,
+    pub beneficiary: Pubkey,
+    pub mint: Pubkey,
+    pub total_amount: u64,
+    pub start_at: i64,
+    pub cliff_at: i64,
+    pub end_at: i64,
+    pub revocable: bool,
+}
+
+#[event]
+pub struct VestedTokensWithdrawn {
+    pub vesting: Pubkey,
+    pub beneficiary: Pubkey,
+    pub amount: u64,
+    pub withdrawn_amount: u64,
+}
+
+#[event]
+pub struct VestingRevoked {
+    pub vesting: Pubkey,
+    pub grantor: Pubkey,
+    pub unvested_amount: u64,
+}
+
+// Tokens
+
+#[event]
+pub struct CompressedMintCreated {
+    pub mint: Pubkey,
+    pub name: String,
+    pub symbol: String,
+    pub decimals: u8,
+    pub uri: String,
+    pub payer: Pubkey,
+    pub freezable: bool,
+    pub max_supply: u64,
+    pub is_mutable: bool,
+}
+
+#[event]
+pub struct CompressedMint2022Created {
+    pub mint: Pubkey,
+    pub name: String,
+    pub symbol: String,
+    pub decimals: u8,
+    pub uri: String,
+    pub payer: Pubkey,
+    pub freezable: bool,
+    pub max_supply: u64,
+    pub transfer_fee_basis_points: Option<u16>,
+    pub permanent_delegate: Option<Pubkey>,
+}
+
+/// A mint created before mint configs got its config and the per-server mint authority
+#[event]
+pub struct CompressedMintMigrated {
+    pub mint: Pubkey,
+    pub server_authority: Pubkey,
+    pub max_supply: u64,
+}
+
+#[event]
+pub struct CompressedMintMetadataUpdated {
+    pub mint: Pubkey,
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+}
+
+#[event]
+pub struct CompressedMintMetadataAuthorityTransferred {
+    pub mint: Pubkey,
+    pub new_update_authority: Pubkey,
+}
+
+#[event]
+pub struct TokensMinted {
+    pub mint: Pubkey,
+    pub recipients: Vec<Pubkey>,
+    pub amounts: Vec<u64>,
+}
+
+#[event]
+pub struct TokensCompressed {
+    pub mint: Pubkey,
+    pub token_account: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct TokensDecompressed {
+    pub mint: Pubkey,
+    pub token_account: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct WalletTokensTransferred {
+    pub vault_id: Pubkey,
+    /// Token owner PDA of the user vault
+    pub owner: Pubkey,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct PdaTokensTransferred {
+    pub mint: Pubkey,
+    pub source: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct UserVaultTokensTransferred {
+    pub vault_id: Pubkey,
+    /// Token owner PDA of the user vault
+    pub owner: Pubkey,
+    pub recipient: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct TokensFrozen {
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct TokensThawed {
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct TokensBurned {
+    pub mint: Pubkey,
+    /// Token owner, the token owner PDA of a user vault or the server authority
+    pub owner: Pubkey,
+    pub authority: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct TokensDelegated {
+    pub vault_id: Pubkey,
+    /// Token owner PDA of the user vault
+    pub owner: Pubkey,
+    pub delegate: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct DelegationRevoked {
+    pub vault_id: Pubkey,
+    /// Token owner PDA of the user vault
+    pub owner: Pubkey,
+    pub mint: Pubkey,
+}
+
+#[event]
+pub struct DelegatedTokensTransferred {
+    pub owner: Pubkey,
+    pub delegate: Pubkey,
+    pub recipient: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+// Users and servers
+
+#[event]
+pub struct UserCreated {
+    pub authority: Pubkey,
+    pub creator: Pubkey,
+    pub username: String,
+}
+
+#[event]
+pub struct UserHandleCreated {
+    pub owner: Pubkey,
+    pub handle: String,
+}
+
+#[event]
+pub struct UserHandleTransferred {
+    pub handle: String,
+    pub from: Pubkey,
+    pub to: Pubkey,
+}
+
+#[event]
+pub struct ServerCreated {
+    pub authority: Pubkey,
+    pub name: String,
+    pub ticker: String,
+}
+
+#[event]
+pub struct ServerOwnershipTransferred {
+    pub name: String,
+    pub ticker: String,
+    pub old_owner: Pubkey,
+    pub new_owner: Pubkey,
+}
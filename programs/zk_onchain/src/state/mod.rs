@@ -3,3 +3,6 @@ pub use error::*;
 
 pub mod seeds;
 pub use seeds::*;
+
+pub mod events;
+pub use events::*;
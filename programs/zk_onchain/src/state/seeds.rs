@@ -2,13 +2,180 @@ pub const SODA_AUTHORITY: &str = "soda-authority";
 
 pub const USER_VAULT: &str = "user-vault";
 
+// user vaults addressed by vault id, the PDA holding their tokens and the record of
+// their authority
+pub const USER_VAULT_V2: &str = "versioned-user-vault";
+
+pub const USER_VAULT_TOKENS: &str = "user-tokens";
+
+pub const USER_VAULT_AUTHORITY: &str = "user-authority";
+
 pub const SERVER_VAULT: &str = "server-vault";
 
 pub const AIRDROP_VAULT: &str = "airdrop-escrow";
 
+// airdrop claims and the PDA holding the airdropped tokens
+pub const AIRDROP_CLAIM: &str = "airdrop-claim";
+
+pub const AIRDROP_TOKEN_AUTHORITY: &str = "airdrop-tokens";
+
 pub const ESCROW_VAULT: &str = "trade-escrow";
 
+pub const ARBITER_ESCROW: &str = "arbiter-escrow";
+
+pub const VESTING_VAULT: &str = "vesting-vault";
+
+/// Seed prefixes of every vault kind. Vault addresses are derived from the prefix followed
+/// by the keys of the vault kind: the authority for server vaults and legacy user vaults,
+/// the vault id for user vaults and their authority records, the creator and airdrop id for
+/// airdrops, the maker and offer id for escrow offers, the buyer and trade id for arbiter
+/// escrows and the grantor and vesting id for vesting grants. The seeds are hashed as one
+/// byte string, so no prefix may equal or start another one.
+pub const VAULT_SEEDS: [&str; 8] = [
+    USER_VAULT,
+    USER_VAULT_V2,
+    USER_VAULT_AUTHORITY,
+    SERVER_VAULT,
+    AIRDROP_VAULT,
+    ESCROW_VAULT,
+    ARBITER_ESCROW,
+    VESTING_VAULT,
+];
+
+const _: () = assert!(
+    seeds_are_distinct(&VAULT_SEEDS),
+    "two vault kinds share a seed prefix"
+);
+
+const fn seeds_are_distinct(seeds: &[&str]) -> bool {
+    let mut i = 0;
+    while i < seeds.len() {
+        let mut j = i + 1;
+        while j < seeds.len() {
+            if is_prefix(seeds[i].as_bytes(), seeds[j].as_bytes())
+                || is_prefix(seeds[j].as_bytes(), seeds[i].as_bytes())
+            {
+                return false;
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+    true
+}
+
+const fn is_prefix(prefix: &[u8], bytes: &[u8]) -> bool {
+    if prefix.len() > bytes.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < prefix.len() {
+        if prefix[i] != bytes[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
 // initialize-vault-config
 pub const VAULT_CONFIG_SEED: &str = "vault-config";
 
 pub const MINT_AUTHORITY: &str = "mint-authority";
+
+pub const FREEZE_AUTHORITY: &str = "freeze-authority";
+
+// supply cap and mint limits of a compressed mint, and the per-signer quota
+pub const MINT_CONFIG: &str = "mint-config";
+
+pub const MINT_QUOTA: &str = "mint-quota";
+
+// config-proposal
+pub const CONFIG_PROPOSAL_SEED: &str = "config-proposal";
+
+// service-signer registry entry
+pub const SERVICE_SIGNER_SEED: &str = "service-signer";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::user::{derive_address_seed, COMPRESSED_MINT_SEED};
+    use crate::vaults::{
+        ArbiterEscrowState, EscrowVaultState, UserVaultAuthority, VestingVaultState,
+    };
+    use anchor_lang::prelude::Pubkey;
+    use std::collections::HashSet;
+
+    #[test]
+    fn vault_seeds_are_distinct() {
+        assert!(seeds_are_distinct(&VAULT_SEEDS));
+    }
+
+    #[test]
+    fn every_seed_is_distinct() {
+        assert!(seeds_are_distinct(&[
+            SODA_AUTHORITY,
+            USER_VAULT,
+            USER_VAULT_V2,
+            USER_VAULT_TOKENS,
+            USER_VAULT_AUTHORITY,
+            SERVER_VAULT,
+            AIRDROP_VAULT,
+            AIRDROP_CLAIM,
+            AIRDROP_TOKEN_AUTHORITY,
+            ESCROW_VAULT,
+            ARBITER_ESCROW,
+            VESTING_VAULT,
+            VAULT_CONFIG_SEED,
+            MINT_AUTHORITY,
+            FREEZE_AUTHORITY,
+            MINT_CONFIG,
+            MINT_QUOTA,
+            CONFIG_PROPOSAL_SEED,
+            SERVICE_SIGNER_SEED,
+            COMPRESSED_MINT_SEED,
+        ]));
+    }
+
+    #[test]
+    fn prefix_seeds_are_rejected() {
+        assert!(!seeds_are_distinct(&["vault", "vault"]));
+        assert!(!seeds_are_distinct(&["vault", "vault-v2"]));
+        assert!(!seeds_are_distinct(&["vault-v2", "user", "vault"]));
+        assert!(seeds_are_distinct(&["user-vault", "server-vault"]));
+    }
+
+    #[test]
+    fn compressed_vaults_derive_distinct_addresses_for_the_same_key() {
+        let key = Pubkey::new_unique();
+
+        let addresses = [
+            derive_address_seed(&[USER_VAULT.as_bytes(), key.as_ref()], &crate::ID),
+            derive_address_seed(&[USER_VAULT_V2.as_bytes(), key.as_ref()], &crate::ID),
+            derive_address_seed(&[SERVER_VAULT.as_bytes(), key.as_ref()], &crate::ID),
+            derive_address_seed(
+                &[AIRDROP_VAULT.as_bytes(), key.as_ref(), key.as_ref()],
+                &crate::ID,
+            ),
+        ];
+
+        let distinct: HashSet<[u8; 32]> = addresses.iter().copied().collect();
+        assert_eq!(distinct.len(), addresses.len());
+    }
+
+    #[test]
+    fn vault_pdas_are_distinct_for_the_same_key() {
+        let key = Pubkey::new_unique();
+        let id = 7u64;
+
+        let addresses = [
+            UserVaultAuthority::find_address(&key).0,
+            EscrowVaultState::find_address(&key, id).0,
+            ArbiterEscrowState::find_address(&key, id).0,
+            VestingVaultState::find_address(&key, id).0,
+        ];
+
+        let distinct: HashSet<Pubkey> = addresses.iter().copied().collect();
+        assert_eq!(distinct.len(), addresses.len());
+    }
+}
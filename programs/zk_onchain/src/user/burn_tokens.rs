@@ -0,0 +1,223 @@
+use account_compression::program::AccountCompression;
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenInterface};
+use light_compressed_token::burn::CompressedTokenInstructionDataBurn;
+use light_compressed_token::cpi::accounts::BurnInstruction;
+use light_compressed_token::program::LightCompressedToken;
+use light_sdk::{
+    compressed_account::LightAccount, light_accounts, merkle_context::PackedAddressMerkleContext,
+};
+use light_system_program::program::LightSystemProgram;
+
+use crate::compressed_transfers::{
+    impl_token_cpi_accounts, sum_token_inputs, CompressedTokenCpiAccounts, CompressedTokenInputs,
+};
+use crate::state::{
+    CustomError, TokensBurned, SERVER_VAULT, USER_VAULT_AUTHORITY, USER_VAULT_TOKENS,
+    VAULT_CONFIG_SEED,
+};
+use crate::vaults::{
+    config_authority, ServerVaultState, UserVaultAuthority, UserVaultStateV2, VaultConfigState,
+};
+use crate::ParamsBurnCompressedTokens;
+
+#[light_accounts]
+pub struct BurnCompressedTokens<'info> {
+    #[account(mut)]
+    #[fee_payer]
+    pub payer: Signer<'info>,
+
+    #[self_program]
+    pub self_program: Program<'info, crate::program::ZkOnchain>,
+
+    /// Server authority, owner of the burned tokens
+    pub current_authority: Signer<'info>,
+
+    #[authority]
+    pub cpi_signer: AccountInfo<'info>,
+
+    /// Server vault of the authority, proves it holds one. Written back unchanged.
+    #[light_account(
+        mut,
+        seeds = [SERVER_VAULT.as_bytes(), current_authority.key().as_ref()]
+    )]
+    pub server_vault: LightAccount<ServerVaultState>,
+
+    #[account(mut)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// CHECK: Token pool of `mint`, checked in the compressed token program
+    #[account(mut)]
+    pub token_pool_pda: AccountInfo<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    /// CHECK: Checked in the compressed token program
+    pub compressed_token_cpi_authority_pda: AccountInfo<'info>,
+
+    pub compressed_token_program: Program<'info, LightCompressedToken>,
+
+    /// Burns pause with the transfers
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority::ID.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
+}
+
+#[derive(Accounts)]
+pub struct BurnUserVaultTokens<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// Authority of the user vault
+    pub current_authority: Signer<'info>,
+
+    /// CHECK: Vault id the token owner and the authority record are derived from
+    pub vault_id: AccountInfo<'info>,
+
+    #[account(
+        seeds = [USER_VAULT_AUTHORITY.as_bytes(), vault_id.key().as_ref()],
+        bump = vault_authority.bump,
+        has_one = current_authority @ CustomError::InvalidAuthority
+    )]
+    pub vault_authority: Account<'info, UserVaultAuthority>,
+
+    /// CHECK: Token owner PDA of the vault id, owner of the burned tokens
+    #[account(
+        address = UserVaultStateV2::find_token_owner(&vault_id.key()).0
+            @ CustomError::InvalidAuthority
+    )]
+    pub token_owner: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// CHECK: Token pool of `mint`, checked in the compressed token program
+    #[account(mut)]
+    pub token_pool_pda: AccountInfo<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    /// CHECK: Checked in the compressed token program
+    pub compressed_token_cpi_authority_pda: AccountInfo<'info>,
+
+    pub compressed_token_program: Program<'info, LightCompressedToken>,
+
+    pub light_system_program: Program<'info, LightSystemProgram>,
+
+    /// CHECK: Checked in light-system-program.
+    pub registered_program_pda: AccountInfo<'info>,
+
+    /// CHECK: Checked in light-system-program.
+    pub noop_program: AccountInfo<'info>,
+
+    /// CHECK: Checked in light-system-program.
+    pub account_compression_authority: AccountInfo<'info>,
+
+    pub account_compression_program: Program<'info, AccountCompression>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Vault burns pause with the transfers
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority::ID.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
+}
+
+/// Burns `amount` of `mint` from the tokens of a user vault, owned by the token owner PDA of
+/// its vault id. The rest of the inputs goes back to the vault as change.
+pub fn burn_user_vault_tokens<'info>(
+    ctx: Context<'_, '_, '_, 'info, BurnUserVaultTokens<'info>>,
+    amount: u64,
+    token_inputs: CompressedTokenInputs,
+) -> Result<()> {
+    let vault_id = ctx.accounts.vault_id.key();
+    let (token_owner, bump) = UserVaultStateV2::find_token_owner(&vault_id);
+    let bump_bytes = [bump];
+    let seeds: &[&[u8]] = &[USER_VAULT_TOKENS.as_bytes(), vault_id.as_ref(), &bump_bytes];
+
+    let accounts = ctx.accounts.token_cpi_accounts(
+        ctx.accounts.token_owner.to_account_info(),
+        ctx.remaining_accounts,
+    );
+    burn_tokens_cpi(
+        &accounts,
+        &[seeds],
+        ctx.accounts.mint.to_account_info(),
+        ctx.accounts.token_pool_pda.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        amount,
+        token_inputs,
+    )?;
+
+    emit!(TokensBurned {
+        mint: ctx.accounts.mint.key(),
+        owner: token_owner,
+        authority: ctx.accounts.current_authority.key(),
+        amount,
+    });
+
+    Ok(())
+}
+
+/// Burns `amount` from the inputs of `accounts.authority`. The token pool of `mint` gives up
+/// the same amount, so the SPL supply goes down with it.
+pub(crate) fn burn_tokens_cpi<'info>(
+    accounts: &CompressedTokenCpiAccounts<'_, 'info>,
+    signer_seeds: &[&[&[u8]]],
+    mint: AccountInfo<'info>,
+    token_pool_pda: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    amount: u64,
+    token_inputs: CompressedTokenInputs,
+) -> Result<()> {
+    require!(amount > 0, CustomError::ZeroAmount);
+    require!(
+        sum_token_inputs(&token_inputs.input_token_data_with_context)? >= amount,
+        CustomError::InsufficientTokenInput
+    );
+
+    let inputs_struct = CompressedTokenInstructionDataBurn {
+        proof: token_inputs.proof,
+        input_token_data_with_context: token_inputs.input_token_data_with_context,
+        cpi_context: None,
+        burn_amount: amount,
+        change_account_merkle_tree_index: token_inputs.change_merkle_tree_index,
+        delegated_transfer: None,
+    };
+
+    let mut data = Vec::new();
+    CompressedTokenInstructionDataBurn::serialize(&inputs_struct, &mut data)?;
+
+    let cpi_accounts = BurnInstruction {
+        fee_payer: accounts.fee_payer.clone(),
+        authority: accounts.authority.clone(),
+        cpi_authority_pda: accounts.cpi_authority_pda.clone(),
+        mint,
+        token_pool_pda,
+        token_program,
+        light_system_program: accounts.light_system_program.clone(),
+        registered_program_pda: accounts.registered_program_pda.clone(),
+        noop_program: accounts.noop_program.clone(),
+        account_compression_authority: accounts.account_compression_authority.clone(),
+        account_compression_program: accounts.account_compression_program.clone(),
+        self_program: accounts.compressed_token_program.clone(),
+        system_program: accounts.system_program.clone(),
+    };
+
+    let cpi_ctx = CpiContext::new_with_signer(
+        accounts.compressed_token_program.clone(),
+        cpi_accounts,
+        signer_seeds,
+    )
+    .with_remaining_accounts(accounts.remaining_accounts.to_vec());
+
+    light_compressed_token::cpi::burn(cpi_ctx, data)
+}
+
+impl_token_cpi_accounts!(BurnCompressedTokens, payer);
+
+impl_token_cpi_accounts!(BurnUserVaultTokens, payer);
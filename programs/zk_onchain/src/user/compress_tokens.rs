@@ -1,9 +1,24 @@
 use account_compression::program::AccountCompression;
 use anchor_lang::prelude::*;
-use anchor_spl::token_interface::TokenInterface;
+use anchor_lang::solana_program::program::invoke_signed;
+use anchor_spl::token_2022::spl_token_2022::{
+    self,
+    extension::{transfer_fee, BaseStateWithExtensions, StateWithExtensions},
+};
+use anchor_spl::token_interface::{Mint, TokenInterface};
+use light_compressed_token::process_transfer::CompressedTokenInstructionDataTransfer;
 use light_compressed_token::program::LightCompressedToken;
 use light_system_program::program::LightSystemProgram;
 
+use crate::{
+    state::{CustomError, TokensCompressed, MINT_CONFIG, VAULT_CONFIG_SEED},
+    user::MintConfig,
+    vaults::{
+        config_authority, require_service_signer, ServiceSignerEntry, VaultConfigState,
+        PERMISSION_COMPRESS,
+    },
+};
+
 #[derive(Accounts)]
 pub struct CompressTokens<'info> {
     #[account(mut)]
@@ -12,12 +27,37 @@ pub struct CompressTokens<'info> {
     #[account(mut)]
     pub service_signer: Signer<'info>,
 
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority::ID.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
+
+    /// Registry entry of the service signer in `config`
+    pub service_signer_entry: Account<'info, ServiceSignerEntry>,
+
+    /// Mint of the compressed tokens
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// Supply cap and mint authority of `mint`
+    #[account(
+        seeds = [MINT_CONFIG.as_bytes(), mint.key().as_ref()],
+        bump = mint_config.bump
+    )]
+    pub mint_config: Box<Account<'info, MintConfig>>,
+
+    /// Server that created the mint
+    #[account(address = mint_config.server_authority @ CustomError::InvalidAuthority)]
+    pub server_authority: Signer<'info>,
+
     #[account(
         mut,
         seeds = [
             crate::state::MINT_AUTHORITY.as_bytes(),
+            mint_config.server_authority.as_ref(),
+            &mint_config.nonce.to_be_bytes()
         ],
-        bump,
+        bump = mint_config.authority_bump,
     )]
     pub authority: UncheckedAccount<'info>,
 
@@ -53,6 +93,22 @@ pub fn compress_tokens<'info>(
     ctx: Context<'_, '_, '_, 'info, CompressTokens<'info>>,
     inputs: Vec<u8>,
 ) -> Result<()> {
+    require_service_signer(
+        &ctx.accounts.config,
+        &ctx.accounts.service_signer_entry,
+        &ctx.accounts.service_signer,
+        PERMISSION_COMPRESS,
+    )?;
+
+    let transfer: CompressedTokenInstructionDataTransfer =
+        CompressedTokenInstructionDataTransfer::deserialize(&mut inputs.as_slice())
+            .map_err(|_| CustomError::InvalidCompressedParams)?;
+    require_keys_eq!(
+        transfer.mint,
+        ctx.accounts.mint.key(),
+        CustomError::InvalidCompressedParams
+    );
+
     let cpi_accounts = light_compressed_token::cpi::accounts::TransferInstruction {
         fee_payer: ctx.accounts.payer.to_account_info(),
         authority: ctx.accounts.authority.to_account_info(),
@@ -71,9 +127,12 @@ pub fn compress_tokens<'info>(
         system_program: ctx.accounts.system_program.to_account_info(),
     };
 
+    let nonce_bytes = ctx.accounts.mint_config.nonce.to_be_bytes();
     let binding: &[&[&[u8]]] = &[&[
         crate::state::MINT_AUTHORITY.as_bytes(),
-        &[ctx.bumps.authority],
+        ctx.accounts.mint_config.server_authority.as_ref(),
+        &nonce_bytes,
+        &[ctx.accounts.mint_config.authority_bump],
     ]];
 
     let context = CpiContext::new_with_signer(
@@ -85,5 +144,59 @@ pub fn compress_tokens<'info>(
     light_compressed_token::cpi::transfer(
         context.with_remaining_accounts(ctx.remaining_accounts.to_vec()),
         inputs.try_to_vec()?,
-    )
+    )?;
+
+    if ctx.accounts.has_transfer_fee()? {
+        ctx.accounts.harvest_withheld_fee(binding)?;
+    }
+
+    emit!(TokensCompressed {
+        mint: transfer.mint,
+        token_account: ctx.accounts.compress_token_account.key(),
+        amount: transfer.compress_or_decompress_amount.unwrap_or_default(),
+    });
+
+    Ok(())
+}
+
+impl<'info> CompressTokens<'info> {
+    fn has_transfer_fee(&self) -> Result<bool> {
+        let mint_info = self.mint.to_account_info();
+        if *mint_info.owner != spl_token_2022::ID {
+            return Ok(false);
+        }
+        let data = mint_info.try_borrow_data()?;
+        let mint = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+        Ok(mint
+            .get_extension::<transfer_fee::TransferFeeConfig>()
+            .is_ok())
+    }
+
+    /// Token-2022 withholds the transfer fee in the token pool on compress. The mint authority
+    /// PDA is the withdraw authority and withdraws it into the pool itself, so the pool holds
+    /// the whole compressed amount. Decompress then takes out exactly the amount it burns, and
+    /// the fee is withheld in the recipient account.
+    fn harvest_withheld_fee(&self, signer_seeds: &[&[&[u8]]]) -> Result<()> {
+        let token_pool = self.token_pool_pda.key();
+        let harvest_ix = transfer_fee::instruction::withdraw_withheld_tokens_from_accounts(
+            &self.token_program.key(),
+            &self.mint.key(),
+            &token_pool,
+            &self.authority.key(),
+            &[],
+            &[&token_pool],
+        )?;
+
+        invoke_signed(
+            &harvest_ix,
+            &[
+                self.mint.to_account_info(),
+                self.token_pool_pda.to_account_info(),
+                self.authority.to_account_info(),
+            ],
+            signer_seeds,
+        )?;
+
+        Ok(())
+    }
 }
@@ -1,8 +1,10 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token_interface::{Mint, TokenInterface};
+use anchor_spl::token_interface::{self, spl_token_2022::instruction::AuthorityType, Mint, SetAuthority, TokenInterface};
 use light_compressed_token::{cpi::accounts::CreateTokenPoolInstruction, program::LightCompressedToken};
 use mpl_token_metadata::{instructions::{CreateMetadataAccountV3,CreateMetadataAccountV3InstructionArgs}, types::DataV2};
 
+use crate::{state::{CompressedMintCreated, MINT_CONFIG, VAULT_CONFIG_SEED}, user::{MintConfig, MintLimits}, vaults::{config_authority, require_service_signer, ServiceSignerEntry, VaultConfigState, PERMISSION_MINT}};
+
 pub const COMPRESSED_MINT_SEED: &str  = "compressed_mint";
 
 #[derive(Accounts)]
@@ -12,22 +14,49 @@ pub const COMPRESSED_MINT_SEED: &str  = "compressed_mint";
     decimals: u8,
     uri: String,
     nonce: u16,
+    freezable: bool,
+    limits: MintLimits,
+    is_mutable: bool,
 )]
 pub struct CreateCompressedMint<'info> {
+    /// Server creating the mint, it alone can mint and compress with it
     #[account(mut)]
     pub payer: Signer<'info>,
 
     #[account(mut)]
     pub service_signer: Signer<'info>,
 
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority::ID.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
+
+    /// Registry entry of the service signer in `config`
+    pub service_signer_entry: Account<'info, ServiceSignerEntry>,
+
+    /// CHECK: Mint authority of this mint, derived from the creating server and the nonce
     #[account(
         seeds = [
             crate::state::MINT_AUTHORITY.as_bytes(),
+            payer.key().as_ref(),
+            &nonce.to_be_bytes()
         ],
         bump,
     )]
     pub authority: UncheckedAccount<'info>,
 
+    /// CHECK: Freeze authority of this mint, derived like the mint authority
+    #[account(
+        seeds = [
+            crate::state::FREEZE_AUTHORITY.as_bytes(),
+            payer.key().as_ref(),
+            &nonce.to_be_bytes()
+        ],
+        bump,
+    )]
+    pub freeze_authority: UncheckedAccount<'info>,
+
     #[account(mut)]
     pub cpi_authority_pda: UncheckedAccount<'info>,
 
@@ -44,10 +73,24 @@ pub struct CreateCompressedMint<'info> {
         bump,
         mint::decimals = decimals,
         mint::authority = authority,
+        mint::freeze_authority = freeze_authority,
         payer = payer,
         mint::token_program = token_program,
     )]
     pub compressed_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// Supply cap and mint limits enforced by `mint_tokens`
+    #[account(
+        init,
+        seeds = [
+            MINT_CONFIG.as_bytes(),
+            compressed_mint.key().as_ref()
+        ],
+        bump,
+        payer = payer,
+        space = MintConfig::LEN,
+    )]
+    pub mint_config: Box<Account<'info, MintConfig>>,
     
 
     #[account(mut)]
@@ -93,10 +136,35 @@ pub fn create_compressed_mint<'info>(
     ctx: Context<CreateCompressedMint>,
     name: String,
     symbol: String,
-    _decimals: u8,
+    decimals: u8,
     uri: String,
-    _nonce: u16,
+    nonce: u16,
+    freezable: bool,
+    limits: MintLimits,
+    is_mutable: bool,
 ) -> Result<()> {
+    require_service_signer(
+        &ctx.accounts.config,
+        &ctx.accounts.service_signer_entry,
+        &ctx.accounts.service_signer,
+        PERMISSION_MINT,
+    )?;
+
+    let max_supply = limits.max_supply;
+    let mint_key = ctx.accounts.compressed_mint.key();
+    let server_authority = ctx.accounts.payer.key();
+    let authority_bump = ctx.bumps.authority;
+    let mint_config_bump = ctx.bumps.mint_config;
+    ctx.accounts.mint_config.initialize(
+        mint_key,
+        server_authority,
+        nonce,
+        authority_bump,
+        limits,
+        Clock::get()?.unix_timestamp,
+        mint_config_bump,
+    )?;
+
     let (_, bump_seed) =
         Pubkey::find_program_address(&[ctx.accounts.payer.key().as_ref()], ctx.program_id);
 
@@ -109,6 +177,39 @@ pub fn create_compressed_mint<'info>(
             .with_signer(&[&[ctx.accounts.payer.key().as_ref(), &[bump_seed]]]),
     )?;
 
+    // Every mint starts with the freeze authority, mints that are not freezable drop it for good
+    if !freezable {
+        token_interface::set_authority(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                SetAuthority {
+                    current_authority: ctx.accounts.freeze_authority.to_account_info(),
+                    account_or_mint: ctx.accounts.compressed_mint.to_account_info(),
+                },
+                &[&[
+                    crate::state::FREEZE_AUTHORITY.as_bytes(),
+                    ctx.accounts.payer.key().as_ref(),
+                    &nonce.to_be_bytes(),
+                    &[ctx.bumps.freeze_authority],
+                ]],
+            ),
+            AuthorityType::FreezeAccount,
+            None,
+        )?;
+    }
+
+    let event = CompressedMintCreated {
+        mint: ctx.accounts.compressed_mint.key(),
+        name: name.clone(),
+        symbol: symbol.clone(),
+        decimals,
+        uri: uri.clone(),
+        payer: ctx.accounts.payer.key(),
+        freezable,
+        max_supply,
+        is_mutable,
+    };
+
     let args = CreateMetadataAccountV3InstructionArgs {
         data: DataV2 {
             name,
@@ -119,7 +220,7 @@ pub fn create_compressed_mint<'info>(
             collection: None,
             uses: None,
         },
-        is_mutable: false,
+        is_mutable,
         collection_details: None,
     };
     
@@ -145,8 +246,15 @@ pub fn create_compressed_mint<'info>(
             ctx.accounts.authority.to_account_info(),
             ctx.accounts.system_program.to_account_info(),
         ],
-        &[&[crate::state::MINT_AUTHORITY.as_bytes(), &[ctx.bumps.authority]]]
+        &[&[
+            crate::state::MINT_AUTHORITY.as_bytes(),
+            server_authority.as_ref(),
+            &nonce.to_be_bytes(),
+            &[authority_bump],
+        ]]
     )?;
 
+    emit!(event);
+
     Ok(())
 }
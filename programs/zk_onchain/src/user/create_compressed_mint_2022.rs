@@ -0,0 +1,330 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program::{invoke, invoke_signed};
+use anchor_lang::system_program::{create_account, CreateAccount};
+use anchor_spl::token_2022::spl_token_2022::{
+    self,
+    extension::{
+        metadata_pointer,
+        transfer_fee::{self, MAX_FEE_BASIS_POINTS},
+        ExtensionType,
+    },
+    state::Mint,
+};
+use anchor_spl::token_2022::Token2022;
+use light_compressed_token::{
+    cpi::accounts::CreateTokenPoolInstruction, program::LightCompressedToken,
+};
+use spl_token_metadata_interface::state::TokenMetadata;
+
+use crate::{
+    state::{
+        CompressedMint2022Created, CustomError, FREEZE_AUTHORITY, MINT_AUTHORITY, MINT_CONFIG,
+        VAULT_CONFIG_SEED,
+    },
+    user::{MintConfig, MintLimits, COMPRESSED_MINT_SEED},
+    vaults::{
+        config_authority, require_service_signer, ServiceSignerEntry, VaultConfigState,
+        PERMISSION_MINT,
+    },
+};
+
+/// Transfer fee of a Token-2022 mint, collected and withdrawn by the mint authority PDA
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct TransferFeeParams {
+    pub basis_points: u16,
+    pub maximum_fee: u64,
+}
+
+/// Optional extensions of a Token-2022 mint, next to the metadata ones every mint gets
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default)]
+pub struct Token2022Extensions {
+    /// Withheld by Token-2022 on every SPL transfer of the mint. Compress withdraws the fee
+    /// withheld in the token pool back into it, so the pool matches the compressed supply.
+    pub transfer_fee: Option<TransferFeeParams>,
+    /// Makes the mint authority PDA the permanent delegate. It can move or burn any balance
+    /// of the mint, the token pool included, so it is never an outside key.
+    pub permanent_delegate: bool,
+}
+
+impl Token2022Extensions {
+    /// Checks the transfer fee before any account is created
+    pub fn validate(&self) -> Result<()> {
+        if let Some(transfer_fee) = &self.transfer_fee {
+            require!(
+                transfer_fee.basis_points <= MAX_FEE_BASIS_POINTS,
+                CustomError::InvalidTransferFee
+            );
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(
+    name: String,
+    symbol: String,
+    decimals: u8,
+    uri: String,
+    nonce: u16,
+)]
+pub struct CreateCompressedMint2022<'info> {
+    /// Server creating the mint, it alone can mint and compress with it
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(mut)]
+    pub service_signer: Signer<'info>,
+
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority::ID.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
+
+    /// Registry entry of the service signer in `config`
+    pub service_signer_entry: Account<'info, ServiceSignerEntry>,
+
+    /// CHECK: Mint authority of this mint, derived from the creating server and the nonce
+    #[account(
+        seeds = [
+            MINT_AUTHORITY.as_bytes(),
+            payer.key().as_ref(),
+            &nonce.to_be_bytes()
+        ],
+        bump,
+    )]
+    pub authority: UncheckedAccount<'info>,
+
+    /// CHECK: Freeze authority of this mint, derived like the mint authority
+    #[account(
+        seeds = [
+            FREEZE_AUTHORITY.as_bytes(),
+            payer.key().as_ref(),
+            &nonce.to_be_bytes()
+        ],
+        bump,
+    )]
+    pub freeze_authority: UncheckedAccount<'info>,
+
+    /// CHECK: Created with its extensions in the handler, the classic and Token-2022 paths
+    /// share the mint address space
+    #[account(
+        mut,
+        seeds = [
+            COMPRESSED_MINT_SEED.as_bytes(),
+            payer.key().as_ref(),
+            &nonce.to_be_bytes()
+        ],
+        bump,
+    )]
+    pub compressed_mint: UncheckedAccount<'info>,
+
+    /// Supply cap and mint limits enforced by `mint_tokens`
+    #[account(
+        init,
+        seeds = [MINT_CONFIG.as_bytes(), compressed_mint.key().as_ref()],
+        bump,
+        payer = payer,
+        space = MintConfig::LEN,
+    )]
+    pub mint_config: Box<Account<'info, MintConfig>>,
+
+    /// CHECK: Checked in the compressed token program
+    #[account(mut)]
+    pub cpi_authority_pda: UncheckedAccount<'info>,
+
+    /// CHECK: Token pool of the mint, created by the compressed token program
+    #[account(mut)]
+    pub token_pool_pda: UncheckedAccount<'info>,
+
+    pub compressed_token_program: Program<'info, LightCompressedToken>,
+
+    pub token_program: Program<'info, Token2022>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Creates a Token-2022 mint carrying its own metadata through the metadata-pointer and
+/// token-metadata extensions instead of Metaplex, and registers its compressed token pool.
+pub fn create_compressed_mint_2022<'info>(
+    ctx: Context<'_, '_, '_, 'info, CreateCompressedMint2022<'info>>,
+    name: String,
+    symbol: String,
+    decimals: u8,
+    uri: String,
+    nonce: u16,
+    freezable: bool,
+    limits: MintLimits,
+    extensions: Token2022Extensions,
+) -> Result<()> {
+    require_service_signer(
+        &ctx.accounts.config,
+        &ctx.accounts.service_signer_entry,
+        &ctx.accounts.service_signer,
+        PERMISSION_MINT,
+    )?;
+    extensions.validate()?;
+
+    let mint = ctx.accounts.compressed_mint.key();
+    let authority = ctx.accounts.authority.key();
+    let server_authority = ctx.accounts.payer.key();
+    let token_program_id = ctx.accounts.token_program.key();
+    let nonce_bytes = nonce.to_be_bytes();
+
+    let mut extension_types = vec![ExtensionType::MetadataPointer];
+    if extensions.transfer_fee.is_some() {
+        extension_types.push(ExtensionType::TransferFeeConfig);
+    }
+    if extensions.permanent_delegate {
+        extension_types.push(ExtensionType::PermanentDelegate);
+    }
+    let space = ExtensionType::try_calculate_account_len::<Mint>(&extension_types)?;
+
+    // The token-metadata extension grows the mint when it is initialized, so the rent for
+    // the metadata is paid up front
+    let metadata = TokenMetadata {
+        update_authority: Some(authority).try_into()?,
+        mint,
+        name: name.clone(),
+        symbol: symbol.clone(),
+        uri: uri.clone(),
+        additional_metadata: vec![],
+    };
+    let lamports = Rent::get()?.minimum_balance(space + metadata.tlv_size_of()?);
+
+    create_account(
+        CpiContext::new_with_signer(
+            ctx.accounts.system_program.to_account_info(),
+            CreateAccount {
+                from: ctx.accounts.payer.to_account_info(),
+                to: ctx.accounts.compressed_mint.to_account_info(),
+            },
+            &[&[
+                COMPRESSED_MINT_SEED.as_bytes(),
+                server_authority.as_ref(),
+                &nonce_bytes,
+                &[ctx.bumps.compressed_mint],
+            ]],
+        ),
+        lamports,
+        space as u64,
+        &token_program_id,
+    )?;
+
+    let mint_account = ctx.accounts.compressed_mint.to_account_info();
+
+    // Extensions are initialized before the mint itself
+    invoke(
+        &metadata_pointer::instruction::initialize(
+            &token_program_id,
+            &mint,
+            Some(authority),
+            Some(mint),
+        )?,
+        &[mint_account.clone()],
+    )?;
+
+    if let Some(transfer_fee) = &extensions.transfer_fee {
+        invoke(
+            &transfer_fee::instruction::initialize_transfer_fee_config(
+                &token_program_id,
+                &mint,
+                Some(&authority),
+                Some(&authority),
+                transfer_fee.basis_points,
+                transfer_fee.maximum_fee,
+            )?,
+            &[mint_account.clone()],
+        )?;
+    }
+
+    if extensions.permanent_delegate {
+        invoke(
+            &spl_token_2022::instruction::initialize_permanent_delegate(
+                &token_program_id,
+                &mint,
+                &authority,
+            )?,
+            &[mint_account.clone()],
+        )?;
+    }
+
+    let freeze_authority = ctx.accounts.freeze_authority.key();
+    invoke(
+        &spl_token_2022::instruction::initialize_mint2(
+            &token_program_id,
+            &mint,
+            &authority,
+            freezable.then_some(&freeze_authority),
+            decimals,
+        )?,
+        &[mint_account.clone()],
+    )?;
+
+    let authority_seeds: &[&[u8]] = &[
+        MINT_AUTHORITY.as_bytes(),
+        server_authority.as_ref(),
+        &nonce_bytes,
+        &[ctx.bumps.authority],
+    ];
+    invoke_signed(
+        &spl_token_metadata_interface::instruction::initialize(
+            &token_program_id,
+            &mint,
+            &authority,
+            &mint,
+            &authority,
+            name.clone(),
+            symbol.clone(),
+            uri.clone(),
+        ),
+        &[
+            mint_account.clone(),
+            ctx.accounts.authority.to_account_info(),
+        ],
+        &[authority_seeds],
+    )?;
+
+    light_compressed_token::cpi::create_token_pool(CpiContext::new(
+        ctx.accounts.compressed_token_program.to_account_info(),
+        CreateTokenPoolInstruction {
+            fee_payer: ctx.accounts.payer.to_account_info(),
+            mint: mint_account,
+            system_program: ctx.accounts.system_program.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+            token_pool_pda: ctx.accounts.token_pool_pda.to_account_info(),
+            cpi_authority_pda: ctx.accounts.cpi_authority_pda.to_account_info(),
+        },
+    ))?;
+
+    let max_supply = limits.max_supply;
+    let mint_config_bump = ctx.bumps.mint_config;
+    ctx.accounts.mint_config.initialize(
+        mint,
+        server_authority,
+        nonce,
+        ctx.bumps.authority,
+        limits,
+        Clock::get()?.unix_timestamp,
+        mint_config_bump,
+    )?;
+
+    emit!(CompressedMint2022Created {
+        mint,
+        name,
+        symbol,
+        decimals,
+        uri,
+        payer: server_authority,
+        freezable,
+        max_supply,
+        transfer_fee_basis_points: extensions
+            .transfer_fee
+            .as_ref()
+            .map(|transfer_fee| transfer_fee.basis_points),
+        permanent_delegate: extensions.permanent_delegate.then_some(authority),
+    });
+
+    Ok(())
+}
@@ -1,7 +1,11 @@
-use crate::state::CustomError;
+use crate::state::{CustomError, TokensDecompressed, VAULT_CONFIG_SEED};
+use crate::vaults::{
+    config_authority, require_service_signer, ServiceSignerEntry, VaultConfigState,
+    PERMISSION_DECOMPRESS,
+};
 use account_compression::program::AccountCompression;
 use anchor_lang::prelude::*;
-use anchor_spl::token::Token;
+use anchor_spl::token_interface::TokenInterface;
 use light_compressed_token::cpi::accounts::TransferInstruction;
 use light_compressed_token::process_transfer::CompressedTokenInstructionDataTransfer;
 use light_compressed_token::program::LightCompressedToken;
@@ -15,6 +19,15 @@ pub struct DecompressTokens<'info> {
     #[account(mut)]
     pub service_signer: Signer<'info>,
 
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority::ID.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
+
+    /// Registry entry of the service signer in `config`
+    pub service_signer_entry: Account<'info, ServiceSignerEntry>,
+
     #[account(mut)]
     pub authority: Signer<'info>,
 
@@ -33,7 +46,7 @@ pub struct DecompressTokens<'info> {
     /// CHECK:
     pub noop_program: UncheckedAccount<'info>,
 
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
 
     pub account_compression_authority: UncheckedAccount<'info>,
 
@@ -47,6 +60,13 @@ pub fn decompress_tokens<'info>(
     ctx: Context<'_, '_, '_, 'info, DecompressTokens<'info>>,
     compressed_params: Vec<u8>,
 ) -> Result<()> {
+    require_service_signer(
+        &ctx.accounts.config,
+        &ctx.accounts.service_signer_entry,
+        &ctx.accounts.service_signer,
+        PERMISSION_DECOMPRESS,
+    )?;
+
     let inputs: CompressedTokenInstructionDataTransfer =
         CompressedTokenInstructionDataTransfer::deserialize(&mut compressed_params.as_slice())
             .map_err(|_| {
@@ -63,7 +83,15 @@ pub fn decompress_tokens<'info>(
             )
             .with_remaining_accounts(ctx.remaining_accounts.to_vec()),
         inputs.try_to_vec()?,
-    )
+    )?;
+
+    emit!(TokensDecompressed {
+        mint: inputs.mint,
+        token_account: ctx.accounts.decompress_account.key(),
+        amount: inputs.compress_or_decompress_amount.unwrap_or_default(),
+    });
+
+    Ok(())
 }
 
 impl<'info> DecompressTokens<'info> {
@@ -0,0 +1,256 @@
+use account_compression::program::AccountCompression;
+use anchor_lang::prelude::*;
+use light_compressed_token::cpi::accounts::GenericInstruction;
+use light_compressed_token::delegation::{
+    CompressedTokenInstructionDataApprove, CompressedTokenInstructionDataRevoke,
+};
+use light_compressed_token::program::LightCompressedToken;
+use light_system_program::program::LightSystemProgram;
+
+use crate::compressed_transfers::{
+    delegated_transfer_compressed_tokens_cpi, impl_token_cpi_accounts, CompressedTokenInputs,
+};
+use crate::state::{
+    CustomError, DelegatedTokensTransferred, DelegationRevoked, TokensDelegated,
+    USER_VAULT_AUTHORITY, USER_VAULT_TOKENS, VAULT_CONFIG_SEED,
+};
+use crate::vaults::{config_authority, UserVaultAuthority, UserVaultStateV2, VaultConfigState};
+
+#[derive(Accounts)]
+pub struct ManageTokenDelegation<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// Authority of the user vault
+    pub current_authority: Signer<'info>,
+
+    /// CHECK: Vault id the token owner and the authority record are derived from
+    pub vault_id: AccountInfo<'info>,
+
+    #[account(
+        seeds = [USER_VAULT_AUTHORITY.as_bytes(), vault_id.key().as_ref()],
+        bump = vault_authority.bump,
+        has_one = current_authority @ CustomError::InvalidAuthority
+    )]
+    pub vault_authority: Account<'info, UserVaultAuthority>,
+
+    /// CHECK: Token owner PDA of the vault id, owner of the delegated tokens
+    #[account(
+        address = UserVaultStateV2::find_token_owner(&vault_id.key()).0
+            @ CustomError::InvalidAuthority
+    )]
+    pub token_owner: AccountInfo<'info>,
+
+    /// CHECK: Checked in the compressed token program
+    pub compressed_token_cpi_authority_pda: AccountInfo<'info>,
+
+    pub compressed_token_program: Program<'info, LightCompressedToken>,
+
+    pub light_system_program: Program<'info, LightSystemProgram>,
+
+    /// CHECK: Checked in light-system-program.
+    pub registered_program_pda: AccountInfo<'info>,
+
+    /// CHECK: Checked in light-system-program.
+    pub noop_program: AccountInfo<'info>,
+
+    /// CHECK: Checked in light-system-program.
+    pub account_compression_authority: AccountInfo<'info>,
+
+    pub account_compression_program: Program<'info, AccountCompression>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Approvals and revocations pause with the transfers
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority::ID.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
+}
+
+/// Lets `delegate` spend up to `delegated_amount` of the user vault's tokens. The inputs are
+/// split into a delegated account and an undelegated change account.
+pub fn approve_delegate<'info>(
+    ctx: Context<'_, '_, '_, 'info, ManageTokenDelegation<'info>>,
+    mint: Pubkey,
+    delegate: Pubkey,
+    delegated_amount: u64,
+    vault_inputs: CompressedTokenInputs,
+) -> Result<()> {
+    require!(delegated_amount > 0, CustomError::ZeroAmount);
+
+    let inputs_struct = CompressedTokenInstructionDataApprove {
+        proof: vault_inputs.proof,
+        mint,
+        input_token_data_with_context: vault_inputs.input_token_data_with_context,
+        cpi_context: None,
+        delegate,
+        delegated_amount,
+        delegate_merkle_tree_index: vault_inputs.recipient_merkle_tree_index,
+        change_account_merkle_tree_index: vault_inputs.change_merkle_tree_index,
+        delegate_lamports: None,
+    };
+
+    let mut data = Vec::new();
+    CompressedTokenInstructionDataApprove::serialize(&inputs_struct, &mut data)?;
+
+    let vault_id = ctx.accounts.vault_id.key();
+    let (_, bump) = UserVaultStateV2::find_token_owner(&vault_id);
+    let bump_bytes = [bump];
+    let seeds: &[&[u8]] = &[USER_VAULT_TOKENS.as_bytes(), vault_id.as_ref(), &bump_bytes];
+
+    light_compressed_token::cpi::approve(
+        ctx.accounts
+            .generic_cpi_context(&[seeds], ctx.remaining_accounts),
+        data,
+    )?;
+
+    emit!(TokensDelegated {
+        vault_id,
+        owner: ctx.accounts.token_owner.key(),
+        delegate,
+        mint,
+        amount: delegated_amount,
+    });
+
+    Ok(())
+}
+
+/// Ends every delegation of the inputs, merging them into one undelegated account.
+pub fn revoke_delegate<'info>(
+    ctx: Context<'_, '_, '_, 'info, ManageTokenDelegation<'info>>,
+    mint: Pubkey,
+    vault_inputs: CompressedTokenInputs,
+) -> Result<()> {
+    let inputs_struct = CompressedTokenInstructionDataRevoke {
+        proof: vault_inputs.proof,
+        mint,
+        input_token_data_with_context: vault_inputs.input_token_data_with_context,
+        cpi_context: None,
+        output_account_merkle_tree_index: vault_inputs.change_merkle_tree_index,
+    };
+
+    let mut data = Vec::new();
+    CompressedTokenInstructionDataRevoke::serialize(&inputs_struct, &mut data)?;
+
+    let vault_id = ctx.accounts.vault_id.key();
+    let (_, bump) = UserVaultStateV2::find_token_owner(&vault_id);
+    let bump_bytes = [bump];
+    let seeds: &[&[u8]] = &[USER_VAULT_TOKENS.as_bytes(), vault_id.as_ref(), &bump_bytes];
+
+    light_compressed_token::cpi::revoke(
+        ctx.accounts
+            .generic_cpi_context(&[seeds], ctx.remaining_accounts),
+        data,
+    )?;
+
+    emit!(DelegationRevoked {
+        vault_id,
+        owner: ctx.accounts.token_owner.key(),
+        mint,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct DelegatedTransferTokens<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// Delegate of the spent token accounts
+    pub delegate: Signer<'info>,
+
+    /// CHECK: Checked in the compressed token program
+    pub compressed_token_cpi_authority_pda: AccountInfo<'info>,
+
+    pub compressed_token_program: Program<'info, LightCompressedToken>,
+
+    pub light_system_program: Program<'info, LightSystemProgram>,
+
+    /// CHECK: Checked in light-system-program.
+    pub registered_program_pda: AccountInfo<'info>,
+
+    /// CHECK: Checked in light-system-program.
+    pub noop_program: AccountInfo<'info>,
+
+    /// CHECK: Checked in light-system-program.
+    pub account_compression_authority: AccountInfo<'info>,
+
+    pub account_compression_program: Program<'info, AccountCompression>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Delegated spending pauses with the transfers
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority::ID.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
+}
+
+/// Spends `amount` of `owner`'s delegated tokens as the delegate. The compressed token program
+/// rejects inputs that are not delegated to the signer, and what is left of the allowance
+/// stays delegated.
+pub fn delegated_transfer<'info>(
+    ctx: Context<'_, '_, '_, 'info, DelegatedTransferTokens<'info>>,
+    owner: Pubkey,
+    mint: Pubkey,
+    amount: u64,
+    recipient: Pubkey,
+    delegated_inputs: CompressedTokenInputs,
+) -> Result<()> {
+    let accounts = ctx.accounts.token_cpi_accounts(
+        ctx.accounts.delegate.to_account_info(),
+        ctx.remaining_accounts,
+    );
+    delegated_transfer_compressed_tokens_cpi(
+        &accounts,
+        mint,
+        amount,
+        recipient,
+        owner,
+        delegated_inputs,
+    )?;
+
+    emit!(DelegatedTokensTransferred {
+        owner,
+        delegate: ctx.accounts.delegate.key(),
+        recipient,
+        mint,
+        amount,
+    });
+
+    Ok(())
+}
+
+impl<'info> ManageTokenDelegation<'info> {
+    fn generic_cpi_context<'a, 'b, 'c>(
+        &self,
+        signer_seeds: &'a [&'b [&'c [u8]]],
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> CpiContext<'a, 'b, 'c, 'info, GenericInstruction<'info>> {
+        let cpi_accounts = GenericInstruction {
+            fee_payer: self.payer.to_account_info(),
+            authority: self.token_owner.to_account_info(),
+            cpi_authority_pda: self.compressed_token_cpi_authority_pda.to_account_info(),
+            light_system_program: self.light_system_program.to_account_info(),
+            registered_program_pda: self.registered_program_pda.to_account_info(),
+            noop_program: self.noop_program.to_account_info(),
+            account_compression_authority: self.account_compression_authority.to_account_info(),
+            account_compression_program: self.account_compression_program.to_account_info(),
+            self_program: self.compressed_token_program.to_account_info(),
+            system_program: self.system_program.to_account_info(),
+        };
+
+        CpiContext::new_with_signer(
+            self.compressed_token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        )
+        .with_remaining_accounts(remaining_accounts.to_vec())
+    }
+}
+
+impl_token_cpi_accounts!(DelegatedTransferTokens, payer);
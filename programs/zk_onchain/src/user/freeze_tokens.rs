@@ -0,0 +1,201 @@
+use account_compression::program::AccountCompression;
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program_option::COption;
+use anchor_spl::token_interface::Mint;
+use light_compressed_token::cpi::accounts::FreezeInstruction;
+use light_compressed_token::freeze::CompressedTokenInstructionDataFreeze;
+use light_compressed_token::program::LightCompressedToken;
+use light_system_program::program::LightSystemProgram;
+
+use crate::compressed_transfers::{sum_token_inputs, CompressedTokenInputs};
+use crate::state::{
+    CustomError, TokensFrozen, TokensThawed, FREEZE_AUTHORITY, MINT_CONFIG, VAULT_CONFIG_SEED,
+};
+use crate::user::MintConfig;
+use crate::vaults::{
+    config_authority, require_service_signer, ServiceSignerEntry, VaultConfigState,
+    PERMISSION_FREEZE,
+};
+
+#[derive(Accounts)]
+pub struct FreezeCompressedTokens<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub service_signer: Signer<'info>,
+
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority::ID.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
+
+    /// Registry entry of the service signer in `config`
+    pub service_signer_entry: Account<'info, ServiceSignerEntry>,
+
+    /// Server that created the mint, only it can freeze tokens of the mint
+    pub server_authority: Signer<'info>,
+
+    #[account(
+        seeds = [MINT_CONFIG.as_bytes(), mint.key().as_ref()],
+        bump = mint_config.bump,
+        has_one = mint @ CustomError::InvalidMint,
+        has_one = server_authority @ CustomError::InvalidAuthority,
+    )]
+    pub mint_config: Box<Account<'info, MintConfig>>,
+
+    /// CHECK: Freeze authority PDA of the mint, derived from its server and nonce
+    #[account(
+        seeds = [
+            FREEZE_AUTHORITY.as_bytes(),
+            mint_config.server_authority.as_ref(),
+            &mint_config.nonce.to_be_bytes()
+        ],
+        bump
+    )]
+    pub freeze_authority: AccountInfo<'info>,
+
+    #[account(
+        constraint = mint.freeze_authority == COption::Some(freeze_authority.key())
+            @ CustomError::MintNotFreezable
+    )]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// CHECK: Checked in the compressed token program
+    pub compressed_token_cpi_authority_pda: AccountInfo<'info>,
+
+    pub compressed_token_program: Program<'info, LightCompressedToken>,
+
+    pub light_system_program: Program<'info, LightSystemProgram>,
+
+    /// CHECK: Checked in light-system-program.
+    pub registered_program_pda: AccountInfo<'info>,
+
+    /// CHECK: Checked in light-system-program.
+    pub noop_program: AccountInfo<'info>,
+
+    /// CHECK: Checked in light-system-program.
+    pub account_compression_authority: AccountInfo<'info>,
+
+    pub account_compression_program: Program<'info, AccountCompression>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Freezes compressed token accounts of `owner`, they cannot be spent until thawed.
+pub fn freeze_compressed_tokens<'info>(
+    ctx: Context<'_, '_, '_, 'info, FreezeCompressedTokens<'info>>,
+    owner: Pubkey,
+    token_inputs: CompressedTokenInputs,
+) -> Result<()> {
+    let amount = invoke_freeze(&ctx, owner, token_inputs, true)?;
+
+    emit!(TokensFrozen {
+        mint: ctx.accounts.mint.key(),
+        owner,
+        amount,
+    });
+
+    Ok(())
+}
+
+/// Thaws frozen compressed token accounts of `owner`.
+pub fn thaw_compressed_tokens<'info>(
+    ctx: Context<'_, '_, '_, 'info, FreezeCompressedTokens<'info>>,
+    owner: Pubkey,
+    token_inputs: CompressedTokenInputs,
+) -> Result<()> {
+    let amount = invoke_freeze(&ctx, owner, token_inputs, false)?;
+
+    emit!(TokensThawed {
+        mint: ctx.accounts.mint.key(),
+        owner,
+        amount,
+    });
+
+    Ok(())
+}
+
+/// Freezes or thaws the inputs signed by the freeze authority PDA of the mint, once the
+/// service signer is checked. Returns the amount of the inputs.
+fn invoke_freeze<'info>(
+    ctx: &Context<'_, '_, '_, 'info, FreezeCompressedTokens<'info>>,
+    owner: Pubkey,
+    token_inputs: CompressedTokenInputs,
+    freeze: bool,
+) -> Result<u64> {
+    require_service_signer(
+        &ctx.accounts.config,
+        &ctx.accounts.service_signer_entry,
+        &ctx.accounts.service_signer,
+        PERMISSION_FREEZE,
+    )?;
+
+    let amount = sum_token_inputs(&token_inputs.input_token_data_with_context)?;
+    let data = freeze_instruction_data(owner, token_inputs)?;
+
+    let server_authority = ctx.accounts.mint_config.server_authority;
+    let nonce = ctx.accounts.mint_config.nonce.to_be_bytes();
+    let bump = [ctx.bumps.freeze_authority];
+    let seeds: &[&[u8]] = &[
+        FREEZE_AUTHORITY.as_bytes(),
+        server_authority.as_ref(),
+        &nonce,
+        &bump,
+    ];
+    let cpi_context = ctx
+        .accounts
+        .freeze_cpi_context(&[seeds], ctx.remaining_accounts);
+    if freeze {
+        light_compressed_token::cpi::freeze(cpi_context, data)?;
+    } else {
+        light_compressed_token::cpi::thaw(cpi_context, data)?;
+    }
+
+    Ok(amount)
+}
+
+/// Freeze and thaw share their instruction data, each input keeps its amount in one output
+fn freeze_instruction_data(owner: Pubkey, token_inputs: CompressedTokenInputs) -> Result<Vec<u8>> {
+    let inputs_struct = CompressedTokenInstructionDataFreeze {
+        proof: token_inputs.proof,
+        owner,
+        input_token_data_with_context: token_inputs.input_token_data_with_context,
+        cpi_context: None,
+        outputs_merkle_tree_index: token_inputs.change_merkle_tree_index,
+    };
+
+    let mut data = Vec::new();
+    CompressedTokenInstructionDataFreeze::serialize(&inputs_struct, &mut data)?;
+
+    Ok(data)
+}
+
+impl<'info> FreezeCompressedTokens<'info> {
+    fn freeze_cpi_context<'a, 'b, 'c>(
+        &self,
+        signer_seeds: &'a [&'b [&'c [u8]]],
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> CpiContext<'a, 'b, 'c, 'info, FreezeInstruction<'info>> {
+        let cpi_accounts = FreezeInstruction {
+            fee_payer: self.payer.to_account_info(),
+            authority: self.freeze_authority.to_account_info(),
+            cpi_authority_pda: self.compressed_token_cpi_authority_pda.to_account_info(),
+            light_system_program: self.light_system_program.to_account_info(),
+            registered_program_pda: self.registered_program_pda.to_account_info(),
+            noop_program: self.noop_program.to_account_info(),
+            account_compression_authority: self.account_compression_authority.to_account_info(),
+            account_compression_program: self.account_compression_program.to_account_info(),
+            self_program: self.compressed_token_program.to_account_info(),
+            system_program: self.system_program.to_account_info(),
+            mint: self.mint.to_account_info(),
+        };
+
+        CpiContext::new_with_signer(
+            self.compressed_token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        )
+        .with_remaining_accounts(remaining_accounts.to_vec())
+    }
+}
@@ -0,0 +1,179 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{program::invoke_signed, program_option::COption};
+use anchor_spl::token_interface::{
+    self, spl_token_2022::instruction::AuthorityType, Mint, SetAuthority, TokenInterface,
+};
+use mpl_token_metadata::{
+    accounts::Metadata,
+    instructions::{UpdateMetadataAccountV2, UpdateMetadataAccountV2InstructionArgs},
+};
+
+use crate::{
+    state::{
+        CompressedMintMetadataAuthorityTransferred, CompressedMintMigrated, CustomError,
+        MINT_AUTHORITY, MINT_CONFIG, VAULT_CONFIG_SEED,
+    },
+    user::{MintConfig, MintLimits, COMPRESSED_MINT_SEED},
+    vaults::{
+        config_authority, require_service_signer, ServiceSignerEntry, VaultConfigState,
+        PERMISSION_MINT,
+    },
+};
+
+#[derive(Accounts)]
+#[instruction(nonce: u16)]
+pub struct MigrateCompressedMint<'info> {
+    /// Server that created the mint
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub service_signer: Signer<'info>,
+
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority::ID.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
+
+    /// Registry entry of the service signer in `config`
+    pub service_signer_entry: Account<'info, ServiceSignerEntry>,
+
+    /// CHECK: Global mint authority of mints created before per-server mint authorities
+    #[account(seeds = [MINT_AUTHORITY.as_bytes()], bump)]
+    pub legacy_authority: UncheckedAccount<'info>,
+
+    /// CHECK: Mint authority the mint moves to, derived from the server and the nonce
+    #[account(
+        seeds = [MINT_AUTHORITY.as_bytes(), payer.key().as_ref(), &nonce.to_be_bytes()],
+        bump
+    )]
+    pub authority: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [COMPRESSED_MINT_SEED.as_bytes(), payer.key().as_ref(), &nonce.to_be_bytes()],
+        bump,
+        constraint = compressed_mint.mint_authority == COption::Some(legacy_authority.key())
+            @ CustomError::InvalidAuthority,
+        mint::token_program = token_program,
+    )]
+    pub compressed_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// Supply cap and mint limits enforced by `mint_tokens` from now on
+    #[account(
+        init,
+        seeds = [MINT_CONFIG.as_bytes(), compressed_mint.key().as_ref()],
+        bump,
+        payer = payer,
+        space = MintConfig::LEN,
+    )]
+    pub mint_config: Box<Account<'info, MintConfig>>,
+
+    /// CHECK: Metaplex metadata of `compressed_mint`, owned by the token metadata program
+    #[account(
+        mut,
+        seeds = [
+            b"metadata",
+            mpl_token_metadata::ID.as_ref(),
+            compressed_mint.key().as_ref()
+        ],
+        bump,
+        seeds::program = mpl_token_metadata::ID
+    )]
+    pub metadata_account: UncheckedAccount<'info>,
+
+    /// CHECK: Token metadata program
+    #[account(executable, address = mpl_token_metadata::ID)]
+    pub mpl_token_metadata: AccountInfo<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Brings a mint created before mint configs existed under `limits`. The mint authority moves
+/// from the global legacy PDA to the per-server PDA, so `mint_tokens` accepts the mint again.
+/// Mutable metadata moves to the per-server PDA as well, so `update_compressed_mint_metadata`
+/// works for the mint. Legacy mints have no freeze authority, nothing else moves.
+pub fn migrate_compressed_mint(
+    ctx: Context<MigrateCompressedMint>,
+    nonce: u16,
+    limits: MintLimits,
+) -> Result<()> {
+    require_service_signer(
+        &ctx.accounts.config,
+        &ctx.accounts.service_signer_entry,
+        &ctx.accounts.service_signer,
+        PERMISSION_MINT,
+    )?;
+    require!(
+        limits.max_supply >= ctx.accounts.compressed_mint.supply,
+        CustomError::InvalidMintLimits
+    );
+
+    let max_supply = limits.max_supply;
+    let mint_key = ctx.accounts.compressed_mint.key();
+    let server_authority = ctx.accounts.payer.key();
+    let authority_bump = ctx.bumps.authority;
+    let mint_config_bump = ctx.bumps.mint_config;
+    ctx.accounts.mint_config.initialize(
+        mint_key,
+        server_authority,
+        nonce,
+        authority_bump,
+        limits,
+        Clock::get()?.unix_timestamp,
+        mint_config_bump,
+    )?;
+    // Tokens minted before the migration count against the cap
+    ctx.accounts.mint_config.total_minted = ctx.accounts.compressed_mint.supply;
+
+    token_interface::set_authority(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            SetAuthority {
+                current_authority: ctx.accounts.legacy_authority.to_account_info(),
+                account_or_mint: ctx.accounts.compressed_mint.to_account_info(),
+            },
+            &[&[MINT_AUTHORITY.as_bytes(), &[ctx.bumps.legacy_authority]]],
+        ),
+        AuthorityType::MintTokens,
+        Some(ctx.accounts.authority.key()),
+    )?;
+
+    let metadata = Metadata::safe_deserialize(&ctx.accounts.metadata_account.try_borrow_data()?)
+        .map_err(|_| CustomError::MissingMetadataAccount)?;
+    if metadata.is_mutable && metadata.update_authority == ctx.accounts.legacy_authority.key() {
+        let update_metadata_ix = UpdateMetadataAccountV2 {
+            metadata: ctx.accounts.metadata_account.key(),
+            update_authority: ctx.accounts.legacy_authority.key(),
+        }
+        .instruction(UpdateMetadataAccountV2InstructionArgs {
+            data: None,
+            new_update_authority: Some(ctx.accounts.authority.key()),
+            primary_sale_happened: None,
+            is_mutable: None,
+        });
+        invoke_signed(
+            &update_metadata_ix,
+            &[
+                ctx.accounts.metadata_account.to_account_info(),
+                ctx.accounts.legacy_authority.to_account_info(),
+            ],
+            &[&[MINT_AUTHORITY.as_bytes(), &[ctx.bumps.legacy_authority]]],
+        )?;
+
+        emit!(CompressedMintMetadataAuthorityTransferred {
+            mint: mint_key,
+            new_update_authority: ctx.accounts.authority.key(),
+        });
+    }
+
+    emit!(CompressedMintMigrated {
+        mint: mint_key,
+        server_authority,
+        max_supply,
+    });
+
+    Ok(())
+}
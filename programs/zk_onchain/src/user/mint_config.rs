@@ -0,0 +1,336 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{CustomError, FREEZE_AUTHORITY, MINT_AUTHORITY, MINT_CONFIG, MINT_QUOTA};
+
+/// Limits a compressed mint is created with
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct MintLimits {
+    /// Hard cap on everything ever minted, burns don't make room again
+    pub max_supply: u64,
+    /// Length of a mint period in seconds
+    pub period_duration: i64,
+    /// Most all service signers together may mint in one period
+    pub period_limit: u64,
+    /// Most one service signer may mint in one period
+    pub service_signer_quota: u64,
+}
+
+/// Mint authority PDA of the mint `server_authority` created with `nonce`. Each server signs
+/// for its own mints only.
+pub fn find_mint_authority(server_authority: &Pubkey, nonce: u16) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            MINT_AUTHORITY.as_bytes(),
+            server_authority.as_ref(),
+            &nonce.to_be_bytes(),
+        ],
+        &crate::ID,
+    )
+}
+
+/// Freeze authority PDA of the freezable mint `server_authority` created with `nonce`.
+/// Scoped like the mint authority, so a server can only freeze tokens of its own mints.
+pub fn find_freeze_authority(server_authority: &Pubkey, nonce: u16) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            FREEZE_AUTHORITY.as_bytes(),
+            server_authority.as_ref(),
+            &nonce.to_be_bytes(),
+        ],
+        &crate::ID,
+    )
+}
+
+#[account]
+pub struct MintConfig {
+    pub mint: Pubkey,
+    /// Creator of the mint, co-signs every mint and compress
+    pub server_authority: Pubkey,
+    pub nonce: u16,
+    pub authority_bump: u8,
+    pub max_supply: u64,
+    /// Minted since the config was created, plus the supply of a migrated mint. Capped by
+    /// `max_supply` and never lowered by burns.
+    pub total_minted: u64,
+    pub period_duration: i64,
+    pub period_limit: u64,
+    pub service_signer_quota: u64,
+    pub period_started_at: i64,
+    /// Minted by all service signers since `period_started_at`
+    pub period_minted: u64,
+    pub bump: u8,
+}
+
+impl MintConfig {
+    pub const LEN: usize = 8 +  // discriminator
+        32 +         // mint
+        32 +         // server_authority
+        2 +          // nonce
+        1 +          // authority_bump
+        8 +          // max_supply
+        8 +          // total_minted
+        8 +          // period_duration
+        8 +          // period_limit
+        8 +          // service_signer_quota
+        8 +          // period_started_at
+        8 +          // period_minted
+        1; // bump
+
+    pub fn find_address(mint: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[MINT_CONFIG.as_bytes(), mint.as_ref()], &crate::ID)
+    }
+
+    pub fn initialize(
+        &mut self,
+        mint: Pubkey,
+        server_authority: Pubkey,
+        nonce: u16,
+        authority_bump: u8,
+        limits: MintLimits,
+        now: i64,
+        bump: u8,
+    ) -> Result<()> {
+        require!(
+            limits.max_supply > 0
+                && limits.period_duration > 0
+                && limits.period_limit > 0
+                && limits.period_limit <= limits.max_supply
+                && limits.service_signer_quota > 0
+                && limits.service_signer_quota <= limits.period_limit,
+            CustomError::InvalidMintLimits
+        );
+
+        self.mint = mint;
+        self.server_authority = server_authority;
+        self.nonce = nonce;
+        self.authority_bump = authority_bump;
+        self.max_supply = limits.max_supply;
+        self.total_minted = 0;
+        self.period_duration = limits.period_duration;
+        self.period_limit = limits.period_limit;
+        self.service_signer_quota = limits.service_signer_quota;
+        self.period_started_at = now;
+        self.period_minted = 0;
+        self.bump = bump;
+
+        Ok(())
+    }
+
+    /// Books `amount` against the supply cap, the period limit and the quota of the minting
+    /// service signer. A period that has run out starts over at `now`.
+    pub fn record_mint(&mut self, quota: &mut MintQuota, amount: u64, now: i64) -> Result<()> {
+        let total_minted = self
+            .total_minted
+            .checked_add(amount)
+            .filter(|minted| *minted <= self.max_supply)
+            .ok_or(CustomError::MintSupplyCapExceeded)?;
+
+        if now.saturating_sub(self.period_started_at) >= self.period_duration {
+            self.period_started_at = now;
+            self.period_minted = 0;
+        }
+        if now.saturating_sub(quota.period_started_at) >= self.period_duration {
+            quota.period_started_at = now;
+            quota.period_minted = 0;
+        }
+
+        self.period_minted = self
+            .period_minted
+            .checked_add(amount)
+            .filter(|minted| *minted <= self.period_limit)
+            .ok_or(CustomError::MintPeriodLimitExceeded)?;
+        quota.period_minted = quota
+            .period_minted
+            .checked_add(amount)
+            .filter(|minted| *minted <= self.service_signer_quota)
+            .ok_or(CustomError::MintQuotaExceeded)?;
+        self.total_minted = total_minted;
+
+        Ok(())
+    }
+}
+
+/// What one service signer minted of a mint in its current period
+#[account]
+pub struct MintQuota {
+    pub mint: Pubkey,
+    pub service_signer: Pubkey,
+    pub period_started_at: i64,
+    pub period_minted: u64,
+    pub bump: u8,
+}
+
+impl MintQuota {
+    pub const LEN: usize = 8 +  // discriminator
+        32 +         // mint
+        32 +         // service_signer
+        8 +          // period_started_at
+        8 +          // period_minted
+        1; // bump
+
+    pub fn find_address(mint: &Pubkey, service_signer: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[
+                MINT_QUOTA.as_bytes(),
+                mint.as_ref(),
+                service_signer.as_ref(),
+            ],
+            &crate::ID,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: i64 = 24 * 60 * 60;
+
+    fn limits() -> MintLimits {
+        MintLimits {
+            max_supply: 1_000,
+            period_duration: DAY,
+            period_limit: 100,
+            service_signer_quota: 60,
+        }
+    }
+
+    fn mint_config(now: i64) -> MintConfig {
+        let mut mint_config = MintConfig {
+            mint: Pubkey::default(),
+            server_authority: Pubkey::default(),
+            nonce: 0,
+            authority_bump: 0,
+            max_supply: 0,
+            total_minted: 0,
+            period_duration: 0,
+            period_limit: 0,
+            service_signer_quota: 0,
+            period_started_at: 0,
+            period_minted: 0,
+            bump: 0,
+        };
+        mint_config
+            .initialize(
+                Pubkey::new_unique(),
+                Pubkey::new_unique(),
+                3,
+                255,
+                limits(),
+                now,
+                255,
+            )
+            .unwrap();
+        mint_config
+    }
+
+    fn quota(mint_config: &MintConfig, now: i64) -> MintQuota {
+        MintQuota {
+            mint: mint_config.mint,
+            service_signer: Pubkey::new_unique(),
+            period_started_at: now,
+            period_minted: 0,
+            bump: 255,
+        }
+    }
+
+    #[test]
+    fn limits_must_nest() {
+        let mut mint_config = mint_config(0);
+        let too_large_quota = MintLimits {
+            service_signer_quota: 101,
+            ..limits()
+        };
+
+        assert_eq!(
+            mint_config
+                .initialize(
+                    Pubkey::default(),
+                    Pubkey::default(),
+                    0,
+                    0,
+                    too_large_quota,
+                    0,
+                    0
+                )
+                .unwrap_err(),
+            CustomError::InvalidMintLimits.into()
+        );
+    }
+
+    #[test]
+    fn mints_count_against_period_and_quota() {
+        let mut mint_config = mint_config(0);
+        let mut quota = quota(&mint_config, 0);
+
+        mint_config.record_mint(&mut quota, 40, 10).unwrap();
+        mint_config.record_mint(&mut quota, 20, 20).unwrap();
+
+        assert_eq!(mint_config.period_minted, 60);
+        assert_eq!(quota.period_minted, 60);
+        assert_eq!(
+            mint_config.record_mint(&mut quota, 1, 30).unwrap_err(),
+            CustomError::MintQuotaExceeded.into()
+        );
+    }
+
+    #[test]
+    fn period_limit_spans_all_service_signers() {
+        let mut mint_config = mint_config(0);
+        let mut first = quota(&mint_config, 0);
+        let mut second = quota(&mint_config, 0);
+
+        mint_config.record_mint(&mut first, 60, 10).unwrap();
+        assert_eq!(
+            mint_config.record_mint(&mut second, 41, 20).unwrap_err(),
+            CustomError::MintPeriodLimitExceeded.into()
+        );
+        mint_config.record_mint(&mut second, 40, 20).unwrap();
+        assert_eq!(mint_config.period_minted, 100);
+    }
+
+    #[test]
+    fn periods_start_over_once_they_run_out() {
+        let mut mint_config = mint_config(0);
+        let mut quota = quota(&mint_config, 0);
+
+        mint_config.record_mint(&mut quota, 60, 10).unwrap();
+        assert_eq!(
+            mint_config.record_mint(&mut quota, 1, DAY - 1).unwrap_err(),
+            CustomError::MintQuotaExceeded.into()
+        );
+
+        mint_config.record_mint(&mut quota, 50, DAY + 5).unwrap();
+
+        assert_eq!(mint_config.period_started_at, DAY + 5);
+        assert_eq!(mint_config.period_minted, 50);
+        assert_eq!(quota.period_started_at, DAY + 5);
+        assert_eq!(quota.period_minted, 50);
+    }
+
+    #[test]
+    fn supply_cap_holds_across_periods() {
+        let mut mint_config = mint_config(0);
+        let mut quota = quota(&mint_config, 0);
+        mint_config.total_minted = 950;
+
+        mint_config.record_mint(&mut quota, 50, 10).unwrap();
+        assert_eq!(mint_config.total_minted, 1_000);
+        assert_eq!(
+            mint_config.record_mint(&mut quota, 1, 2 * DAY).unwrap_err(),
+            CustomError::MintSupplyCapExceeded.into()
+        );
+    }
+
+    #[test]
+    fn refused_mints_are_not_booked() {
+        let mut mint_config = mint_config(0);
+        let mut quota = quota(&mint_config, 0);
+
+        assert_eq!(
+            mint_config.record_mint(&mut quota, 61, 10).unwrap_err(),
+            CustomError::MintQuotaExceeded.into()
+        );
+        assert_eq!(mint_config.total_minted, 0);
+    }
+}
@@ -2,11 +2,20 @@ use account_compression::program::AccountCompression;
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token::{Token, TokenAccount},
-    token_interface::Mint,
+    token_interface::{Mint, TokenAccount, TokenInterface},
 };
 use light_compressed_token::{cpi::accounts::MintToInstruction, program::LightCompressedToken};
 use light_system_program::program::LightSystemProgram;
+
+use crate::{
+    state::{CustomError, TokensMinted, MINT_CONFIG, MINT_QUOTA, VAULT_CONFIG_SEED},
+    user::{MintConfig, MintQuota},
+    vaults::{
+        config_authority, require_service_signer, ServiceSignerEntry, VaultConfigState,
+        PERMISSION_MINT,
+    },
+};
+
 #[derive(Accounts)]
 pub struct MintTokens<'info> {
     #[account(mut)]
@@ -15,11 +24,26 @@ pub struct MintTokens<'info> {
     #[account(mut)]
     pub service_signer: Signer<'info>,
 
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority::ID.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
+
+    /// Registry entry of the service signer in `config`
+    pub service_signer_entry: Account<'info, ServiceSignerEntry>,
+
+    /// Server that created `mint`
+    #[account(address = mint_config.server_authority @ CustomError::InvalidAuthority)]
+    pub server_authority: Signer<'info>,
+
     #[account(
         seeds = [
             crate::state::MINT_AUTHORITY.as_bytes(),
+            mint_config.server_authority.as_ref(),
+            &mint_config.nonce.to_be_bytes()
         ],
-        bump,
+        bump = mint_config.authority_bump,
         constraint = mint.mint_authority.unwrap() == authority.key()
     )]
     pub authority: UncheckedAccount<'info>,
@@ -27,7 +51,7 @@ pub struct MintTokens<'info> {
     pub cpi_authority_pda: UncheckedAccount<'info>,
 
     #[account(mut)]
-    pub token_pool_pda: Account<'info, TokenAccount>,
+    pub token_pool_pda: InterfaceAccount<'info, TokenAccount>,
 
     pub light_system_program: Program<'info, LightSystemProgram>,
 
@@ -51,9 +75,32 @@ pub struct MintTokens<'info> {
     )]
     pub mint: Box<InterfaceAccount<'info, Mint>>,
 
+    /// Supply cap and mint limits of `mint`
+    #[account(
+        mut,
+        seeds = [MINT_CONFIG.as_bytes(), mint.key().as_ref()],
+        bump = mint_config.bump
+    )]
+    pub mint_config: Box<Account<'info, MintConfig>>,
+
+    /// Period quota of `service_signer` for `mint`, created on its first mint
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = MintQuota::LEN,
+        seeds = [
+            MINT_QUOTA.as_bytes(),
+            mint.key().as_ref(),
+            service_signer.key().as_ref()
+        ],
+        bump
+    )]
+    pub mint_quota: Box<Account<'info, MintQuota>>,
+
     pub light_compressed_token: Program<'info, LightCompressedToken>,
 
-    pub token_program: Program<'info, Token>,
+    /// SPL Token or Token-2022, whichever owns `mint`
+    pub token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
     pub rent_program: Sysvar<'info, Rent>,
@@ -65,6 +112,32 @@ pub fn mint_tokens<'info>(
     amounts: Vec<u64>,
     lamports: Option<u64>,
 ) -> Result<()> {
+    require_service_signer(
+        &ctx.accounts.config,
+        &ctx.accounts.service_signer_entry,
+        &ctx.accounts.service_signer,
+        PERMISSION_MINT,
+    )?;
+
+    require!(
+        public_keys.len() == amounts.len(),
+        CustomError::InvalidParameter
+    );
+    let amount = amounts
+        .iter()
+        .try_fold(0u64, |sum, amount| sum.checked_add(*amount))
+        .ok_or(CustomError::MintSupplyCapExceeded)?;
+
+    let mint_quota = &mut ctx.accounts.mint_quota;
+    if mint_quota.mint == Pubkey::default() {
+        mint_quota.mint = ctx.accounts.mint.key();
+        mint_quota.service_signer = ctx.accounts.service_signer.key();
+        mint_quota.bump = ctx.bumps.mint_quota;
+    }
+    ctx.accounts
+        .mint_config
+        .record_mint(mint_quota, amount, Clock::get()?.unix_timestamp)?;
+
     let cpi_accounts = MintToInstruction {
         fee_payer: ctx.accounts.payer.to_account_info(),
         authority: ctx.accounts.authority.to_account_info(),
@@ -88,11 +161,18 @@ pub fn mint_tokens<'info>(
     let payer_key = ctx.accounts.payer.key();
 
     let payer_bump = [bump_seed_payer];
-    let authority_bump = [ctx.bumps.authority];
+    let server_authority = ctx.accounts.mint_config.server_authority;
+    let nonce_bytes = ctx.accounts.mint_config.nonce.to_be_bytes();
+    let authority_bump = [ctx.accounts.mint_config.authority_bump];
 
     let seeds = [
         &[payer_key.as_ref(), &payer_bump][..],
-        &[crate::state::MINT_AUTHORITY.as_bytes(), &authority_bump][..],
+        &[
+            crate::state::MINT_AUTHORITY.as_bytes(),
+            server_authority.as_ref(),
+            &nonce_bytes,
+            &authority_bump,
+        ][..],
     ];
 
     let cpi_context = CpiContext::new_with_signer(
@@ -101,5 +181,15 @@ pub fn mint_tokens<'info>(
         &seeds[..],
     );
 
-    light_compressed_token::cpi::mint_to(cpi_context, public_keys, amounts, lamports)
+    let event = TokensMinted {
+        mint: ctx.accounts.mint.key(),
+        recipients: public_keys.clone(),
+        amounts: amounts.clone(),
+    };
+
+    light_compressed_token::cpi::mint_to(cpi_context, public_keys, amounts, lamports)?;
+
+    emit!(event);
+
+    Ok(())
 }
@@ -4,11 +4,35 @@ pub mod create_compressed_mint;
 pub use compress_tokens::*;
 pub use create_compressed_mint::*;
 
+pub mod create_compressed_mint_2022;
+pub use create_compressed_mint_2022::*;
+
+pub mod update_compressed_mint_metadata;
+pub use update_compressed_mint_metadata::*;
+
 pub mod mint_to;
 pub use mint_to::*;
 
+pub mod mint_config;
+pub use mint_config::*;
+
+pub mod migrate_compressed_mint;
+pub use migrate_compressed_mint::*;
+
 pub mod transfer_compressed_tokens;
 pub use transfer_compressed_tokens::*;
 
 pub mod decompress_tokens;
 pub use decompress_tokens::*;
+
+pub mod transfer_vault_tokens;
+pub use transfer_vault_tokens::*;
+
+pub mod delegate_tokens;
+pub use delegate_tokens::*;
+
+pub mod freeze_tokens;
+pub use freeze_tokens::*;
+
+pub mod burn_tokens;
+pub use burn_tokens::*;
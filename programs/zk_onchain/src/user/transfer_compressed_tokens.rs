@@ -1,14 +1,15 @@
-use std::str::FromStr;
-
 use account_compression::program::AccountCompression;
 use anchor_lang::prelude::*;
 use anchor_spl::associated_token::AssociatedToken;
 use light_compressed_token::program::LightCompressedToken;
-use light_sdk::merkle_context::AddressMerkleContext;
 use light_system_program::cpi::accounts::InvokeCpiInstruction;
-use light_utils::{hash_to_bn254_field_size_be, hashv_to_bn254_field_size_be};
+use light_utils::hashv_to_bn254_field_size_be;
 
-use crate::state::USER_VAULT;
+use crate::state::{
+    CustomError, WalletTokensTransferred, USER_VAULT_AUTHORITY, USER_VAULT_TOKENS,
+    VAULT_CONFIG_SEED,
+};
+use crate::vaults::{config_authority, UserVaultAuthority, UserVaultStateV2, VaultConfigState};
 
 #[derive(Accounts)]
 pub struct TransferCompressedTokensWallet<'info> {
@@ -21,8 +22,22 @@ pub struct TransferCompressedTokensWallet<'info> {
 
     pub current_authority: Signer<'info>,
 
-    #[account(mut)]
-    pub user_vault: AccountInfo<'info>,
+    /// CHECK: Vault id the token owner and the authority record are derived from
+    pub vault_id: AccountInfo<'info>,
+
+    #[account(
+        seeds = [USER_VAULT_AUTHORITY.as_bytes(), vault_id.key().as_ref()],
+        bump = vault_authority.bump,
+        has_one = current_authority @ CustomError::InvalidAuthority
+    )]
+    pub vault_authority: Account<'info, UserVaultAuthority>,
+
+    /// CHECK: Token owner PDA of the vault id, owner of the sent tokens
+    #[account(
+        address = UserVaultStateV2::find_token_owner(&vault_id.key()).0
+            @ CustomError::InvalidAuthority
+    )]
+    pub token_owner: AccountInfo<'info>,
 
     pub self_program: Program<'info, crate::program::ZkOnchain>,
 
@@ -41,15 +56,24 @@ pub struct TransferCompressedTokensWallet<'info> {
     pub associated_token_program: Program<'info, AssociatedToken>,
 
     pub rent_program: Sysvar<'info, Rent>,
+
+    /// Stops the transfer while transfers are paused
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority::ID.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
 }
 
+/// Invokes the light system program with `transfer_inputs`, signed by the token owner PDA of
+/// the vault id once its current authority has signed.
 pub fn transfer_compressed_tokens_wallet<'info>(
     ctx: Context<TransferCompressedTokensWallet>,
     transfer_inputs: Vec<u8>,
 ) -> Result<()> {
     let cpi_accounts = InvokeCpiInstruction {
-        fee_payer: ctx.accounts.user_vault.to_account_info(),
-        authority: ctx.accounts.user_vault.to_account_info(),
+        fee_payer: ctx.accounts.payer.to_account_info(),
+        authority: ctx.accounts.token_owner.to_account_info(),
         registered_program_pda: ctx.accounts.registered_program_pda.to_account_info(),
         noop_program: ctx.accounts.noop_program.to_account_info(),
         account_compression_authority: ctx.accounts.account_compression_authority.to_account_info(),
@@ -61,11 +85,10 @@ pub fn transfer_compressed_tokens_wallet<'info>(
         cpi_context_account: None,
     };
 
-    let (user_vault, bump) = derive_user_vault_with_bump(ctx.accounts.current_authority.key());
-    let binding = ctx.accounts.current_authority.key();
-    msg!("{}", Pubkey::from(user_vault));
+    let vault_id = ctx.accounts.vault_id.key();
+    let (token_owner, bump) = UserVaultStateV2::find_token_owner(&vault_id);
     let bump_bytes = [bump];
-    let seeds = &[USER_VAULT.as_bytes(), binding.as_ref(), &bump_bytes];
+    let seeds = &[USER_VAULT_TOKENS.as_bytes(), vault_id.as_ref(), &bump_bytes];
     let signer_seeds = &[&seeds[..]];
 
     let cpi_context = CpiContext::new_with_signer(
@@ -74,28 +97,15 @@ pub fn transfer_compressed_tokens_wallet<'info>(
         signer_seeds,
     );
 
-    light_system_program::cpi::invoke_cpi(cpi_context, transfer_inputs)
-}
-
-pub fn derive_user_vault_with_bump(authority: Pubkey) -> ([u8; 32], u8) {
-    let address_merkle_tree_queue_pubkey =
-        Pubkey::from_str("aq1S9z4reTSQAdgWHGD2zDaS39sjGrAxbR31vxJ2F4F").unwrap();
-
-    let address_merkle_tree_pubkey =
-        Pubkey::from_str("amt1Ayt45jfbdw5YSo7iz6WZxUmnZsQTYXy82hVwyC2").unwrap();
-
-    let address_merkle_context = AddressMerkleContext {
-        address_merkle_tree_pubkey,
-        address_queue_pubkey: address_merkle_tree_queue_pubkey,
-    };
-    let address_seed =
-        derive_address_seed(&[USER_VAULT.as_bytes(), authority.as_ref()], &crate::ID);
+    light_system_program::cpi::invoke_cpi(cpi_context, transfer_inputs)?;
 
-    let merkle_tree_pubkey = address_merkle_context.address_merkle_tree_pubkey.to_bytes();
-    let final_input = [merkle_tree_pubkey, address_seed].concat();
-    let (final_address, bump) = hash_to_bn254_field_size_be(final_input.as_slice()).unwrap();
+    emit!(WalletTokensTransferred {
+        vault_id,
+        owner: token_owner,
+        authority: ctx.accounts.current_authority.key(),
+    });
 
-    (final_address, bump)
+    Ok(())
 }
 
 pub fn derive_address_seed(seeds: &[&[u8]], program_id: &Pubkey) -> [u8; 32] {
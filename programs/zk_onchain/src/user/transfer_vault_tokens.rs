@@ -0,0 +1,109 @@
+use account_compression::program::AccountCompression;
+use anchor_lang::prelude::*;
+use light_compressed_token::program::LightCompressedToken;
+use light_system_program::program::LightSystemProgram;
+
+use crate::compressed_transfers::{
+    impl_token_cpi_accounts, transfer_compressed_tokens_cpi, CompressedTokenInputs,
+};
+use crate::state::{
+    CustomError, UserVaultTokensTransferred, USER_VAULT_AUTHORITY, USER_VAULT_TOKENS,
+    VAULT_CONFIG_SEED,
+};
+use crate::vaults::{config_authority, UserVaultAuthority, UserVaultStateV2, VaultConfigState};
+
+#[derive(Accounts)]
+pub struct TransferUserVaultTokens<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// Authority of the user vault
+    pub current_authority: Signer<'info>,
+
+    /// CHECK: Vault id the token owner and the authority record are derived from
+    pub vault_id: AccountInfo<'info>,
+
+    #[account(
+        seeds = [USER_VAULT_AUTHORITY.as_bytes(), vault_id.key().as_ref()],
+        bump = vault_authority.bump,
+        has_one = current_authority @ CustomError::InvalidAuthority
+    )]
+    pub vault_authority: Account<'info, UserVaultAuthority>,
+
+    /// CHECK: Token owner PDA of the vault id, owner of the sent tokens
+    #[account(
+        address = UserVaultStateV2::find_token_owner(&vault_id.key()).0
+            @ CustomError::InvalidAuthority
+    )]
+    pub token_owner: AccountInfo<'info>,
+
+    /// CHECK: Checked in the compressed token program
+    pub compressed_token_cpi_authority_pda: AccountInfo<'info>,
+
+    pub compressed_token_program: Program<'info, LightCompressedToken>,
+
+    pub light_system_program: Program<'info, LightSystemProgram>,
+
+    /// CHECK: Checked in light-system-program.
+    pub registered_program_pda: AccountInfo<'info>,
+
+    /// CHECK: Checked in light-system-program.
+    pub noop_program: AccountInfo<'info>,
+
+    /// CHECK: Checked in light-system-program.
+    pub account_compression_authority: AccountInfo<'info>,
+
+    pub account_compression_program: Program<'info, AccountCompression>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Vault transfers pause with the other transfers
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority::ID.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
+}
+
+/// Sends `amount` of `mint` from the tokens of a user vault to `recipient`, signed by the
+/// token owner PDA of its vault id. The rest of the inputs stays with the vault as change.
+pub fn transfer_user_vault_tokens<'info>(
+    ctx: Context<'_, '_, '_, 'info, TransferUserVaultTokens<'info>>,
+    mint: Pubkey,
+    amount: u64,
+    recipient: Pubkey,
+    vault_inputs: CompressedTokenInputs,
+) -> Result<()> {
+    require!(amount > 0, CustomError::ZeroAmount);
+
+    let vault_id = ctx.accounts.vault_id.key();
+    let (token_owner, bump) = UserVaultStateV2::find_token_owner(&vault_id);
+    let bump_bytes = [bump];
+    let seeds: &[&[u8]] = &[USER_VAULT_TOKENS.as_bytes(), vault_id.as_ref(), &bump_bytes];
+
+    let accounts = ctx.accounts.token_cpi_accounts(
+        ctx.accounts.token_owner.to_account_info(),
+        ctx.remaining_accounts,
+    );
+    transfer_compressed_tokens_cpi(
+        &accounts,
+        &[seeds],
+        mint,
+        amount,
+        recipient,
+        token_owner,
+        vault_inputs,
+    )?;
+
+    emit!(UserVaultTokensTransferred {
+        vault_id,
+        owner: token_owner,
+        recipient,
+        mint,
+        amount,
+    });
+
+    Ok(())
+}
+
+impl_token_cpi_accounts!(TransferUserVaultTokens, payer);
@@ -0,0 +1,268 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{instruction::Instruction, program::invoke_signed};
+use anchor_lang::system_program::{transfer, Transfer};
+use anchor_spl::token_2022::spl_token_2022::{
+    self,
+    extension::{BaseStateWithExtensions, StateWithExtensions},
+};
+use anchor_spl::token_interface::{Mint, TokenInterface};
+use mpl_token_metadata::{
+    accounts::Metadata,
+    instructions::{UpdateMetadataAccountV2, UpdateMetadataAccountV2InstructionArgs},
+    types::DataV2,
+};
+use spl_token_metadata_interface::state::{Field, TokenMetadata};
+
+use crate::{
+    state::{
+        CompressedMintMetadataAuthorityTransferred, CompressedMintMetadataUpdated, CustomError,
+        MINT_AUTHORITY, MINT_CONFIG, VAULT_CONFIG_SEED,
+    },
+    user::MintConfig,
+    vaults::{config_authority, VaultConfigState},
+};
+
+#[derive(Accounts)]
+pub struct UpdateCompressedMintMetadata<'info> {
+    /// Server that created `mint`, pays the rent of a longer Token-2022 metadata
+    #[account(
+        mut,
+        address = mint_config.server_authority @ CustomError::InvalidAuthority
+    )]
+    pub server_authority: Signer<'info>,
+
+    #[account(
+        seeds = [MINT_CONFIG.as_bytes(), mint.key().as_ref()],
+        bump = mint_config.bump
+    )]
+    pub mint_config: Box<Account<'info, MintConfig>>,
+
+    /// CHECK: Mint authority of `mint`, update authority of its metadata until handed off
+    #[account(
+        seeds = [
+            MINT_AUTHORITY.as_bytes(),
+            mint_config.server_authority.as_ref(),
+            &mint_config.nonce.to_be_bytes()
+        ],
+        bump = mint_config.authority_bump
+    )]
+    pub authority: UncheckedAccount<'info>,
+
+    /// Holds the metadata itself when it is a Token-2022 mint
+    #[account(mut)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// CHECK: Metaplex metadata of `mint`, owned by the token metadata program. Token-2022
+    /// mints have none.
+    #[account(
+        mut,
+        seeds = [
+            b"metadata",
+            mpl_token_metadata::ID.as_ref(),
+            mint.key().as_ref()
+        ],
+        bump,
+        seeds::program = mpl_token_metadata::ID
+    )]
+    pub metadata_account: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Token metadata program, only for mints outside Token-2022
+    #[account(executable, address = mpl_token_metadata::ID)]
+    pub mpl_token_metadata: Option<AccountInfo<'info>>,
+
+    /// Owner of `mint`
+    #[account(address = *mint.to_account_info().owner @ CustomError::InvalidAuthority)]
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Stops metadata updates while mints are paused
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority::ID.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
+}
+
+/// Replaces the name, symbol and uri of a mint created with mutable metadata. Token-2022
+/// mints are updated through the token-metadata interface of the mint itself.
+pub fn update_compressed_mint_metadata(
+    ctx: Context<UpdateCompressedMintMetadata>,
+    name: String,
+    symbol: String,
+    uri: String,
+) -> Result<()> {
+    if ctx.accounts.is_token_2022() {
+        ctx.accounts.top_up_metadata_rent(&name, &symbol, &uri)?;
+
+        let token_program_id = ctx.accounts.token_program.key();
+        let mint = ctx.accounts.mint.key();
+        let authority = ctx.accounts.authority.key();
+        for (field, value) in [
+            (Field::Name, name.clone()),
+            (Field::Symbol, symbol.clone()),
+            (Field::Uri, uri.clone()),
+        ] {
+            ctx.accounts.invoke_as_authority(
+                &spl_token_metadata_interface::instruction::update_field(
+                    &token_program_id,
+                    &mint,
+                    &authority,
+                    field,
+                    value,
+                ),
+                ctx.accounts.mint.to_account_info(),
+            )?;
+        }
+    } else {
+        let args = UpdateMetadataAccountV2InstructionArgs {
+            data: Some(DataV2 {
+                name: name.clone(),
+                symbol: symbol.clone(),
+                uri: uri.clone(),
+                seller_fee_basis_points: 0,
+                creators: None,
+                collection: None,
+                uses: None,
+            }),
+            new_update_authority: None,
+            primary_sale_happened: None,
+            is_mutable: None,
+        };
+        ctx.accounts.update_metaplex_metadata(args)?;
+    }
+
+    emit!(CompressedMintMetadataUpdated {
+        mint: ctx.accounts.mint.key(),
+        name,
+        symbol,
+        uri,
+    });
+
+    Ok(())
+}
+
+/// Hands the metadata update authority to `new_update_authority`, usually the server owner.
+/// From then on the metadata is updated through the token metadata program, or Token-2022
+/// for its mints, directly.
+pub fn transfer_compressed_mint_metadata_authority(
+    ctx: Context<UpdateCompressedMintMetadata>,
+    new_update_authority: Pubkey,
+) -> Result<()> {
+    if ctx.accounts.is_token_2022() {
+        ctx.accounts.invoke_as_authority(
+            &spl_token_metadata_interface::instruction::update_authority(
+                &ctx.accounts.token_program.key(),
+                &ctx.accounts.mint.key(),
+                &ctx.accounts.authority.key(),
+                Some(new_update_authority).try_into()?,
+            ),
+            ctx.accounts.mint.to_account_info(),
+        )?;
+    } else {
+        let args = UpdateMetadataAccountV2InstructionArgs {
+            data: None,
+            new_update_authority: Some(new_update_authority),
+            primary_sale_happened: None,
+            is_mutable: None,
+        };
+        ctx.accounts.update_metaplex_metadata(args)?;
+    }
+
+    emit!(CompressedMintMetadataAuthorityTransferred {
+        mint: ctx.accounts.mint.key(),
+        new_update_authority,
+    });
+
+    Ok(())
+}
+
+impl<'info> UpdateCompressedMintMetadata<'info> {
+    fn is_token_2022(&self) -> bool {
+        self.token_program.key() == spl_token_2022::ID
+    }
+
+    fn update_metaplex_metadata(&self, args: UpdateMetadataAccountV2InstructionArgs) -> Result<()> {
+        let (Some(metadata_account), Some(_)) = (&self.metadata_account, &self.mpl_token_metadata)
+        else {
+            return err!(CustomError::MissingMetadataAccount);
+        };
+
+        // Immutable metadata, and metadata handed off to another update authority, would
+        // only fail deep in the token metadata program
+        let metadata = Metadata::safe_deserialize(&metadata_account.try_borrow_data()?)
+            .map_err(|_| CustomError::MissingMetadataAccount)?;
+        require!(
+            metadata.is_mutable && metadata.update_authority == self.authority.key(),
+            CustomError::MetadataNotUpdatable
+        );
+
+        let update_metadata_ix = UpdateMetadataAccountV2 {
+            metadata: metadata_account.key(),
+            update_authority: self.authority.key(),
+        }
+        .instruction(args);
+
+        self.invoke_as_authority(&update_metadata_ix, metadata_account.to_account_info())
+    }
+
+    /// Token-2022 reallocates the mint for a longer metadata but leaves the rent to the
+    /// caller, so the server pays the difference up front
+    fn top_up_metadata_rent(&self, name: &str, symbol: &str, uri: &str) -> Result<()> {
+        let mint_info = self.mint.to_account_info();
+        let (current_size, updated_size) = {
+            let data = mint_info.try_borrow_data()?;
+            let mint = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+            let metadata = mint.get_variable_len_extension::<TokenMetadata>()?;
+            let current_size = metadata.tlv_size_of()?;
+            let updated = TokenMetadata {
+                name: name.to_string(),
+                symbol: symbol.to_string(),
+                uri: uri.to_string(),
+                ..metadata
+            };
+            (current_size, updated.tlv_size_of()?)
+        };
+
+        if updated_size <= current_size {
+            return Ok(());
+        }
+        let rent = Rent::get()?.minimum_balance(mint_info.data_len() + updated_size - current_size);
+        let top_up = rent.saturating_sub(mint_info.lamports());
+        if top_up > 0 {
+            transfer(
+                CpiContext::new(
+                    self.system_program.to_account_info(),
+                    Transfer {
+                        from: self.server_authority.to_account_info(),
+                        to: mint_info,
+                    },
+                ),
+                top_up,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Invokes `instruction` signed by the mint authority PDA, which updates `account`
+    fn invoke_as_authority(
+        &self,
+        instruction: &Instruction,
+        account: AccountInfo<'info>,
+    ) -> Result<()> {
+        let nonce_bytes = self.mint_config.nonce.to_be_bytes();
+        invoke_signed(
+            instruction,
+            &[account, self.authority.to_account_info()],
+            &[&[
+                MINT_AUTHORITY.as_bytes(),
+                self.mint_config.server_authority.as_ref(),
+                &nonce_bytes,
+                &[self.mint_config.authority_bump],
+            ]],
+        )?;
+
+        Ok(())
+    }
+}
@@ -1,18 +1,38 @@
-use crate::{state::ESCROW_VAULT, ParamsInitializeAirdropVault, ParamsUpdateAirdropVaultAuthority};
+use crate::{
+    state::{AIRDROP_CLAIM, AIRDROP_TOKEN_AUTHORITY, AIRDROP_VAULT},
+    ParamsClaimAirdrop, ParamsClawbackAirdrop, ParamsInitializeAirdropVault,
+    ParamsUpdateAirdropVaultAuthority,
+};
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::keccak;
+use light_compressed_token::program::LightCompressedToken;
 use light_sdk::{
     compressed_account::LightAccount, light_account, light_accounts,
     merkle_context::PackedAddressMerkleContext,
 };
 
-use crate::{state::VAULT_CONFIG_SEED, VaultConfigState};
+use crate::compressed_transfers::impl_token_cpi_accounts;
+use crate::{config_authority, state::VAULT_CONFIG_SEED, ServiceSignerEntry, VaultConfigState};
 
 use super::VaultType;
 
+/// Prefixes keep a leaf from ever hashing like an inner node
+const AIRDROP_LEAF_PREFIX: u8 = 0;
+const AIRDROP_NODE_PREFIX: u8 = 1;
+
 #[derive(AnchorSerialize, AnchorDeserialize)]
 pub struct AirdropVaultParams {
     pub server_id: String,
     pub server_name: String,
+    pub mint: Pubkey,
+    /// Root of the `(recipient, amount)` leaves, see `airdrop_leaf`
+    pub merkle_root: [u8; 32],
+    /// Sum of all leaves, moved from the authority into the airdrop on creation
+    pub total_amount: u64,
+    /// Claims are refused from this time on and the rest can be clawed back
+    pub claim_deadline: i64,
+    /// Receives the unclaimed tokens, has to be the funding server authority
+    pub clawback_destination: Pubkey,
 }
 
 #[light_account]
@@ -20,14 +40,94 @@ pub struct AirdropVaultParams {
 pub struct AirdropVaultState {
     pub server_id: String,
     pub server_name: String,
+    /// Server authority that funded the airdrop, its addresses are derived from it
+    #[truncate]
+    pub creator: Pubkey,
+    /// May claw back the unclaimed tokens, see `ClawbackAirdrop`
     #[truncate]
     pub current_authority: Pubkey,
     pub vault_type: VaultType,
+    #[truncate]
+    pub airdrop_id: Pubkey,
+    #[truncate]
+    pub mint: Pubkey,
+    #[truncate]
+    pub merkle_root: [u8; 32],
+    pub total_amount: u64,
+    pub claimed_amount: u64,
+    pub claim_deadline: i64,
+    /// Server authority at creation time, receives the clawback. Stays the same when the
+    /// server vault or the airdrop changes authority.
+    #[truncate]
+    pub clawback_destination: Pubkey,
+    pub clawed_back_amount: u64,
     pub created_at: i64,
     pub modified_at: i64,
 }
 
+impl AirdropVaultState {
+    pub fn is_expired(&self, now: i64) -> bool {
+        now >= self.claim_deadline
+    }
+
+    /// Funded amount that was neither claimed nor clawed back
+    pub fn unclaimed_amount(&self) -> u64 {
+        self.total_amount - self.claimed_amount - self.clawed_back_amount
+    }
+
+    /// PDA owning the airdrop's compressed tokens, signs the claim transfers.
+    /// Scoped by the creator, so servers can't take each other's airdrop ids.
+    pub fn find_token_authority(creator: &Pubkey, airdrop_id: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[
+                AIRDROP_TOKEN_AUTHORITY.as_bytes(),
+                creator.as_ref(),
+                airdrop_id.as_ref(),
+            ],
+            &crate::ID,
+        )
+    }
+}
+
+/// Receipt of one claim. Its address is derived from the airdrop's token authority and
+/// the recipient, so a second claim fails on address uniqueness.
+#[light_account]
+#[derive(Clone, Debug, Default)]
+pub struct AirdropClaimReceipt {
+    #[truncate]
+    pub airdrop_id: Pubkey,
+    #[truncate]
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub claimed_at: i64,
+}
+
+pub fn airdrop_leaf(recipient: &Pubkey, amount: u64) -> [u8; 32] {
+    keccak::hashv(&[
+        &[AIRDROP_LEAF_PREFIX],
+        recipient.as_ref(),
+        &amount.to_le_bytes(),
+    ])
+    .to_bytes()
+}
+
+/// Pairs are hashed in sorted order, so proofs carry no left/right flags
+pub fn airdrop_node(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let (first, second) = if a <= b { (a, b) } else { (b, a) };
+
+    keccak::hashv(&[&[AIRDROP_NODE_PREFIX], first, second]).to_bytes()
+}
+
+pub fn verify_airdrop_proof(root: &[u8; 32], leaf: [u8; 32], proof: &[[u8; 32]]) -> bool {
+    let computed = proof
+        .iter()
+        .fold(leaf, |node, sibling| airdrop_node(&node, sibling));
+
+    computed == *root
+}
+
 #[light_accounts]
+#[instruction(params: AirdropVaultParams)]
 pub struct InitializeAirdropVault<'info> {
     #[account(mut)]
     #[fee_payer]
@@ -39,28 +139,41 @@ pub struct InitializeAirdropVault<'info> {
     /// CHECK: Checked in light-system-program.
     pub service_signer: Signer<'info>,
 
+    /// Server authority, funds the airdrop from its compressed tokens and becomes its creator
     pub current_authority: Signer<'info>,
 
+    /// CHECK: Stable id of the airdrop, only its key is used
+    pub airdrop_id: AccountInfo<'info>,
+
     #[authority]
     pub cpi_signer: AccountInfo<'info>,
 
     #[light_account(
         init,
         seeds = [
-            ESCROW_VAULT.as_bytes(),
-            current_authority.key().as_ref()
+            AIRDROP_VAULT.as_bytes(),
+            current_authority.key().as_ref(),
+            airdrop_id.key().as_ref()
         ],
     )]
-    pub server_vault: LightAccount<AirdropVaultState>,
+    pub airdrop_vault: LightAccount<AirdropVaultState>,
+
+    /// CHECK: Token authority of the creator and airdrop id, checked in the handler
+    pub airdrop_token_authority: AccountInfo<'info>,
 
     #[account(
-        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority.key().as_ref()],
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority::ID.as_ref()],
         bump
     )]
     pub config: AccountLoader<'info, VaultConfigState>,
 
-    /// CHECK: Config authority pubkey used for PDA derivation
-    pub config_authority: AccountInfo<'info>,
+    /// Registry entry of the service signer in `config`
+    pub service_signer_entry: Account<'info, ServiceSignerEntry>,
+
+    /// CHECK: Checked in the compressed token program
+    pub compressed_token_cpi_authority_pda: AccountInfo<'info>,
+
+    pub compressed_token_program: Program<'info, LightCompressedToken>,
 }
 
 #[light_accounts]
@@ -80,15 +193,229 @@ pub struct UpdateAirdropVaultAuthority<'info> {
 
     pub new_authority: Signer<'info>,
 
+    /// CHECK: Creator the address of `airdrop_vault` was derived from
+    pub airdrop_creator: AccountInfo<'info>,
+
+    /// CHECK: Airdrop id the address of `airdrop_vault` was derived from
+    pub airdrop_id: AccountInfo<'info>,
+
     #[authority]
     pub cpi_signer: AccountInfo<'info>,
 
+    /// The address does not depend on the authority, so the vault is updated in place
+    #[light_account(
+        mut,
+        seeds = [
+            AIRDROP_VAULT.as_bytes(),
+            airdrop_creator.key().as_ref(),
+            airdrop_id.key().as_ref()
+        ]
+    )]
+    pub airdrop_vault: LightAccount<AirdropVaultState>,
+
+    /// Authority updates pause with the airdrops
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority::ID.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
+
+    /// Registry entry of the service signer in `config`
+    pub service_signer_entry: Account<'info, ServiceSignerEntry>,
+}
+
+#[light_accounts]
+pub struct ClaimAirdrop<'info> {
+    /// Pays the claim, does not have to be the recipient
+    #[account(mut)]
+    #[fee_payer]
+    pub payer: Signer<'info>,
+
+    #[self_program]
+    pub self_program: Program<'info, crate::program::ZkOnchain>,
+
+    #[authority]
+    pub cpi_signer: AccountInfo<'info>,
+
+    /// CHECK: Recipient of the leaf, checked by the merkle proof
+    pub recipient: AccountInfo<'info>,
+
+    /// CHECK: Creator the address of `airdrop_vault` was derived from
+    pub airdrop_creator: AccountInfo<'info>,
+
+    /// CHECK: Airdrop id the address of `airdrop_vault` was derived from
+    pub airdrop_id: AccountInfo<'info>,
+
+    /// CHECK: Token authority of the vault's creator and airdrop id, checked in the handler
+    pub airdrop_token_authority: AccountInfo<'info>,
+
     #[light_account(
         mut,
         seeds = [
-            ESCROW_VAULT.as_bytes(),
-            current_authority.key().as_ref()
+            AIRDROP_VAULT.as_bytes(),
+            airdrop_creator.key().as_ref(),
+            airdrop_id.key().as_ref()
         ]
     )]
-    pub server_vault: LightAccount<AirdropVaultState>,
+    pub airdrop_vault: LightAccount<AirdropVaultState>,
+
+    #[light_account(
+        init,
+        seeds = [
+            AIRDROP_CLAIM.as_bytes(),
+            airdrop_token_authority.key().as_ref(),
+            recipient.key().as_ref()
+        ],
+    )]
+    pub claim_receipt: LightAccount<AirdropClaimReceipt>,
+
+    /// Claims pause with the airdrops
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority::ID.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
+
+    /// CHECK: Checked in the compressed token program
+    pub compressed_token_cpi_authority_pda: AccountInfo<'info>,
+
+    pub compressed_token_program: Program<'info, LightCompressedToken>,
+}
+
+#[light_accounts]
+pub struct ClawbackAirdrop<'info> {
+    #[account(mut)]
+    #[fee_payer]
+    pub payer: Signer<'info>,
+
+    #[self_program]
+    pub self_program: Program<'info, crate::program::ZkOnchain>,
+
+    /// Airdrop vault authority
+    pub current_authority: Signer<'info>,
+
+    /// CHECK: Creator the address of `airdrop_vault` was derived from
+    pub airdrop_creator: AccountInfo<'info>,
+
+    /// CHECK: Airdrop id the address of `airdrop_vault` was derived from
+    pub airdrop_id: AccountInfo<'info>,
+
+    #[authority]
+    pub cpi_signer: AccountInfo<'info>,
+
+    /// CHECK: Token authority of the vault's creator and airdrop id, checked in the handler
+    pub airdrop_token_authority: AccountInfo<'info>,
+
+    #[light_account(
+        mut,
+        seeds = [
+            AIRDROP_VAULT.as_bytes(),
+            airdrop_creator.key().as_ref(),
+            airdrop_id.key().as_ref()
+        ]
+    )]
+    pub airdrop_vault: LightAccount<AirdropVaultState>,
+
+    /// Clawbacks pause with the airdrops
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority::ID.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
+
+    /// CHECK: Checked in the compressed token program
+    pub compressed_token_cpi_authority_pda: AccountInfo<'info>,
+
+    pub compressed_token_program: Program<'info, LightCompressedToken>,
+}
+
+impl_token_cpi_accounts!(InitializeAirdropVault, payer);
+
+impl_token_cpi_accounts!(ClaimAirdrop, payer);
+
+impl_token_cpi_accounts!(ClawbackAirdrop, payer);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_leaf_is_its_own_root() {
+        let recipient = Pubkey::new_unique();
+        let leaf = airdrop_leaf(&recipient, 10);
+
+        assert!(verify_airdrop_proof(&leaf, leaf, &[]));
+        assert!(!verify_airdrop_proof(
+            &leaf,
+            airdrop_leaf(&recipient, 11),
+            &[]
+        ));
+    }
+
+    #[test]
+    fn proof_verifies_regardless_of_sibling_order() {
+        let a = airdrop_leaf(&Pubkey::new_unique(), 1);
+        let b = airdrop_leaf(&Pubkey::new_unique(), 2);
+        let c = airdrop_leaf(&Pubkey::new_unique(), 3);
+        let root = airdrop_node(&airdrop_node(&a, &b), &c);
+
+        assert_eq!(airdrop_node(&a, &b), airdrop_node(&b, &a));
+        assert!(verify_airdrop_proof(&root, a, &[b, c]));
+        assert!(verify_airdrop_proof(&root, b, &[a, c]));
+        assert!(verify_airdrop_proof(&root, c, &[airdrop_node(&a, &b)]));
+    }
+
+    #[test]
+    fn wrong_amount_recipient_or_proof_is_rejected() {
+        let recipient = Pubkey::new_unique();
+        let other = airdrop_leaf(&Pubkey::new_unique(), 5);
+        let root = airdrop_node(&airdrop_leaf(&recipient, 5), &other);
+
+        assert!(verify_airdrop_proof(
+            &root,
+            airdrop_leaf(&recipient, 5),
+            &[other]
+        ));
+        assert!(!verify_airdrop_proof(
+            &root,
+            airdrop_leaf(&recipient, 6),
+            &[other]
+        ));
+        assert!(!verify_airdrop_proof(
+            &root,
+            airdrop_leaf(&Pubkey::new_unique(), 5),
+            &[other]
+        ));
+        assert!(!verify_airdrop_proof(
+            &root,
+            airdrop_leaf(&recipient, 5),
+            &[]
+        ));
+    }
+
+    #[test]
+    fn token_authority_is_scoped_by_creator() {
+        let airdrop_id = Pubkey::new_unique();
+        let (first, _) =
+            AirdropVaultState::find_token_authority(&Pubkey::new_unique(), &airdrop_id);
+        let (second, _) =
+            AirdropVaultState::find_token_authority(&Pubkey::new_unique(), &airdrop_id);
+
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn unclaimed_amount_excludes_claims_and_clawback() {
+        let airdrop_vault = AirdropVaultState {
+            total_amount: 100,
+            claimed_amount: 30,
+            clawed_back_amount: 20,
+            claim_deadline: 50,
+            ..Default::default()
+        };
+
+        assert_eq!(airdrop_vault.unclaimed_amount(), 50);
+        assert!(!airdrop_vault.is_expired(49));
+        assert!(airdrop_vault.is_expired(50));
+    }
 }
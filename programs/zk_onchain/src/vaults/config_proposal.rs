@@ -0,0 +1,401 @@
+use crate::state::*;
+use anchor_lang::prelude::*;
+
+use super::{ServiceSignerOperation, VaultConfigState, MAX_CONFIG_ADMINS};
+
+/// Config administration queued for M-of-N admin approval
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub enum ConfigAction {
+    ManageServiceSigner {
+        service_signer: Pubkey,
+        operation: ServiceSignerOperation,
+    },
+    ProposeVaultAuthority {
+        new_authority: Pubkey,
+    },
+    CancelVaultAuthorityTransfer,
+    UpdateAuthorityTransferDelay {
+        delay: i64,
+    },
+    SetConfigAdmins {
+        admins: Vec<Pubkey>,
+        threshold: u8,
+    },
+    SetGuardian {
+        guardian: Pubkey,
+    },
+}
+
+impl ConfigAction {
+    // Largest variant: SetConfigAdmins with a full admin set
+    pub const MAX_LEN: usize = 1 + // variant
+        4 + (32 * MAX_CONFIG_ADMINS) + // admins
+        1; // threshold
+}
+
+#[account]
+pub struct ConfigProposal {
+    pub config: Pubkey,
+    pub proposal_id: u64,
+    pub proposer: Pubkey,
+    pub action: ConfigAction,
+    pub approvers: Vec<Pubkey>,
+    pub executed: bool,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+impl ConfigProposal {
+    pub const LEN: usize = 8 +  // discriminator
+        32 +         // config
+        8 +          // proposal_id
+        32 +         // proposer
+        ConfigAction::MAX_LEN + // action
+        4 + (32 * MAX_CONFIG_ADMINS) + // approvers
+        1 +          // executed
+        8 +          // created_at
+        1; // bump
+
+    /// Approvals only count while the approver is still an admin, so a reduced admin set
+    /// cannot execute with approvals of removed admins
+    pub fn is_approved(&self, config: &VaultConfigState) -> bool {
+        config.count_admin_approvals(&self.approvers) >= config.approval_threshold as usize
+    }
+
+    /// Approved proposals are executed by a current admin or by their proposer
+    pub fn can_be_executed_by(&self, config: &VaultConfigState, executor: &Pubkey) -> bool {
+        config.is_admin(executor) || self.proposer == *executor
+    }
+}
+
+#[derive(Accounts)]
+pub struct CreateConfigProposal<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config.load()?.created_by.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
+
+    #[account(
+        init,
+        payer = proposer,
+        space = ConfigProposal::LEN,
+        seeds = [
+            CONFIG_PROPOSAL_SEED.as_bytes(),
+            config.key().as_ref(),
+            &config.load()?.proposal_count.to_le_bytes()
+        ],
+        bump
+    )]
+    pub proposal: Account<'info, ConfigProposal>,
+
+    #[account(
+        mut,
+        constraint = config.load()?.is_admin(&proposer.key()) @ CustomError::NotConfigAdmin
+    )]
+    pub proposer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn create_config_proposal(
+    ctx: Context<CreateConfigProposal>,
+    action: ConfigAction,
+) -> Result<()> {
+    let config = &mut ctx.accounts.config.load_mut()?;
+    let clock = Clock::get()?;
+    let proposer = ctx.accounts.proposer.key();
+
+    if let ConfigAction::SetConfigAdmins { admins, threshold } = &action {
+        validate_config_admins(admins, *threshold)?;
+    }
+
+    let proposal = &mut ctx.accounts.proposal;
+    proposal.config = ctx.accounts.config.key();
+    proposal.proposal_id = config.proposal_count;
+    proposal.proposer = proposer;
+    proposal.action = action;
+    // Creating a proposal counts as the proposer's approval
+    proposal.approvers = vec![proposer];
+    proposal.executed = false;
+    proposal.created_at = clock.unix_timestamp;
+    proposal.bump = ctx.bumps.proposal;
+
+    config.proposal_count += 1;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ApproveConfigProposal<'info> {
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config.load()?.created_by.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
+
+    #[account(
+        mut,
+        seeds = [
+            CONFIG_PROPOSAL_SEED.as_bytes(),
+            config.key().as_ref(),
+            &proposal.proposal_id.to_le_bytes()
+        ],
+        bump = proposal.bump,
+        constraint = (proposal.config == config.key()) @ CustomError::InvalidProposal
+    )]
+    pub proposal: Account<'info, ConfigProposal>,
+
+    #[account(
+        constraint = config.load()?.is_admin(&admin.key()) @ CustomError::NotConfigAdmin
+    )]
+    pub admin: Signer<'info>,
+}
+
+pub fn approve_config_proposal(ctx: Context<ApproveConfigProposal>) -> Result<()> {
+    let proposal = &mut ctx.accounts.proposal;
+    let admin = ctx.accounts.admin.key();
+
+    require!(!proposal.executed, CustomError::ProposalAlreadyExecuted);
+    require!(
+        !proposal.approvers.contains(&admin),
+        CustomError::ProposalAlreadyApproved
+    );
+    require!(
+        proposal.approvers.len() < MAX_CONFIG_ADMINS,
+        CustomError::TooManyConfigAdmins
+    );
+
+    proposal.approvers.push(admin);
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CloseConfigProposal<'info> {
+    #[account(
+        mut,
+        close = proposer,
+        has_one = proposer @ CustomError::InvalidAuthority
+    )]
+    pub proposal: Account<'info, ConfigProposal>,
+
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+}
+
+/// Closing an unexecuted proposal cancels it, closing an executed one reclaims its rent
+pub fn close_config_proposal(_ctx: Context<CloseConfigProposal>) -> Result<()> {
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetConfigAdmins<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config.load()?.created_by.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
+
+    pub authority: Signer<'info>,
+
+    /// Approved proposal, required once multisig administration is enabled
+    #[account(mut)]
+    pub proposal: Option<Account<'info, ConfigProposal>>,
+}
+
+/// Replaces the admin set. An empty set turns multisig administration off again.
+pub fn set_config_admins(
+    ctx: Context<SetConfigAdmins>,
+    admins: Vec<Pubkey>,
+    threshold: u8,
+) -> Result<()> {
+    let config_key = ctx.accounts.config.key();
+    let config = &mut ctx.accounts.config.load_mut()?;
+    let clock = Clock::get()?;
+
+    validate_config_admins(&admins, threshold)?;
+
+    authorize_config_action(
+        config,
+        config_key,
+        &ctx.accounts.authority,
+        ctx.accounts.proposal.as_mut(),
+        &ConfigAction::SetConfigAdmins {
+            admins: admins.clone(),
+            threshold,
+        },
+    )?;
+
+    config.admins = [Pubkey::default(); MAX_CONFIG_ADMINS];
+    for (i, admin) in admins.iter().enumerate() {
+        config.admins[i] = *admin;
+    }
+    config.admins_count = admins.len() as u8;
+    config.approval_threshold = threshold;
+
+    // Update modification metadata
+    config.modified_at = clock.unix_timestamp;
+    config.modified_by = ctx.accounts.authority.key();
+
+    emit!(VaultConfigUpdated {
+        config: config_key,
+        updated_by: ctx.accounts.authority.key(),
+        change: VaultConfigChange::AdminsUpdated { admins, threshold },
+        timestamp: clock.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+fn validate_config_admins(admins: &[Pubkey], threshold: u8) -> Result<()> {
+    require!(
+        admins.len() <= MAX_CONFIG_ADMINS,
+        CustomError::TooManyConfigAdmins
+    );
+
+    for (i, admin) in admins.iter().enumerate() {
+        require!(*admin != Pubkey::default(), CustomError::InvalidParameter);
+        require!(
+            !admins[..i].contains(admin),
+            CustomError::DuplicateConfigAdmin
+        );
+    }
+
+    if admins.is_empty() {
+        require!(threshold == 0, CustomError::InvalidApprovalThreshold);
+    } else {
+        require!(
+            threshold > 0 && threshold as usize <= admins.len(),
+            CustomError::InvalidApprovalThreshold
+        );
+    }
+
+    Ok(())
+}
+
+/// Authorizes a config admin action.
+///
+/// Without admins the `current_update_authority` signs directly. Once admins are set,
+/// the action must match a proposal that reached the approval threshold, and it is
+/// executed by an admin or by the proposer. The proposal is marked executed so it
+/// cannot be replayed.
+pub fn authorize_config_action(
+    config: &VaultConfigState,
+    config_key: Pubkey,
+    authority: &Signer,
+    proposal: Option<&mut Account<ConfigProposal>>,
+    action: &ConfigAction,
+) -> Result<()> {
+    if !config.is_multisig_enabled() {
+        require!(
+            authority.key() == config.current_update_authority,
+            CustomError::InvalidAuthority
+        );
+        return Ok(());
+    }
+
+    let proposal = proposal.ok_or(CustomError::ProposalRequired)?;
+
+    require!(proposal.config == config_key, CustomError::InvalidProposal);
+    require!(!proposal.executed, CustomError::ProposalAlreadyExecuted);
+    require!(proposal.action == *action, CustomError::InvalidProposal);
+    require!(
+        proposal.is_approved(config),
+        CustomError::ProposalNotApproved
+    );
+    require!(
+        proposal.can_be_executed_by(config, &authority.key()),
+        CustomError::InvalidAuthority
+    );
+
+    proposal.executed = true;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(admins: &[Pubkey], threshold: u8) -> VaultConfigState {
+        let mut config = VaultConfigState::default();
+        for (i, admin) in admins.iter().enumerate() {
+            config.admins[i] = *admin;
+        }
+        config.admins_count = admins.len() as u8;
+        config.approval_threshold = threshold;
+        config
+    }
+
+    fn proposal(proposer: Pubkey, approvers: Vec<Pubkey>) -> ConfigProposal {
+        ConfigProposal {
+            config: Pubkey::new_unique(),
+            proposal_id: 0,
+            proposer,
+            action: ConfigAction::CancelVaultAuthorityTransfer,
+            approvers,
+            executed: false,
+            created_at: 0,
+            bump: 255,
+        }
+    }
+
+    #[test]
+    fn threshold_needs_enough_admin_approvals() {
+        let admins = [
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        ];
+        let config = config(&admins, 2);
+
+        assert!(!proposal(admins[0], vec![admins[0]]).is_approved(&config));
+        assert!(proposal(admins[0], vec![admins[0], admins[2]]).is_approved(&config));
+    }
+
+    #[test]
+    fn approvals_of_removed_admins_do_not_count() {
+        let admins = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let removed = Pubkey::new_unique();
+        let config = config(&admins, 2);
+        let proposal = proposal(admins[0], vec![admins[0], removed]);
+
+        assert_eq!(config.count_admin_approvals(&proposal.approvers), 1);
+        assert!(!proposal.is_approved(&config));
+    }
+
+    #[test]
+    fn admins_and_the_proposer_execute() {
+        let admins = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let former_admin = Pubkey::new_unique();
+        let config = config(&admins, 1);
+        let proposal = proposal(former_admin, vec![admins[0]]);
+
+        assert!(proposal.can_be_executed_by(&config, &admins[1]));
+        assert!(proposal.can_be_executed_by(&config, &former_admin));
+        assert!(!proposal.can_be_executed_by(&config, &Pubkey::new_unique()));
+    }
+
+    #[test]
+    fn admin_sets_need_a_reachable_threshold() {
+        let admins = vec![Pubkey::new_unique(), Pubkey::new_unique()];
+
+        assert!(validate_config_admins(&admins, 2).is_ok());
+        assert!(validate_config_admins(&[], 0).is_ok());
+        assert_eq!(
+            validate_config_admins(&admins, 3).unwrap_err(),
+            CustomError::InvalidApprovalThreshold.into()
+        );
+        assert_eq!(
+            validate_config_admins(&admins, 0).unwrap_err(),
+            CustomError::InvalidApprovalThreshold.into()
+        );
+        assert_eq!(
+            validate_config_admins(&[admins[0], admins[0]], 1).unwrap_err(),
+            CustomError::DuplicateConfigAdmin.into()
+        );
+    }
+}
@@ -0,0 +1,133 @@
+use crate::state::*;
+use anchor_lang::prelude::*;
+
+use super::{
+    authorize_config_action, config_authority, ConfigAction, ConfigProposal, VaultConfigState,
+};
+
+// Subsystem pause bits, `paused` halts every subsystem at once
+pub const PAUSE_VAULTS: u8 = 1 << 0;
+pub const PAUSE_MINTS: u8 = 1 << 1;
+pub const PAUSE_TRANSFERS: u8 = 1 << 2;
+pub const PAUSE_ESCROW: u8 = 1 << 3;
+pub const PAUSE_AIRDROP: u8 = 1 << 4;
+pub const PAUSE_VESTING: u8 = 1 << 5;
+pub const PAUSE_ALL_SUBSYSTEMS: u8 =
+    PAUSE_VAULTS | PAUSE_MINTS | PAUSE_TRANSFERS | PAUSE_ESCROW | PAUSE_AIRDROP | PAUSE_VESTING;
+
+#[derive(Accounts)]
+pub struct SetPauseState<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config.load()?.created_by.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
+
+    #[account(
+        constraint = (guardian.key() == config.load()?.guardian) @ CustomError::NotGuardian
+    )]
+    pub guardian: Signer<'info>,
+}
+
+/// Sets the global pause flag and the per-subsystem pause bits.
+pub fn set_pause_state(ctx: Context<SetPauseState>, paused: bool, pause_flags: u8) -> Result<()> {
+    let config_key = ctx.accounts.config.key();
+    let config = &mut ctx.accounts.config.load_mut()?;
+    let clock = Clock::get()?;
+
+    require!(
+        pause_flags & !PAUSE_ALL_SUBSYSTEMS == 0,
+        CustomError::InvalidParameter
+    );
+
+    config.paused = paused as u8;
+    config.pause_flags = pause_flags;
+
+    // Update modification metadata
+    config.modified_at = clock.unix_timestamp;
+    config.modified_by = ctx.accounts.guardian.key();
+
+    emit!(VaultConfigUpdated {
+        config: config_key,
+        updated_by: ctx.accounts.guardian.key(),
+        change: VaultConfigChange::PauseStateUpdated {
+            paused,
+            pause_flags,
+        },
+        timestamp: clock.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetGuardian<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config.load()?.created_by.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
+
+    pub authority: Signer<'info>,
+
+    /// Approved proposal, required once multisig administration is enabled
+    #[account(mut)]
+    pub proposal: Option<Account<'info, ConfigProposal>>,
+}
+
+/// Replaces the guardian. The default pubkey leaves the config without a guardian.
+pub fn set_guardian(ctx: Context<SetGuardian>, guardian: Pubkey) -> Result<()> {
+    let config_key = ctx.accounts.config.key();
+    let config = &mut ctx.accounts.config.load_mut()?;
+    let clock = Clock::get()?;
+
+    authorize_config_action(
+        config,
+        config_key,
+        &ctx.accounts.authority,
+        ctx.accounts.proposal.as_mut(),
+        &ConfigAction::SetGuardian { guardian },
+    )?;
+
+    require!(
+        guardian != config.current_update_authority,
+        CustomError::InvalidParameter
+    );
+
+    config.guardian = guardian;
+
+    // Update modification metadata
+    config.modified_at = clock.unix_timestamp;
+    config.modified_by = ctx.accounts.authority.key();
+
+    emit!(VaultConfigUpdated {
+        config: config_key,
+        updated_by: ctx.accounts.authority.key(),
+        change: VaultConfigChange::GuardianUpdated { guardian },
+        timestamp: clock.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+/// Rejects the instruction while the program or `subsystem` is paused, a zero `subsystem`
+/// checks the global pause alone. Every pausable instruction takes the vault config seeded
+/// with the config authority as `config` and calls this first. Only the canonical config,
+/// created by the config authority, is trusted for this check.
+pub fn require_not_paused(
+    config: &AccountLoader<'_, VaultConfigState>,
+    subsystem: u8,
+) -> Result<()> {
+    let config = config.load()?;
+
+    require_keys_eq!(
+        config.created_by,
+        config_authority::ID,
+        CustomError::InvalidConfig
+    );
+    require!(!config.is_paused(subsystem), CustomError::ProgramPaused);
+
+    Ok(())
+}
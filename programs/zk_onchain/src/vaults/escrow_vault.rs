@@ -1,94 +1,1096 @@
-use crate::{state::ESCROW_VAULT, ParamsInitializeEscrowVault, ParamsUpdateEscrowVaultAuthority};
+use crate::state::*;
+use account_compression::program::AccountCompression;
 use anchor_lang::prelude::*;
-use light_sdk::{
-    compressed_account::LightAccount, light_account, light_accounts,
-    merkle_context::PackedAddressMerkleContext,
-};
+use light_compressed_token::program::LightCompressedToken;
+use light_system_program::program::LightSystemProgram;
 
-use crate::{state::VAULT_CONFIG_SEED, VaultConfigState};
+use crate::compressed_transfers::{
+    impl_token_cpi_accounts, transfer_compressed_tokens_cpi, CompressedTokenInputs,
+};
+use crate::{config_authority, ServiceSignerEntry, VaultConfigState, PERMISSION_MANAGE_ESCROW};
 
-use super::VaultType;
+pub const MAX_ESCROW_SERVER_ID_LEN: usize = 32;
+pub const MAX_ESCROW_SERVER_NAME_LEN: usize = 64;
 
 #[derive(AnchorSerialize, AnchorDeserialize)]
 pub struct EscrowVaultParams {
     pub server_id: String,
     pub server_name: String,
+    /// Lets one maker keep several offers open
+    pub offer_id: u64,
+    pub mint_offered: Pubkey,
+    pub amount_offered: u64,
+    pub mint_wanted: Pubkey,
+    /// Price as `price_numerator` of `mint_wanted` per `price_denominator` of `mint_offered`
+    pub price_numerator: u64,
+    pub price_denominator: u64,
+    /// Smallest fill accepted, except for the last piece of the offer
+    pub min_fill_amount: u64,
+    pub expires_at: i64,
 }
 
-#[light_account]
-#[derive(Clone, Debug, Default)]
+/// An open trade offer. The offer address also owns the escrowed compressed tokens,
+/// so only this program can release them.
+#[account]
 pub struct EscrowVaultState {
     pub server_id: String,
     pub server_name: String,
-    #[truncate]
+    /// Maker of the offer
     pub current_authority: Pubkey,
-    pub vault_type: VaultType,
+    pub offer_id: u64,
+    pub mint_offered: Pubkey,
+    /// Amount locked when the offer was opened
+    pub amount_offered: u64,
+    /// Amount still locked and available to takers
+    pub remaining_offered: u64,
+    pub mint_wanted: Pubkey,
+    pub price_numerator: u64,
+    pub price_denominator: u64,
+    pub min_fill_amount: u64,
+    pub expires_at: i64,
     pub created_at: i64,
     pub modified_at: i64,
+    pub bump: u8,
+}
+
+impl EscrowVaultState {
+    pub const LEN: usize = 8 +  // discriminator
+        4 + MAX_ESCROW_SERVER_ID_LEN +   // server_id
+        4 + MAX_ESCROW_SERVER_NAME_LEN + // server_name
+        32 +         // current_authority
+        8 +          // offer_id
+        32 +         // mint_offered
+        8 +          // amount_offered
+        8 +          // remaining_offered
+        32 +         // mint_wanted
+        8 +          // price_numerator
+        8 +          // price_denominator
+        8 +          // min_fill_amount
+        8 +          // expires_at
+        8 +          // created_at
+        8 +          // modified_at
+        1; // bump
+
+    pub fn find_address(maker: &Pubkey, offer_id: u64) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[
+                ESCROW_VAULT.as_bytes(),
+                maker.as_ref(),
+                &offer_id.to_le_bytes(),
+            ],
+            &crate::ID,
+        )
+    }
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        now >= self.expires_at
+    }
+
+    /// Amount of `mint_wanted` a taker pays for `fill_amount`, rounded up in the maker's favour
+    pub fn fill_price(&self, fill_amount: u64) -> Result<u64> {
+        let price = (fill_amount as u128)
+            .checked_mul(self.price_numerator as u128)
+            .and_then(|value| value.checked_add(self.price_denominator as u128 - 1))
+            .map(|value| value / self.price_denominator as u128)
+            .ok_or(CustomError::InvalidEscrowFill)?;
+
+        u64::try_from(price).map_err(|_| CustomError::InvalidEscrowFill.into())
+    }
+
+    /// Checks a fill against the remaining amount and the minimum fill size.
+    /// The last piece of an offer may be smaller than the minimum.
+    pub fn validate_fill(&self, fill_amount: u64) -> Result<()> {
+        require!(
+            fill_amount > 0
+                && fill_amount <= self.remaining_offered
+                && (fill_amount >= self.min_fill_amount || fill_amount == self.remaining_offered),
+            CustomError::InvalidEscrowFill
+        );
+
+        Ok(())
+    }
 }
 
-#[light_accounts]
+fn validate_price(price_numerator: u64, price_denominator: u64) -> Result<()> {
+    require!(
+        price_numerator > 0 && price_denominator > 0,
+        CustomError::InvalidEscrowOffer
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(params: EscrowVaultParams)]
 pub struct InitializeEscrowVault<'info> {
     #[account(mut)]
-    #[fee_payer]
     pub payer: Signer<'info>,
 
-    #[self_program]
-    pub self_program: Program<'info, crate::program::ZkOnchain>,
-
     /// CHECK: Checked in light-system-program.
     pub service_signer: Signer<'info>,
 
+    /// Maker, owner of the offered compressed tokens
     pub current_authority: Signer<'info>,
 
-    #[authority]
-    pub cpi_signer: AccountInfo<'info>,
-
-    #[light_account(
+    #[account(
         init,
+        payer = payer,
+        space = EscrowVaultState::LEN,
+        seeds = [
+            ESCROW_VAULT.as_bytes(),
+            current_authority.key().as_ref(),
+            &params.offer_id.to_le_bytes()
+        ],
+        bump
+    )]
+    pub escrow_vault: Account<'info, EscrowVaultState>,
+
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority::ID.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
+
+    /// Registry entry of the service signer in `config`
+    pub service_signer_entry: Account<'info, ServiceSignerEntry>,
+
+    /// CHECK: Checked in the compressed token program
+    pub compressed_token_cpi_authority_pda: AccountInfo<'info>,
+
+    pub compressed_token_program: Program<'info, LightCompressedToken>,
+
+    pub light_system_program: Program<'info, LightSystemProgram>,
+
+    /// CHECK: Checked in light-system-program.
+    pub registered_program_pda: AccountInfo<'info>,
+
+    /// CHECK: Checked in light-system-program.
+    pub noop_program: AccountInfo<'info>,
+
+    /// CHECK: Checked in light-system-program.
+    pub account_compression_authority: AccountInfo<'info>,
+
+    pub account_compression_program: Program<'info, AccountCompression>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Opens an offer and locks `amount_offered` of the maker's compressed tokens in it.
+pub fn initialize_escrow_vault<'info>(
+    ctx: Context<'_, '_, '_, 'info, InitializeEscrowVault<'info>>,
+    params: EscrowVaultParams,
+    maker_inputs: CompressedTokenInputs,
+) -> Result<()> {
+    let clock = Clock::get()?;
+
+    require!(
+        !params.server_id.is_empty() && params.server_id.len() <= MAX_ESCROW_SERVER_ID_LEN,
+        CustomError::InvalidServerId
+    );
+    require!(
+        !params.server_name.is_empty() && params.server_name.len() <= MAX_ESCROW_SERVER_NAME_LEN,
+        CustomError::InvalidServerName
+    );
+    require!(
+        params.amount_offered > 0
+            && params.min_fill_amount <= params.amount_offered
+            && params.mint_offered != params.mint_wanted
+            && params.expires_at > clock.unix_timestamp,
+        CustomError::InvalidEscrowOffer
+    );
+    validate_price(params.price_numerator, params.price_denominator)?;
+
+    let escrow_key = ctx.accounts.escrow_vault.key();
+    let maker = ctx.accounts.current_authority.key();
+
+    let escrow_vault = &mut ctx.accounts.escrow_vault;
+    escrow_vault.server_id = params.server_id;
+    escrow_vault.server_name = params.server_name;
+    escrow_vault.current_authority = maker;
+    escrow_vault.offer_id = params.offer_id;
+    escrow_vault.mint_offered = params.mint_offered;
+    escrow_vault.amount_offered = params.amount_offered;
+    escrow_vault.remaining_offered = params.amount_offered;
+    escrow_vault.mint_wanted = params.mint_wanted;
+    escrow_vault.price_numerator = params.price_numerator;
+    escrow_vault.price_denominator = params.price_denominator;
+    escrow_vault.min_fill_amount = params.min_fill_amount;
+    escrow_vault.expires_at = params.expires_at;
+    escrow_vault.created_at = clock.unix_timestamp;
+    escrow_vault.modified_at = clock.unix_timestamp;
+    escrow_vault.bump = ctx.bumps.escrow_vault;
+
+    // Lock the offered tokens under the offer address
+    let accounts = ctx.accounts.token_cpi_accounts(
+        ctx.accounts.current_authority.to_account_info(),
+        ctx.remaining_accounts,
+    );
+    transfer_compressed_tokens_cpi(
+        &accounts,
+        &[],
+        params.mint_offered,
+        params.amount_offered,
+        escrow_key,
+        maker,
+        maker_inputs,
+    )?;
+
+    emit!(EscrowOfferCreated {
+        escrow: escrow_key,
+        maker,
+        mint_offered: params.mint_offered,
+        amount_offered: params.amount_offered,
+        mint_wanted: params.mint_wanted,
+        price_numerator: params.price_numerator,
+        price_denominator: params.price_denominator,
+        min_fill_amount: params.min_fill_amount,
+        expires_at: params.expires_at,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct UpdateEscrowVault<'info> {
+    /// Maker of the offer
+    pub current_authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = current_authority @ CustomError::InvalidAuthority,
         seeds = [
             ESCROW_VAULT.as_bytes(),
-            current_authority.key().as_ref()
+            escrow_vault.current_authority.as_ref(),
+            &escrow_vault.offer_id.to_le_bytes()
         ],
+        bump = escrow_vault.bump
     )]
-    pub server_vault: LightAccount<EscrowVaultState>,
+    pub escrow_vault: Account<'info, EscrowVaultState>,
 
+    /// Offer updates pause with the escrow
     #[account(
-        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority.key().as_ref()],
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority::ID.as_ref()],
         bump
     )]
     pub config: AccountLoader<'info, VaultConfigState>,
+}
+
+/// Reprices an open offer or moves its deadline. The escrowed tokens stay locked.
+pub fn update_escrow_vault(
+    ctx: Context<UpdateEscrowVault>,
+    price_numerator: u64,
+    price_denominator: u64,
+    expires_at: i64,
+) -> Result<()> {
+    let clock = Clock::get()?;
+    let escrow_vault = &mut ctx.accounts.escrow_vault;
 
-    /// CHECK: Config authority pubkey used for PDA derivation
-    pub config_authority: AccountInfo<'info>,
+    require!(
+        !escrow_vault.is_expired(clock.unix_timestamp),
+        CustomError::EscrowOfferExpired
+    );
+    require!(
+        expires_at > clock.unix_timestamp,
+        CustomError::InvalidEscrowOffer
+    );
+    validate_price(price_numerator, price_denominator)?;
+
+    escrow_vault.price_numerator = price_numerator;
+    escrow_vault.price_denominator = price_denominator;
+    escrow_vault.expires_at = expires_at;
+    escrow_vault.modified_at = clock.unix_timestamp;
+
+    emit!(EscrowOfferUpdated {
+        escrow: escrow_vault.key(),
+        price_numerator,
+        price_denominator,
+        expires_at,
+    });
+
+    Ok(())
 }
 
-#[light_accounts]
-pub struct UpdateEscrowVaultAuthority<'info> {
+#[derive(Accounts)]
+pub struct AcceptEscrowOffer<'info> {
     #[account(mut)]
-    #[fee_payer]
     pub payer: Signer<'info>,
 
-    #[self_program]
-    pub self_program: Program<'info, crate::program::ZkOnchain>,
+    /// Taker, owner of the wanted compressed tokens
+    pub taker: Signer<'info>,
+
+    /// Closed once the last piece is filled
+    #[account(
+        mut,
+        seeds = [
+            ESCROW_VAULT.as_bytes(),
+            escrow_vault.current_authority.as_ref(),
+            &escrow_vault.offer_id.to_le_bytes()
+        ],
+        bump = escrow_vault.bump
+    )]
+    pub escrow_vault: Account<'info, EscrowVaultState>,
+
+    /// CHECK: Maker of the offer, receives the rent of the closed offer
+    #[account(
+        mut,
+        address = escrow_vault.current_authority @ CustomError::InvalidAuthority
+    )]
+    pub maker: AccountInfo<'info>,
+
+    /// Fills pause with the escrow
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority::ID.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
+
+    /// CHECK: Checked in the compressed token program
+    pub compressed_token_cpi_authority_pda: AccountInfo<'info>,
+
+    pub compressed_token_program: Program<'info, LightCompressedToken>,
+
+    pub light_system_program: Program<'info, LightSystemProgram>,
 
     /// CHECK: Checked in light-system-program.
-    pub service_signer: Signer<'info>,
+    pub registered_program_pda: AccountInfo<'info>,
 
-    /// CHECK:
-    pub current_authority: Signer<'info>,
+    /// CHECK: Checked in light-system-program.
+    pub noop_program: AccountInfo<'info>,
+
+    /// CHECK: Checked in light-system-program.
+    pub account_compression_authority: AccountInfo<'info>,
+
+    pub account_compression_program: Program<'info, AccountCompression>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Fills `fill_amount` of the offer: the taker pays the maker at the offer price and
+/// receives that much of the escrowed tokens in the same instruction.
+/// The offer is closed once nothing remains.
+pub fn accept_escrow_offer<'info>(
+    ctx: Context<'_, '_, '_, 'info, AcceptEscrowOffer<'info>>,
+    fill_amount: u64,
+    taker_inputs: CompressedTokenInputs,
+    escrow_inputs: CompressedTokenInputs,
+) -> Result<()> {
+    let clock = Clock::get()?;
+    let escrow_vault = &ctx.accounts.escrow_vault;
+
+    require!(
+        !escrow_vault.is_expired(clock.unix_timestamp),
+        CustomError::EscrowOfferExpired
+    );
+    escrow_vault.validate_fill(fill_amount)?;
+
+    let amount_paid = escrow_vault.fill_price(fill_amount)?;
+    let remaining_offered = escrow_vault.remaining_offered - fill_amount;
+
+    let escrow_key = escrow_vault.key();
+    let maker = escrow_vault.current_authority;
+    let taker = ctx.accounts.taker.key();
+
+    // Taker pays the maker, the rest of the taker's inputs comes back as change
+    let accounts = ctx
+        .accounts
+        .token_cpi_accounts(ctx.accounts.taker.to_account_info(), ctx.remaining_accounts);
+    transfer_compressed_tokens_cpi(
+        &accounts,
+        &[],
+        escrow_vault.mint_wanted,
+        amount_paid,
+        maker,
+        taker,
+        taker_inputs,
+    )?;
+
+    // Escrow releases the fill to the taker. The unfilled rest stays locked under the
+    // offer, after the last piece any surplus goes back to the maker.
+    let escrow_change_owner = if remaining_offered == 0 {
+        maker
+    } else {
+        escrow_key
+    };
+    let offer_id = escrow_vault.offer_id.to_le_bytes();
+    let bump = [escrow_vault.bump];
+    let seeds: &[&[u8]] = &[ESCROW_VAULT.as_bytes(), maker.as_ref(), &offer_id, &bump];
 
-    pub new_authority: Signer<'info>,
+    let accounts = ctx
+        .accounts
+        .token_cpi_accounts(escrow_vault.to_account_info(), ctx.remaining_accounts);
+    transfer_compressed_tokens_cpi(
+        &accounts,
+        &[seeds],
+        escrow_vault.mint_offered,
+        fill_amount,
+        taker,
+        escrow_change_owner,
+        escrow_inputs,
+    )?;
 
-    #[authority]
-    pub cpi_signer: AccountInfo<'info>,
+    emit!(EscrowOfferFilled {
+        escrow: escrow_key,
+        maker,
+        taker,
+        amount_filled: fill_amount,
+        amount_paid,
+        remaining_offered,
+    });
 
-    #[light_account(
+    let escrow_vault = &mut ctx.accounts.escrow_vault;
+    escrow_vault.remaining_offered = remaining_offered;
+    escrow_vault.modified_at = clock.unix_timestamp;
+
+    if remaining_offered == 0 {
+        escrow_vault.close(ctx.accounts.maker.to_account_info())?;
+    }
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CancelEscrowOffer<'info> {
+    /// The maker at any time, anyone once the offer expired
+    #[account(mut)]
+    pub signer: Signer<'info>,
+
+    #[account(
         mut,
+        close = maker,
         seeds = [
             ESCROW_VAULT.as_bytes(),
-            current_authority.key().as_ref()
-        ]
+            escrow_vault.current_authority.as_ref(),
+            &escrow_vault.offer_id.to_le_bytes()
+        ],
+        bump = escrow_vault.bump
+    )]
+    pub escrow_vault: Account<'info, EscrowVaultState>,
+
+    /// CHECK: Maker of the offer, receives the escrowed tokens and the rent
+    #[account(
+        mut,
+        address = escrow_vault.current_authority @ CustomError::InvalidAuthority
+    )]
+    pub maker: AccountInfo<'info>,
+
+    /// Cancellations pause with the escrow
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority::ID.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
+
+    /// CHECK: Checked in the compressed token program
+    pub compressed_token_cpi_authority_pda: AccountInfo<'info>,
+
+    pub compressed_token_program: Program<'info, LightCompressedToken>,
+
+    pub light_system_program: Program<'info, LightSystemProgram>,
+
+    /// CHECK: Checked in light-system-program.
+    pub registered_program_pda: AccountInfo<'info>,
+
+    /// CHECK: Checked in light-system-program.
+    pub noop_program: AccountInfo<'info>,
+
+    /// CHECK: Checked in light-system-program.
+    pub account_compression_authority: AccountInfo<'info>,
+
+    pub account_compression_program: Program<'info, AccountCompression>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Returns the unfilled rest of the offer to the maker and closes it.
+pub fn cancel_escrow_offer<'info>(
+    ctx: Context<'_, '_, '_, 'info, CancelEscrowOffer<'info>>,
+    escrow_inputs: CompressedTokenInputs,
+) -> Result<()> {
+    let clock = Clock::get()?;
+    let escrow_vault = &ctx.accounts.escrow_vault;
+    let maker = escrow_vault.current_authority;
+    let expired = escrow_vault.is_expired(clock.unix_timestamp);
+
+    if ctx.accounts.signer.key() != maker {
+        require!(expired, CustomError::EscrowOfferNotExpired);
+    }
+
+    let offer_id = escrow_vault.offer_id.to_le_bytes();
+    let bump = [escrow_vault.bump];
+    let seeds: &[&[u8]] = &[ESCROW_VAULT.as_bytes(), maker.as_ref(), &offer_id, &bump];
+
+    let accounts = ctx
+        .accounts
+        .token_cpi_accounts(escrow_vault.to_account_info(), ctx.remaining_accounts);
+    transfer_compressed_tokens_cpi(
+        &accounts,
+        &[seeds],
+        escrow_vault.mint_offered,
+        escrow_vault.remaining_offered,
+        maker,
+        maker,
+        escrow_inputs,
+    )?;
+
+    emit!(EscrowOfferCancelled {
+        escrow: escrow_vault.key(),
+        maker,
+        expired,
+    });
+
+    Ok(())
+}
+
+impl_token_cpi_accounts!(InitializeEscrowVault, payer);
+
+impl_token_cpi_accounts!(AcceptEscrowOffer, payer);
+
+impl_token_cpi_accounts!(CancelEscrowOffer, signer);
+
+// Arbiter escrow: a buyer locks payment for an off-chain delivery, an arbiter settles disputes
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArbiterEscrowStatus {
+    /// Payment is locked and waits for the buyer to release it
+    Funded,
+    /// A party disputed the trade, only the arbiter can settle it now
+    Disputed,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct ArbiterEscrowParams {
+    /// Lets one buyer keep several trades open
+    pub trade_id: u64,
+    pub seller: Pubkey,
+    pub arbiter: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+#[account]
+pub struct ArbiterEscrowState {
+    pub buyer: Pubkey,
+    pub seller: Pubkey,
+    pub arbiter: Pubkey,
+    pub trade_id: u64,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub status: ArbiterEscrowStatus,
+    /// Party that opened the dispute, default while funded
+    pub disputed_by: Pubkey,
+    pub created_at: i64,
+    pub modified_at: i64,
+    pub bump: u8,
+}
+
+impl ArbiterEscrowState {
+    pub const LEN: usize = 8 +  // discriminator
+        32 +         // buyer
+        32 +         // seller
+        32 +         // arbiter
+        8 +          // trade_id
+        32 +         // mint
+        8 +          // amount
+        1 +          // status
+        32 +         // disputed_by
+        8 +          // created_at
+        8 +          // modified_at
+        1; // bump
+
+    pub fn find_address(buyer: &Pubkey, trade_id: u64) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[
+                ARBITER_ESCROW.as_bytes(),
+                buyer.as_ref(),
+                &trade_id.to_le_bytes(),
+            ],
+            &crate::ID,
+        )
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(params: ArbiterEscrowParams)]
+pub struct InitializeArbiterEscrow<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: Checked in light-system-program.
+    pub service_signer: Signer<'info>,
+
+    /// Buyer, owner of the locked compressed tokens
+    pub buyer: Signer<'info>,
+
+    /// CHECK: Must sign unless it is a registered service signer, see `arbiter_entry`
+    #[account(address = params.arbiter @ CustomError::InvalidArbiter)]
+    pub arbiter: AccountInfo<'info>,
+
+    /// Registry entry of the arbiter, when the arbiter is a service signer
+    pub arbiter_entry: Option<Account<'info, ServiceSignerEntry>>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = ArbiterEscrowState::LEN,
+        seeds = [
+            ARBITER_ESCROW.as_bytes(),
+            buyer.key().as_ref(),
+            &params.trade_id.to_le_bytes()
+        ],
+        bump
+    )]
+    pub arbiter_escrow: Account<'info, ArbiterEscrowState>,
+
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority::ID.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
+
+    /// Registry entry of the service signer in `config`
+    pub service_signer_entry: Account<'info, ServiceSignerEntry>,
+
+    /// CHECK: Checked in the compressed token program
+    pub compressed_token_cpi_authority_pda: AccountInfo<'info>,
+
+    pub compressed_token_program: Program<'info, LightCompressedToken>,
+
+    pub light_system_program: Program<'info, LightSystemProgram>,
+
+    /// CHECK: Checked in light-system-program.
+    pub registered_program_pda: AccountInfo<'info>,
+
+    /// CHECK: Checked in light-system-program.
+    pub noop_program: AccountInfo<'info>,
+
+    /// CHECK: Checked in light-system-program.
+    pub account_compression_authority: AccountInfo<'info>,
+
+    pub account_compression_program: Program<'info, AccountCompression>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Opens a trade and locks `amount` of the buyer's compressed tokens until the buyer
+/// releases them or the arbiter settles a dispute.
+///
+/// The arbiter is either a registered service signer with `PERMISSION_MANAGE_ESCROW`,
+/// or any other key (e.g. a server vault authority) that co-signs to accept the role.
+pub fn initialize_arbiter_escrow<'info>(
+    ctx: Context<'_, '_, '_, 'info, InitializeArbiterEscrow<'info>>,
+    params: ArbiterEscrowParams,
+    buyer_inputs: CompressedTokenInputs,
+) -> Result<()> {
+    let clock = Clock::get()?;
+    let buyer = ctx.accounts.buyer.key();
+
+    require!(
+        params.amount > 0 && params.seller != buyer,
+        CustomError::InvalidEscrowOffer
+    );
+    require!(
+        params.arbiter != buyer && params.arbiter != params.seller,
+        CustomError::InvalidArbiter
+    );
+
+    match &ctx.accounts.arbiter_entry {
+        Some(arbiter_entry) => {
+            arbiter_entry.validate(&ctx.accounts.config.key(), &params.arbiter)?;
+            require!(
+                arbiter_entry.is_active(clock.unix_timestamp)
+                    && arbiter_entry.has_permission(PERMISSION_MANAGE_ESCROW),
+                CustomError::InvalidArbiter
+            );
+        }
+        None => require!(ctx.accounts.arbiter.is_signer, CustomError::InvalidArbiter),
+    }
+
+    let escrow_key = ctx.accounts.arbiter_escrow.key();
+
+    let arbiter_escrow = &mut ctx.accounts.arbiter_escrow;
+    arbiter_escrow.buyer = buyer;
+    arbiter_escrow.seller = params.seller;
+    arbiter_escrow.arbiter = params.arbiter;
+    arbiter_escrow.trade_id = params.trade_id;
+    arbiter_escrow.mint = params.mint;
+    arbiter_escrow.amount = params.amount;
+    arbiter_escrow.status = ArbiterEscrowStatus::Funded;
+    arbiter_escrow.disputed_by = Pubkey::default();
+    arbiter_escrow.created_at = clock.unix_timestamp;
+    arbiter_escrow.modified_at = clock.unix_timestamp;
+    arbiter_escrow.bump = ctx.bumps.arbiter_escrow;
+
+    // The escrow PDA owns the payment until the trade settles
+    let accounts = ctx
+        .accounts
+        .token_cpi_accounts(ctx.accounts.buyer.to_account_info(), ctx.remaining_accounts);
+    transfer_compressed_tokens_cpi(
+        &accounts,
+        &[],
+        params.mint,
+        params.amount,
+        escrow_key,
+        buyer,
+        buyer_inputs,
+    )?;
+
+    emit!(ArbiterEscrowCreated {
+        escrow: escrow_key,
+        buyer,
+        seller: params.seller,
+        arbiter: params.arbiter,
+        mint: params.mint,
+        amount: params.amount,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ReleaseArbiterEscrow<'info> {
+    /// Buyer confirming delivery, pays the transaction
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    #[account(
+        mut,
+        close = buyer,
+        has_one = buyer @ CustomError::InvalidAuthority,
+        seeds = [
+            ARBITER_ESCROW.as_bytes(),
+            arbiter_escrow.buyer.as_ref(),
+            &arbiter_escrow.trade_id.to_le_bytes()
+        ],
+        bump = arbiter_escrow.bump
+    )]
+    pub arbiter_escrow: Account<'info, ArbiterEscrowState>,
+
+    /// Releases pause with the escrow
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority::ID.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
+
+    /// CHECK: Checked in the compressed token program
+    pub compressed_token_cpi_authority_pda: AccountInfo<'info>,
+
+    pub compressed_token_program: Program<'info, LightCompressedToken>,
+
+    pub light_system_program: Program<'info, LightSystemProgram>,
+
+    /// CHECK: Checked in light-system-program.
+    pub registered_program_pda: AccountInfo<'info>,
+
+    /// CHECK: Checked in light-system-program.
+    pub noop_program: AccountInfo<'info>,
+
+    /// CHECK: Checked in light-system-program.
+    pub account_compression_authority: AccountInfo<'info>,
+
+    pub account_compression_program: Program<'info, AccountCompression>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Pays the whole escrow to the seller and closes the trade. The buyer may release
+/// during a dispute too, since that only settles it in the seller's favour.
+pub fn release_arbiter_escrow<'info>(
+    ctx: Context<'_, '_, '_, 'info, ReleaseArbiterEscrow<'info>>,
+    escrow_inputs: CompressedTokenInputs,
+) -> Result<()> {
+    let arbiter_escrow = &ctx.accounts.arbiter_escrow;
+    let buyer = arbiter_escrow.buyer;
+    let seller = arbiter_escrow.seller;
+
+    let trade_id = arbiter_escrow.trade_id.to_le_bytes();
+    let bump = [arbiter_escrow.bump];
+    let seeds: &[&[u8]] = &[ARBITER_ESCROW.as_bytes(), buyer.as_ref(), &trade_id, &bump];
+
+    let accounts = ctx
+        .accounts
+        .token_cpi_accounts(arbiter_escrow.to_account_info(), ctx.remaining_accounts);
+    transfer_compressed_tokens_cpi(
+        &accounts,
+        &[seeds],
+        arbiter_escrow.mint,
+        arbiter_escrow.amount,
+        seller,
+        seller,
+        escrow_inputs,
+    )?;
+
+    emit!(ArbiterEscrowReleased {
+        escrow: arbiter_escrow.key(),
+        buyer,
+        seller,
+        amount: arbiter_escrow.amount,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct OpenArbiterDispute<'info> {
+    /// Buyer or seller of the trade
+    pub signer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            ARBITER_ESCROW.as_bytes(),
+            arbiter_escrow.buyer.as_ref(),
+            &arbiter_escrow.trade_id.to_le_bytes()
+        ],
+        bump = arbiter_escrow.bump
+    )]
+    pub arbiter_escrow: Account<'info, ArbiterEscrowState>,
+
+    /// Disputes pause with the escrow
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority::ID.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
+}
+
+/// Hands the trade to the arbiter. From here only the arbiter can settle it, or the
+/// buyer by releasing the payment to the seller.
+pub fn open_arbiter_dispute(ctx: Context<OpenArbiterDispute>) -> Result<()> {
+    let signer = ctx.accounts.signer.key();
+    let arbiter_escrow = &mut ctx.accounts.arbiter_escrow;
+
+    require!(
+        signer == arbiter_escrow.buyer || signer == arbiter_escrow.seller,
+        CustomError::InvalidAuthority
+    );
+    require!(
+        arbiter_escrow.status == ArbiterEscrowStatus::Funded,
+        CustomError::InvalidArbiterEscrowStatus
+    );
+
+    arbiter_escrow.status = ArbiterEscrowStatus::Disputed;
+    arbiter_escrow.disputed_by = signer;
+    arbiter_escrow.modified_at = Clock::get()?.unix_timestamp;
+
+    emit!(ArbiterDisputeOpened {
+        escrow: arbiter_escrow.key(),
+        opened_by: signer,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ResolveArbiterDispute<'info> {
+    /// Arbiter named in the trade, pays the transaction
+    #[account(mut)]
+    pub arbiter: Signer<'info>,
+
+    #[account(
+        mut,
+        close = buyer,
+        has_one = arbiter @ CustomError::InvalidArbiter,
+        seeds = [
+            ARBITER_ESCROW.as_bytes(),
+            arbiter_escrow.buyer.as_ref(),
+            &arbiter_escrow.trade_id.to_le_bytes()
+        ],
+        bump = arbiter_escrow.bump
     )]
-    pub server_vault: LightAccount<EscrowVaultState>,
+    pub arbiter_escrow: Account<'info, ArbiterEscrowState>,
+
+    /// CHECK: Buyer of the trade, receives the rent of the closed escrow
+    #[account(
+        mut,
+        address = arbiter_escrow.buyer @ CustomError::InvalidAuthority
+    )]
+    pub buyer: AccountInfo<'info>,
+
+    /// Resolutions pause with the escrow
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority::ID.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
+
+    /// CHECK: Checked in the compressed token program
+    pub compressed_token_cpi_authority_pda: AccountInfo<'info>,
+
+    pub compressed_token_program: Program<'info, LightCompressedToken>,
+
+    pub light_system_program: Program<'info, LightSystemProgram>,
+
+    /// CHECK: Checked in light-system-program.
+    pub registered_program_pda: AccountInfo<'info>,
+
+    /// CHECK: Checked in light-system-program.
+    pub noop_program: AccountInfo<'info>,
+
+    /// CHECK: Checked in light-system-program.
+    pub account_compression_authority: AccountInfo<'info>,
+
+    pub account_compression_program: Program<'info, AccountCompression>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Splits a disputed escrow: `buyer_amount` goes back to the buyer, the rest to the seller.
+pub fn resolve_arbiter_dispute<'info>(
+    ctx: Context<'_, '_, '_, 'info, ResolveArbiterDispute<'info>>,
+    buyer_amount: u64,
+    escrow_inputs: CompressedTokenInputs,
+) -> Result<()> {
+    let arbiter_escrow = &ctx.accounts.arbiter_escrow;
+
+    require!(
+        arbiter_escrow.status == ArbiterEscrowStatus::Disputed,
+        CustomError::InvalidArbiterEscrowStatus
+    );
+    require!(
+        buyer_amount <= arbiter_escrow.amount,
+        CustomError::InvalidDisputeSplit
+    );
+
+    let buyer = arbiter_escrow.buyer;
+    let seller = arbiter_escrow.seller;
+    let seller_amount = arbiter_escrow.amount - buyer_amount;
+
+    let trade_id = arbiter_escrow.trade_id.to_le_bytes();
+    let bump = [arbiter_escrow.bump];
+    let seeds: &[&[u8]] = &[ARBITER_ESCROW.as_bytes(), buyer.as_ref(), &trade_id, &bump];
+
+    // The buyer's share is the transfer, the seller's share comes out as change.
+    // A full refund or a full payout is a single output.
+    let (amount, recipient, change_owner) = match (buyer_amount, seller_amount) {
+        (0, _) => (seller_amount, seller, seller),
+        (_, 0) => (buyer_amount, buyer, buyer),
+        _ => (buyer_amount, buyer, seller),
+    };
+
+    let accounts = ctx
+        .accounts
+        .token_cpi_accounts(arbiter_escrow.to_account_info(), ctx.remaining_accounts);
+    transfer_compressed_tokens_cpi(
+        &accounts,
+        &[seeds],
+        arbiter_escrow.mint,
+        amount,
+        recipient,
+        change_owner,
+        escrow_inputs,
+    )?;
+
+    emit!(ArbiterDisputeResolved {
+        escrow: arbiter_escrow.key(),
+        arbiter: arbiter_escrow.arbiter,
+        buyer_amount,
+        seller_amount,
+    });
+
+    Ok(())
+}
+
+impl_token_cpi_accounts!(InitializeArbiterEscrow, payer);
+
+impl_token_cpi_accounts!(ReleaseArbiterEscrow, buyer);
+
+impl_token_cpi_accounts!(ResolveArbiterDispute, arbiter);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offer(
+        amount_offered: u64,
+        price_numerator: u64,
+        price_denominator: u64,
+        min_fill_amount: u64,
+    ) -> EscrowVaultState {
+        EscrowVaultState {
+            server_id: "server".to_string(),
+            server_name: "Server".to_string(),
+            current_authority: Pubkey::new_unique(),
+            offer_id: 0,
+            mint_offered: Pubkey::new_unique(),
+            amount_offered,
+            remaining_offered: amount_offered,
+            mint_wanted: Pubkey::new_unique(),
+            price_numerator,
+            price_denominator,
+            min_fill_amount,
+            expires_at: 100,
+            created_at: 0,
+            modified_at: 0,
+            bump: 255,
+        }
+    }
+
+    #[test]
+    fn fill_price_scales_with_the_fill() {
+        let offer = offer(1_000, 3, 2, 1);
+
+        assert_eq!(offer.fill_price(1_000).unwrap(), 1_500);
+        assert_eq!(offer.fill_price(10).unwrap(), 15);
+    }
+
+    #[test]
+    fn fill_price_rounds_up_for_the_maker() {
+        let offer = offer(1_000, 1, 3, 1);
+
+        assert_eq!(offer.fill_price(1).unwrap(), 1);
+        assert_eq!(offer.fill_price(3).unwrap(), 1);
+        assert_eq!(offer.fill_price(4).unwrap(), 2);
+    }
+
+    #[test]
+    fn fill_price_rejects_prices_past_u64() {
+        let offer = offer(u64::MAX, 2, 1, 1);
+
+        assert_eq!(offer.fill_price(u64::MAX / 2).unwrap(), u64::MAX - 1);
+        assert_eq!(
+            offer.fill_price(u64::MAX).unwrap_err(),
+            CustomError::InvalidEscrowFill.into()
+        );
+    }
+
+    #[test]
+    fn fills_respect_the_minimum_and_the_remaining_amount() {
+        let mut offer = offer(1_000, 1, 1, 100);
+
+        assert!(offer.validate_fill(100).is_ok());
+        assert!(offer.validate_fill(1_000).is_ok());
+        assert_eq!(
+            offer.validate_fill(0).unwrap_err(),
+            CustomError::InvalidEscrowFill.into()
+        );
+        assert_eq!(
+            offer.validate_fill(99).unwrap_err(),
+            CustomError::InvalidEscrowFill.into()
+        );
+        assert_eq!(
+            offer.validate_fill(1_001).unwrap_err(),
+            CustomError::InvalidEscrowFill.into()
+        );
+
+        offer.remaining_offered = 50;
+        assert!(offer.validate_fill(50).is_ok());
+        assert_eq!(
+            offer.validate_fill(40).unwrap_err(),
+            CustomError::InvalidEscrowFill.into()
+        );
+    }
+
+    #[test]
+    fn prices_need_both_terms() {
+        assert!(validate_price(1, 1).is_ok());
+        assert_eq!(
+            validate_price(0, 1).unwrap_err(),
+            CustomError::InvalidEscrowOffer.into()
+        );
+        assert_eq!(
+            validate_price(1, 0).unwrap_err(),
+            CustomError::InvalidEscrowOffer.into()
+        );
+    }
 }
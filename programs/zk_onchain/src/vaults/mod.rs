@@ -4,6 +4,15 @@ pub use user_vault::*;
 pub mod vault_config;
 pub use vault_config::*;
 
+pub mod config_proposal;
+pub use config_proposal::*;
+
+pub mod service_signer_registry;
+pub use service_signer_registry::*;
+
+pub mod emergency_pause;
+pub use emergency_pause::*;
+
 pub mod server_vault;
 pub use server_vault::*;
 
@@ -12,3 +21,6 @@ pub use escrow_vault::*;
 
 pub mod airdrop_vault;
 pub use airdrop_vault::*;
+
+pub mod vesting_vault;
+pub use vesting_vault::*;
@@ -1,11 +1,15 @@
 use crate::{state::SERVER_VAULT, ParamsInitializeServerVault, ParamsUpdateServerVaultAuthority};
 use anchor_lang::prelude::*;
+use light_compressed_token::program::LightCompressedToken;
 use light_sdk::{
     compressed_account::LightAccount, light_account, light_accounts,
     merkle_context::PackedAddressMerkleContext,
 };
 
-use crate::{state::VAULT_CONFIG_SEED, VaultConfigState};
+use crate::{
+    compressed_transfers::impl_token_cpi_accounts, config_authority, state::VAULT_CONFIG_SEED,
+    ServiceSignerEntry, VaultConfigState,
+};
 
 use super::VaultType;
 
@@ -54,13 +58,13 @@ pub struct InitializeServerVault<'info> {
     pub server_vault: LightAccount<ServerVaultState>,
 
     #[account(
-        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority.key().as_ref()],
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority::ID.as_ref()],
         bump
     )]
     pub config: AccountLoader<'info, VaultConfigState>,
 
-    /// CHECK: Config authority pubkey used for PDA derivation
-    pub config_authority: AccountInfo<'info>,
+    /// Registry entry of the service signer in `config`
+    pub service_signer_entry: Account<'info, ServiceSignerEntry>,
 }
 
 #[light_accounts]
@@ -83,12 +87,40 @@ pub struct UpdateServerVaultAuthority<'info> {
     #[authority]
     pub cpi_signer: AccountInfo<'info>,
 
+    /// Vault at the address of the current authority, closed by the transfer
     #[light_account(
-        mut,
+        close,
         seeds = [
             SERVER_VAULT.as_bytes(),
             current_authority.key().as_ref()
         ]
     )]
     pub server_vault: LightAccount<ServerVaultState>,
+
+    /// Same vault re-created at the address of the new authority
+    #[light_account(
+        init,
+        seeds = [
+            SERVER_VAULT.as_bytes(),
+            new_authority.key().as_ref()
+        ],
+    )]
+    pub new_server_vault: LightAccount<ServerVaultState>,
+
+    /// CHECK: Checked in the compressed token program
+    pub compressed_token_cpi_authority_pda: AccountInfo<'info>,
+
+    pub compressed_token_program: Program<'info, LightCompressedToken>,
+
+    /// Authority updates pause with the vaults
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority::ID.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
+
+    /// Registry entry of the service signer in `config`
+    pub service_signer_entry: Account<'info, ServiceSignerEntry>,
 }
+
+impl_token_cpi_accounts!(UpdateServerVaultAuthority, payer);
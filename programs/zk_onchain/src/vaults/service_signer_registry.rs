@@ -0,0 +1,473 @@
+use crate::state::*;
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{self, Allocate, Assign, CreateAccount, Transfer};
+
+use super::{grow_vault_config, VaultConfigState, MAX_SERVICE_SIGNERS};
+
+// Service signer permission bits
+pub const PERMISSION_CREATE_USER_VAULT: u32 = 1 << 0;
+pub const PERMISSION_CREATE_SERVER_VAULT: u32 = 1 << 1;
+pub const PERMISSION_MINT: u32 = 1 << 2;
+pub const PERMISSION_COMPRESS: u32 = 1 << 3;
+pub const PERMISSION_DECOMPRESS: u32 = 1 << 4;
+pub const PERMISSION_MANAGE_ESCROW: u32 = 1 << 5;
+pub const PERMISSION_MANAGE_AIRDROP: u32 = 1 << 6;
+pub const PERMISSION_MANAGE_VESTING: u32 = 1 << 7;
+pub const PERMISSION_FREEZE: u32 = 1 << 8;
+pub const PERMISSION_UPDATE_USER_VAULT: u32 = 1 << 9;
+pub const PERMISSION_UPDATE_SERVER_VAULT: u32 = 1 << 10;
+pub const PERMISSION_ALL: u32 = PERMISSION_CREATE_USER_VAULT
+    | PERMISSION_CREATE_SERVER_VAULT
+    | PERMISSION_MINT
+    | PERMISSION_COMPRESS
+    | PERMISSION_DECOMPRESS
+    | PERMISSION_MANAGE_ESCROW
+    | PERMISSION_MANAGE_AIRDROP
+    | PERMISSION_MANAGE_VESTING
+    | PERMISSION_FREEZE
+    | PERMISSION_UPDATE_USER_VAULT
+    | PERMISSION_UPDATE_SERVER_VAULT;
+
+/// One registered service signer. Its existence at the
+/// `[SERVICE_SIGNER_SEED, config, signer]` PDA is the registration.
+#[account]
+pub struct ServiceSignerEntry {
+    pub config: Pubkey,
+    pub signer: Pubkey,
+    pub added_at: i64,
+    pub added_by: Pubkey,
+    pub permissions: u32,
+    pub valid_from: i64,
+    /// Zero means the signer never expires
+    pub valid_until: i64,
+    pub bump: u8,
+}
+
+impl ServiceSignerEntry {
+    pub const LEN: usize = 8 +  // discriminator
+        32 +         // config
+        32 +         // signer
+        8 +          // added_at
+        32 +         // added_by
+        4 +          // permissions
+        8 +          // valid_from
+        8 +          // valid_until
+        1; // bump
+
+    pub fn find_address(config: &Pubkey, signer: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[
+                SERVICE_SIGNER_SEED.as_bytes(),
+                config.as_ref(),
+                signer.as_ref(),
+            ],
+            &crate::ID,
+        )
+    }
+
+    pub fn validate(&self, config: &Pubkey, signer: &Pubkey) -> Result<()> {
+        require!(
+            self.config == *config && self.signer == *signer,
+            CustomError::UnregisteredServiceSigner
+        );
+
+        Ok(())
+    }
+
+    pub fn is_active(&self, now: i64) -> bool {
+        now >= self.valid_from && (self.valid_until == 0 || now < self.valid_until)
+    }
+
+    pub fn has_permission(&self, permission: u32) -> bool {
+        self.permissions & permission == permission
+    }
+
+    pub fn validate_permissions(permissions: u32) -> Result<()> {
+        require!(
+            permissions & !PERMISSION_ALL == 0,
+            CustomError::InvalidServiceSignerPermissions
+        );
+
+        Ok(())
+    }
+
+    pub fn validate_window(valid_from: i64, valid_until: i64) -> Result<()> {
+        require!(
+            valid_until == 0 || valid_until > valid_from,
+            CustomError::InvalidServiceSignerWindow
+        );
+
+        Ok(())
+    }
+
+    /// End of this entry's window once a replacement takes over at `activate_at`. A hand-over
+    /// never extends the window, so one scheduled after the entry expires is refused.
+    pub fn valid_until_after_rotation(&self, activate_at: i64) -> Result<i64> {
+        if self.valid_until == 0 {
+            return Ok(activate_at);
+        }
+        require!(
+            activate_at <= self.valid_until,
+            CustomError::InvalidServiceSignerWindow
+        );
+
+        Ok(self.valid_until.min(activate_at))
+    }
+}
+
+/// Creates the registry entry PDA for `entry.signer`, funded by `payer`.
+/// The bump is filled in here.
+pub(crate) fn create_service_signer_entry<'info>(
+    entry_info: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program_info: &AccountInfo<'info>,
+    mut entry: ServiceSignerEntry,
+) -> Result<()> {
+    ServiceSignerEntry::validate_permissions(entry.permissions)?;
+    ServiceSignerEntry::validate_window(entry.valid_from, entry.valid_until)?;
+
+    let (address, bump) = ServiceSignerEntry::find_address(&entry.config, &entry.signer);
+    require_keys_eq!(
+        entry_info.key(),
+        address,
+        CustomError::InvalidServiceSignerEntry
+    );
+    require!(
+        entry_info.data_is_empty(),
+        CustomError::DuplicateServiceSigner
+    );
+
+    let seeds: &[&[u8]] = &[
+        SERVICE_SIGNER_SEED.as_bytes(),
+        entry.config.as_ref(),
+        entry.signer.as_ref(),
+        &[bump],
+    ];
+    let rent = Rent::get()?.minimum_balance(ServiceSignerEntry::LEN);
+    let current_lamports = entry_info.lamports();
+
+    if current_lamports == 0 {
+        system_program::create_account(
+            CpiContext::new_with_signer(
+                system_program_info.clone(),
+                CreateAccount {
+                    from: payer.clone(),
+                    to: entry_info.clone(),
+                },
+                &[seeds],
+            ),
+            rent,
+            ServiceSignerEntry::LEN as u64,
+            &crate::ID,
+        )?;
+    } else {
+        // Someone pre-funded the address, so top it up instead of creating it
+        let required_lamports = rent.saturating_sub(current_lamports);
+        if required_lamports > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    system_program_info.clone(),
+                    Transfer {
+                        from: payer.clone(),
+                        to: entry_info.clone(),
+                    },
+                ),
+                required_lamports,
+            )?;
+        }
+        system_program::allocate(
+            CpiContext::new_with_signer(
+                system_program_info.clone(),
+                Allocate {
+                    account_to_allocate: entry_info.clone(),
+                },
+                &[seeds],
+            ),
+            ServiceSignerEntry::LEN as u64,
+        )?;
+        system_program::assign(
+            CpiContext::new_with_signer(
+                system_program_info.clone(),
+                Assign {
+                    account_to_assign: entry_info.clone(),
+                },
+                &[seeds],
+            ),
+            &crate::ID,
+        )?;
+    }
+
+    entry.bump = bump;
+    let mut data = entry_info.try_borrow_mut_data()?;
+    entry.try_serialize(&mut &mut data[..])?;
+
+    Ok(())
+}
+
+/// Closes the registry entry of `signer` and returns its rent to `destination`.
+pub(crate) fn close_service_signer_entry<'info>(
+    entry_info: &AccountInfo<'info>,
+    destination: &AccountInfo<'info>,
+    config: Pubkey,
+    signer: Pubkey,
+) -> Result<()> {
+    require!(
+        !entry_info.data_is_empty() && entry_info.owner == &crate::ID,
+        CustomError::InvalidSignerExist
+    );
+
+    let entry = Account::<ServiceSignerEntry>::try_from(entry_info)?;
+    entry.validate(&config, &signer)?;
+
+    anchor_lang::common::close(entry_info.clone(), destination.clone())
+}
+
+/// Registers `new_entry.signer` with the permissions of `old_signer`, which stays valid
+/// until `new_entry.valid_from`. The hand-over cannot be scheduled after the old entry
+/// expires. An immediate rotation closes the old entry right away.
+///
+/// Returns whether the old entry was closed.
+pub(crate) fn rotate_service_signer_entry<'info>(
+    old_entry_info: &AccountInfo<'info>,
+    new_entry_info: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program_info: &AccountInfo<'info>,
+    old_signer: Pubkey,
+    mut new_entry: ServiceSignerEntry,
+    now: i64,
+) -> Result<bool> {
+    require_keys_neq!(
+        old_signer,
+        new_entry.signer,
+        CustomError::DuplicateServiceSigner
+    );
+
+    let mut old_entry = Account::<ServiceSignerEntry>::try_from(old_entry_info)?;
+    old_entry.validate(&new_entry.config, &old_signer)?;
+
+    new_entry.permissions = old_entry.permissions;
+    create_service_signer_entry(
+        new_entry_info,
+        payer,
+        system_program_info,
+        new_entry.clone(),
+    )?;
+
+    if new_entry.valid_from <= now {
+        close_service_signer_entry(old_entry_info, payer, new_entry.config, old_signer)?;
+        return Ok(true);
+    }
+
+    // Both keys overlap until the scheduled hand-over
+    old_entry.valid_until = old_entry.valid_until_after_rotation(new_entry.valid_from)?;
+    old_entry.exit(&crate::ID)?;
+
+    Ok(false)
+}
+
+/// Replaces the permission bitmask of an existing registry entry.
+pub(crate) fn update_service_signer_permissions(
+    entry_info: &AccountInfo<'_>,
+    config: Pubkey,
+    signer: Pubkey,
+    permissions: u32,
+) -> Result<()> {
+    ServiceSignerEntry::validate_permissions(permissions)?;
+
+    let mut entry = Account::<ServiceSignerEntry>::try_from(entry_info)?;
+    entry.validate(&config, &signer)?;
+    entry.permissions = permissions;
+
+    entry.exit(&crate::ID)
+}
+
+#[derive(Accounts)]
+pub struct MigrateServiceSigners<'info> {
+    /// CHECK: May still have the legacy layout, which does not load as `VaultConfigState`.
+    /// Its discriminator and address are checked in `grow_vault_config`
+    #[account(mut, owner = crate::ID)]
+    pub config: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Moves the signers of the legacy fixed array into registry entries.
+///
+/// Only already approved signers are copied, so anyone may crank it. The entry PDAs
+/// are passed as remaining accounts in the same order as `service_signers`.
+/// Legacy signers could do everything and never expired, so their entries get
+/// `PERMISSION_ALL` and an open validity window. A config that still has the legacy
+/// layout is grown first.
+pub fn migrate_service_signers<'info>(
+    ctx: Context<'_, '_, '_, 'info, MigrateServiceSigners<'info>>,
+) -> Result<()> {
+    let config_info = ctx.accounts.config.to_account_info();
+    grow_vault_config(
+        &config_info,
+        &ctx.accounts.payer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+
+    let config_key = config_info.key();
+    let config_loader = AccountLoader::<VaultConfigState>::try_from(&config_info)?;
+    let config = &mut config_loader.load_mut()?;
+    let clock = Clock::get()?;
+
+    let legacy_count = config.service_signers_count as usize;
+    require!(legacy_count > 0, CustomError::ServiceSignersNotConfigured);
+    require!(
+        ctx.remaining_accounts.len() == legacy_count,
+        CustomError::InvalidParameter
+    );
+
+    let added_by = config.current_update_authority;
+    for (i, entry_info) in ctx.remaining_accounts.iter().enumerate() {
+        create_service_signer_entry(
+            entry_info,
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            ServiceSignerEntry {
+                config: config_key,
+                signer: config.service_signers[i],
+                added_at: clock.unix_timestamp,
+                added_by,
+                permissions: PERMISSION_ALL,
+                valid_from: 0,
+                valid_until: 0,
+                bump: 0,
+            },
+        )?;
+    }
+
+    config.registered_signers_count = config
+        .registered_signers_count
+        .checked_add(legacy_count as u32)
+        .ok_or(CustomError::TooManyServiceSigners)?;
+    config.service_signers = [Pubkey::default(); MAX_SERVICE_SIGNERS];
+    config.service_signers_count = 0;
+
+    // Update modification metadata
+    config.modified_at = clock.unix_timestamp;
+    config.modified_by = ctx.accounts.payer.key();
+
+    emit!(VaultConfigUpdated {
+        config: config_key,
+        updated_by: ctx.accounts.payer.key(),
+        change: VaultConfigChange::ServiceSignersMigrated {
+            count: legacy_count as u32,
+        },
+        timestamp: clock.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(permissions: u32, valid_from: i64, valid_until: i64) -> ServiceSignerEntry {
+        ServiceSignerEntry {
+            config: Pubkey::new_unique(),
+            signer: Pubkey::new_unique(),
+            added_at: 0,
+            added_by: Pubkey::new_unique(),
+            permissions,
+            valid_from,
+            valid_until,
+            bump: 255,
+        }
+    }
+
+    #[test]
+    fn entry_validates_only_its_own_config_and_signer() {
+        let entry = entry(PERMISSION_ALL, 0, 0);
+
+        assert!(entry.validate(&entry.config, &entry.signer).is_ok());
+        assert_eq!(
+            entry
+                .validate(&entry.config, &Pubkey::new_unique())
+                .unwrap_err(),
+            CustomError::UnregisteredServiceSigner.into()
+        );
+        assert_eq!(
+            entry
+                .validate(&Pubkey::new_unique(), &entry.signer)
+                .unwrap_err(),
+            CustomError::UnregisteredServiceSigner.into()
+        );
+        assert_eq!(
+            entry.validate(&entry.signer, &entry.config).unwrap_err(),
+            CustomError::UnregisteredServiceSigner.into()
+        );
+    }
+
+    #[test]
+    fn entry_is_active_within_its_window() {
+        let entry = entry(PERMISSION_ALL, 100, 200);
+
+        assert!(!entry.is_active(99));
+        assert!(entry.is_active(100));
+        assert!(entry.is_active(199));
+        assert!(!entry.is_active(200));
+    }
+
+    #[test]
+    fn entry_without_end_never_expires() {
+        let entry = entry(PERMISSION_ALL, 100, 0);
+
+        assert!(!entry.is_active(99));
+        assert!(entry.is_active(i64::MAX));
+    }
+
+    #[test]
+    fn entry_needs_every_bit_of_a_permission() {
+        let entry = entry(PERMISSION_MINT | PERMISSION_COMPRESS, 0, 0);
+
+        assert!(entry.has_permission(PERMISSION_MINT));
+        assert!(entry.has_permission(PERMISSION_MINT | PERMISSION_COMPRESS));
+        assert!(!entry.has_permission(PERMISSION_DECOMPRESS));
+        assert!(!entry.has_permission(PERMISSION_MINT | PERMISSION_DECOMPRESS));
+    }
+
+    #[test]
+    fn unknown_permission_bits_are_refused() {
+        assert!(ServiceSignerEntry::validate_permissions(PERMISSION_ALL).is_ok());
+        assert_eq!(
+            ServiceSignerEntry::validate_permissions(PERMISSION_ALL | 1 << 31).unwrap_err(),
+            CustomError::InvalidServiceSignerPermissions.into()
+        );
+    }
+
+    #[test]
+    fn window_has_to_end_after_it_starts() {
+        assert!(ServiceSignerEntry::validate_window(100, 0).is_ok());
+        assert!(ServiceSignerEntry::validate_window(100, 101).is_ok());
+        assert_eq!(
+            ServiceSignerEntry::validate_window(100, 100).unwrap_err(),
+            CustomError::InvalidServiceSignerWindow.into()
+        );
+    }
+
+    #[test]
+    fn rotation_ends_an_open_window_at_the_hand_over() {
+        let entry = entry(PERMISSION_ALL, 0, 0);
+
+        assert_eq!(entry.valid_until_after_rotation(150).unwrap(), 150);
+    }
+
+    #[test]
+    fn rotation_never_extends_an_expiring_entry() {
+        let entry = entry(PERMISSION_ALL, 0, 200);
+
+        assert_eq!(entry.valid_until_after_rotation(150).unwrap(), 150);
+        assert_eq!(entry.valid_until_after_rotation(200).unwrap(), 200);
+        assert_eq!(
+            entry.valid_until_after_rotation(201).unwrap_err(),
+            CustomError::InvalidServiceSignerWindow.into()
+        );
+    }
+}
@@ -1,6 +1,7 @@
-use crate::{ParamsInitializeUserVault, ParamsUpdateUserVaultAuthority};
+use crate::{ParamsInitializeUserVault, ParamsMigrateUserVault, ParamsUpdateUserVaultAuthority};
 use anchor_lang::prelude::*;
 use borsh::{BorshDeserialize, BorshSerialize};
+use light_compressed_token::program::LightCompressedToken;
 use light_hasher::bytes::AsByteVec;
 use light_sdk::{
     compressed_account::LightAccount, light_account, light_accounts,
@@ -8,8 +9,13 @@ use light_sdk::{
 };
 
 use crate::{
-    state::{USER_VAULT, VAULT_CONFIG_SEED},
-    VaultConfigState,
+    compressed_transfers::impl_token_cpi_accounts,
+    config_authority,
+    state::{
+        CustomError, USER_VAULT, USER_VAULT_AUTHORITY, USER_VAULT_TOKENS, USER_VAULT_V2,
+        VAULT_CONFIG_SEED,
+    },
+    ServiceSignerEntry, VaultConfigState,
 };
 
 #[derive(Clone, Debug, Eq, PartialEq, BorshDeserialize, BorshSerialize)]
@@ -17,6 +23,9 @@ pub enum VaultType {
     User,
     Server,
     Other,
+    // Appended so the discriminants of stored vaults stay the same
+    Airdrop,
+    Vesting,
 }
 
 impl anchor_lang::IdlBuild for VaultType {}
@@ -33,6 +42,8 @@ impl Default for VaultType {
     }
 }
 
+/// Vault layout from before vault ids, at the address of its current authority.
+/// `migrate_user_vault` moves it to a `UserVaultStateV2`.
 #[light_account]
 #[derive(Clone, Debug, Default)]
 pub struct UserVaultState {
@@ -42,6 +53,63 @@ pub struct UserVaultState {
     pub modified_at: i64,
 }
 
+impl UserVaultState {
+    /// PDA owning the compressed tokens of a legacy vault. `migrate_user_vault` moves them to
+    /// the token owner of the migrated vault.
+    pub fn find_legacy_token_owner(authority: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[USER_VAULT.as_bytes(), authority.as_ref()], &crate::ID)
+    }
+}
+
+/// User vault at an address derived from its vault id, so it keeps its address
+/// across authority changes
+#[light_account]
+#[derive(Clone, Debug, Default)]
+pub struct UserVaultStateV2 {
+    #[truncate]
+    pub current_authority: Pubkey,
+    pub vault_type: VaultType,
+    pub modified_at: i64,
+    /// Key the vault address was derived from, stays the same across authority changes
+    #[truncate]
+    pub vault_id: Pubkey,
+}
+
+impl UserVaultStateV2 {
+    /// PDA owning the compressed tokens of the vault. The program signs for it once the
+    /// current authority of the vault has signed.
+    pub fn find_token_owner(vault_id: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[USER_VAULT_TOKENS.as_bytes(), vault_id.as_ref()],
+            &crate::ID,
+        )
+    }
+}
+
+/// Authority of a user vault, kept next to the compressed vault by its init, migration and
+/// authority updates. Instructions acting for the vault check it here, so they don't have to
+/// spend and rewrite the compressed vault.
+#[account]
+pub struct UserVaultAuthority {
+    pub vault_id: Pubkey,
+    pub current_authority: Pubkey,
+    pub bump: u8,
+}
+
+impl UserVaultAuthority {
+    pub const LEN: usize = 8 +  // discriminator
+        32 +         // vault_id
+        32 +         // current_authority
+        1; // bump
+
+    pub fn find_address(vault_id: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[USER_VAULT_AUTHORITY.as_bytes(), vault_id.as_ref()],
+            &crate::ID,
+        )
+    }
+}
+
 #[light_accounts]
 pub struct InitializeUserVault<'info> {
     #[account(mut)]
@@ -62,20 +130,30 @@ pub struct InitializeUserVault<'info> {
     #[light_account(
         init,
         seeds = [
-            USER_VAULT.as_bytes(),
+            USER_VAULT_V2.as_bytes(),
             current_authority.key().as_ref()
         ],
     )]
-    pub user_vault: LightAccount<UserVaultState>,
+    pub user_vault: LightAccount<UserVaultStateV2>,
 
+    /// Authority record of the new vault, its vault id is the current authority
     #[account(
-        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority.key().as_ref()],
+        init,
+        payer = payer,
+        space = UserVaultAuthority::LEN,
+        seeds = [USER_VAULT_AUTHORITY.as_bytes(), current_authority.key().as_ref()],
+        bump
+    )]
+    pub vault_authority: Account<'info, UserVaultAuthority>,
+
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority::ID.as_ref()],
         bump
     )]
     pub config: AccountLoader<'info, VaultConfigState>,
 
-    /// CHECK: Config authority pubkey used for PDA derivation
-    pub config_authority: AccountInfo<'info>,
+    /// Registry entry of the service signer in `config`
+    pub service_signer_entry: Account<'info, ServiceSignerEntry>,
 }
 
 #[light_accounts]
@@ -95,15 +173,162 @@ pub struct UpdateUserVaultAuthority<'info> {
 
     pub new_authority: Signer<'info>,
 
+    /// CHECK: Vault id the address of `user_vault` was derived from
+    pub vault_id: AccountInfo<'info>,
+
     #[authority]
     pub cpi_signer: AccountInfo<'info>,
 
     #[light_account(
         mut,
+        seeds = [
+            USER_VAULT_V2.as_bytes(),
+            vault_id.key().as_ref()
+        ]
+    )]
+    pub user_vault: LightAccount<UserVaultStateV2>,
+
+    /// Authority record of the vault, created with the vault or by its migration
+    #[account(
+        mut,
+        seeds = [USER_VAULT_AUTHORITY.as_bytes(), vault_id.key().as_ref()],
+        bump = vault_authority.bump,
+        has_one = current_authority @ CustomError::InvalidAuthority
+    )]
+    pub vault_authority: Account<'info, UserVaultAuthority>,
+
+    /// Authority updates pause with the vaults
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority::ID.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
+
+    /// Registry entry of the service signer in `config`
+    pub service_signer_entry: Account<'info, ServiceSignerEntry>,
+}
+
+#[light_accounts]
+pub struct MigrateUserVault<'info> {
+    #[account(mut)]
+    #[fee_payer]
+    pub payer: Signer<'info>,
+
+    #[self_program]
+    pub self_program: Program<'info, crate::program::ZkOnchain>,
+
+    pub current_authority: Signer<'info>,
+
+    #[authority]
+    pub cpi_signer: AccountInfo<'info>,
+
+    /// Legacy vault, always at the address of its current authority
+    #[light_account(
+        close,
         seeds = [
             USER_VAULT.as_bytes(),
             current_authority.key().as_ref()
         ]
     )]
-    pub user_vault: LightAccount<UserVaultState>,
+    pub legacy_vault: LightAccount<UserVaultState>,
+
+    /// Same vault with the current authority as its vault id
+    #[light_account(
+        init,
+        seeds = [
+            USER_VAULT_V2.as_bytes(),
+            current_authority.key().as_ref()
+        ],
+    )]
+    pub user_vault: LightAccount<UserVaultStateV2>,
+
+    /// Authority record of the migrated vault
+    #[account(
+        init,
+        payer = payer,
+        space = UserVaultAuthority::LEN,
+        seeds = [USER_VAULT_AUTHORITY.as_bytes(), current_authority.key().as_ref()],
+        bump
+    )]
+    pub vault_authority: Account<'info, UserVaultAuthority>,
+
+    /// CHECK: Owner of the legacy vault's compressed tokens, signed for by the program
+    #[account(seeds = [USER_VAULT.as_bytes(), current_authority.key().as_ref()], bump)]
+    pub legacy_token_owner: AccountInfo<'info>,
+
+    /// CHECK: Checked in the compressed token program
+    pub compressed_token_cpi_authority_pda: AccountInfo<'info>,
+
+    pub compressed_token_program: Program<'info, LightCompressedToken>,
+
+    /// Migrations pause with the vaults
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority::ID.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
+}
+
+impl_token_cpi_accounts!(MigrateUserVault, payer);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compressed_transfers::{balance_output, CompressedTokenInputs};
+    use light_compressed_token::process_transfer::InputTokenDataWithContext;
+    use light_system_program::{
+        invoke::processor::CompressedProof, sdk::compressed_account::PackedMerkleContext,
+    };
+
+    fn token_inputs(amounts: &[u64]) -> CompressedTokenInputs {
+        CompressedTokenInputs {
+            proof: CompressedProof {
+                a: [0; 32],
+                b: [0; 64],
+                c: [0; 32],
+            },
+            input_token_data_with_context: amounts
+                .iter()
+                .enumerate()
+                .map(|(leaf_index, amount)| InputTokenDataWithContext {
+                    amount: *amount,
+                    delegate_index: None,
+                    merkle_context: PackedMerkleContext {
+                        merkle_tree_pubkey_index: 0,
+                        nullifier_queue_pubkey_index: 1,
+                        leaf_index: leaf_index as u32,
+                        queue_index: None,
+                    },
+                    root_index: 0,
+                    lamports: None,
+                    tlv: None,
+                })
+                .collect(),
+            recipient_merkle_tree_index: 0,
+            change_merkle_tree_index: 0,
+        }
+    }
+
+    #[test]
+    fn migration_moves_the_legacy_balance_to_the_token_owner_of_the_vault_id() {
+        let authority = Pubkey::new_unique();
+        let (legacy_token_owner, _) = UserVaultState::find_legacy_token_owner(&authority);
+        let (token_owner, _) = UserVaultStateV2::find_token_owner(&authority);
+        assert_ne!(legacy_token_owner, token_owner);
+
+        let output = balance_output(&token_inputs(&[40, 60]), token_owner).unwrap();
+
+        assert_eq!(output.owner, token_owner);
+        assert_eq!(output.amount, 100);
+    }
+
+    #[test]
+    fn empty_legacy_balances_are_refused() {
+        let (token_owner, _) = UserVaultStateV2::find_token_owner(&Pubkey::new_unique());
+
+        assert_eq!(
+            balance_output(&token_inputs(&[]), token_owner).unwrap_err(),
+            CustomError::ZeroAmount.into()
+        );
+    }
 }
@@ -1,7 +1,21 @@
 use crate::state::*;
 use anchor_lang::prelude::*;
+use anchor_lang::system_program;
 
+use super::{
+    authorize_config_action, close_service_signer_entry, create_service_signer_entry,
+    rotate_service_signer_entry, update_service_signer_permissions, ConfigAction, ConfigProposal,
+    ServiceSignerEntry,
+};
+
+// Size of the legacy signer array, signers now live in `ServiceSignerEntry` PDAs
 pub const MAX_SERVICE_SIGNERS: usize = 3;
+pub const MAX_CONFIG_ADMINS: usize = 10;
+
+// Bounds for the update authority transfer timelock, in seconds
+pub const DEFAULT_AUTHORITY_TRANSFER_DELAY: i64 = 2 * 24 * 60 * 60;
+pub const MIN_AUTHORITY_TRANSFER_DELAY: i64 = 60 * 60;
+pub const MAX_AUTHORITY_TRANSFER_DELAY: i64 = 30 * 24 * 60 * 60;
 pub mod config_authority {
     use anchor_lang::prelude::declare_id;
     #[cfg(feature = "devnet")]
@@ -16,7 +30,7 @@ pub struct InitializeConfig<'info> {
         init,
         payer = payer,
         space = VaultConfigState::LEN,
-        seeds = [VAULT_CONFIG_SEED.as_bytes(), payer.key().as_ref()],
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), authority.key().as_ref()],
         bump
     )]
     pub config: AccountLoader<'info, VaultConfigState>,
@@ -36,6 +50,7 @@ pub struct InitializeConfig<'info> {
 #[repr(packed)]
 #[derive(Default)]
 pub struct VaultConfigState {
+    /// Legacy signer list, emptied by `migrate_service_signers`
     pub service_signers: [Pubkey; MAX_SERVICE_SIGNERS],
     pub service_signers_count: u8,
     pub created_at: i64,
@@ -43,6 +58,17 @@ pub struct VaultConfigState {
     pub modified_at: i64,
     pub modified_by: Pubkey,
     pub current_update_authority: Pubkey,
+    pub pending_update_authority: Pubkey,
+    pub authority_transfer_eta: i64,
+    pub authority_transfer_delay: i64,
+    pub admins: [Pubkey; MAX_CONFIG_ADMINS],
+    pub admins_count: u8,
+    pub approval_threshold: u8,
+    pub proposal_count: u64,
+    pub registered_signers_count: u32,
+    pub guardian: Pubkey,
+    pub paused: u8,
+    pub pause_flags: u8,
 }
 
 pub fn initialize_vault_config(ctx: Context<InitializeConfig>) -> Result<()> {
@@ -53,13 +79,39 @@ pub fn initialize_vault_config(ctx: Context<InitializeConfig>) -> Result<()> {
     config.service_signers = [Pubkey::default(); MAX_SERVICE_SIGNERS];
     config.service_signers_count = 0;
 
-    // Set initial metadata
+    // Set initial metadata, `created_by` is the seed of the config PDA
     config.created_at = clock.unix_timestamp;
-    config.created_by = ctx.accounts.payer.key();
+    config.created_by = ctx.accounts.authority.key();
     config.modified_at = clock.unix_timestamp;
     config.modified_by = ctx.accounts.payer.key();
     config.current_update_authority = ctx.accounts.authority.key();
 
+    // No authority transfer in flight
+    config.pending_update_authority = Pubkey::default();
+    config.authority_transfer_eta = 0;
+    config.authority_transfer_delay = DEFAULT_AUTHORITY_TRANSFER_DELAY;
+
+    // Single authority administration until admins are set
+    config.admins = [Pubkey::default(); MAX_CONFIG_ADMINS];
+    config.admins_count = 0;
+    config.approval_threshold = 0;
+    config.proposal_count = 0;
+
+    // Service signers are registered as entry PDAs
+    config.registered_signers_count = 0;
+
+    // No guardian until one is set, nothing paused
+    config.guardian = Pubkey::default();
+    config.paused = 0;
+    config.pause_flags = 0;
+
+    emit!(VaultConfigInitialized {
+        config: ctx.accounts.config.key(),
+        created_by: ctx.accounts.authority.key(),
+        update_authority: ctx.accounts.authority.key(),
+        timestamp: clock.unix_timestamp,
+    });
+
     Ok(())
 }
 
@@ -71,7 +123,129 @@ impl VaultConfigState {
         32 +         // created_by
         8 +          // modified_at
         32 +         // modified_by
-        32; // current_update_authority
+        32 +         // current_update_authority
+        32 +         // pending_update_authority
+        8 +          // authority_transfer_eta
+        8 +          // authority_transfer_delay
+        (32 * MAX_CONFIG_ADMINS) +  // admins array
+        1 +          // admins_count
+        1 +          // approval_threshold
+        8 +          // proposal_count
+        4 +          // registered_signers_count
+        32 +         // guardian
+        1 +          // paused
+        1; // pause_flags
+}
+
+// Size of a config created before the timelock, admin, registry and pause fields
+pub const LEGACY_VAULT_CONFIG_LEN: usize = 8 +  // discriminator
+    (32 * MAX_SERVICE_SIGNERS) +  // service_signers array
+    1 +          // service_signers_count
+    8 +          // created_at
+    32 +         // created_by
+    8 +          // modified_at
+    32 +         // modified_by
+    32; // current_update_authority
+
+// Offset of `created_by`, it did not move when the config grew
+const CREATED_BY_OFFSET: usize = 8 + (32 * MAX_SERVICE_SIGNERS) + 1 + 8;
+
+#[derive(Accounts)]
+pub struct MigrateVaultConfig<'info> {
+    /// CHECK: Legacy config, too short to load as `VaultConfigState`. Its discriminator
+    /// and address are checked in `grow_vault_config`
+    #[account(mut, owner = crate::ID)]
+    pub config: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Grows a config created before the timelock fields to `VaultConfigState::LEN`.
+///
+/// The new fields are appended, so the existing ones keep their offsets and values.
+/// The result does not depend on the caller, so anyone may crank it and pay the rent.
+pub fn migrate_vault_config(ctx: Context<MigrateVaultConfig>) -> Result<()> {
+    let config = ctx.accounts.config.to_account_info();
+
+    let grown = grow_vault_config(
+        &config,
+        &ctx.accounts.payer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    require!(grown, CustomError::InvalidConfig);
+
+    emit!(VaultConfigUpdated {
+        config: config.key(),
+        updated_by: ctx.accounts.payer.key(),
+        change: VaultConfigChange::Migrated {
+            len: VaultConfigState::LEN as u32,
+        },
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+/// Checks that `config` is a vault config and grows it when it still has the legacy
+/// layout. The appended fields start zeroed, which disables the admin set, the guardian
+/// and the pause, and the authority transfer delay gets its default.
+///
+/// Returns whether the account was grown.
+pub fn grow_vault_config<'info>(
+    config: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program_info: &AccountInfo<'info>,
+) -> Result<bool> {
+    let data_len = config.data_len();
+    require!(
+        data_len == LEGACY_VAULT_CONFIG_LEN || data_len == VaultConfigState::LEN,
+        CustomError::InvalidConfig
+    );
+
+    {
+        let data = config.try_borrow_data()?;
+        require!(
+            data[..8] == VaultConfigState::DISCRIMINATOR,
+            CustomError::InvalidConfig
+        );
+
+        let created_by = Pubkey::try_from(&data[CREATED_BY_OFFSET..CREATED_BY_OFFSET + 32])
+            .map_err(|_| CustomError::InvalidConfig)?;
+        let (expected, _) = Pubkey::find_program_address(
+            &[VAULT_CONFIG_SEED.as_bytes(), created_by.as_ref()],
+            &crate::ID,
+        );
+        require_keys_eq!(config.key(), expected, CustomError::InvalidConfig);
+    }
+
+    if data_len == VaultConfigState::LEN {
+        return Ok(false);
+    }
+
+    let rent = Rent::get()?.minimum_balance(VaultConfigState::LEN);
+    let top_up = rent.saturating_sub(config.lamports());
+    if top_up > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                system_program_info.clone(),
+                system_program::Transfer {
+                    from: payer.clone(),
+                    to: config.clone(),
+                },
+            ),
+            top_up,
+        )?;
+    }
+
+    config.realloc(VaultConfigState::LEN, true)?;
+
+    let loader = AccountLoader::<VaultConfigState>::try_from(config)?;
+    loader.load_mut()?.authority_transfer_delay = DEFAULT_AUTHORITY_TRANSFER_DELAY;
+
+    Ok(true)
 }
 
 #[derive(Accounts)]
@@ -83,60 +257,214 @@ pub struct ManageServiceSigner<'info> {
     )]
     pub config: AccountLoader<'info, VaultConfigState>,
 
-    #[account(
-        constraint = (authority.key() == config.load()?.current_update_authority) @ CustomError::InvalidAuthority
-    )]
     pub authority: Signer<'info>,
 
-    /// The service signer being added or removed
-    pub service_signer: Signer<'info>,
+    /// CHECK: The service signer being managed, it only has to sign when added
+    pub service_signer: AccountInfo<'info>,
+
+    /// CHECK: Registry entry PDA of the service signer, created on add and closed on remove
+    #[account(mut)]
+    pub service_signer_entry: UncheckedAccount<'info>,
+
+    /// Funds new registry entries and receives the rent of removed ones
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Approved proposal, required once multisig administration is enabled
+    #[account(mut)]
+    pub proposal: Option<Account<'info, ConfigProposal>>,
+
+    /// Replacement key, only used by `Rotate`
+    pub new_service_signer: Option<Signer<'info>>,
+
+    /// CHECK: Registry entry PDA of the replacement key, created by `Rotate`
+    #[account(mut)]
+    pub new_service_signer_entry: Option<UncheckedAccount<'info>>,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
 pub enum ServiceSignerOperation {
-    Add,
+    /// Register the signer with a permission bitmask, valid in `[valid_from, valid_until)`.
+    /// A zero `valid_until` never expires.
+    Add {
+        permissions: u32,
+        valid_from: i64,
+        valid_until: i64,
+    },
     Remove,
+    /// Replace the permission bitmask of a registered signer
+    UpdatePermissions {
+        permissions: u32,
+    },
+    /// Hand the signer's permissions over to `new_signer` from `activate_at` on.
+    /// The old key stays valid until then, or is removed when `activate_at` has passed.
+    Rotate {
+        new_signer: Pubkey,
+        activate_at: i64,
+        valid_until: i64,
+    },
 }
 
 pub fn manage_service_signer(
     ctx: Context<ManageServiceSigner>,
     operation: ServiceSignerOperation,
 ) -> Result<()> {
+    let config_key = ctx.accounts.config.key();
     let config = &mut ctx.accounts.config.load_mut()?;
     let clock = Clock::get()?;
     let service_signer_key = ctx.accounts.service_signer.key();
 
+    authorize_config_action(
+        config,
+        config_key,
+        &ctx.accounts.authority,
+        ctx.accounts.proposal.as_mut(),
+        &ConfigAction::ManageServiceSigner {
+            service_signer: service_signer_key,
+            operation: operation.clone(),
+        },
+    )?;
+
+    // Legacy signers have to be migrated before they can be managed
+    require!(
+        !config.is_service_signer(&service_signer_key),
+        CustomError::DuplicateServiceSigner
+    );
+
+    let entry_info = ctx.accounts.service_signer_entry.to_account_info();
+    let payer_info = ctx.accounts.payer.to_account_info();
+
     match operation {
-        ServiceSignerOperation::Add => {
+        ServiceSignerOperation::Add {
+            permissions,
+            valid_from,
+            valid_until,
+        } => {
+            // Proves the service owns the key it registers
             require!(
-                config.service_signers_count < MAX_SERVICE_SIGNERS as u8,
-                CustomError::TooManyServiceSigners
+                ctx.accounts.service_signer.is_signer,
+                CustomError::InvalidSigner
             );
 
-            require!(
-                !config.is_service_signer(&service_signer_key),
-                CustomError::DuplicateServiceSigner
-            );
+            create_service_signer_entry(
+                &entry_info,
+                &payer_info,
+                &ctx.accounts.system_program.to_account_info(),
+                ServiceSignerEntry {
+                    config: config_key,
+                    signer: service_signer_key,
+                    added_at: clock.unix_timestamp,
+                    added_by: ctx.accounts.authority.key(),
+                    permissions,
+                    valid_from,
+                    valid_until,
+                    bump: 0,
+                },
+            )?;
+
+            config.registered_signers_count = config
+                .registered_signers_count
+                .checked_add(1)
+                .ok_or(CustomError::TooManyServiceSigners)?;
 
-            let current_count = config.service_signers_count as usize;
-            config.service_signers[current_count] = service_signer_key;
-            config.service_signers_count += 1;
+            emit!(ServiceSignerAdded {
+                config: config_key,
+                signer: service_signer_key,
+                permissions,
+                valid_from,
+                valid_until,
+                added_by: ctx.accounts.authority.key(),
+            });
         }
         ServiceSignerOperation::Remove => {
-            let current_count = config.service_signers_count as usize;
-            let position = config.service_signers[..current_count]
-                .iter()
-                .position(|signer| signer == &service_signer_key)
-                .ok_or(CustomError::InvalidSignerExist)?;
-
-            // Shift remaining signers left to fill the gap
-            for i in position..(current_count - 1) {
-                config.service_signers[i] = config.service_signers[i + 1];
+            close_service_signer_entry(&entry_info, &payer_info, config_key, service_signer_key)?;
+
+            config.registered_signers_count = config
+                .registered_signers_count
+                .checked_sub(1)
+                .ok_or(CustomError::ServiceSignerCountMismatch)?;
+
+            emit!(ServiceSignerRemoved {
+                config: config_key,
+                signer: service_signer_key,
+                removed_by: ctx.accounts.authority.key(),
+            });
+        }
+        ServiceSignerOperation::UpdatePermissions { permissions } => {
+            update_service_signer_permissions(
+                &entry_info,
+                config_key,
+                service_signer_key,
+                permissions,
+            )?;
+
+            emit!(ServiceSignerPermissionsUpdated {
+                config: config_key,
+                signer: service_signer_key,
+                permissions,
+                updated_by: ctx.accounts.authority.key(),
+            });
+        }
+        ServiceSignerOperation::Rotate {
+            new_signer,
+            activate_at,
+            valid_until,
+        } => {
+            // The replacement key signs to prove the service owns it
+            let new_service_signer = ctx
+                .accounts
+                .new_service_signer
+                .as_ref()
+                .ok_or(CustomError::InvalidSigner)?;
+            require_keys_eq!(
+                new_service_signer.key(),
+                new_signer,
+                CustomError::InvalidSigner
+            );
+            let new_entry_info = ctx
+                .accounts
+                .new_service_signer_entry
+                .as_ref()
+                .ok_or(CustomError::InvalidServiceSignerEntry)?
+                .to_account_info();
+
+            let old_entry_closed = rotate_service_signer_entry(
+                &entry_info,
+                &new_entry_info,
+                &payer_info,
+                &ctx.accounts.system_program.to_account_info(),
+                service_signer_key,
+                ServiceSignerEntry {
+                    config: config_key,
+                    signer: new_signer,
+                    added_at: clock.unix_timestamp,
+                    added_by: ctx.accounts.authority.key(),
+                    // Copied from the old entry
+                    permissions: 0,
+                    valid_from: activate_at,
+                    valid_until,
+                    bump: 0,
+                },
+                clock.unix_timestamp,
+            )?;
+
+            // A scheduled rotation keeps both entries until the old one is removed
+            if !old_entry_closed {
+                config.registered_signers_count = config
+                    .registered_signers_count
+                    .checked_add(1)
+                    .ok_or(CustomError::TooManyServiceSigners)?;
             }
 
-            // Clear the last position and decrement count
-            config.service_signers[current_count - 1] = Pubkey::default();
-            config.service_signers_count -= 1;
+            emit!(ServiceSignerRotated {
+                config: config_key,
+                old_signer: service_signer_key,
+                new_signer,
+                activate_at,
+                rotated_by: ctx.accounts.authority.key(),
+            });
         }
     }
 
@@ -153,10 +481,60 @@ impl VaultConfigState {
             .iter()
             .any(|signer| signer == pubkey)
     }
+
+    pub fn is_multisig_enabled(&self) -> bool {
+        self.approval_threshold > 0
+    }
+
+    pub fn is_admin(&self, pubkey: &Pubkey) -> bool {
+        self.admins[..self.admins_count as usize]
+            .iter()
+            .any(|admin| admin == pubkey)
+    }
+
+    pub fn is_paused(&self, subsystem: u8) -> bool {
+        self.paused != 0 || self.pause_flags & subsystem != 0
+    }
+
+    /// Number of approvers that are still admins of this config
+    pub fn count_admin_approvals(&self, approvers: &[Pubkey]) -> usize {
+        approvers
+            .iter()
+            .filter(|approver| self.is_admin(approver))
+            .count()
+    }
+}
+
+/// Rejects service signers that have no registry entry in the canonical vault config,
+/// are outside their validity window or lack the `permission` bit the instruction requires.
+pub fn require_service_signer<'info>(
+    config: &AccountLoader<'info, VaultConfigState>,
+    service_signer_entry: &Account<'info, ServiceSignerEntry>,
+    service_signer: &Signer<'info>,
+    permission: u32,
+) -> Result<()> {
+    // Contexts only check the config seeds against its own `created_by`
+    let (canonical_config, _) = Pubkey::find_program_address(
+        &[VAULT_CONFIG_SEED.as_bytes(), config_authority::ID.as_ref()],
+        &crate::ID,
+    );
+    require_keys_eq!(config.key(), canonical_config, CustomError::InvalidConfig);
+
+    service_signer_entry.validate(&config.key(), &service_signer.key())?;
+    require!(
+        service_signer_entry.is_active(Clock::get()?.unix_timestamp),
+        CustomError::ServiceSignerExpired
+    );
+    require!(
+        service_signer_entry.has_permission(permission),
+        CustomError::MissingServiceSignerPermission
+    );
+
+    Ok(())
 }
 
 #[derive(Accounts)]
-pub struct UpdateVaultAuthority<'info> {
+pub struct ProposeVaultAuthority<'info> {
     #[account(
         mut,
         seeds = [VAULT_CONFIG_SEED.as_bytes(), config.load()?.created_by.as_ref()],
@@ -164,24 +542,210 @@ pub struct UpdateVaultAuthority<'info> {
     )]
     pub config: AccountLoader<'info, VaultConfigState>,
 
+    pub current_authority: Signer<'info>,
+
+    /// CHECK: Only recorded as the pending authority, it signs on accept
+    pub new_authority: AccountInfo<'info>,
+
+    /// Approved proposal, required once multisig administration is enabled
+    #[account(mut)]
+    pub proposal: Option<Account<'info, ConfigProposal>>,
+}
+
+pub fn propose_vault_authority(ctx: Context<ProposeVaultAuthority>) -> Result<()> {
+    let config_key = ctx.accounts.config.key();
+    let config = &mut ctx.accounts.config.load_mut()?;
+    let clock = Clock::get()?;
+    let new_authority = ctx.accounts.new_authority.key();
+
+    authorize_config_action(
+        config,
+        config_key,
+        &ctx.accounts.current_authority,
+        ctx.accounts.proposal.as_mut(),
+        &ConfigAction::ProposeVaultAuthority { new_authority },
+    )?;
+
+    require!(
+        config.pending_update_authority == Pubkey::default(),
+        CustomError::AuthorityTransferPending
+    );
+    require!(
+        new_authority != Pubkey::default() && new_authority != config.current_update_authority,
+        CustomError::InvalidAuthority
+    );
+
+    // Start the timelock
+    config.pending_update_authority = new_authority;
+    config.authority_transfer_eta = clock
+        .unix_timestamp
+        .checked_add(config.authority_transfer_delay)
+        .ok_or(CustomError::InvalidTimelockDelay)?;
+
+    // Update modification metadata
+    config.modified_at = clock.unix_timestamp;
+    config.modified_by = ctx.accounts.current_authority.key();
+
+    emit!(VaultConfigUpdated {
+        config: config_key,
+        updated_by: ctx.accounts.current_authority.key(),
+        change: VaultConfigChange::AuthorityProposed {
+            new_authority,
+            eta: config.authority_transfer_eta,
+        },
+        timestamp: clock.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct AcceptVaultAuthority<'info> {
     #[account(
-        constraint = (current_authority.key() == config.load()?.current_update_authority) @ CustomError::InvalidAuthority
+        mut,
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config.load()?.created_by.as_ref()],
+        bump
     )]
-    pub current_authority: Signer<'info>,
+    pub config: AccountLoader<'info, VaultConfigState>,
 
+    #[account(
+        constraint = (new_authority.key() == config.load()?.pending_update_authority) @ CustomError::NoPendingAuthorityTransfer
+    )]
     pub new_authority: Signer<'info>,
 }
 
-pub fn update_vault_authority(ctx: Context<UpdateVaultAuthority>) -> Result<()> {
+pub fn accept_vault_authority(ctx: Context<AcceptVaultAuthority>) -> Result<()> {
+    let config_key = ctx.accounts.config.key();
     let config = &mut ctx.accounts.config.load_mut()?;
     let clock = Clock::get()?;
 
+    require!(
+        clock.unix_timestamp >= config.authority_transfer_eta,
+        CustomError::AuthorityTransferTimelocked
+    );
+
     // Update the authority
-    config.current_update_authority = ctx.accounts.new_authority.key();
+    config.current_update_authority = config.pending_update_authority;
+    config.pending_update_authority = Pubkey::default();
+    config.authority_transfer_eta = 0;
+
+    // Update modification metadata
+    config.modified_at = clock.unix_timestamp;
+    config.modified_by = ctx.accounts.new_authority.key();
+
+    emit!(VaultConfigUpdated {
+        config: config_key,
+        updated_by: ctx.accounts.new_authority.key(),
+        change: VaultConfigChange::AuthorityAccepted {
+            new_authority: ctx.accounts.new_authority.key(),
+        },
+        timestamp: clock.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CancelVaultAuthorityTransfer<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config.load()?.created_by.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
+
+    pub current_authority: Signer<'info>,
+
+    /// Approved proposal, required once multisig administration is enabled
+    #[account(mut)]
+    pub proposal: Option<Account<'info, ConfigProposal>>,
+}
+
+pub fn cancel_vault_authority_transfer(ctx: Context<CancelVaultAuthorityTransfer>) -> Result<()> {
+    let config_key = ctx.accounts.config.key();
+    let config = &mut ctx.accounts.config.load_mut()?;
+    let clock = Clock::get()?;
+
+    authorize_config_action(
+        config,
+        config_key,
+        &ctx.accounts.current_authority,
+        ctx.accounts.proposal.as_mut(),
+        &ConfigAction::CancelVaultAuthorityTransfer,
+    )?;
+
+    require!(
+        config.pending_update_authority != Pubkey::default(),
+        CustomError::NoPendingAuthorityTransfer
+    );
+
+    config.pending_update_authority = Pubkey::default();
+    config.authority_transfer_eta = 0;
+
+    // Update modification metadata
+    config.modified_at = clock.unix_timestamp;
+    config.modified_by = ctx.accounts.current_authority.key();
+
+    emit!(VaultConfigUpdated {
+        config: config_key,
+        updated_by: ctx.accounts.current_authority.key(),
+        change: VaultConfigChange::AuthorityTransferCancelled,
+        timestamp: clock.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct UpdateAuthorityTransferDelay<'info> {
+    #[account(
+        mut,
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config.load()?.created_by.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
+
+    pub current_authority: Signer<'info>,
+
+    /// Approved proposal, required once multisig administration is enabled
+    #[account(mut)]
+    pub proposal: Option<Account<'info, ConfigProposal>>,
+}
+
+pub fn update_authority_transfer_delay(
+    ctx: Context<UpdateAuthorityTransferDelay>,
+    delay: i64,
+) -> Result<()> {
+    let config_key = ctx.accounts.config.key();
+    let config = &mut ctx.accounts.config.load_mut()?;
+    let clock = Clock::get()?;
+
+    authorize_config_action(
+        config,
+        config_key,
+        &ctx.accounts.current_authority,
+        ctx.accounts.proposal.as_mut(),
+        &ConfigAction::UpdateAuthorityTransferDelay { delay },
+    )?;
+
+    require!(
+        (MIN_AUTHORITY_TRANSFER_DELAY..=MAX_AUTHORITY_TRANSFER_DELAY).contains(&delay),
+        CustomError::InvalidTimelockDelay
+    );
+
+    // Only applies to transfers proposed from now on
+    config.authority_transfer_delay = delay;
 
     // Update modification metadata
     config.modified_at = clock.unix_timestamp;
     config.modified_by = ctx.accounts.current_authority.key();
 
+    emit!(VaultConfigUpdated {
+        config: config_key,
+        updated_by: ctx.accounts.current_authority.key(),
+        change: VaultConfigChange::TransferDelayUpdated { delay },
+        timestamp: clock.unix_timestamp,
+    });
+
     Ok(())
 }
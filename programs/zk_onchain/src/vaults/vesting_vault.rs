@@ -0,0 +1,550 @@
+use crate::state::*;
+use account_compression::program::AccountCompression;
+use anchor_lang::prelude::*;
+use light_compressed_token::program::LightCompressedToken;
+use light_system_program::program::LightSystemProgram;
+
+use crate::compressed_transfers::{
+    impl_token_cpi_accounts, sum_token_inputs, transfer_compressed_tokens_cpi,
+    CompressedTokenInputs,
+};
+use crate::{
+    config_authority, ServiceSignerEntry, UserVaultAuthority, UserVaultStateV2, VaultConfigState,
+};
+
+use super::VaultType;
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct VestingVaultParams {
+    /// Lets one grantor keep several grants open
+    pub vesting_id: u64,
+    pub beneficiary: Pubkey,
+    /// Vault id of the beneficiary's user vault, withdrawals go to the vault's token owner. The
+    /// vault must be held by `beneficiary`.
+    pub beneficiary_vault_id: Pubkey,
+    pub mint: Pubkey,
+    pub total_amount: u64,
+    /// Vesting runs linearly from `start_at` to `end_at`, nothing is released before `cliff_at`
+    pub start_at: i64,
+    pub cliff_at: i64,
+    pub end_at: i64,
+    /// Lets the grantor take back the unvested part
+    pub revocable: bool,
+}
+
+#[account]
+pub struct VestingVaultState {
+    pub grantor: Pubkey,
+    pub beneficiary: Pubkey,
+    /// Token owner PDA of the beneficiary's user vault, see `UserVaultStateV2::find_token_owner`
+    pub beneficiary_token_owner: Pubkey,
+    pub vesting_id: u64,
+    pub vault_type: VaultType,
+    pub mint: Pubkey,
+    pub total_amount: u64,
+    pub withdrawn_amount: u64,
+    pub start_at: i64,
+    pub cliff_at: i64,
+    pub end_at: i64,
+    pub revocable: bool,
+    pub created_at: i64,
+    pub modified_at: i64,
+    pub bump: u8,
+}
+
+impl VestingVaultState {
+    pub const LEN: usize = 8 +  // discriminator
+        32 +         // grantor
+        32 +         // beneficiary
+        32 +         // beneficiary_token_owner
+        8 +          // vesting_id
+        1 +          // vault_type
+        32 +         // mint
+        8 +          // total_amount
+        8 +          // withdrawn_amount
+        8 +          // start_at
+        8 +          // cliff_at
+        8 +          // end_at
+        1 +          // revocable
+        8 +          // created_at
+        8 +          // modified_at
+        1; // bump
+
+    pub fn find_address(grantor: &Pubkey, vesting_id: u64) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[
+                VESTING_VAULT.as_bytes(),
+                grantor.as_ref(),
+                &vesting_id.to_le_bytes(),
+            ],
+            &crate::ID,
+        )
+    }
+
+    /// Amount vested at `now`: nothing before the cliff, then linear up to `end_at`
+    pub fn vested_amount(&self, now: i64) -> u64 {
+        if now < self.cliff_at {
+            return 0;
+        }
+        if now >= self.end_at {
+            return self.total_amount;
+        }
+
+        let elapsed = (now - self.start_at) as u128;
+        let duration = (self.end_at - self.start_at) as u128;
+
+        (self.total_amount as u128 * elapsed / duration) as u64
+    }
+
+    pub fn releasable_amount(&self, now: i64) -> u64 {
+        self.vested_amount(now) - self.withdrawn_amount
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(params: VestingVaultParams)]
+pub struct InitializeVestingVault<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: Checked in light-system-program.
+    pub service_signer: Signer<'info>,
+
+    /// Grantor, owner of the granted compressed tokens
+    pub grantor: Signer<'info>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = VestingVaultState::LEN,
+        seeds = [
+            VESTING_VAULT.as_bytes(),
+            grantor.key().as_ref(),
+            &params.vesting_id.to_le_bytes()
+        ],
+        bump
+    )]
+    pub vesting_vault: Account<'info, VestingVaultState>,
+
+    /// Authority record of the beneficiary's user vault, the vault must be held by the
+    /// beneficiary
+    #[account(
+        seeds = [USER_VAULT_AUTHORITY.as_bytes(), params.beneficiary_vault_id.as_ref()],
+        bump = beneficiary_vault.bump,
+        constraint = beneficiary_vault.current_authority == params.beneficiary
+            @ CustomError::InvalidAuthority
+    )]
+    pub beneficiary_vault: Account<'info, UserVaultAuthority>,
+
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority::ID.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
+
+    /// Registry entry of the service signer in `config`
+    pub service_signer_entry: Account<'info, ServiceSignerEntry>,
+
+    /// CHECK: Checked in the compressed token program
+    pub compressed_token_cpi_authority_pda: AccountInfo<'info>,
+
+    pub compressed_token_program: Program<'info, LightCompressedToken>,
+
+    pub light_system_program: Program<'info, LightSystemProgram>,
+
+    /// CHECK: Checked in light-system-program.
+    pub registered_program_pda: AccountInfo<'info>,
+
+    /// CHECK: Checked in light-system-program.
+    pub noop_program: AccountInfo<'info>,
+
+    /// CHECK: Checked in light-system-program.
+    pub account_compression_authority: AccountInfo<'info>,
+
+    pub account_compression_program: Program<'info, AccountCompression>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Opens a grant and locks `total_amount` of the grantor's compressed tokens in the
+/// vesting vault PDA.
+pub fn initialize_vesting_vault<'info>(
+    ctx: Context<'_, '_, '_, 'info, InitializeVestingVault<'info>>,
+    params: VestingVaultParams,
+    grantor_inputs: CompressedTokenInputs,
+) -> Result<()> {
+    let clock = Clock::get()?;
+
+    require!(
+        params.total_amount > 0
+            && params.start_at <= params.cliff_at
+            && params.cliff_at <= params.end_at
+            && params.start_at < params.end_at,
+        CustomError::InvalidVestingSchedule
+    );
+
+    let vesting_key = ctx.accounts.vesting_vault.key();
+    let grantor = ctx.accounts.grantor.key();
+    let (beneficiary_token_owner, _) =
+        UserVaultStateV2::find_token_owner(&params.beneficiary_vault_id);
+
+    let vesting_vault = &mut ctx.accounts.vesting_vault;
+    vesting_vault.grantor = grantor;
+    vesting_vault.beneficiary = params.beneficiary;
+    vesting_vault.beneficiary_token_owner = beneficiary_token_owner;
+    vesting_vault.vesting_id = params.vesting_id;
+    vesting_vault.vault_type = VaultType::Vesting;
+    vesting_vault.mint = params.mint;
+    vesting_vault.total_amount = params.total_amount;
+    vesting_vault.withdrawn_amount = 0;
+    vesting_vault.start_at = params.start_at;
+    vesting_vault.cliff_at = params.cliff_at;
+    vesting_vault.end_at = params.end_at;
+    vesting_vault.revocable = params.revocable;
+    vesting_vault.created_at = clock.unix_timestamp;
+    vesting_vault.modified_at = clock.unix_timestamp;
+    vesting_vault.bump = ctx.bumps.vesting_vault;
+
+    let accounts = ctx.accounts.token_cpi_accounts(
+        ctx.accounts.grantor.to_account_info(),
+        ctx.remaining_accounts,
+    );
+    transfer_compressed_tokens_cpi(
+        &accounts,
+        &[],
+        params.mint,
+        params.total_amount,
+        vesting_key,
+        grantor,
+        grantor_inputs,
+    )?;
+
+    emit!(VestingVaultCreated {
+        vesting: vesting_key,
+        grantor,
+        beneficiary: params.beneficiary,
+        mint: params.mint,
+        total_amount: params.total_amount,
+        start_at: params.start_at,
+        cliff_at: params.cliff_at,
+        end_at: params.end_at,
+        revocable: params.revocable,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct WithdrawVestedTokens<'info> {
+    /// Beneficiary of the grant, pays the transaction
+    #[account(mut)]
+    pub beneficiary: Signer<'info>,
+
+    /// Closed once everything is withdrawn
+    #[account(
+        mut,
+        has_one = beneficiary @ CustomError::InvalidAuthority,
+        seeds = [
+            VESTING_VAULT.as_bytes(),
+            vesting_vault.grantor.as_ref(),
+            &vesting_vault.vesting_id.to_le_bytes()
+        ],
+        bump = vesting_vault.bump
+    )]
+    pub vesting_vault: Account<'info, VestingVaultState>,
+
+    /// CHECK: Grantor of the grant, receives the rent of the closed vault
+    #[account(
+        mut,
+        address = vesting_vault.grantor @ CustomError::InvalidAuthority
+    )]
+    pub grantor: AccountInfo<'info>,
+
+    /// Withdrawals pause with the vesting
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority::ID.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
+
+    /// CHECK: Checked in the compressed token program
+    pub compressed_token_cpi_authority_pda: AccountInfo<'info>,
+
+    pub compressed_token_program: Program<'info, LightCompressedToken>,
+
+    pub light_system_program: Program<'info, LightSystemProgram>,
+
+    /// CHECK: Checked in light-system-program.
+    pub registered_program_pda: AccountInfo<'info>,
+
+    /// CHECK: Checked in light-system-program.
+    pub noop_program: AccountInfo<'info>,
+
+    /// CHECK: Checked in light-system-program.
+    pub account_compression_authority: AccountInfo<'info>,
+
+    pub account_compression_program: Program<'info, AccountCompression>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Moves everything vested so far and not yet withdrawn into the beneficiary's user vault.
+pub fn withdraw_vested_tokens<'info>(
+    ctx: Context<'_, '_, '_, 'info, WithdrawVestedTokens<'info>>,
+    vesting_inputs: CompressedTokenInputs,
+) -> Result<()> {
+    let clock = Clock::get()?;
+    let vesting_vault = &ctx.accounts.vesting_vault;
+
+    let amount = vesting_vault.releasable_amount(clock.unix_timestamp);
+    require!(amount > 0, CustomError::NothingVested);
+
+    let vesting_key = vesting_vault.key();
+    let grantor = vesting_vault.grantor;
+    let withdrawn_amount = vesting_vault.withdrawn_amount + amount;
+    let fully_withdrawn = withdrawn_amount == vesting_vault.total_amount;
+
+    let vesting_id = vesting_vault.vesting_id.to_le_bytes();
+    let bump = [vesting_vault.bump];
+    let seeds: &[&[u8]] = &[
+        VESTING_VAULT.as_bytes(),
+        grantor.as_ref(),
+        &vesting_id,
+        &bump,
+    ];
+
+    // Anything sent to the vault on top of the grant goes to the beneficiary at the end
+    let change_owner = if fully_withdrawn {
+        vesting_vault.beneficiary_token_owner
+    } else {
+        vesting_key
+    };
+
+    let accounts = ctx
+        .accounts
+        .token_cpi_accounts(vesting_vault.to_account_info(), ctx.remaining_accounts);
+    transfer_compressed_tokens_cpi(
+        &accounts,
+        &[seeds],
+        vesting_vault.mint,
+        amount,
+        vesting_vault.beneficiary_token_owner,
+        change_owner,
+        vesting_inputs,
+    )?;
+
+    emit!(VestedTokensWithdrawn {
+        vesting: vesting_key,
+        beneficiary: vesting_vault.beneficiary,
+        amount,
+        withdrawn_amount,
+    });
+
+    let vesting_vault = &mut ctx.accounts.vesting_vault;
+    vesting_vault.withdrawn_amount = withdrawn_amount;
+    vesting_vault.modified_at = clock.unix_timestamp;
+
+    if fully_withdrawn {
+        vesting_vault.close(ctx.accounts.grantor.to_account_info())?;
+    }
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct RevokeVesting<'info> {
+    /// Grantor of the grant, pays the transaction
+    #[account(mut)]
+    pub grantor: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = grantor @ CustomError::InvalidAuthority,
+        seeds = [
+            VESTING_VAULT.as_bytes(),
+            vesting_vault.grantor.as_ref(),
+            &vesting_vault.vesting_id.to_le_bytes()
+        ],
+        bump = vesting_vault.bump
+    )]
+    pub vesting_vault: Account<'info, VestingVaultState>,
+
+    /// Revocations pause with the vesting
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), config_authority::ID.as_ref()],
+        bump
+    )]
+    pub config: AccountLoader<'info, VaultConfigState>,
+
+    /// CHECK: Checked in the compressed token program
+    pub compressed_token_cpi_authority_pda: AccountInfo<'info>,
+
+    pub compressed_token_program: Program<'info, LightCompressedToken>,
+
+    pub light_system_program: Program<'info, LightSystemProgram>,
+
+    /// CHECK: Checked in light-system-program.
+    pub registered_program_pda: AccountInfo<'info>,
+
+    /// CHECK: Checked in light-system-program.
+    pub noop_program: AccountInfo<'info>,
+
+    /// CHECK: Checked in light-system-program.
+    pub account_compression_authority: AccountInfo<'info>,
+
+    pub account_compression_program: Program<'info, AccountCompression>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Returns the unvested part of a revocable grant to the grantor. What vested up to now
+/// stays withdrawable by the beneficiary, and the grant stops vesting. Everything else in
+/// the inputs goes to the grantor too, so only the beneficiary's share stays in the vault.
+pub fn revoke_vesting<'info>(
+    ctx: Context<'_, '_, '_, 'info, RevokeVesting<'info>>,
+    vesting_inputs: CompressedTokenInputs,
+) -> Result<()> {
+    let clock = Clock::get()?;
+    let now = clock.unix_timestamp;
+    let vesting_vault = &ctx.accounts.vesting_vault;
+
+    require!(vesting_vault.revocable, CustomError::VestingNotRevocable);
+
+    let vested_amount = vesting_vault.vested_amount(now);
+    let unvested_amount = vesting_vault.total_amount - vested_amount;
+    require!(unvested_amount > 0, CustomError::InvalidVestingSchedule);
+
+    // The change output is exactly what the beneficiary can still withdraw
+    let owed_amount = vested_amount - vesting_vault.withdrawn_amount;
+    let input_amount = sum_token_inputs(&vesting_inputs.input_token_data_with_context)?;
+    let returned_amount = input_amount
+        .checked_sub(owed_amount)
+        .filter(|returned| *returned >= unvested_amount)
+        .ok_or(CustomError::InsufficientTokenInput)?;
+
+    let vesting_key = vesting_vault.key();
+    let grantor = vesting_vault.grantor;
+
+    let vesting_id = vesting_vault.vesting_id.to_le_bytes();
+    let bump = [vesting_vault.bump];
+    let seeds: &[&[u8]] = &[
+        VESTING_VAULT.as_bytes(),
+        grantor.as_ref(),
+        &vesting_id,
+        &bump,
+    ];
+
+    let accounts = ctx
+        .accounts
+        .token_cpi_accounts(vesting_vault.to_account_info(), ctx.remaining_accounts);
+    transfer_compressed_tokens_cpi(
+        &accounts,
+        &[seeds],
+        vesting_vault.mint,
+        returned_amount,
+        grantor,
+        vesting_key,
+        vesting_inputs,
+    )?;
+
+    emit!(VestingRevoked {
+        vesting: vesting_key,
+        grantor,
+        unvested_amount,
+    });
+
+    // Freeze the schedule at the vested amount, all of it is releasable from now on
+    let vesting_vault = &mut ctx.accounts.vesting_vault;
+    vesting_vault.total_amount = vested_amount;
+    vesting_vault.cliff_at = vesting_vault.cliff_at.min(now);
+    vesting_vault.end_at = vesting_vault.end_at.min(now);
+    vesting_vault.revocable = false;
+    vesting_vault.modified_at = now;
+
+    // Nothing is owed, so no change went to the vault and closing it strands no tokens
+    if owed_amount == 0 {
+        vesting_vault.close(ctx.accounts.grantor.to_account_info())?;
+    }
+
+    Ok(())
+}
+
+impl_token_cpi_accounts!(InitializeVestingVault, payer);
+
+impl_token_cpi_accounts!(WithdrawVestedTokens, beneficiary);
+
+impl_token_cpi_accounts!(RevokeVesting, grantor);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grant(total_amount: u64, start_at: i64, cliff_at: i64, end_at: i64) -> VestingVaultState {
+        VestingVaultState {
+            grantor: Pubkey::new_unique(),
+            beneficiary: Pubkey::new_unique(),
+            beneficiary_token_owner: Pubkey::new_unique(),
+            vesting_id: 0,
+            vault_type: VaultType::Vesting,
+            mint: Pubkey::new_unique(),
+            total_amount,
+            withdrawn_amount: 0,
+            start_at,
+            cliff_at,
+            end_at,
+            revocable: false,
+            created_at: start_at,
+            modified_at: start_at,
+            bump: 255,
+        }
+    }
+
+    #[test]
+    fn nothing_vests_before_the_cliff() {
+        let grant = grant(1_000, 100, 150, 200);
+
+        assert_eq!(grant.vested_amount(0), 0);
+        assert_eq!(grant.vested_amount(149), 0);
+    }
+
+    #[test]
+    fn cliff_releases_what_vested_since_the_start() {
+        let grant = grant(1_000, 100, 150, 200);
+
+        assert_eq!(grant.vested_amount(150), 500);
+        assert_eq!(grant.vested_amount(175), 750);
+    }
+
+    #[test]
+    fn everything_vests_at_the_end() {
+        let grant = grant(1_000, 100, 100, 200);
+
+        assert_eq!(grant.vested_amount(200), 1_000);
+        assert_eq!(grant.vested_amount(i64::MAX), 1_000);
+    }
+
+    #[test]
+    fn vesting_rounds_down() {
+        let grant = grant(10, 0, 0, 3);
+
+        assert_eq!(grant.vested_amount(1), 3);
+        assert_eq!(grant.vested_amount(2), 6);
+        assert_eq!(grant.vested_amount(3), 10);
+    }
+
+    #[test]
+    fn large_grants_do_not_overflow() {
+        let grant = grant(u64::MAX, 0, 0, 1_000);
+
+        assert_eq!(grant.vested_amount(500), u64::MAX / 2);
+    }
+
+    #[test]
+    fn releasable_amount_excludes_withdrawals() {
+        let mut grant = grant(1_000, 0, 0, 100);
+        grant.withdrawn_amount = 300;
+
+        assert_eq!(grant.releasable_amount(50), 200);
+        assert_eq!(grant.releasable_amount(100), 700);
+    }
+}